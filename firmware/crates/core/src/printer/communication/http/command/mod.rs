@@ -4,7 +4,11 @@ mod sender_and_receiver;
 
 pub use sender_and_receiver::*;
 
-use crate::printer::components::{g_code::GCodeCommand, Peripherals, Printer3DComponents};
+use crate::printer::components::{
+	g_code::{execute::FilePosition, GCodeCommand},
+	persisted_settings::PersistedSettings,
+	Peripherals, Printer3DComponents,
+};
 
 /// A command sent from the `Communication` thread to the `Components` thread to be [`executed`].
 ///
@@ -12,6 +16,12 @@ use crate::printer::components::{g_code::GCodeCommand, Peripherals, Printer3DCom
 pub enum Command<P: Peripherals>
 {
 	AddGCodeCommandsToBuffer(Vec<Box<dyn GCodeCommand<P>>>),
+	/// Commands read from the file being printed, each one with its position in the file.
+	AddFileGCodeCommandsToBuffer(Vec<(Box<dyn GCodeCommand<P>>, FilePosition)>),
+	/// Settings loaded from the flash memory at startup.
+	ApplyPersistedSettings(PersistedSettings),
+	/// The print in execution has been cancelled (check [`Printer3DComponents::cancel_print`]).
+	CancelPrint,
 }
 
 impl<P: Peripherals> Command<P>
@@ -31,6 +41,24 @@ impl<P: Peripherals> Command<P>
 					}
 				}
 			},
+			Command::AddFileGCodeCommandsToBuffer(commands) =>
+			{
+				if let Some(g_code_executer) = components.g_code_executer.as_mut()
+				{
+					for (command, file_position) in commands
+					{
+						g_code_executer.add_file_command_to_buffer(command, file_position);
+					}
+				}
+			},
+			Command::ApplyPersistedSettings(settings) => components.apply_persisted_settings(settings),
+			Command::CancelPrint =>
+			{
+				if let Err(error) = components.cancel_print()
+				{
+					log::error!("Couldn't turn off the layer fan while cancelling the print: {error:?}");
+				}
+			},
 		}
 	}
 }
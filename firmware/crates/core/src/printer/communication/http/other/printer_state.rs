@@ -2,7 +2,10 @@ use serde::{ser::SerializeStruct, Serialize};
 use spin::Mutex;
 
 use crate::{
-	printer::components::{temperature::TemperaturePidController, Peripherals},
+	printer::components::{
+		drivers::filament_sensor::FilamentSensorState, emergency_stop, temperature::TemperaturePidController,
+		Peripherals,
+	},
 	utils::measurement::temperature::Temperature,
 };
 
@@ -16,16 +19,19 @@ pub fn get_current_state() -> PrinterState
 }
 
 /// Updates the "screenshot" of the state of some components of the machine based on the current and target temperatures
-/// of the two PID controllers (saves the result in the static instance of [`PrinterState`] so that you can later retrieve
-/// the state using [`get_current_state`]).
+/// of the two PID controllers, on the state of the filament sensor (`None` if the machine has no filament sensor) and
+/// on whether the machine has been [`halted`](emergency_stop::is_halted), and saves the result in the static instance
+/// of [`PrinterState`] so that you can later retrieve the state using [`get_current_state`].
 pub fn tick<P: Peripherals>(
 	hotend_pid_controller: &TemperaturePidController<P::CartridgeHeaterPin, P::Adc, P::HotendAdcPin>,
 	bed_pid_controller: &TemperaturePidController<P::HeatedBedHeaterPin, P::Adc, P::HeatedBedAdcPin>,
+	filament_sensor_state: Option<FilamentSensorState>,
 )
 {
-	PRINTER_STATE
-		.lock()
-		.tick::<P>(hotend_pid_controller, bed_pid_controller)
+	let mut printer_state = PRINTER_STATE.lock();
+	printer_state.tick::<P>(hotend_pid_controller, bed_pid_controller);
+	printer_state.filament_sensor_state = filament_sensor_state;
+	printer_state.is_halted = emergency_stop::is_halted();
 }
 
 #[derive(Clone)]
@@ -36,6 +42,8 @@ pub struct PrinterState
 	hotend_target_temperature: Option<Temperature>,
 	bed_current_temperature: Option<Temperature>,
 	bed_target_temperature: Option<Temperature>,
+	filament_sensor_state: Option<FilamentSensorState>,
+	is_halted: bool,
 }
 
 impl PrinterState
@@ -45,6 +53,8 @@ impl PrinterState
 		hotend_target_temperature: None,
 		bed_current_temperature: None,
 		bed_target_temperature: None,
+		filament_sensor_state: None,
+		is_halted: false,
 	};
 
 	/// Updates the "screenshot" of the state of some components of the machine based on the current and target temperatures
@@ -81,7 +91,7 @@ impl Serialize for PrinterState
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 	where S: serde::Serializer
 	{
-		let mut state = serializer.serialize_struct("PrinterState", 4)?;
+		let mut state = serializer.serialize_struct("PrinterState", 6)?;
 		let mut serialize_field = |field_name, field_value: Option<Temperature>| {
 			state.serialize_field(
 				field_name,
@@ -93,6 +103,8 @@ impl Serialize for PrinterState
 		(serialize_field)("hotendTargetTemperature", self.hotend_target_temperature)?;
 		(serialize_field)("bedCurrentTemperature", self.bed_current_temperature)?;
 		(serialize_field)("bedTargetTemperature", self.bed_target_temperature)?;
+		state.serialize_field("filamentSensorState", &self.filament_sensor_state)?;
+		state.serialize_field("isHalted", &self.is_halted)?;
 
 		state.end()
 	}
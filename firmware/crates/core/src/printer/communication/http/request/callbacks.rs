@@ -1,3 +1,5 @@
+use embedded_hal::spi::SpiDevice;
+use embedded_io::Write as _;
 use embedded_svc::http::server::{Connection, HandlerError, Request, Response};
 use serde::{Deserialize, Serialize};
 use spin::MutexGuard;
@@ -6,9 +8,25 @@ use crate::printer::{
 	communication::http::{
 		command::Command,
 		other::printer_state,
-		resources::{Resources, ResourcesImpl},
+		resources::{CancelPrintError, Resources, ResourcesImpl, StartPrintError},
+	},
+	components::{
+		emergency_stop, filament_change,
+		file_system::{
+			path,
+			regions::{
+				data::ExpectedChecksum,
+				metadata::{FileId, StoreError as StoreMetadatasError},
+			},
+			CreateFileError, UpdateFileError, REPLACEMENT_FILE_SUFFIX,
+		},
+		g_code::parser::ImmediateCommand,
+		pauser, persisted_settings, print_history,
+		print_history::PrintOutcome,
+		print_process, print_queue,
+		time::SystemTime,
+		Peripherals,
 	},
-	components::{file_system::regions::metadata::FileId, pauser, time::SystemTime, Peripherals},
 };
 
 const SER_BUFFER_SIZE: usize = super::STACK_SIZE / 3;
@@ -57,44 +75,60 @@ pub fn list_files<C: Connection, P: Peripherals>(
 	let mut resources = get_resources(&resources)?;
 	let _ = check_security(&mut request, &mut resources)?;
 
-	let mut file_system = &mut resources.file_system;
-	let file_metadatas = Vec::from(file_system.get_existing_files_metadatas());
+	let directory = request.header("Directory").map(String::from);
 
 	#[derive(Serialize)]
 	#[serde(rename_all = "camelCase")]
-	struct HttpResponse
+	struct HttpResponse<'a>
 	{
-		files: Vec<File>,
+		files: Vec<File<'a>>,
+		directories: Vec<&'a str>,
 	}
 
 	#[derive(Serialize)]
 	#[serde(rename_all = "camelCase")]
-	struct File
+	struct File<'a>
 	{
-		name: String,
+		name: &'a str,
 		size_in_bytes: u32,
 		file_id: u32,
+		upload_time: Option<u64>,
+		last_print_time: Option<u64>,
+		print_count: u32,
+		sha256: Option<String>,
+		uploaded_bytes_count: Option<u32>,
 	}
-	let mut response_message = Vec::<File>::with_capacity(file_metadatas.len());
 
-	for file_metadata in file_metadatas
+	let (file_metadatas, directories) = match &directory
 	{
-		if let Ok(mut file_reader) = file_system.read_file(file_metadata.id)
+		Some(directory) =>
 		{
-			if let Ok(file_name) = file_reader.read_name(&mut file_system)
-			{
-				response_message.push(File {
-					name: file_name,
-					size_in_bytes: file_metadata.file_data_length + file_metadata.file_name_length,
-					file_id: u32::from_le_bytes(file_metadata.id.to_bytes()),
-				});
-			}
-		}
-	}
-
-	let response_message = HttpResponse {
-		files: response_message,
+			let directory_content = resources.file_system.list_directory(directory);
+			(directory_content.files, directory_content.directories)
+		},
+		None => (
+			resources.file_system.get_existing_files_metadatas().iter().collect(),
+			Vec::new(),
+		),
 	};
+	let files = file_metadatas
+		.into_iter()
+		.filter(|file_metadata| !is_reserved_file_name(&file_metadata.name))
+		.map(|file_metadata| File {
+			name: &file_metadata.name,
+			size_in_bytes: file_metadata.file_data_length,
+			file_id: u32::from_le_bytes(file_metadata.id.to_bytes()),
+			upload_time: file_metadata.upload_time,
+			last_print_time: file_metadata.last_print_time,
+			print_count: file_metadata.print_count,
+			sha256: file_metadata
+				.content_sha256
+				.map(|sha256| sha256.iter().map(|byte| format!("{:02x}", byte)).collect()),
+			uploaded_bytes_count: file_metadata.partial_data_length,
+		})
+		.collect();
+
+	let response_message = HttpResponse { files, directories };
 
 	let mut response = ok_response(request)?;
 	send_response!(
@@ -113,7 +147,11 @@ pub fn options_list_files<C: Connection, P: Peripherals>(
 	request: Request<&mut C>, _: Resources<P>,
 ) -> Result<(), HandlerError>
 {
-	options_callback(request, "list-files", "")
+	options_callback(
+		request,
+		"list-files",
+		", Directory, File-Name, Upload-Time, Content-Range, Content-SHA256, Content-CRC32",
+	)
 }
 
 pub fn delete_file<C: Connection, P: Peripherals>(
@@ -125,8 +163,6 @@ pub fn delete_file<C: Connection, P: Peripherals>(
 	let mut resources = get_resources(&resources)?;
 	let _ = check_security(&mut request, &mut resources)?;
 
-	let file_system = &mut resources.file_system;
-
 	#[derive(Deserialize)]
 	#[serde(rename_all = "camelCase")]
 	struct HttpRequest
@@ -135,21 +171,990 @@ pub fn delete_file<C: Connection, P: Peripherals>(
 	}
 	let request = deserialize_request!(BUFFER_SIZE = 100, CALLBACK = "delete_file", HttpRequest, request);
 	let file_id = FileId::from_bytes(request.file_id.to_le_bytes());
+	check_file_isnt_reserved(&resources, file_id)?;
 
-	file_system
+	resources
+		.file_system
 		.delete_file(file_id)
 		.map_err(|_| HandlerError::new("Unable to delete a file from the file system"))?;
+	if resources.print_queue.remove_file(file_id)
+	{
+		store_print_queue(&mut resources)?;
+	}
+
+	log::info!("Successfully handled `delete-file` HTTP request");
+
+	Ok(())
+}
+
+pub fn rename_file<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `rename-file` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	#[derive(Deserialize)]
+	#[serde(rename_all = "camelCase")]
+	struct HttpRequest
+	{
+		file_id: u32,
+		new_name: UnescapedString,
+	}
+	let request = deserialize_request!(BUFFER_SIZE = 400, CALLBACK = "rename_file", HttpRequest, request);
+	let file_id = FileId::from_bytes(request.file_id.to_le_bytes());
+	check_file_isnt_reserved(&resources, file_id)?;
+
+	if is_reserved_file_name(&request.new_name.0)
+	{
+		return Err(HandlerError::new("The new name of the file is reserved"));
+	}
+
+	resources
+		.file_system
+		.rename_file(file_id, request.new_name.0)
+		.map_err(|error| match error
+		{
+			UpdateFileError::InvalidName => HandlerError::new("The new name of the file is not valid"),
+			UpdateFileError::AlreadyExists =>
+			{
+				HandlerError::new("A file or a directory with the new name already exists")
+			},
+			_ => HandlerError::new("Unable to rename the file"),
+		})?;
+
+	log::info!("Successfully handled `rename-file` HTTP request");
+
+	Ok(())
+}
+
+pub fn compact_files<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `compact-files` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	resources
+		.file_system
+		.compact()
+		.map_err(|_| HandlerError::new("Unable to compact the file system"))?;
+
+	log::info!("Successfully handled `compact-files` HTTP request");
+
+	Ok(())
+}
+
+pub fn files_wear_statistics<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `files-wear-statistics` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	let wear_statistics = resources.file_system.get_wear_statistics();
+	let ecc_counters = resources.file_system.get_ecc_counters();
+
+	#[derive(Serialize)]
+	#[serde(rename_all = "camelCase")]
+	struct HttpResponse
+	{
+		blocks_count: u16,
+		bad_blocks_count: u16,
+		min_erase_count: u32,
+		max_erase_count: u32,
+		average_erase_count: f32,
+		most_erased_block_index: u16,
+		total_erase_count: u64,
+		read_pages_count: u64,
+		corrected_pages_count: u32,
+		uncorrectable_pages_count: u32,
+	}
+	let response_message = HttpResponse {
+		blocks_count: wear_statistics.blocks_count,
+		bad_blocks_count: resources.file_system.get_bad_blocks_count(),
+		min_erase_count: wear_statistics.min_erase_count,
+		max_erase_count: wear_statistics.max_erase_count,
+		average_erase_count: wear_statistics.get_average_erase_count(),
+		most_erased_block_index: wear_statistics.most_erased_block_index,
+		total_erase_count: wear_statistics.total_erase_count,
+		read_pages_count: ecc_counters.read_pages_count,
+		corrected_pages_count: ecc_counters.corrected_pages_count,
+		uncorrectable_pages_count: ecc_counters.uncorrectable_pages_count,
+	};
+
+	let mut response = ok_response(request)?;
+	send_response!(
+		BUFFER_SIZE = 400,
+		CALLBACK = "files_wear_statistics",
+		response_message,
+		response
+	);
+
+	log::info!("Successfully handled `files-wear-statistics` HTTP request");
+
+	Ok(())
+}
+
+pub fn options_files_wear_statistics<C: Connection, P: Peripherals>(
+	request: Request<&mut C>, _: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	options_callback(request, "files-wear-statistics", "")
+}
+
+pub fn download_file<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `download-file` HTTP request");
+
+	let mut locked_resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut locked_resources)?;
+
+	// The URI is `/v1/files/{fileId}/content`
+	let file_id = request
+		.uri()
+		.split('?')
+		.next()
+		.and_then(|path| path.strip_prefix("/v1/files/"))
+		.and_then(|path| path.strip_suffix("/content"))
+		.and_then(|file_id| file_id.parse::<u32>().ok())
+		.ok_or(HandlerError::new(
+			"The URI of the request isn't `/v1/files/{fileId}/content`",
+		))?;
+	let file_id = FileId::from_bytes(file_id.to_le_bytes());
+	check_file_isnt_reserved(&locked_resources, file_id)?;
+
+	let file_metadata = locked_resources
+		.file_system
+		.get_existing_files_metadatas()
+		.iter()
+		.find(|file_metadata| file_metadata.id == file_id)
+		.cloned()
+		.ok_or(HandlerError::new("The file doesn't exist"))?;
+	let mut file_reader = locked_resources
+		.file_system
+		.read_file(file_id)
+		.map_err(|_| HandlerError::new("The upload of the file hasn't been completed"))?;
+
+	let file_length = file_metadata.file_data_length;
+	let content_disposition = format!(
+		"attachment; filename=\"{}\"",
+		path::get_file_name(&file_metadata.name).replace(['"', '\\'], "_")
+	);
+	let range = match request.header("Range")
+	{
+		Some(range) => match parse_range(range, file_length)
+		{
+			Some(range) => Some(range),
+			None =>
+			{
+				drop(locked_resources);
+				let content_range = format!("bytes */{}", file_length);
+				let mut response = request.into_response(
+					416,
+					Some("Range Not Satisfiable"),
+					&[("Access-Control-Allow-Origin", "*"), ("Content-Range", &content_range)],
+				)?;
+				response.flush()?;
+
+				return Ok(());
+			},
+		},
+		None => None,
+	};
+	let (first_byte_index, last_byte_index) = range.unwrap_or((0, file_length.saturating_sub(1)));
+	let content_length = match file_length
+	{
+		0 => 0,
+		_ => last_byte_index - first_byte_index + 1,
+	};
+
+	let content_length_header = content_length.to_string();
+	let content_range_header = format!("bytes {}-{}/{}", first_byte_index, last_byte_index, file_length);
+	let mut headers = vec![
+		("Access-Control-Allow-Origin", "*"),
+		("Access-Control-Expose-Headers", "Content-Disposition, Content-Range"),
+		("Content-Type", "application/octet-stream"),
+		("Content-Disposition", content_disposition.as_str()),
+		("Content-Length", content_length_header.as_str()),
+		("Accept-Ranges", "bytes"),
+	];
+	let (status, message) = match range
+	{
+		Some(_) =>
+		{
+			headers.push(("Content-Range", content_range_header.as_str()));
+			(206, "Partial Content")
+		},
+		None => (200, "OK"),
+	};
+	// Sending the file may take a while, so the resources are locked only while reading each chunk of it (otherwise
+	// the print process couldn't run in the meantime)
+	drop(locked_resources);
+	let mut response = request.into_response(status, Some(message), &headers)?;
+
+	file_reader.seek(first_byte_index);
+	let mut buffer = [0; SER_BUFFER_SIZE];
+	let mut remaining_bytes_count = content_length;
+	while remaining_bytes_count > 0
+	{
+		let chunk_length = (remaining_bytes_count as usize).min(buffer.len());
+		let read_bytes_count = file_reader
+			.read_data(&mut resources.lock().file_system, &mut buffer[..chunk_length])
+			.map_err(|_| HandlerError::new("Unable to read the file"))?;
+		if read_bytes_count == 0
+		{
+			return Err(HandlerError::new("The file is shorter than expected"));
+		}
+		response
+			.write_all(&buffer[..read_bytes_count as usize])
+			.map_err(|_| HandlerError::new("`download_file` couldn't write the response"))?;
+		remaining_bytes_count -= read_bytes_count;
+	}
+	response.flush()?;
+
+	log::info!("Successfully handled `download-file` HTTP request");
+
+	Ok(())
+}
+
+pub fn options_download_file<C: Connection, P: Peripherals>(
+	request: Request<&mut C>, _: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	options_callback(request, "download-file", ", Range")
+}
+
+pub fn print_file<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `print-file` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	#[derive(Deserialize)]
+	#[serde(rename_all = "camelCase")]
+	struct HttpRequest
+	{
+		file_id: u32,
+		#[serde(default)]
+		unix_time: Option<u64>,
+	}
+	let request = deserialize_request!(BUFFER_SIZE = 100, CALLBACK = "print_file", HttpRequest, request);
+	let file_id = FileId::from_bytes(request.file_id.to_le_bytes());
+	check_file_isnt_reserved(&resources, file_id)?;
+
+	resources
+		.start_print(file_id, request.unix_time)
+		.map_err(start_print_error_to_handler_error)?;
+
+	log::info!("Successfully handled `print-file` HTTP request");
+
+	Ok(())
+}
+
+pub fn send_file<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `send-file` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	let file_name = request
+		.header("File-Name")
+		.ok_or(HandlerError::new("The request doesn't have a `File-Name` header"))?
+		.to_string();
+	if is_reserved_file_name(&file_name)
+	{
+		return Err(HandlerError::new("The name of the file is reserved"));
+	}
+	let content_length = request
+		.header("Content-Length")
+		.ok_or(HandlerError::new("The request doesn't have a `Content-Length` header"))?;
+	let content_length = content_length
+		.parse::<u32>()
+		.map_err(|_| HandlerError::new("The `Content-Length` header is not a valid number"))?;
+	let upload_time = request
+		.header("Upload-Time")
+		.map(|upload_time| upload_time.parse::<u64>())
+		.transpose()
+		.map_err(|_| HandlerError::new("The `Upload-Time` header is not a valid number"))?;
+	let expected_checksum = match (request.header("Content-SHA256"), request.header("Content-CRC32"))
+	{
+		(Some(sha256), _) => Some(ExpectedChecksum::Sha256(parse_hex_bytes(sha256).ok_or(
+			HandlerError::new("The `Content-SHA256` header is not a valid SHA-256 hash"),
+		)?)),
+		(None, Some(crc32)) => Some(ExpectedChecksum::Crc32(
+			u32::from_str_radix(crc32, 16)
+				.map_err(|_| HandlerError::new("The `Content-CRC32` header is not a valid CRC-32"))?,
+		)),
+		(None, None) => None,
+	};
+	// The `Content-Range` header is used to upload the file in more requests (for example to resume an interrupted upload)
+	let (first_byte_index, file_length) = match request.header("Content-Range")
+	{
+		Some(content_range) =>
+		{
+			let (first_byte_index, _, file_length) = parse_content_range(content_range)
+				.filter(|&(first_byte_index, last_byte_index, file_length)| {
+					first_byte_index <= last_byte_index
+						&& last_byte_index < file_length
+						&& last_byte_index - first_byte_index + 1 == content_length
+				})
+				.ok_or(HandlerError::new("The `Content-Range` header is not valid"))?;
+			(first_byte_index, file_length)
+		},
+		None => (0, content_length),
+	};
+
+	log::info!(
+		"Receive bytes {}..{} of file `{}` of {} bytes",
+		first_byte_index,
+		first_byte_index + content_length,
+		file_name,
+		file_length
+	);
+
+	let mut file_writer = if first_byte_index == 0
+	{
+		// A new upload of a file replaces its previous interrupted uploads
+		let partial_files_ids: Vec<_> = resources
+			.file_system
+			.get_existing_files_metadatas()
+			.iter()
+			.filter(|file_metadata| file_metadata.is_partial() && file_metadata.name == file_name)
+			.map(|file_metadata| file_metadata.id)
+			.collect();
+		for file_id in partial_files_ids
+		{
+			resources
+				.file_system
+				.delete_file(file_id)
+				.map_err(|_| HandlerError::new("Unable to delete a previous upload of the file"))?;
+		}
+
+		resources
+			.file_system
+			.create_file(file_name, file_length)
+			.map_err(|error| match error
+			{
+				CreateFileError::InvalidName => HandlerError::new("The name of the file is not valid"),
+				CreateFileError::AlreadyExists =>
+				{
+					HandlerError::new("A file or a directory with this name already exists")
+				},
+				CreateFileError::NotEnoughSpaceAvailable =>
+				{
+					HandlerError::new("Not enough space available in the flash memory")
+				},
+				CreateFileError::Compact(StoreMetadatasError::DoesntFitInBlock) =>
+				{
+					HandlerError::new("There are too many files, delete some of them")
+				},
+				CreateFileError::Compact(StoreMetadatasError::Spi(_)) =>
+				{
+					HandlerError::new("Unable to compact the files to make room for the file")
+				},
+			})?
+	}
+	else
+	{
+		let partial_file = resources
+			.file_system
+			.get_existing_files_metadatas()
+			.iter()
+			.find(|file_metadata| {
+				file_metadata.is_partial()
+					&& file_metadata.name == file_name
+					&& file_metadata.file_data_length == file_length
+			})
+			.ok_or(HandlerError::new("There's no interrupted upload of the file to resume"))?;
+		if partial_file.partial_data_length != Some(first_byte_index)
+		{
+			return Err(HandlerError::new(&format!(
+				"Resume the upload of the file from the byte {}",
+				partial_file.partial_data_length.unwrap_or_default()
+			)));
+		}
+
+		let partial_file_id = partial_file.id;
+		resources
+			.file_system
+			.resume_writing(partial_file_id)
+			.map_err(|_| HandlerError::new("Unable to resume the upload of the file"))?
+	};
+	if let Some(upload_time) = upload_time
+	{
+		file_writer.set_upload_time(upload_time);
+	}
+	if let Some(expected_checksum) = expected_checksum
+	{
+		file_writer.set_expected_checksum(expected_checksum);
+	}
+
+	let mut buffer = [0; super::STACK_SIZE];
+	let mut received_bytes_count = 0;
+	while received_bytes_count < content_length
+	{
+		let read_bytes = match request.read(&mut buffer)
+		{
+			Ok(0) | Err(_) => break,
+			Ok(read_bytes) => read_bytes.min((content_length - received_bytes_count) as usize),
+		};
+
+		if let Err(error) = file_writer.write_data(&mut resources.file_system, &buffer[0..read_bytes])
+		{
+			let _ = file_writer.discard(&mut resources.file_system);
+			return Err(HandlerError::new(&format!("{:#?}", error)));
+		}
+		received_bytes_count += read_bytes as u32;
+	}
+
+	let written_data_length = file_writer.get_written_data_length();
+	if written_data_length < file_length
+	{
+		// Keep the received data so that the upload can be resumed with another request
+		file_writer
+			.suspend_writing(&mut resources.file_system)
+			.map_err(|error| HandlerError::new(&format!("{:#?}", error)))?;
+
+		if received_bytes_count < content_length
+		{
+			return Err(HandlerError::new(&format!(
+				"Upload interrupted, resume it from the byte {}",
+				written_data_length
+			)));
+		}
+	}
+	else
+	{
+		file_writer
+			.finish_writing(&mut resources.file_system)
+			.map_err(|error| HandlerError::new(&format!("{:#?}", error)))?;
+	}
+
+	log::info!("Successfully handled `send-file` HTTP request");
+
+	Ok(())
+}
+
+pub fn get_print_status<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `get-print-status` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	#[derive(Serialize)]
+	#[serde(rename_all = "camelCase")]
+	struct HttpResponse
+	{
+		is_printing: bool,
+		file_name_being_printed: String,
+		print_duration_in_seconds: i32,
+		time_printed_in_seconds: i32,
+		is_paused: bool,
+		is_waiting_for_filament: bool,
+	}
+
+	let response_message = match resources.print_process.get_file_being_printed()
+	{
+		Some(file_id_being_printed) =>
+		{
+			let file_name_being_printed = resources
+				.file_system
+				.get_existing_files_metadatas()
+				.iter()
+				.find(|file_metadata| file_metadata.id == file_id_being_printed)
+				.map(|file_metadata| file_metadata.name.clone())
+				.ok_or(HandlerError::new("The file being printed doesn't exist"))?;
+
+			let time_printed_in_seconds = resources.print_process.get_print_start_time().and_then(|start_time| {
+				resources
+					.system_time
+					.as_ref()
+					.and_then(|system_time| Some((system_time.now() - start_time).as_secs() as i32))
+			});
+
+			HttpResponse {
+				is_printing: true,
+				file_name_being_printed,
+				print_duration_in_seconds: resources
+					.print_process
+					.get_print_estimated_duration_in_seconds()
+					.map(|value| value as i32)
+					.unwrap_or(-1),
+				time_printed_in_seconds: time_printed_in_seconds.unwrap_or(-1),
+				is_paused: pauser::is_paused(),
+				is_waiting_for_filament: filament_change::is_waiting_for_confirmation(),
+			}
+		},
+		None => HttpResponse {
+			is_printing: false,
+			file_name_being_printed: String::new(),
+			print_duration_in_seconds: -1,
+			time_printed_in_seconds: -1,
+			is_paused: false,
+			is_waiting_for_filament: filament_change::is_waiting_for_confirmation(),
+		},
+	};
+
+	let mut response = ok_response(request)?;
+	send_response!(
+		BUFFER_SIZE = SER_BUFFER_SIZE,
+		CALLBACK = "get_print_status",
+		response_message,
+		response
+	);
+
+	log::info!("Successfully handled `get-print-status` HTTP request");
+
+	Ok(())
+}
+
+pub fn options_get_print_status<C: Connection, P: Peripherals>(
+	request: Request<&mut C>, _: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	options_callback(request, "print-status", "")
+}
+
+pub fn pause_or_resume<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `pause-or-resume` HTTP request");
+
+	let _ = check_security(&mut request, &mut get_resources(&resources)?)?;
+
+	pauser::toggle_pause();
+
+	let mut response = ok_response(request)?;
+	response.flush()?;
+
+	log::info!("Successfully handled `pause-or-resume` HTTP request");
+
+	Ok(())
+}
+
+pub fn cancel_print<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `cancel-print` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	resources.cancel_print().map_err(|error| match error
+	{
+		CancelPrintError::NotPrinting => HandlerError::new("No file is being printed"),
+		CancelPrintError::Send(_) => HandlerError::new("Unable to stop the machine"),
+		CancelPrintError::StorePrintHistory(_) => HandlerError::new("Unable to store the print history"),
+	})?;
+
+	let mut response = ok_response(request)?;
+	response.flush()?;
+
+	log::info!("Successfully handled `cancel-print` HTTP request");
+
+	Ok(())
+}
+
+pub fn confirm_filament_change<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `confirm-filament-change` HTTP request");
+
+	let _ = check_security(&mut request, &mut get_resources(&resources)?)?;
+
+	if !filament_change::confirm()
+	{
+		return Err(HandlerError::new("No filament change is waiting for confirmation"));
+	}
+
+	let mut response = ok_response(request)?;
+	response.flush()?;
+
+	log::info!("Successfully handled `confirm-filament-change` HTTP request");
+
+	Ok(())
+}
+
+pub fn emergency_stop<C: Connection, P: Peripherals>(
+	request: Request<&mut C>, _: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `emergency-stop` HTTP request");
+
+	// It's not sent to the `Components` thread, so that it's not delayed by the commands it's executing, and it doesn't
+	// lock the resources to check the security, so that it's not rejected while another request is being handled
+	emergency_stop::stop();
+
+	let mut response = ok_response(request)?;
+	response.flush()?;
+
+	log::info!("Successfully handled `emergency-stop` HTTP request");
+
+	Ok(())
+}
+
+pub fn reset_after_emergency_stop<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `reset-after-emergency-stop` HTTP request");
+
+	let _ = check_security(&mut request, &mut get_resources(&resources)?)?;
+
+	if !emergency_stop::request_reset()
+	{
+		return Err(HandlerError::new("The machine hasn't been halted by an emergency stop"));
+	}
+
+	let mut response = ok_response(request)?;
+	response.flush()?;
+
+	log::info!("Successfully handled `reset-after-emergency-stop` HTTP request");
+
+	Ok(())
+}
+
+pub fn get_interrupted_print<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `get-interrupted-print` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	#[derive(Serialize)]
+	#[serde(rename_all = "camelCase")]
+	struct HttpResponse
+	{
+		has_interrupted_print: bool,
+		file_id: u32,
+		file_name: String,
+		printed_bytes_count: u32,
+		file_size_in_bytes: u32,
+		z_in_millimeters: f32,
+	}
+
+	let response_message = match resources.interrupted_print.as_ref()
+	{
+		Some(checkpoint) =>
+		{
+			let file_metadata = resources
+				.file_system
+				.get_existing_files_metadatas()
+				.iter()
+				.find(|file_metadata| file_metadata.id == checkpoint.file_id)
+				.ok_or(HandlerError::new("The file of the interrupted print doesn't exist"))?;
+
+			HttpResponse {
+				has_interrupted_print: true,
+				file_id: u32::from_le_bytes(checkpoint.file_id.to_bytes()),
+				file_name: file_metadata.name.clone(),
+				printed_bytes_count: checkpoint.file_offset,
+				file_size_in_bytes: file_metadata.file_data_length,
+				z_in_millimeters: checkpoint.position[2].as_millimeters_f32(),
+			}
+		},
+		None => HttpResponse {
+			has_interrupted_print: false,
+			file_id: 0,
+			file_name: String::new(),
+			printed_bytes_count: 0,
+			file_size_in_bytes: 0,
+			z_in_millimeters: 0.,
+		},
+	};
+
+	let mut response = ok_response(request)?;
+	send_response!(
+		BUFFER_SIZE = SER_BUFFER_SIZE,
+		CALLBACK = "get_interrupted_print",
+		response_message,
+		response
+	);
+
+	log::info!("Successfully handled `get-interrupted-print` HTTP request");
+
+	Ok(())
+}
+
+pub fn options_get_interrupted_print<C: Connection, P: Peripherals>(
+	request: Request<&mut C>, _: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	options_callback(request, "interrupted-print", "")
+}
+
+pub fn resume_interrupted_print<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `resume-interrupted-print` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	if resources.print_process.is_printing()
+	{
+		return Err(HandlerError::new("A file is already being printed"));
+	}
+	let checkpoint = resources
+		.interrupted_print
+		.clone()
+		.ok_or(HandlerError::new("There isn't an interrupted print to resume"))?;
+	if resources.file_system.read_file(checkpoint.file_id).is_err()
+	{
+		return Err(HandlerError::new("The file of the interrupted print doesn't exist"));
+	}
+
+	let resume_g_code = checkpoint.resume_g_code();
+	let mut commands = Vec::new();
+	for line in resume_g_code.lines()
+	{
+		let parsed_line = resources
+			.print_process
+			.parse_line_to_execute(line)
+			.map_err(|_| HandlerError::new("Invalid G-code to resume the print"))?;
+		if let Some(command) = parsed_line.command
+		{
+			commands.push(command);
+		}
+	}
+	resources
+		.command_sender
+		.send_command(Command::AddGCodeCommandsToBuffer(commands))
+		.map_err(|_| HandlerError::new("Coudln't send a Command::AddGCodeCommandsToBuffer"))?;
+
+	let current_time = resources.system_time.as_ref().map(|time| time.now());
+	resources
+		.print_process
+		.resume_file(checkpoint.file_id, checkpoint.file_offset, current_time);
+	resources.start_print_job(checkpoint.file_id, None);
+	resources.interrupted_print = None;
+
+	log::info!("Successfully handled `resume-interrupted-print` HTTP request");
+
+	Ok(())
+}
+
+pub fn discard_interrupted_print<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `discard-interrupted-print` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	if resources.interrupted_print.take().is_some()
+	{
+		resources
+			.file_system
+			.clear_checkpoint()
+			.map_err(|_| HandlerError::new("Unable to discard the interrupted print"))?;
+	}
+
+	log::info!("Successfully handled `discard-interrupted-print` HTTP request");
+
+	Ok(())
+}
+
+pub fn get_print_queue<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `get-print-queue` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	#[derive(Serialize)]
+	#[serde(rename_all = "camelCase")]
+	struct HttpResponse<'a>
+	{
+		files: Vec<QueuedFile<'a>>,
+		wait_for_bed_clearing: bool,
+		is_waiting_for_bed_clearing: bool,
+	}
+
+	#[derive(Serialize)]
+	#[serde(rename_all = "camelCase")]
+	struct QueuedFile<'a>
+	{
+		file_id: u32,
+		file_name: &'a str,
+	}
+
+	let files_metadatas = resources.file_system.get_existing_files_metadatas();
+	let files = resources
+		.print_queue
+		.get_files_ids()
+		.iter()
+		.map(|&file_id| QueuedFile {
+			file_id: u32::from_le_bytes(file_id.to_bytes()),
+			file_name: files_metadatas
+				.iter()
+				.find(|file_metadata| file_metadata.id == file_id)
+				.map_or("", |file_metadata| &file_metadata.name),
+		})
+		.collect();
+	let response_message = HttpResponse {
+		files,
+		wait_for_bed_clearing: resources.print_queue.wait_for_bed_clearing,
+		is_waiting_for_bed_clearing: resources.print_queue.is_waiting_for_bed_clearing,
+	};
+
+	let mut response = ok_response(request)?;
+	send_response!(
+		BUFFER_SIZE = SER_BUFFER_SIZE,
+		CALLBACK = "get_print_queue",
+		response_message,
+		response
+	);
+
+	log::info!("Successfully handled `get-print-queue` HTTP request");
+
+	Ok(())
+}
+
+pub fn options_get_print_queue<C: Connection, P: Peripherals>(
+	request: Request<&mut C>, _: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	options_callback(request, "print-queue", "")
+}
+
+pub fn add_to_print_queue<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `add-to-print-queue` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	#[derive(Deserialize)]
+	#[serde(rename_all = "camelCase")]
+	struct HttpRequest
+	{
+		file_id: u32,
+		#[serde(default)]
+		index: Option<u16>,
+	}
+	let request = deserialize_request!(BUFFER_SIZE = 100, CALLBACK = "add_to_print_queue", HttpRequest, request);
+	let file_id = FileId::from_bytes(request.file_id.to_le_bytes());
+
+	if resources.file_system.read_file(file_id).is_err()
+	{
+		return Err(HandlerError::new(
+			"The file doesn't exist or its upload hasn't been completed",
+		));
+	}
+	resources
+		.print_queue
+		.add(file_id, request.index.map(|index| index as usize));
+	store_print_queue(&mut resources)?;
+
+	log::info!("Successfully handled `add-to-print-queue` HTTP request");
+
+	Ok(())
+}
+
+pub fn remove_from_print_queue<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `remove-from-print-queue` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	#[derive(Deserialize)]
+	#[serde(rename_all = "camelCase")]
+	struct HttpRequest
+	{
+		index: u16,
+	}
+	let request = deserialize_request!(
+		BUFFER_SIZE = 100,
+		CALLBACK = "remove_from_print_queue",
+		HttpRequest,
+		request
+	);
+
+	resources
+		.print_queue
+		.remove(request.index as usize)
+		.ok_or(HandlerError::new("There isn't a file at that index of the print queue"))?;
+	store_print_queue(&mut resources)?;
+
+	log::info!("Successfully handled `remove-from-print-queue` HTTP request");
+
+	Ok(())
+}
+
+pub fn move_in_print_queue<C: Connection, P: Peripherals>(
+	mut request: Request<&mut C>, resources: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	log::info!("Start handling `move-in-print-queue` HTTP request");
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	#[derive(Deserialize)]
+	#[serde(rename_all = "camelCase")]
+	struct HttpRequest
+	{
+		from_index: u16,
+		to_index: u16,
+	}
+	let request = deserialize_request!(
+		BUFFER_SIZE = 100,
+		CALLBACK = "move_in_print_queue",
+		HttpRequest,
+		request
+	);
+
+	if !resources
+		.print_queue
+		.move_file(request.from_index as usize, request.to_index as usize)
+	{
+		return Err(HandlerError::new("There isn't a file at that index of the print queue"));
+	}
+	store_print_queue(&mut resources)?;
 
-	log::info!("Successfully handled `delete-file` HTTP request");
+	log::info!("Successfully handled `move-in-print-queue` HTTP request");
 
 	Ok(())
 }
 
-pub fn print_file<C: Connection, P: Peripherals>(
+pub fn set_print_queue_settings<C: Connection, P: Peripherals>(
 	mut request: Request<&mut C>, resources: Resources<P>,
 ) -> Result<(), HandlerError>
 {
-	log::info!("Start handling `print-file` HTTP request");
+	log::info!("Start handling `set-print-queue-settings` HTTP request");
 
 	let mut resources = get_resources(&resources)?;
 	let _ = check_security(&mut request, &mut resources)?;
@@ -158,165 +1163,175 @@ pub fn print_file<C: Connection, P: Peripherals>(
 	#[serde(rename_all = "camelCase")]
 	struct HttpRequest
 	{
-		file_id: u32,
+		wait_for_bed_clearing: bool,
 	}
-	let request = deserialize_request!(BUFFER_SIZE = 100, CALLBACK = "print_file", HttpRequest, request);
-	let file_id = FileId::from_bytes(request.file_id.to_le_bytes());
+	let request = deserialize_request!(
+		BUFFER_SIZE = 100,
+		CALLBACK = "set_print_queue_settings",
+		HttpRequest,
+		request
+	);
 
-	let current_time = resources.system_time.as_ref().map(|time| time.now());
-	resources.print_process.print_file(file_id, current_time);
+	resources.print_queue.wait_for_bed_clearing = request.wait_for_bed_clearing;
+	store_print_queue(&mut resources)?;
 
-	log::info!("Successfully handled `print-file` HTTP request");
+	log::info!("Successfully handled `set-print-queue-settings` HTTP request");
 
 	Ok(())
 }
 
-pub fn send_file<C: Connection, P: Peripherals>(
+pub fn start_print_queue<C: Connection, P: Peripherals>(
 	mut request: Request<&mut C>, resources: Resources<P>,
 ) -> Result<(), HandlerError>
 {
-	log::info!("Start handling `send-file` HTTP request");
+	log::info!("Start handling `start-print-queue` HTTP request");
 
 	let mut resources = get_resources(&resources)?;
 	let _ = check_security(&mut request, &mut resources)?;
 
-	let file_name = request
-		.header("File-Name")
-		.ok_or(HandlerError::new("The request doesn't have a `File-Name` header"))?;
-	let file_length = request
-		.header("Content-Length")
-		.ok_or(HandlerError::new("The request doesn't have a `Content-Length` header"))?;
-	let file_length = file_length
-		.parse::<u32>()
-		.map_err(|_| HandlerError::new("The `Content-Length` header is not a valid number"))?;
-
-	log::info!("Receive file `{}` of {} bytes", file_name, file_length);
-
-	let mut file_writer = resources
-		.file_system
-		.create_file(file_name, file_length)
-		.map_err(|_| HandlerError::new("Not enough space available in the flash memory"))?;
-
-	let mut buffer = [0; super::STACK_SIZE];
-	while let Ok(read_bytes) = request.read(&mut buffer)
+	#[derive(Deserialize)]
+	#[serde(rename_all = "camelCase")]
+	struct HttpRequest
 	{
-		if read_bytes == 0
-		{
-			break;
-		}
-
-		file_writer
-			.write_data(&mut resources.file_system, &buffer[0..read_bytes])
-			.map_err(|error| HandlerError::new(&format!("{:#?}", error)))?;
+		#[serde(default)]
+		unix_time: Option<u64>,
 	}
+	let request = deserialize_request!(BUFFER_SIZE = 100, CALLBACK = "start_print_queue", HttpRequest, request);
 
-	file_writer
-		.finish_writing(&mut resources.file_system)
-		.map_err(|error| HandlerError::new(&format!("{:#?}", error)))?;
+	resources
+		.start_next_queued_print(request.unix_time)
+		.map_err(start_print_error_to_handler_error)?;
 
-	log::info!("Successfully handled `send-file` HTTP request");
+	log::info!("Successfully handled `start-print-queue` HTTP request");
 
 	Ok(())
 }
 
-pub fn get_print_status<C: Connection, P: Peripherals>(
+pub fn get_print_history<C: Connection, P: Peripherals>(
 	mut request: Request<&mut C>, resources: Resources<P>,
 ) -> Result<(), HandlerError>
 {
-	log::info!("Start handling `get-print-status` HTTP request");
+	log::info!("Start handling `get-print-history` HTTP request");
 
 	let mut resources = get_resources(&resources)?;
 	let _ = check_security(&mut request, &mut resources)?;
 
 	#[derive(Serialize)]
 	#[serde(rename_all = "camelCase")]
-	struct HttpResponse
+	struct HttpResponse<'a>
 	{
-		is_printing: bool,
-		file_name_being_printed: String,
-		print_duration_in_seconds: i32,
-		time_printed_in_seconds: i32,
-		is_paused: bool,
+		prints: Vec<Print<'a>>,
 	}
 
-	let response_message = match resources.print_process.get_file_being_printed()
+	#[derive(Serialize)]
+	#[serde(rename_all = "camelCase")]
+	struct Print<'a>
 	{
-		Some(file_id_being_printed) =>
-		{
-			let mut file_being_printed = resources
-				.file_system
-				.read_file(file_id_being_printed)
-				.map_err(|_| HandlerError::new("Couldn't open the file for read"))?;
-			let file_name_being_printed = file_being_printed
-				.read_name(&mut resources.file_system)
-				.map_err(|_| HandlerError::new("Couldn't read the name of the file being printed"))?;
-
-			let time_printed_in_seconds = resources.print_process.get_print_start_time().and_then(|start_time| {
-				resources
-					.system_time
-					.as_ref()
-					.and_then(|system_time| Some((system_time.now() - start_time).as_secs() as i32))
-			});
+		file_name: &'a str,
+		start_unix_time: Option<u64>,
+		end_unix_time: Option<u64>,
+		duration_in_seconds: u32,
+		outcome: &'static str,
+		error: Option<&'a str>,
+		filament_used_in_millimeters: f32,
+	}
 
-			HttpResponse {
-				is_printing: true,
-				file_name_being_printed,
-				print_duration_in_seconds: resources
-					.print_process
-					.get_print_estimated_duration_in_seconds()
-					.map(|value| value as i32)
-					.unwrap_or(-1),
-				time_printed_in_seconds: time_printed_in_seconds.unwrap_or(-1),
-				is_paused: pauser::is_paused(),
-			}
-		},
-		None => HttpResponse {
-			is_printing: false,
-			file_name_being_printed: String::new(),
-			print_duration_in_seconds: -1,
-			time_printed_in_seconds: -1,
-			is_paused: false,
-		},
-	};
+	let prints = resources
+		.print_history
+		.get_records()
+		.rev()
+		.map(|print_record| Print {
+			file_name: &print_record.file_name,
+			start_unix_time: print_record.start_unix_time,
+			end_unix_time: print_record.get_end_unix_time(),
+			duration_in_seconds: print_record.duration_in_seconds,
+			outcome: match print_record.outcome
+			{
+				PrintOutcome::Completed => "completed",
+				PrintOutcome::Cancelled => "cancelled",
+				PrintOutcome::Failed(_) => "failed",
+			},
+			error: match &print_record.outcome
+			{
+				PrintOutcome::Failed(error) => Some(error.as_str()),
+				_ => None,
+			},
+			filament_used_in_millimeters: print_record.filament_used_in_millimeters,
+		})
+		.collect();
+	let response_message = HttpResponse { prints };
 
 	let mut response = ok_response(request)?;
 	send_response!(
 		BUFFER_SIZE = SER_BUFFER_SIZE,
-		CALLBACK = "get_print_status",
+		CALLBACK = "get_print_history",
 		response_message,
 		response
 	);
 
-	log::info!("Successfully handled `get-print-status` HTTP request");
+	log::info!("Successfully handled `get-print-history` HTTP request");
 
 	Ok(())
 }
 
-pub fn options_get_print_status<C: Connection, P: Peripherals>(
+pub fn options_get_print_history<C: Connection, P: Peripherals>(
 	request: Request<&mut C>, _: Resources<P>,
 ) -> Result<(), HandlerError>
 {
-	options_callback(request, "print-status", "")
+	options_callback(request, "print-history", "")
 }
 
-pub fn pause_or_resume<C: Connection, P: Peripherals>(
+pub fn get_print_statistics<C: Connection, P: Peripherals>(
 	mut request: Request<&mut C>, resources: Resources<P>,
 ) -> Result<(), HandlerError>
 {
-	log::info!("Start handling `pause-or-resume` HTTP request");
+	log::info!("Start handling `get-print-statistics` HTTP request");
 
-	let _ = check_security(&mut request, &mut get_resources(&resources)?)?;
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
 
-	pauser::toggle_pause();
+	#[derive(Serialize)]
+	#[serde(rename_all = "camelCase")]
+	struct HttpResponse
+	{
+		print_hours: f32,
+		filament_used_in_meters: f32,
+		jobs_count: u32,
+		completed_jobs_count: u32,
+		cancelled_jobs_count: u32,
+		failed_jobs_count: u32,
+	}
+
+	let statistics = resources.print_history.get_statistics();
+	let response_message = HttpResponse {
+		print_hours: (statistics.print_duration_in_seconds as f64 / 3600.) as f32,
+		filament_used_in_meters: (statistics.filament_used_in_millimeters / 1000.) as f32,
+		jobs_count: statistics.get_jobs_count(),
+		completed_jobs_count: statistics.completed_jobs_count,
+		cancelled_jobs_count: statistics.cancelled_jobs_count,
+		failed_jobs_count: statistics.failed_jobs_count,
+	};
 
 	let mut response = ok_response(request)?;
-	response.flush()?;
+	send_response!(
+		BUFFER_SIZE = 300,
+		CALLBACK = "get_print_statistics",
+		response_message,
+		response
+	);
 
-	log::info!("Successfully handled `pause-or-resume` HTTP request");
+	log::info!("Successfully handled `get-print-statistics` HTTP request");
 
 	Ok(())
 }
 
+pub fn options_get_print_statistics<C: Connection, P: Peripherals>(
+	request: Request<&mut C>, _: Resources<P>,
+) -> Result<(), HandlerError>
+{
+	options_callback(request, "print-statistics", "")
+}
+
 pub fn printer_state<C: Connection, P: Peripherals>(
 	mut request: Request<&mut C>, resources: Resources<P>,
 ) -> Result<(), HandlerError>
@@ -471,25 +1486,45 @@ pub fn send_g_code_commands<C: Connection, P: Peripherals>(
 {
 	log::info!("Start handling `send-gcode-commands` HTTP request");
 
-	let mut resources = get_resources(&resources)?;
-	let _ = check_security(&mut request, &mut resources)?;
-
 	#[derive(Deserialize)]
 	#[serde(rename_all = "camelCase")]
 	struct HttpRequest
 	{
-		commands: String,
+		commands: UnescapedString,
 	}
-	let request = deserialize_request!(
+	let http_request = deserialize_request!(
 		BUFFER_SIZE = 2048,
 		CALLBACK = "send_g_code_commands",
 		HttpRequest,
 		request
 	);
-	let lines = request.commands.lines();
-	let mut commands = Vec::with_capacity(lines.clone().count());
-	for line in lines
+
+	// Like the `emergency-stop` HTTP request, it doesn't lock the resources to check the security, so that it's not
+	// rejected while another request is being handled (and the other commands are useless once the machine is halted)
+	let commands_lines = http_request.commands.0.lines();
+	if commands_lines
+		.clone()
+		.any(|line| ImmediateCommand::parse(line) == Some(ImmediateCommand::EmergencyStop))
+	{
+		emergency_stop::stop();
+
+		log::info!("Successfully handled `send-gcode-commands` HTTP request with an emergency stop");
+
+		return Ok(());
+	}
+
+	let mut resources = get_resources(&resources)?;
+	let _ = check_security(&mut request, &mut resources)?;
+
+	let mut commands = Vec::with_capacity(commands_lines.clone().count());
+	for line in commands_lines
 	{
+		if ImmediateCommand::parse(line) == Some(ImmediateCommand::CancelPrint)
+		{
+			print_process::request_cancel();
+			continue;
+		}
+
 		let parsed_line = resources
 			.print_process
 			.parse_line_to_execute(line)
@@ -510,6 +1545,153 @@ pub fn send_g_code_commands<C: Connection, P: Peripherals>(
 	Ok(())
 }
 
+/// Parses a `Content-Range` header like `bytes 1000-1999/5000`, returning the index of the first and of the last byte
+/// of the range and the length of the whole content.
+fn parse_content_range(content_range: &str) -> Option<(u32, u32, u32)>
+{
+	let (range, complete_length) = content_range.strip_prefix("bytes ")?.split_once('/')?;
+	let (first_byte_index, last_byte_index) = range.split_once('-')?;
+
+	Some((
+		first_byte_index.parse().ok()?,
+		last_byte_index.parse().ok()?,
+		complete_length.parse().ok()?,
+	))
+}
+
+/// Parses a `Range` header with a single range (like `bytes=1000-1999`, `bytes=1000-` or `bytes=-500`) of a content
+/// of `content_length` bytes, returning the index of the first and of the last byte of the range.
+///
+/// Returns `None` if the header isn't valid or if the range isn't satisfiable.
+fn parse_range(range: &str, content_length: u32) -> Option<(u32, u32)>
+{
+	let (first_byte_index, last_byte_index) = range.strip_prefix("bytes=")?.trim().split_once('-')?;
+	let last_index = content_length.checked_sub(1)?;
+
+	let (first_byte_index, last_byte_index) = match (first_byte_index, last_byte_index)
+	{
+		("", suffix_length) =>
+		{
+			let suffix_length = suffix_length.parse::<u32>().ok().filter(|&length| length > 0)?;
+			(content_length.saturating_sub(suffix_length), last_index)
+		},
+		(first_byte_index, "") => (first_byte_index.parse().ok()?, last_index),
+		(first_byte_index, last_byte_index) => (
+			first_byte_index.parse().ok()?,
+			last_byte_index.parse::<u32>().ok()?.min(last_index),
+		),
+	};
+
+	(first_byte_index <= last_byte_index).then_some((first_byte_index, last_byte_index))
+}
+
+/// Parses a string of `2 * N` hexadecimal digits to the `N` bytes it represents.
+fn parse_hex_bytes<const N: usize>(hex: &str) -> Option<[u8; N]>
+{
+	if hex.len() != 2 * N || !hex.is_ascii()
+	{
+		return None;
+	}
+
+	let mut bytes = [0; N];
+	for (index, byte) in bytes.iter_mut().enumerate()
+	{
+		*byte = u8::from_str_radix(&hex[2 * index..2 * index + 2], 16).ok()?;
+	}
+
+	Some(bytes)
+}
+
+/// A string of a JSON request, which is unescaped while it's deserialized (`serde_json_core` can only deserialize a
+/// [`str`] borrowed from the request, with its escape sequences).
+struct UnescapedString(String);
+impl<'de> Deserialize<'de> for UnescapedString
+{
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+	{
+		struct Visitor;
+		impl<'de> serde::de::Visitor<'de> for Visitor
+		{
+			type Value = UnescapedString;
+
+			fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+			{
+				formatter.write_str("a string")
+			}
+
+			fn visit_str<E: serde::de::Error>(self, string: &str) -> Result<Self::Value, E>
+			{
+				unescape_json_string(string)
+					.map(UnescapedString)
+					.ok_or_else(|| E::custom("invalid escape sequence"))
+			}
+		}
+
+		deserializer.deserialize_str(Visitor)
+	}
+}
+
+/// Replaces the escape sequences (like `\n` or `\u00e8`) of the content of a JSON string, returning `None` if one of
+/// them is invalid.
+fn unescape_json_string(string: &str) -> Option<String>
+{
+	let mut unescaped_string = String::with_capacity(string.len());
+	let mut chars = string.chars();
+	while let Some(char) = chars.next()
+	{
+		if char != '\\'
+		{
+			unescaped_string.push(char);
+			continue;
+		}
+
+		let unescaped_char = match chars.next()?
+		{
+			'"' => '"',
+			'\\' => '\\',
+			'/' => '/',
+			'b' => '\u{8}',
+			'f' => '\u{c}',
+			'n' => '\n',
+			'r' => '\r',
+			't' => '\t',
+			'u' =>
+			{
+				let mut code_units = vec![take_utf16_code_unit(&mut chars)?];
+				// A character outside the Basic Multilingual Plane is escaped as a surrogate pair
+				if (0xD800..0xDC00).contains(&code_units[0])
+				{
+					if chars.next()? != '\\' || chars.next()? != 'u'
+					{
+						return None;
+					}
+					code_units.push(take_utf16_code_unit(&mut chars)?);
+				}
+
+				char::decode_utf16(code_units).next()?.ok()?
+			},
+			_ => return None,
+		};
+		unescaped_string.push(unescaped_char);
+	}
+
+	Some(unescaped_string)
+}
+
+/// Takes the 4 hexadecimal digits of a `\uXXXX` escape sequence from `chars`.
+fn take_utf16_code_unit(chars: &mut std::str::Chars) -> Option<u16>
+{
+	let hex = chars.as_str().get(..4)?;
+	if !hex.chars().all(|char| char.is_ascii_hexdigit())
+	{
+		return None;
+	}
+	let code_unit = u16::from_str_radix(hex, 16).ok()?;
+	*chars = chars.as_str()[4..].chars();
+
+	Some(code_unit)
+}
+
 fn options_callback<C: Connection>(
 	request: Request<&mut C>, callback_name: &str, allowed_headers: &str,
 ) -> Result<(), HandlerError>
@@ -535,6 +1717,61 @@ fn options_callback<C: Connection>(
 	Ok(())
 }
 
+/// Returns `true` if a file with the provided `name` is used by the firmware itself (so it must be hidden to the user).
+fn is_reserved_file_name(name: &str) -> bool
+{
+	// The files are replaced by writing their new content in another file first
+	let name = name.strip_suffix(REPLACEMENT_FILE_SUFFIX).unwrap_or(name);
+
+	[
+		persisted_settings::FILE_NAME,
+		print_queue::FILE_NAME,
+		print_history::FILE_NAME,
+	]
+	.contains(&name)
+}
+
+/// Returns an error if the file with the provided `file_id` is used by the firmware itself (so the user can't access
+/// it).
+fn check_file_isnt_reserved<P: Peripherals>(resources: &ResourcesImpl<P>, file_id: FileId) -> Result<(), HandlerError>
+{
+	let is_reserved = resources
+		.file_system
+		.get_existing_files_metadatas()
+		.iter()
+		.any(|file_metadata| file_metadata.id == file_id && is_reserved_file_name(&file_metadata.name));
+
+	match is_reserved
+	{
+		true => Err(HandlerError::new("The file is reserved")),
+		false => Ok(()),
+	}
+}
+
+fn start_print_error_to_handler_error<Spi: SpiDevice<u8>>(error: StartPrintError<Spi>) -> HandlerError
+{
+	match error
+	{
+		StartPrintError::Halted => HandlerError::new("The machine has been halted by an emergency stop"),
+		StartPrintError::AlreadyPrinting => HandlerError::new("A file is already being printed"),
+		StartPrintError::FileDoesntExist =>
+		{
+			HandlerError::new("The file doesn't exist or its upload hasn't been completed")
+		},
+		StartPrintError::RecordPrint(_) => HandlerError::new("Unable to update the metadata of the file to print"),
+		StartPrintError::ClearInterruptedPrint(_) => HandlerError::new("Unable to discard the interrupted print"),
+		StartPrintError::EmptyQueue => HandlerError::new("The print queue is empty"),
+		StartPrintError::StorePrintQueue(_) => HandlerError::new("Unable to store the print queue"),
+	}
+}
+
+fn store_print_queue<P: Peripherals>(resources: &mut MutexGuard<'_, ResourcesImpl<P>>) -> Result<(), HandlerError>
+{
+	resources
+		.store_print_queue()
+		.map_err(|_| HandlerError::new("Unable to store the print queue"))
+}
+
 fn get_resources<P: Peripherals>(resources: &Resources<P>) -> Result<MutexGuard<'_, ResourcesImpl<P>>, HandlerError>
 {
 	resources
@@ -576,3 +1813,179 @@ fn ok_response<C: Connection>(request: Request<&mut C>) -> Result<Response<&mut
 		],
 	)
 }
+
+#[cfg(test)]
+mod tests
+{
+	use embedded_svc::http::Method;
+
+	use super::*;
+	use crate::printer::{
+		communication::http::{
+			command::CommandsSender,
+			resources::tests::{create_file, new_resources},
+		},
+		components::mock::{MockFlashMemory, MockHttpConnection},
+	};
+
+	/// Returns the ID of the file with the provided `name` as it's sent in the HTTP requests, or `None` if the file
+	/// doesn't exist.
+	fn find_file_id<P: Peripherals>(resources: &Resources<P>, name: &str) -> Option<u32>
+	{
+		resources
+			.lock()
+			.file_system
+			.get_existing_files_metadatas()
+			.iter()
+			.find(|file_metadata| file_metadata.name == name)
+			.map(|file_metadata| u32::from_le_bytes(file_metadata.id.to_bytes()))
+	}
+
+	#[test]
+	fn emergency_stop_works_while_the_resources_are_locked()
+	{
+		let _emergency_stop_lock = emergency_stop::TESTS_LOCK.lock();
+		let memory = MockFlashMemory::default();
+		let resources = new_resources(&memory);
+		let _locked_resources = resources.lock();
+
+		let mut connection = MockHttpConnection::new(Method::Post, "/v1/emergency-stop", &[], &[]);
+		let result = emergency_stop(Request::wrap(&mut connection), resources.clone());
+		let is_halted = emergency_stop::is_halted();
+		emergency_stop::reset();
+
+		assert!(result.is_ok());
+		assert!(is_halted);
+		assert_eq!(connection.get_response_status(), Some(200));
+	}
+
+	#[test]
+	fn emergency_stop_command_is_handled_as_soon_as_its_received()
+	{
+		let _emergency_stop_lock = emergency_stop::TESTS_LOCK.lock();
+		let memory = MockFlashMemory::default();
+		let resources = new_resources(&memory);
+		let _locked_resources = resources.lock();
+
+		let mut connection = MockHttpConnection::new(
+			Method::Post,
+			"/v1/gcode-commands",
+			&[],
+			br#"{"commands":"G28\nM112 ;Stop!\nG1 X10"}"#,
+		);
+		let result = send_g_code_commands(Request::wrap(&mut connection), resources.clone());
+		let is_halted = emergency_stop::is_halted();
+		emergency_stop::reset();
+
+		assert!(result.is_ok());
+		assert!(is_halted);
+	}
+
+	#[test]
+	fn json_strings_are_unescaped()
+	{
+		assert_eq!(unescape_json_string("cube.gcode").unwrap(), "cube.gcode");
+		assert_eq!(
+			unescape_json_string(r#"G28\nM104 S200\t;\"Heat\"\\\/"#).unwrap(),
+			"G28\nM104 S200\t;\"Heat\"\\/"
+		);
+		assert_eq!(unescape_json_string(r"Perch\u00e9 \ud83d\ude00").unwrap(), "Perché 😀");
+	}
+
+	#[test]
+	fn invalid_json_escape_sequences_are_rejected()
+	{
+		assert_eq!(unescape_json_string(r"\"), None);
+		assert_eq!(unescape_json_string(r"\a"), None);
+		assert_eq!(unescape_json_string(r"\u00"), None);
+		assert_eq!(unescape_json_string(r"\u+0e9"), None);
+		assert_eq!(unescape_json_string(r"\ud83d"), None);
+		assert_eq!(unescape_json_string(r"\ude00"), None);
+	}
+
+	#[test]
+	fn cancel_print_command_is_handled_as_soon_as_its_received()
+	{
+		let memory = MockFlashMemory::default();
+		let resources = new_resources(&memory);
+		let (commands_sender, _commands_receiver) = CommandsSender::new();
+		resources.lock().command_sender = commands_sender;
+
+		let mut connection =
+			MockHttpConnection::new(Method::Post, "/v1/gcode-commands", &[], br#"{"commands":"G28\nM524"}"#);
+		let result = send_g_code_commands(Request::wrap(&mut connection), resources.clone());
+
+		assert!(result.is_ok());
+		assert!(print_process::take_cancel_request());
+	}
+
+	#[test]
+	fn files_with_a_reserved_name_cant_be_uploaded()
+	{
+		let memory = MockFlashMemory::default();
+		let resources = new_resources(&memory);
+		let replacement_file_name = format!("{}{}", print_queue::FILE_NAME, REPLACEMENT_FILE_SUFFIX);
+
+		for file_name in [persisted_settings::FILE_NAME, &replacement_file_name]
+		{
+			let mut connection = MockHttpConnection::new(
+				Method::Post,
+				"/v1/files",
+				&[("File-Name", file_name), ("Content-Length", "2")],
+				b"M2",
+			);
+			let result = send_file(Request::wrap(&mut connection), resources.clone());
+
+			assert!(result.is_err());
+			assert_eq!(find_file_id(&resources, file_name), None);
+		}
+	}
+
+	#[test]
+	fn reserved_files_cant_be_deleted()
+	{
+		let memory = MockFlashMemory::default();
+		let resources = new_resources(&memory);
+		create_file(&mut resources.lock(), print_history::FILE_NAME);
+		let file_id = find_file_id(&resources, print_history::FILE_NAME).unwrap();
+
+		let body = format!(r#"{{"fileId":{file_id}}}"#);
+		let mut connection = MockHttpConnection::new(Method::Post, "/v1/delete-file", &[], body.as_bytes());
+		let result = delete_file(Request::wrap(&mut connection), resources.clone());
+
+		assert!(result.is_err());
+		assert_eq!(find_file_id(&resources, print_history::FILE_NAME), Some(file_id));
+	}
+
+	#[test]
+	fn reserved_files_cant_be_downloaded()
+	{
+		let memory = MockFlashMemory::default();
+		let resources = new_resources(&memory);
+		create_file(&mut resources.lock(), persisted_settings::FILE_NAME);
+		let file_id = find_file_id(&resources, persisted_settings::FILE_NAME).unwrap();
+
+		let uri = format!("/v1/files/{file_id}/content");
+		let mut connection = MockHttpConnection::new(Method::Get, &uri, &[], &[]);
+		let result = download_file(Request::wrap(&mut connection), resources.clone());
+
+		assert!(result.is_err());
+		assert!(connection.get_response_body().is_empty());
+	}
+
+	#[test]
+	fn reserved_files_cant_be_printed()
+	{
+		let memory = MockFlashMemory::default();
+		let resources = new_resources(&memory);
+		create_file(&mut resources.lock(), print_queue::FILE_NAME);
+		let file_id = find_file_id(&resources, print_queue::FILE_NAME).unwrap();
+
+		let body = format!(r#"{{"fileId":{file_id}}}"#);
+		let mut connection = MockHttpConnection::new(Method::Post, "/v1/print-file", &[], body.as_bytes());
+		let result = print_file(Request::wrap(&mut connection), resources.clone());
+
+		assert!(result.is_err());
+		assert!(!resources.lock().print_process.is_printing());
+	}
+}
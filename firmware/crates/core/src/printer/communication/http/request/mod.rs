@@ -34,11 +34,27 @@ pub const STACK_SIZE: usize = 30_000;
 pub enum HttpRequest
 {
 	Hello,
-	/// List the metadatas of all the G-code files saved in the file system.
+	/// List the metadatas of all the G-code files saved in the file system (or only of the files and the subdirectories
+	/// directly inside a specific directory).
 	ListFiles,
 	OptionsListFiles,
 	/// Delete a specific file from the file system.
 	DeleteFile,
+	/// Change the name of a specific file (its name can be a path, so this can also move it to another directory).
+	RenameFile,
+	/// Move all the files next to each other so that the free space of the flash memory is contiguous (this is also
+	/// done automatically when a file that can't fit in the fragmented free space is [`sent`](Self::SendFile)).
+	CompactFiles,
+	/// Get how much the blocks of the flash memory used by the file system have been worn out by the erases (like the
+	/// erase count of the most erased block).
+	FilesWearStatistics,
+	OptionsFilesWearStatistics,
+	/// Download the content of a specific file (or only a range of its bytes).
+	///
+	/// Its URI contains a wildcard, so it must come after the other requests whose URI starts with `/v1/files/` (the
+	/// handlers are matched in the order they are registered).
+	DownloadFile,
+	OptionsDownloadFile,
 	/// Start printing a specific file.
 	PrintFile,
 	/// Send a G-code file to the printer (that later on could be [`printed`](Self::PrintFile)).
@@ -49,6 +65,47 @@ pub enum HttpRequest
 	OptionsGetPrintStatus,
 	/// Pause or resume (based on the previous state) the current print.
 	PauseOrResume,
+	/// Stop the current print, move the nozzle away from the printed object and turn off the heaters.
+	CancelPrint,
+	/// Confirm that the new filament has been inserted in the extruder during a filament change (which is then loaded
+	/// before resuming the print).
+	ConfirmFilamentChange,
+	/// Halt the machine immediately, turning off the heaters and the stepper motors (check [`emergency_stop`]).
+	///
+	/// [`emergency_stop`]: crate::printer::components::emergency_stop
+	EmergencyStop,
+	/// Reboot the machine after an emergency stop, so that it can work again.
+	ResetAfterEmergencyStop,
+	/// Get the info about the print that was in execution when the machine has been turned off (if there was one), like
+	/// the name of its file and how much of it had been printed.
+	GetInterruptedPrint,
+	OptionsGetInterruptedPrint,
+	/// Continue the print that was in execution when the machine has been turned off from its last checkpoint.
+	ResumeInterruptedPrint,
+	/// Forget the print that was in execution when the machine has been turned off, so that it can't be resumed.
+	DiscardInterruptedPrint,
+	/// Get the files waiting to be printed after the current print, in the order in which they will be printed.
+	GetPrintQueue,
+	OptionsGetPrintQueue,
+	/// Add a specific file to the print queue (at its end or at a specific index).
+	AddToPrintQueue,
+	/// Remove the file at a specific index from the print queue.
+	RemoveFromPrintQueue,
+	/// Move the file at a specific index of the print queue to another index.
+	MoveInPrintQueue,
+	/// Choose if, when a print has been completed, the next file of the queue is printed right away or only after the
+	/// user has confirmed that the bed has been cleared.
+	SetPrintQueueSettings,
+	/// Start printing the first file of the print queue (this is also how the user confirms that the bed has been
+	/// cleared).
+	StartPrintQueue,
+	/// Get the last prints that have ended (from the newest to the oldest), with their outcome, duration and used
+	/// filament.
+	GetPrintHistory,
+	OptionsGetPrintHistory,
+	/// Get the totals of all the prints ever executed by the machine (like the print hours and the used filament).
+	GetPrintStatistics,
+	OptionsGetPrintStatistics,
 	/// Get the status of various components of the machine (like the current temperature of the hotend, or the target
 	/// temperature of the bed).
 	PrinterState,
@@ -84,10 +141,35 @@ impl HttpRequest
 			HttpRequest::OptionsListFiles => Method::Options,
 			HttpRequest::SendFile => Method::Post,
 			HttpRequest::DeleteFile => Method::Delete,
+			HttpRequest::RenameFile => Method::Post,
+			HttpRequest::CompactFiles => Method::Post,
+			HttpRequest::FilesWearStatistics => Method::Get,
+			HttpRequest::OptionsFilesWearStatistics => Method::Options,
+			HttpRequest::DownloadFile => Method::Get,
+			HttpRequest::OptionsDownloadFile => Method::Options,
 			HttpRequest::PrintFile => Method::Post,
 			HttpRequest::GetPrintStatus => Method::Get,
 			HttpRequest::OptionsGetPrintStatus => Method::Options,
 			HttpRequest::PauseOrResume => Method::Post,
+			HttpRequest::CancelPrint => Method::Post,
+			HttpRequest::ConfirmFilamentChange => Method::Post,
+			HttpRequest::EmergencyStop => Method::Post,
+			HttpRequest::ResetAfterEmergencyStop => Method::Post,
+			HttpRequest::GetInterruptedPrint => Method::Get,
+			HttpRequest::OptionsGetInterruptedPrint => Method::Options,
+			HttpRequest::ResumeInterruptedPrint => Method::Post,
+			HttpRequest::DiscardInterruptedPrint => Method::Delete,
+			HttpRequest::GetPrintQueue => Method::Get,
+			HttpRequest::OptionsGetPrintQueue => Method::Options,
+			HttpRequest::AddToPrintQueue => Method::Post,
+			HttpRequest::RemoveFromPrintQueue => Method::Delete,
+			HttpRequest::MoveInPrintQueue => Method::Post,
+			HttpRequest::SetPrintQueueSettings => Method::Post,
+			HttpRequest::StartPrintQueue => Method::Post,
+			HttpRequest::GetPrintHistory => Method::Get,
+			HttpRequest::OptionsGetPrintHistory => Method::Options,
+			HttpRequest::GetPrintStatistics => Method::Get,
+			HttpRequest::OptionsGetPrintStatistics => Method::Options,
 			HttpRequest::PrinterState => Method::Get,
 			HttpRequest::OptionsPrinterState => Method::Options,
 			HttpRequest::Move => Method::Post,
@@ -101,6 +183,8 @@ impl HttpRequest
 	/// Returns the relative URI that the request received by the server should have to invoke the [`callback`]
 	/// of this variant of the enum.
 	///
+	/// The URI can end with a `*` wildcard, that matches any sequence of characters.
+	///
 	/// [`callback`]: Self::get_callback
 	pub fn get_uri(&self) -> &'static str
 	{
@@ -111,10 +195,35 @@ impl HttpRequest
 			HttpRequest::OptionsListFiles => "/v1/files",
 			HttpRequest::SendFile => "/v1/files",
 			HttpRequest::DeleteFile => "/v1/files",
+			HttpRequest::RenameFile => "/v1/files/rename",
+			HttpRequest::CompactFiles => "/v1/files/compact",
+			HttpRequest::FilesWearStatistics => "/v1/files/wear-statistics",
+			HttpRequest::OptionsFilesWearStatistics => "/v1/files/wear-statistics",
+			HttpRequest::DownloadFile => "/v1/files/*",
+			HttpRequest::OptionsDownloadFile => "/v1/files/*",
 			HttpRequest::PrintFile => "/v1/print",
 			HttpRequest::GetPrintStatus => "/v1/print/status",
 			HttpRequest::OptionsGetPrintStatus => "/v1/print/status",
 			HttpRequest::PauseOrResume => "/v1/print/toggle-pause",
+			HttpRequest::CancelPrint => "/v1/print/cancel",
+			HttpRequest::ConfirmFilamentChange => "/v1/filament-change/confirm",
+			HttpRequest::EmergencyStop => "/v1/emergency-stop",
+			HttpRequest::ResetAfterEmergencyStop => "/v1/emergency-stop/reset",
+			HttpRequest::GetInterruptedPrint => "/v1/print/recovery",
+			HttpRequest::OptionsGetInterruptedPrint => "/v1/print/recovery",
+			HttpRequest::ResumeInterruptedPrint => "/v1/print/recovery/resume",
+			HttpRequest::DiscardInterruptedPrint => "/v1/print/recovery",
+			HttpRequest::GetPrintQueue => "/v1/print-queue",
+			HttpRequest::OptionsGetPrintQueue => "/v1/print-queue",
+			HttpRequest::AddToPrintQueue => "/v1/print-queue",
+			HttpRequest::RemoveFromPrintQueue => "/v1/print-queue",
+			HttpRequest::MoveInPrintQueue => "/v1/print-queue/move",
+			HttpRequest::SetPrintQueueSettings => "/v1/print-queue/settings",
+			HttpRequest::StartPrintQueue => "/v1/print-queue/start",
+			HttpRequest::GetPrintHistory => "/v1/history",
+			HttpRequest::OptionsGetPrintHistory => "/v1/history",
+			HttpRequest::GetPrintStatistics => "/v1/stats",
+			HttpRequest::OptionsGetPrintStatistics => "/v1/stats",
 			HttpRequest::PrinterState => "/v1/printer/state",
 			HttpRequest::OptionsPrinterState => "/v1/printer/state",
 			HttpRequest::ListGCodeCommandsInMemory => "/v1/gcode-commands",
@@ -138,11 +247,36 @@ impl HttpRequest
 			HttpRequest::ListFiles => callbacks::list_files,
 			HttpRequest::OptionsListFiles => callbacks::options_list_files,
 			HttpRequest::DeleteFile => callbacks::delete_file,
+			HttpRequest::RenameFile => callbacks::rename_file,
+			HttpRequest::CompactFiles => callbacks::compact_files,
+			HttpRequest::FilesWearStatistics => callbacks::files_wear_statistics,
+			HttpRequest::OptionsFilesWearStatistics => callbacks::options_files_wear_statistics,
+			HttpRequest::DownloadFile => callbacks::download_file,
+			HttpRequest::OptionsDownloadFile => callbacks::options_download_file,
 			HttpRequest::PrintFile => callbacks::print_file,
 			HttpRequest::SendFile => callbacks::send_file,
 			HttpRequest::GetPrintStatus => callbacks::get_print_status,
 			HttpRequest::OptionsGetPrintStatus => callbacks::options_get_print_status,
 			HttpRequest::PauseOrResume => callbacks::pause_or_resume,
+			HttpRequest::CancelPrint => callbacks::cancel_print,
+			HttpRequest::ConfirmFilamentChange => callbacks::confirm_filament_change,
+			HttpRequest::EmergencyStop => callbacks::emergency_stop,
+			HttpRequest::ResetAfterEmergencyStop => callbacks::reset_after_emergency_stop,
+			HttpRequest::GetInterruptedPrint => callbacks::get_interrupted_print,
+			HttpRequest::OptionsGetInterruptedPrint => callbacks::options_get_interrupted_print,
+			HttpRequest::ResumeInterruptedPrint => callbacks::resume_interrupted_print,
+			HttpRequest::DiscardInterruptedPrint => callbacks::discard_interrupted_print,
+			HttpRequest::GetPrintQueue => callbacks::get_print_queue,
+			HttpRequest::OptionsGetPrintQueue => callbacks::options_get_print_queue,
+			HttpRequest::AddToPrintQueue => callbacks::add_to_print_queue,
+			HttpRequest::RemoveFromPrintQueue => callbacks::remove_from_print_queue,
+			HttpRequest::MoveInPrintQueue => callbacks::move_in_print_queue,
+			HttpRequest::SetPrintQueueSettings => callbacks::set_print_queue_settings,
+			HttpRequest::StartPrintQueue => callbacks::start_print_queue,
+			HttpRequest::GetPrintHistory => callbacks::get_print_history,
+			HttpRequest::OptionsGetPrintHistory => callbacks::options_get_print_history,
+			HttpRequest::GetPrintStatistics => callbacks::get_print_statistics,
+			HttpRequest::OptionsGetPrintStatistics => callbacks::options_get_print_statistics,
 			HttpRequest::PrinterState => callbacks::printer_state,
 			HttpRequest::OptionsPrinterState => callbacks::options_printer_state,
 			HttpRequest::Move => callbacks::move_,
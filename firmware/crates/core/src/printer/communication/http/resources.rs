@@ -9,14 +9,30 @@
 //!
 //! [`Communication`]: super::super::Communication
 
-use std::sync::Arc;
+use std::sync::{mpsc::SendError, Arc};
 
+use embedded_hal::spi::SpiDevice;
 use spin::{Mutex, MutexGuard};
 
-use super::{command::CommandsSender, other::GCodeHistory};
+use super::{
+	command::{Command, CommandsSender},
+	other::GCodeHistory,
+};
 use crate::printer::{
 	communication::{ota::OverTheAirUpdater, security::Security},
-	components::{file_system::FileSystem, print_process::PrintProcess, Peripherals},
+	components::{
+		emergency_stop,
+		file_system::{
+			regions::{checkpoint::StoreError as StoreCheckpointError, metadata::FileId},
+			FileSystem, ReplaceFileError, UpdateFileError,
+		},
+		power_loss_recovery::PrintCheckpoint,
+		print_history::{self, PrintHistory, PrintJob, PrintOutcome},
+		print_process::PrintProcess,
+		print_queue::{self, PrintQueue},
+		time::SystemTime,
+		Peripherals,
+	},
 };
 
 /// A container of resources that can be used by the [`callbacks`] of the http requests and also by the
@@ -37,19 +53,46 @@ pub struct ResourcesImpl<P: Peripherals>
 	pub print_process: PrintProcess<P>,
 
 	pub g_code_history: GCodeHistory,
+
+	/// The checkpoint of the print that was in execution when the machine has been turned off (check
+	/// [`power_loss_recovery`]). It becomes `None` when that print is resumed or discarded, or when another print starts.
+	///
+	/// [`power_loss_recovery`]: crate::printer::components::power_loss_recovery
+	pub interrupted_print: Option<PrintCheckpoint>,
+
+	/// The files to print after the current print (check [`print_queue`]). Call [`ResourcesImpl::store_print_queue`]
+	/// after changing it.
+	pub print_queue: PrintQueue,
+
+	/// The log of the prints that have ended (check [`print_history`]).
+	pub print_history: PrintHistory,
+	/// The print in execution, that is added to the `print_history` when it [`ends`](ResourcesImpl::end_print_job).
+	pub print_job: Option<PrintJob>,
+
+	/// Seconds since the Unix epoch at which the time returned by the `system_time` was zero (check
+	/// [`ResourcesImpl::get_unix_time`]).
+	unix_time_at_system_time_zero: Option<u64>,
 }
 
 impl<P: Peripherals> Resources<P>
 {
 	/// Wraps the provided resources in an `Arc<Mutex>>` and returns the resulting [`Resources`].
 	pub fn new(
-		system_time: Option<P::SystemTime>, file_system: FileSystem<P::FlashChip, P::FlashSpi>,
+		system_time: Option<P::SystemTime>, mut file_system: FileSystem<P::FlashChip, P::FlashSpi>,
 		ota_updater: OverTheAirUpdater<P::Ota>, security: Security, command_sender: CommandsSender<P>,
 		print_process: PrintProcess<P>,
 	) -> Self
 	{
 		log::info!("Create the resources used in the HTTP uri handlers");
 
+		let interrupted_print = file_system.get_checkpoint().and_then(PrintCheckpoint::from_bytes);
+		if interrupted_print.is_some()
+		{
+			log::info!("A print has been interrupted, it can be resumed from its last checkpoint");
+		}
+		let print_queue = print_queue::load_from_file_system(&mut file_system).unwrap_or_default();
+		let print_history = print_history::load_from_file_system(&mut file_system).unwrap_or_default();
+
 		Self(Arc::new(Mutex::new(ResourcesImpl {
 			system_time,
 			file_system,
@@ -58,6 +101,11 @@ impl<P: Peripherals> Resources<P>
 			command_sender,
 			print_process,
 			g_code_history: GCodeHistory::new(),
+			interrupted_print,
+			print_queue,
+			print_history,
+			print_job: None,
+			unix_time_at_system_time_zero: None,
 		})))
 	}
 
@@ -84,6 +132,207 @@ impl<P: Peripherals> ResourcesImpl<P>
 	{
 		(&mut self.file_system, &mut self.print_process)
 	}
+
+	/// Starts printing the file with the provided `file_id`, recording the print in its metadata (`unix_time` is the
+	/// time at which the print starts, if it's known).
+	///
+	/// Returns an error without doing anything if another print is [`in execution`] or the machine has been
+	/// [`halted`](emergency_stop::is_halted).
+	///
+	/// [`in execution`]: PrintProcess::is_printing
+	pub fn start_print(&mut self, file_id: FileId, unix_time: Option<u64>) -> Result<(), StartPrintError<P::FlashSpi>>
+	{
+		if emergency_stop::is_halted()
+		{
+			return Err(StartPrintError::Halted);
+		}
+		if self.print_process.is_printing()
+		{
+			return Err(StartPrintError::AlreadyPrinting);
+		}
+		if self.file_system.read_file(file_id).is_err()
+		{
+			return Err(StartPrintError::FileDoesntExist);
+		}
+		let unix_time = self.get_unix_time(unix_time);
+		self.file_system
+			.record_print(file_id, unix_time)
+			.map_err(StartPrintError::RecordPrint)?;
+
+		// The interrupted print can't be resumed anymore, since this print will overwrite its checkpoint
+		if self.interrupted_print.take().is_some()
+		{
+			self.file_system
+				.clear_checkpoint()
+				.map_err(StartPrintError::ClearInterruptedPrint)?;
+		}
+
+		let current_time = self.system_time.as_ref().map(|time| time.now());
+		self.print_process.print_file(file_id, current_time);
+		self.print_queue.is_waiting_for_bed_clearing = false;
+		self.start_print_job(file_id, unix_time);
+
+		Ok(())
+	}
+
+	/// Cancels the print in execution: no more commands are read from its file, the `Components` thread is asked to
+	/// [`stop the machine safely`] and the print is added to the [`print history`](Self::print_history) as cancelled.
+	///
+	/// [`stop the machine safely`]: crate::printer::components::Printer3DComponents::cancel_print
+	pub fn cancel_print(&mut self) -> Result<(), CancelPrintError<P>>
+	{
+		if !self.print_process.is_printing()
+		{
+			return Err(CancelPrintError::NotPrinting);
+		}
+
+		self.print_process.stop();
+		self.command_sender
+			.send_command(Command::CancelPrint)
+			.map_err(CancelPrintError::Send)?;
+		self.end_print_job(PrintOutcome::Cancelled)
+			.map_err(CancelPrintError::StorePrintHistory)
+	}
+
+	/// Starts tracking the print of the file with the provided `file_id` (which must have just started), so that it's
+	/// added to the [`print history`](Self::print_history) when it [`ends`](Self::end_print_job).
+	pub fn start_print_job(&mut self, file_id: FileId, unix_time: Option<u64>)
+	{
+		let file_name = self
+			.file_system
+			.get_existing_files_metadatas()
+			.iter()
+			.find(|file_metadata| file_metadata.id == file_id)
+			.map(|file_metadata| file_metadata.name.clone())
+			.unwrap_or_default();
+		let unix_time = self.get_unix_time(unix_time);
+		let current_time = self.system_time.as_ref().map(|time| time.now());
+
+		self.print_job = Some(PrintJob::start(file_name, unix_time, current_time));
+	}
+
+	/// Adds the [`print in execution`](Self::print_job) (if there's one) to the [`print history`](Self::print_history)
+	/// with the provided `outcome`, and stores the history in the file system.
+	pub fn end_print_job(&mut self, outcome: PrintOutcome) -> Result<(), ReplaceFileError<P::FlashSpi>>
+	{
+		if let Some(print_job) = self.print_job.take()
+		{
+			let current_time = self.system_time.as_ref().map(|time| time.now());
+			let print_record = print_job.end(outcome, current_time);
+			log::info!("A print has ended: {print_record:?}");
+
+			self.print_history.add(print_record);
+			print_history::store_in_file_system(&mut self.file_system, &self.print_history)?;
+		}
+
+		Ok(())
+	}
+
+	/// Returns the provided `unix_time` (seconds since the Unix epoch) if it's `Some`, remembering it to calculate the
+	/// current Unix time when it's not provided. Returns `None` if a Unix time has never been provided (or if there's
+	/// no `system_time`).
+	pub fn get_unix_time(&mut self, unix_time: Option<u64>) -> Option<u64>
+	{
+		let current_time_in_seconds = self.system_time.as_ref().map(|time| time.now().as_secs());
+		match unix_time
+		{
+			Some(unix_time) =>
+			{
+				self.unix_time_at_system_time_zero = current_time_in_seconds
+					.and_then(|current_time_in_seconds| unix_time.checked_sub(current_time_in_seconds));
+				Some(unix_time)
+			},
+			None => self.unix_time_at_system_time_zero.zip(current_time_in_seconds).map(
+				|(unix_time_at_system_time_zero, current_time_in_seconds)| {
+					unix_time_at_system_time_zero + current_time_in_seconds
+				},
+			),
+		}
+	}
+
+	/// [`Starts printing`](Self::start_print) the first file of the [`print queue`](Self::print_queue), removing it
+	/// from the queue only if the print has started.
+	pub fn start_next_queued_print(&mut self, unix_time: Option<u64>) -> Result<(), StartPrintError<P::FlashSpi>>
+	{
+		if emergency_stop::is_halted()
+		{
+			return Err(StartPrintError::Halted);
+		}
+		if self.print_process.is_printing()
+		{
+			return Err(StartPrintError::AlreadyPrinting);
+		}
+		let file_id = *self
+			.print_queue
+			.get_files_ids()
+			.first()
+			.ok_or(StartPrintError::EmptyQueue)?;
+		self.start_print(file_id, unix_time)?;
+
+		self.print_queue.take_next();
+		self.store_print_queue().map_err(StartPrintError::StorePrintQueue)
+	}
+
+	/// Stores the current [`print queue`](Self::print_queue) in the file system, so that it's kept after a reboot.
+	pub fn store_print_queue(&mut self) -> Result<(), ReplaceFileError<P::FlashSpi>>
+	{
+		print_queue::store_in_file_system(&mut self.file_system, &self.print_queue)
+	}
+}
+
+/// An error returned by [`ResourcesImpl::start_print`] and [`ResourcesImpl::start_next_queued_print`].
+pub enum StartPrintError<Spi: SpiDevice<u8>>
+{
+	/// The machine has been halted by an [`emergency stop`](emergency_stop).
+	Halted,
+	/// Another print is in execution.
+	AlreadyPrinting,
+	/// The file to print doesn't exist or its upload hasn't been completed.
+	FileDoesntExist,
+	RecordPrint(UpdateFileError<Spi>),
+	ClearInterruptedPrint(StoreCheckpointError<Spi>),
+	/// There isn't a file in the print queue.
+	EmptyQueue,
+	StorePrintQueue(ReplaceFileError<Spi>),
+}
+
+impl<Spi: SpiDevice<u8>> std::fmt::Debug for StartPrintError<Spi>
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		match self
+		{
+			Self::Halted => write!(f, "Halted"),
+			Self::AlreadyPrinting => write!(f, "AlreadyPrinting"),
+			Self::FileDoesntExist => write!(f, "FileDoesntExist"),
+			Self::RecordPrint(arg0) => f.debug_tuple("RecordPrint").field(arg0).finish(),
+			Self::ClearInterruptedPrint(arg0) => f.debug_tuple("ClearInterruptedPrint").field(arg0).finish(),
+			Self::EmptyQueue => write!(f, "EmptyQueue"),
+			Self::StorePrintQueue(arg0) => f.debug_tuple("StorePrintQueue").field(arg0).finish(),
+		}
+	}
+}
+
+/// An error returned by [`ResourcesImpl::cancel_print`].
+pub enum CancelPrintError<P: Peripherals>
+{
+	/// No file is being printed.
+	NotPrinting,
+	Send(SendError<Command<P>>),
+	StorePrintHistory(ReplaceFileError<P::FlashSpi>),
+}
+
+impl<P: Peripherals> std::fmt::Debug for CancelPrintError<P>
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		match self
+		{
+			Self::NotPrinting => write!(f, "NotPrinting"),
+			Self::Send(arg0) => f.debug_tuple("Send").field(arg0).finish(),
+			Self::StorePrintHistory(arg0) => f.debug_tuple("StorePrintHistory").field(arg0).finish(),
+		}
+	}
 }
 
 impl<P: Peripherals> Clone for Resources<P>
@@ -94,3 +343,118 @@ impl<P: Peripherals> Clone for Resources<P>
 		Self(Arc::clone(&self.0))
 	}
 }
+
+#[cfg(test)]
+pub(crate) mod tests
+{
+	use super::*;
+	use crate::printer::{
+		communication::security::{Configuration, PasswordConfiguration},
+		components::{
+			drivers::spi_flash_memory::{SpiFlashMemory, MT29F2G01ABAGDWB},
+			file_system::regions::RegionsConfig,
+			mock::{MockFlashMemory, MockOta, MockPeripherals},
+		},
+	};
+
+	/// Returns a file system stored in `memory`, like it's created when the microcontroller boots.
+	fn boot_file_system(
+		memory: &MockFlashMemory<MT29F2G01ABAGDWB>,
+	) -> FileSystem<MT29F2G01ABAGDWB, MockFlashMemory<MT29F2G01ABAGDWB>>
+	{
+		let spi_flash_memory = SpiFlashMemory::new(memory.clone(), MT29F2G01ABAGDWB);
+		FileSystem::new(spi_flash_memory, RegionsConfig::default::<MT29F2G01ABAGDWB>()).unwrap()
+	}
+
+	pub(crate) fn new_resources(memory: &MockFlashMemory<MT29F2G01ABAGDWB>) -> Resources<MockPeripherals>
+	{
+		let security = Security::new(Configuration {
+			password: PasswordConfiguration::None,
+		})
+		.unwrap();
+
+		Resources::new(
+			None,
+			boot_file_system(memory),
+			OverTheAirUpdater::new(MockOta, || ()),
+			security,
+			CommandsSender::new().0,
+			PrintProcess::new(10),
+		)
+	}
+
+	pub(crate) fn create_file(resources: &mut ResourcesImpl<MockPeripherals>, name: &str) -> FileId
+	{
+		let mut file_writer = resources.file_system.create_file(name, 6).unwrap();
+		file_writer.write_data(&mut resources.file_system, b"G28\nM2").unwrap();
+		file_writer.finish_writing(&mut resources.file_system).unwrap();
+
+		resources
+			.file_system
+			.get_existing_files_metadatas()
+			.iter()
+			.find(|file_metadata| file_metadata.name == name)
+			.unwrap()
+			.id
+	}
+
+	#[test]
+	fn queued_file_is_removed_from_the_queue_when_its_print_starts()
+	{
+		let _emergency_stop_lock = emergency_stop::TESTS_LOCK.lock();
+		let memory = MockFlashMemory::default();
+		let resources = new_resources(&memory);
+		let mut resources = resources.lock();
+		let cube_id = create_file(&mut resources, "cube.gcode");
+		let benchy_id = create_file(&mut resources, "benchy.gcode");
+		resources.print_queue.add(cube_id, None);
+		resources.print_queue.add(benchy_id, None);
+		resources.store_print_queue().unwrap();
+
+		resources.start_next_queued_print(None).unwrap();
+		assert_eq!(resources.print_process.get_file_being_printed(), Some(cube_id));
+		assert_eq!(resources.print_queue.get_files_ids(), [benchy_id]);
+
+		// The queue is stored without the file being printed
+		let mut file_system = boot_file_system(&memory);
+		assert_eq!(
+			print_queue::load_from_file_system(&mut file_system)
+				.unwrap()
+				.get_files_ids(),
+			[benchy_id]
+		);
+	}
+
+	#[test]
+	fn queued_file_is_kept_in_the_queue_if_its_print_doesnt_start()
+	{
+		let _emergency_stop_lock = emergency_stop::TESTS_LOCK.lock();
+		let memory = MockFlashMemory::default();
+		let resources = new_resources(&memory);
+		let mut resources = resources.lock();
+		let cube_id = create_file(&mut resources, "cube.gcode");
+		let benchy_id = create_file(&mut resources, "benchy.gcode");
+
+		resources.start_print(cube_id, None).unwrap();
+		resources.print_queue.add(benchy_id, None);
+		assert!(matches!(
+			resources.start_next_queued_print(None),
+			Err(StartPrintError::AlreadyPrinting)
+		));
+		assert_eq!(resources.print_queue.get_files_ids(), [benchy_id]);
+
+		resources.print_process.stop();
+		resources.file_system.delete_file(benchy_id).unwrap();
+		assert!(matches!(
+			resources.start_next_queued_print(None),
+			Err(StartPrintError::FileDoesntExist)
+		));
+		assert_eq!(resources.print_queue.get_files_ids(), [benchy_id]);
+
+		resources.print_queue.take_next();
+		assert!(matches!(
+			resources.start_next_queued_print(None),
+			Err(StartPrintError::EmptyQueue)
+		));
+	}
+}
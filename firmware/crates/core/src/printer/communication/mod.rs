@@ -21,7 +21,7 @@ use self::{
 	communicator::wifi::{CreationConfig as WifiCreationConfig, WifiCommunicator},
 	http::{
 		command::{Command, CommandsSender},
-		resources::Resources,
+		resources::{CancelPrintError, Resources, StartPrintError},
 	},
 	security::Security,
 };
@@ -40,7 +40,11 @@ pub use multi_thread::*;
 
 // Module components that facilitate communication.
 use super::components::{
+	emergency_stop,
 	file_system::{self, regions::RegionsConfig, FileSystem},
+	persisted_settings,
+	power_loss_recovery::{self, CheckpointRequest},
+	print_history::{self, PrintOutcome},
 	print_process::{self, PrintProcessError},
 	Peripherals,
 };
@@ -72,7 +76,7 @@ impl<P: Peripherals + 'static> Communication<P>
 	///
 	/// A result containing the newly created `Communication` instance or an error if creation fails.
 	pub fn new(
-		peripherals: &mut P, configuration: CommunicationConfig, command_sender: CommandsSender<P>,
+		peripherals: &mut P, configuration: CommunicationConfig, mut command_sender: CommandsSender<P>,
 	) -> Result<
 		Self,
 		CreationError<P::WifiDriver, WifiCommunicator<P::WifiDriver, P::Server>, P::FlashSpi, P::ServerError>,
@@ -104,7 +108,7 @@ impl<P: Peripherals + 'static> Communication<P>
 
 		let mut wifi = wifi_communication_option.unwrap();
 
-		let file_system = FileSystem::new(
+		let mut file_system = FileSystem::new(
 			SpiFlashMemory::new(
 				peripherals
 					.take_flash_spi()
@@ -116,6 +120,13 @@ impl<P: Peripherals + 'static> Communication<P>
 			configuration.file_system,
 		)
 		.map_err(CreationError::FileSystem)?;
+		if let Some(settings) = persisted_settings::load_from_file_system(&mut file_system)
+		{
+			log::info!("Loaded the persisted settings from the flash memory");
+			command_sender
+				.send_command(Command::ApplyPersistedSettings(settings))
+				.map_err(|_| CreationError::SendPersistedSettings)?;
+		}
 		let http_handler_resources = http::resources::Resources::<P>::new(
 			peripherals.take_system_time(),
 			file_system,
@@ -161,6 +172,68 @@ impl<P: Peripherals + 'static> Communication<P>
 			{
 				resources.ota_updater.reboot();
 			}
+			if emergency_stop::take_reset_request()
+			{
+				log::info!("Rebooting to reset the machine after an emergency stop");
+				resources.ota_updater.reboot();
+			}
+
+			if let Some(settings) = persisted_settings::take_save_request()
+			{
+				persisted_settings::store_in_file_system(&mut resources.file_system, &settings)
+					.map_err(TickError::StorePersistedSettings)?;
+				log::info!("Stored the persisted settings in the flash memory");
+			}
+
+			match power_loss_recovery::take_request()
+			{
+				Some(CheckpointRequest::Store(checkpoint)) => resources
+					.file_system
+					.store_checkpoint(&checkpoint.to_bytes())
+					.map_err(TickError::StorePrintCheckpoint)?,
+				Some(CheckpointRequest::Clear) => resources
+					.file_system
+					.clear_checkpoint()
+					.map_err(TickError::StorePrintCheckpoint)?,
+				None => (),
+			}
+
+			if let Some(reason) = print_history::take_failure_report()
+			{
+				let mut end_print_job_result = Ok(());
+				if resources.print_process.is_printing()
+				{
+					log::warn!("The print has been stopped by a failure of the components: {reason}");
+					resources.print_process.stop();
+					end_print_job_result = resources.end_print_job(PrintOutcome::Failed(reason));
+				}
+				// The `Components` thread is waiting for this, even if the print couldn't be recorded
+				print_history::set_failure_recorded();
+
+				end_print_job_result.map_err(TickError::StorePrintHistory)?;
+			}
+
+			if emergency_stop::is_halted() && resources.print_process.is_printing()
+			{
+				log::warn!("The print has been stopped by an emergency stop");
+				resources.print_process.stop();
+				resources
+					.end_print_job(PrintOutcome::Failed(String::from("Emergency stop")))
+					.map_err(TickError::StorePrintHistory)?;
+			}
+
+			if print_process::take_cancel_request()
+			{
+				match resources.cancel_print()
+				{
+					Ok(()) => log::info!("The print has been cancelled"),
+					Err(CancelPrintError::NotPrinting) =>
+					{
+						log::warn!("Couldn't cancel the print: no file is being printed")
+					},
+					Err(error) => return Err(TickError::CancelPrint(error)),
+				}
+			}
 
 			let (file_system, print_process) = resources.get_file_system_and_print_process();
 			match print_process.tick(file_system, print_process::get_commands_in_buffer_count())
@@ -171,13 +244,47 @@ impl<P: Peripherals + 'static> Communication<P>
 					{
 						resources
 							.command_sender
-							.send_command(Command::AddGCodeCommandsToBuffer(result.read_commands))
+							.send_command(Command::AddFileGCodeCommandsToBuffer(result.read_commands))
 							.map_err(TickError::Send)?;
 
 						resources.g_code_history.add_read_lines(read_lines);
 					}
 				},
-				Err(error) => return Err(TickError::PrintProcessTick(error)),
+				// The line that can't be parsed is skipped, so the print can continue
+				Err(error @ PrintProcessError::CouldntParseLine(_)) => return Err(TickError::PrintProcessTick(error)),
+				Err(error) =>
+				{
+					resources.print_process.stop();
+					let error = TickError::PrintProcessTick(error);
+					resources
+						.end_print_job(PrintOutcome::Failed(format!("{error:?}")))
+						.map_err(TickError::StorePrintHistory)?;
+
+					return Err(error);
+				},
+			}
+
+			if resources.print_process.take_completed_print().is_some()
+			{
+				log::info!("The print has been completed");
+				let end_print_job_result = resources.end_print_job(PrintOutcome::Completed);
+
+				if !resources.print_queue.get_files_ids().is_empty()
+				{
+					if resources.print_queue.wait_for_bed_clearing
+					{
+						log::info!("The next print of the queue will start when the bed has been cleared");
+						resources.print_queue.is_waiting_for_bed_clearing = true;
+					}
+					else
+					{
+						resources
+							.start_next_queued_print(None)
+							.map_err(TickError::StartQueuedPrint)?;
+					}
+				}
+
+				end_print_job_result.map_err(TickError::StorePrintHistory)?;
 			}
 		}
 
@@ -205,6 +312,8 @@ pub enum CreationError<WifiDriver: Wifi, WifiCommunicator: Communicator, Spi: Sp
 	HttpServer(ServerError),
 	/// An error related to the file system.
 	FileSystem(file_system::CreationError<Spi>),
+	/// It has been impossible to send the persisted settings loaded from the file system to the `Components` thread.
+	SendPersistedSettings,
 }
 
 /// Errors that may occur during the tick operation of the `Communication` struct.
@@ -214,6 +323,16 @@ pub enum TickError<P: Peripherals>
 	Send(SendError<Command<P>>),
 	/// An error occurred during the print process tick operation.
 	PrintProcessTick(PrintProcessError<P::FlashSpi>),
+	/// An error occurred while storing the persisted settings in the file system.
+	StorePersistedSettings(file_system::ReplaceFileError<P::FlashSpi>),
+	/// An error occurred while storing (or clearing) the checkpoint of the current print in the flash memory.
+	StorePrintCheckpoint(file_system::regions::checkpoint::StoreError<P::FlashSpi>),
+	/// An error occurred while starting the next print of the queue after a print has been completed.
+	StartQueuedPrint(StartPrintError<P::FlashSpi>),
+	/// An error occurred while storing the print history in the file system.
+	StorePrintHistory(file_system::ReplaceFileError<P::FlashSpi>),
+	/// An error occurred while cancelling the print because of an `M524` G-code command.
+	CancelPrint(CancelPrintError<P>),
 }
 
 impl<P: Peripherals> Debug for TickError<P>
@@ -224,6 +343,11 @@ impl<P: Peripherals> Debug for TickError<P>
 		{
 			TickError::Send(error) => f.debug_tuple("Send").field(error).finish(),
 			TickError::PrintProcessTick(error) => f.debug_tuple("PrintProcessTick").field(error).finish(),
+			TickError::StorePersistedSettings(error) => f.debug_tuple("StorePersistedSettings").field(error).finish(),
+			TickError::StorePrintCheckpoint(error) => f.debug_tuple("StorePrintCheckpoint").field(error).finish(),
+			TickError::StartQueuedPrint(error) => f.debug_tuple("StartQueuedPrint").field(error).finish(),
+			TickError::StorePrintHistory(error) => f.debug_tuple("StorePrintHistory").field(error).finish(),
+			TickError::CancelPrint(error) => f.debug_tuple("CancelPrint").field(error).finish(),
 		}
 	}
 }
@@ -244,6 +368,7 @@ impl<WifiDriver: Wifi, WifiCommunicator: Communicator, Spi: SpiDevice<u8>, Serve
 			CreationError::HttpServer(err) => f.debug_tuple("HttpServer").field(err).finish(),
 			CreationError::WifiRegisterRequests(err) => f.debug_tuple("WifiRegisterRequests").field(err).finish(),
 			CreationError::Security(err) => f.debug_tuple("Security").field(err).finish(),
+			CreationError::SendPersistedSettings => write!(f, "SendPersistedSettings"),
 		}
 	}
 }
@@ -266,4 +391,6 @@ pub struct CommunicationConfig
 	pub max_commands_in_buffer_before_reading_new: u16,
 	/// Delay duration between communication ticks.
 	pub delay_between_ticks: Duration,
+	/// Size (in bytes) of the stack of the thread that runs the communication.
+	pub thread_stack_size: usize,
 }
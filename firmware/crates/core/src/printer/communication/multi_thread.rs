@@ -46,9 +46,10 @@ impl<P: Peripherals + 'static> MultiThreadCommunication<P>
 
 		let system_time = peripherals.take_system_time();
 		let delay_between_ticks = configuration.delay_between_ticks;
+		let thread_stack_size = configuration.thread_stack_size;
 
 		let join_handle = std::thread::Builder::new()
-			.stack_size(15_000)
+			.stack_size(thread_stack_size)
 			.name("Communication".to_string())
 			.spawn(move || {
 				let mut communication =
@@ -144,6 +145,12 @@ pub enum SendablePeripherals<P: Peripherals>
 		y_axis_endstop: Option<P::YAxisEndstop>,
 		z_axis_endstop: Option<P::ZAxisEndstop>,
 
+		/// Filament sensor input pin (it's optional).
+		filament_sensor_pin: Option<P::FilamentSensorPin>,
+
+		/// Emergency stop button input pin (it's optional).
+		emergency_stop_button_pin: Option<P::EmergencyStopButtonPin>,
+
 		/// Fan control pins.
 		layer_fan_pin: Option<P::FanPin>,
 		hotend_fan_pin: Option<P::FanPin>,
@@ -245,6 +252,8 @@ impl<P: Peripherals> SendablePeripherals<P>
 			x_axis_endstop: peripherals.take_x_axis_endstop(),
 			y_axis_endstop: peripherals.take_y_axis_endstop(),
 			z_axis_endstop: peripherals.take_z_axis_endstop(),
+			filament_sensor_pin: peripherals.take_filament_sensor_pin(),
+			emergency_stop_button_pin: peripherals.take_emergency_stop_button_pin(),
 			layer_fan_pin: peripherals.take_layer_fan_pin(),
 			hotend_fan_pin: peripherals.take_hotend_fan_pin(),
 			bed_cartridge_heater_pin: peripherals.take_bed_cartridge_heater_pin(),
@@ -281,6 +290,9 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 	type YAxisEndstop = P::YAxisEndstop;
 	type ZAxisEndstop = P::ZAxisEndstop;
 
+	type FilamentSensorPin = P::FilamentSensorPin;
+	type EmergencyStopButtonPin = P::EmergencyStopButtonPin;
+
 	type CartridgeHeaterPin = P::CartridgeHeaterPin;
 	type HotendAdcPin = P::HotendAdcPin;
 
@@ -329,6 +341,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -374,6 +388,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -419,6 +435,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -464,6 +482,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -509,6 +529,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -554,6 +576,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -599,6 +623,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -644,6 +670,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -689,6 +717,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -734,6 +764,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -779,6 +811,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -824,6 +858,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -869,6 +905,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -914,6 +952,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -959,6 +999,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -984,6 +1026,100 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 		}
 	}
 
+	fn take_filament_sensor_pin(&mut self) -> Option<Self::FilamentSensorPin>
+	{
+		match self
+		{
+			SendablePeripherals::ComponentsThread {
+				watchdog_creator,
+				stepper_ticker_timer,
+				kinematics,
+				left_motor_dir_pin,
+				left_motor_step_pin,
+				right_motor_dir_pin,
+				right_motor_step_pin,
+				z_axis_motor_dir_pin,
+				z_axis_motor_step_pin,
+				extruder_motor_dir_pin,
+				extruder_motor_step_pin,
+				uart_driver,
+				x_axis_endstop,
+				y_axis_endstop,
+				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
+				layer_fan_pin,
+				hotend_fan_pin,
+				bed_cartridge_heater_pin,
+				bed_thermistor_pin,
+				hotend_cartridge_heater_pin,
+				hotend_thermistor_pin,
+				adc,
+				system_time,
+			} => filament_sensor_pin.take(),
+			SendablePeripherals::CommunicationThread {
+				watchdog_creator,
+				system_time,
+				flash_chip,
+				flash_spi,
+				wifi_driver,
+				server,
+				ota,
+				#[cfg(feature = "usb")]
+				usb_bus,
+				#[cfg(feature = "usb")]
+				usb_sense_pin,
+			} => None,
+		}
+	}
+
+	fn take_emergency_stop_button_pin(&mut self) -> Option<Self::EmergencyStopButtonPin>
+	{
+		match self
+		{
+			SendablePeripherals::ComponentsThread {
+				watchdog_creator,
+				stepper_ticker_timer,
+				kinematics,
+				left_motor_dir_pin,
+				left_motor_step_pin,
+				right_motor_dir_pin,
+				right_motor_step_pin,
+				z_axis_motor_dir_pin,
+				z_axis_motor_step_pin,
+				extruder_motor_dir_pin,
+				extruder_motor_step_pin,
+				uart_driver,
+				x_axis_endstop,
+				y_axis_endstop,
+				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
+				layer_fan_pin,
+				hotend_fan_pin,
+				bed_cartridge_heater_pin,
+				bed_thermistor_pin,
+				hotend_cartridge_heater_pin,
+				hotend_thermistor_pin,
+				adc,
+				system_time,
+			} => emergency_stop_button_pin.take(),
+			SendablePeripherals::CommunicationThread {
+				watchdog_creator,
+				system_time,
+				flash_chip,
+				flash_spi,
+				wifi_driver,
+				server,
+				ota,
+				#[cfg(feature = "usb")]
+				usb_bus,
+				#[cfg(feature = "usb")]
+				usb_sense_pin,
+			} => None,
+		}
+	}
+
 	fn take_flash_chip(&mut self) -> Option<Self::FlashChip>
 	{
 		match self
@@ -1004,6 +1140,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1049,6 +1187,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1094,6 +1234,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1139,6 +1281,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1184,6 +1328,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1229,6 +1375,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1274,6 +1422,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1319,6 +1469,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1364,6 +1516,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1409,6 +1563,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1454,6 +1610,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1499,6 +1657,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1544,6 +1704,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1595,6 +1757,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -1641,6 +1805,8 @@ impl<P: Peripherals> Peripherals for SendablePeripherals<P>
 				x_axis_endstop,
 				y_axis_endstop,
 				z_axis_endstop,
+				filament_sensor_pin,
+				emergency_stop_button_pin,
 				layer_fan_pin,
 				hotend_fan_pin,
 				bed_cartridge_heater_pin,
@@ -9,8 +9,13 @@
 //! for the printer's components, including PID settings for temperature control and
 //! motion control parameters.
 
-use super::motion;
-use crate::utils::math::Percentage;
+use std::time::Duration;
+
+use super::{drivers::filament_sensor::FilamentSensorConfig, motion};
+use crate::utils::{
+	math::{vectors::Vector2, Percentage},
+	measurement::{distance::Distance, temperature::Temperature},
+};
 
 /// Holds configurations for different components.
 pub struct ComponentsConfig
@@ -29,6 +34,91 @@ pub struct ComponentsConfig
 
 	/// Configuration settings for the motion controller.
 	pub motion_controller: motion::CreationConfig,
+
+	/// Minimum time between two checkpoints of the print stored in the flash memory (check [`power_loss_recovery`]).
+	/// A shorter interval makes less of the print be repeated after a power loss, but wears out the flash memory faster.
+	///
+	/// [`power_loss_recovery`]: super::power_loss_recovery
+	pub print_checkpoint_interval: Duration,
+
+	/// What the machine does after a print has been cancelled.
+	pub end_print_sequence: EndPrintSequence,
+
+	/// What the machine does while a print is paused.
+	pub pause_sequence: PauseSequence,
+
+	/// How the filament is unloaded and loaded when it's changed.
+	pub filament_change: FilamentChangeConfig,
+
+	/// How the filament sensor detects the filament (it's used only if the machine has one).
+	pub filament_sensor: FilamentSensorConfig,
+}
+
+/// What the machine does after a print has been [`cancelled`] (once the heaters and the layer fan have been turned
+/// off).
+///
+/// [`cancelled`]: super::Printer3DComponents::cancel_print
+#[derive(Clone, Debug)]
+pub struct EndPrintSequence
+{
+	/// The moves that take the nozzle away from the printed object.
+	pub parking: ParkingSequence,
+
+	/// Time the stepper motors keep holding their position after the sequence, before being disabled.
+	pub disable_steppers_after: Duration,
+}
+
+/// What the machine does while a print is [`paused`].
+///
+/// [`paused`]: super::pauser
+#[derive(Clone, Debug)]
+pub struct PauseSequence
+{
+	/// The moves that take the nozzle away from the printed object, which are reversed when the print is resumed.
+	pub parking: ParkingSequence,
+
+	/// Temperature the hotend is kept at while the print is paused, so that the filament doesn't degrade in the nozzle
+	/// (if this is `None` the temperature isn't changed).
+	/// The previous temperature is reached again before the nozzle goes back to the printed object.
+	pub hotend_temperature: Option<Temperature>,
+}
+
+/// The moves made by the machine to take the nozzle away from the printed object.
+#[derive(Clone, Debug)]
+pub struct ParkingSequence
+{
+	/// Length of filament pulled back into the nozzle, so that it doesn't ooze on the printed object.
+	pub retraction_length: Distance,
+	pub retraction_speed_mm_s: f32,
+
+	/// Distance the nozzle is raised from where the print has been stopped (it's raised less if it would go beyond the
+	/// [`max_z`](super::motion::CreationConfig::max_z)).
+	pub z_lift: Distance,
+	pub z_lift_speed_mm_s: f32,
+
+	/// Position of the XY plane the nozzle is moved to after it has been raised (it's not moved if this is `None`).
+	pub park_position: Option<Vector2>,
+	pub park_speed_mm_s: f32,
+}
+
+/// How the filament is moved when it's [`changed`] (the speeds are in millimeters of filament per second).
+///
+/// [`changed`]: super::filament_change
+#[derive(Clone, Debug)]
+pub struct FilamentChangeConfig
+{
+	/// Length of filament pulled back to take it out of the extruder (it should be a bit longer than the path from the
+	/// extruder gears to the nozzle).
+	pub unload_length: Distance,
+	pub unload_speed_mm_s: f32,
+
+	/// Length of filament quickly pushed to bring the new filament from the extruder gears close to the nozzle.
+	pub load_length: Distance,
+	pub load_speed_mm_s: f32,
+
+	/// Length of filament slowly extruded after the load, to flush the old filament out of the nozzle.
+	pub purge_length: Distance,
+	pub purge_speed_mm_s: f32,
 }
 
 /// Temperature-related configurations.
@@ -64,6 +154,7 @@ pub mod temperature
 	///
 	/// This struct defines the parameters necessary to configure a thermistor,
 	/// including its beta value and resistance characteristics.
+	#[derive(Clone, Copy, Debug)]
 	pub struct ThermistorConfig
 	{
 		/// The beta value of the thermistor.
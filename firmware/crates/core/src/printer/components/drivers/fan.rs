@@ -15,6 +15,8 @@ pub struct Fan<P: PwmPin>
 	pin: P,
 	/// The minimum duty cycle required to make the fan start moving.
 	minimum_duty_cycle_fan_moves: Percentage,
+	/// The last speed [`set`](Self::set_speed).
+	speed: Percentage,
 }
 
 impl<P: PwmPin> Fan<P>
@@ -40,6 +42,7 @@ impl<P: PwmPin> Fan<P>
 		Self {
 			pin,
 			minimum_duty_cycle_fan_moves,
+			speed: Percentage::ZERO,
 		}
 	}
 
@@ -50,6 +53,7 @@ impl<P: PwmPin> Fan<P>
 	/// Check [`Self::new`] for more info.
 	pub fn set_speed(&mut self, mut speed: Percentage) -> Result<(), <P as PwmPin>::Error>
 	{
+		self.speed = speed;
 		if speed.into_0_to_1() > 0.
 		{
 			speed = Percentage::from_0_to_1(math::map(
@@ -62,4 +66,10 @@ impl<P: PwmPin> Fan<P>
 
 		self.pin.set_duty_cycle(speed)
 	}
+
+	/// Returns the last speed provided to [`Self::set_speed`] (or [`Percentage::ZERO`] if it has never been called).
+	pub fn get_speed(&self) -> Percentage
+	{
+		self.speed
+	}
 }
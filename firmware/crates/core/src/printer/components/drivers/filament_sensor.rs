@@ -0,0 +1,332 @@
+//! This module provides the implementation for a sensor that detects if the filament has run out (or if it's jammed)
+//! while printing.
+//!
+//! The [`FilamentSensor`] reads a [`Button`] that is either pressed by the filament (a [`switch`]) or toggled by a
+//! wheel moved by the filament (a [`motion encoder`]), and it compares its debounced signal with the length of filament
+//! the extruder has pushed.
+//!
+//! [`switch`]: FilamentSensorKind::Switch
+//! [`motion encoder`]: FilamentSensorKind::MotionEncoder
+
+use std::time::Duration;
+
+use embedded_hal::digital::{ErrorType, InputPin};
+use serde::Serialize;
+
+use super::button::Button;
+use crate::utils::measurement::distance::Distance;
+
+/// How a [`FilamentSensor`] detects the filament.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilamentSensorKind
+{
+	/// A switch that is pressed while there is filament in it.
+	Switch,
+	/// A wheel moved by the filament that toggles the signal every `distance_per_pulse` of filament that passes
+	/// through it, so that it can also detect if the filament is jammed (or ground by the extruder).
+	MotionEncoder
+	{
+		distance_per_pulse: Distance
+	},
+}
+
+/// How a [`FilamentSensor`] works.
+#[derive(Clone, Copy, Debug)]
+pub struct FilamentSensorConfig
+{
+	pub kind: FilamentSensorKind,
+	/// How long the signal of the sensor must stay the same before its new value is considered valid.
+	pub debounce_time: Duration,
+	/// Length of filament the extruder must push without the sensor detecting it before the filament is considered
+	/// [`run out`] (or [`jammed`]).
+	///
+	/// With a [`switch`] this lets the filament between the sensor and the extruder be used, while with a
+	/// [`motion encoder`] this must be greater than its `distance_per_pulse`.
+	///
+	/// [`run out`]: FilamentSensorState::RunOut
+	/// [`jammed`]: FilamentSensorState::Jammed
+	/// [`switch`]: FilamentSensorKind::Switch
+	/// [`motion encoder`]: FilamentSensorKind::MotionEncoder
+	pub runout_distance: Distance,
+}
+
+/// What a [`FilamentSensor`] has detected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FilamentSensorState
+{
+	/// The filament is where it should be.
+	Ok,
+	/// The [`switch`] doesn't detect the filament, but the [`runout distance`] hasn't been extruded yet.
+	///
+	/// [`switch`]: FilamentSensorKind::Switch
+	/// [`runout distance`]: FilamentSensorConfig::runout_distance
+	NoFilament,
+	/// The [`switch`] hasn't detected the filament while the [`runout distance`] has been extruded.
+	///
+	/// [`switch`]: FilamentSensorKind::Switch
+	/// [`runout distance`]: FilamentSensorConfig::runout_distance
+	RunOut,
+	/// The [`motion encoder`] has detected less filament than the one extruded by more than the
+	/// [`runout distance`], so the filament is jammed or it has run out.
+	///
+	/// [`motion encoder`]: FilamentSensorKind::MotionEncoder
+	/// [`runout distance`]: FilamentSensorConfig::runout_distance
+	Jammed,
+}
+
+/// A sensor that detects if the filament has run out or if it's jammed (check the [`module's`] documentation), reading
+/// the signal of the `P` pin.
+///
+/// [`module's`]: self
+pub struct FilamentSensor<P: InputPin>
+{
+	button: Button<P>,
+	config: FilamentSensorConfig,
+
+	/// The last value read from the [`Button`], and the time at which it was read for the first time.
+	last_reading: Option<(bool, Duration)>,
+	/// The last value read from the [`Button`] that has lasted at least for the debounce time.
+	debounced_reading: Option<bool>,
+	/// Length of filament extruded that the sensor hasn't detected.
+	undetected_filament: Distance,
+	state: FilamentSensorState,
+}
+
+impl<P: InputPin> FilamentSensor<P>
+{
+	/// Returns a [`FilamentSensor`] that reads the provided `button` and works as described by the `config`.
+	pub fn new(button: Button<P>, config: FilamentSensorConfig) -> Self
+	{
+		Self {
+			button,
+			config,
+			last_reading: None,
+			debounced_reading: None,
+			undetected_filament: Distance::ZERO,
+			state: FilamentSensorState::Ok,
+		}
+	}
+
+	/// Reads the sensor and compares its signal with the `extruded_filament` length pushed by the extruder since the
+	/// last call to this method (`current_time` is the time elapsed since any instant, and it's used to debounce the
+	/// signal).
+	///
+	/// Returns `Ok(true)` if the filament has just [`run out`] or [`jammed`] (it's returned only once until you call
+	/// [`Self::rearm`]), and `Err(<P as ErrorType>::Error)` if there has been an error while reading the pin.
+	///
+	/// [`run out`]: FilamentSensorState::RunOut
+	/// [`jammed`]: FilamentSensorState::Jammed
+	pub fn tick(&mut self, extruded_filament: Distance, current_time: Duration)
+		-> Result<bool, <P as ErrorType>::Error>
+	{
+		let reading = self.button.is_pressed()?;
+		let (last_reading, last_reading_time) = *self.last_reading.get_or_insert((reading, current_time));
+		if reading != last_reading
+		{
+			self.last_reading = Some((reading, current_time));
+		}
+
+		let is_reading_stable =
+			reading == last_reading && current_time >= last_reading_time + self.config.debounce_time;
+		let mut has_signal_toggled = false;
+		if is_reading_stable && self.debounced_reading != Some(reading)
+		{
+			has_signal_toggled = self.debounced_reading.is_some();
+			self.debounced_reading = Some(reading);
+		}
+
+		self.undetected_filament += extruded_filament;
+		match self.config.kind
+		{
+			FilamentSensorKind::Switch =>
+			{
+				if self.is_filament_detected()
+				{
+					self.undetected_filament = Distance::ZERO;
+				}
+			},
+			FilamentSensorKind::MotionEncoder { distance_per_pulse } =>
+			{
+				if has_signal_toggled
+				{
+					self.undetected_filament -= distance_per_pulse;
+				}
+			},
+		}
+		// The filament detected in excess (for example while retracting) isn't used to compensate the next one
+		self.undetected_filament = self.undetected_filament.max(Distance::ZERO);
+
+		let has_failed = self.undetected_filament >= self.config.runout_distance;
+		let state = match (self.config.kind, has_failed)
+		{
+			(FilamentSensorKind::Switch, true) => FilamentSensorState::RunOut,
+			(FilamentSensorKind::MotionEncoder { .. }, true) => FilamentSensorState::Jammed,
+			(FilamentSensorKind::Switch, false) if !self.is_filament_detected() => FilamentSensorState::NoFilament,
+			(_, false) => FilamentSensorState::Ok,
+		};
+		let has_just_failed = has_failed && !self.has_failed();
+		self.state = state;
+
+		Ok(has_just_failed)
+	}
+
+	/// Forgets the filament extruded until now, so that the filament is considered [`run out`] (or [`jammed`]) only
+	/// after the whole [`runout distance`] has been extruded again (call this when the filament has been replaced).
+	///
+	/// [`run out`]: FilamentSensorState::RunOut
+	/// [`jammed`]: FilamentSensorState::Jammed
+	/// [`runout distance`]: FilamentSensorConfig::runout_distance
+	pub fn rearm(&mut self)
+	{
+		self.undetected_filament = Distance::ZERO;
+		if self.has_failed()
+		{
+			self.state = FilamentSensorState::Ok;
+		}
+	}
+
+	/// Returns what the sensor detected the last time you called [`Self::tick`].
+	pub fn get_state(&self) -> FilamentSensorState
+	{
+		self.state
+	}
+
+	fn has_failed(&self) -> bool
+	{
+		matches!(self.state, FilamentSensorState::RunOut | FilamentSensorState::Jammed)
+	}
+
+	/// Returns `true` if the debounced signal says there is filament (it's always `true` for a motion encoder).
+	fn is_filament_detected(&self) -> bool
+	{
+		match self.config.kind
+		{
+			FilamentSensorKind::Switch => self.debounced_reading.unwrap_or(true),
+			FilamentSensorKind::MotionEncoder { .. } => true,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::printer::components::mock::MockInputPin;
+
+	const DEBOUNCE_TIME: Duration = Duration::from_millis(20);
+	const TICK_PERIOD: Duration = Duration::from_millis(5);
+
+	fn sensor(kind: FilamentSensorKind) -> (FilamentSensor<MockInputPin>, MockInputPin)
+	{
+		let pin = MockInputPin::default();
+		let sensor = FilamentSensor::new(
+			Button::new(pin.clone()),
+			FilamentSensorConfig {
+				kind,
+				debounce_time: DEBOUNCE_TIME,
+				runout_distance: Distance::from_millimeters(10),
+			},
+		);
+
+		(sensor, pin)
+	}
+
+	/// Ticks the `sensor` `ticks_count` times (extruding `extruded_filament_per_tick` each time), starting at `time`,
+	/// and returns how many times it has failed.
+	fn tick_for(
+		sensor: &mut FilamentSensor<MockInputPin>, time: &mut Duration, ticks_count: u32,
+		extruded_filament_per_tick: Distance,
+	) -> u32
+	{
+		let mut failures_count = 0;
+		for _ in 0..ticks_count
+		{
+			*time += TICK_PERIOD;
+			failures_count += sensor.tick(extruded_filament_per_tick, *time).unwrap() as u32;
+		}
+
+		failures_count
+	}
+
+	#[test]
+	fn switch_runs_out_after_the_runout_distance()
+	{
+		let (mut sensor, mut pin) = sensor(FilamentSensorKind::Switch);
+		let mut time = Duration::ZERO;
+
+		pin.set_level(true);
+		assert_eq!(tick_for(&mut sensor, &mut time, 20, Distance::MILLIMETER), 0);
+		assert_eq!(sensor.get_state(), FilamentSensorState::Ok);
+
+		pin.set_level(false);
+		assert_eq!(tick_for(&mut sensor, &mut time, 10, Distance::MILLIMETER), 0);
+		assert_eq!(sensor.get_state(), FilamentSensorState::NoFilament);
+		assert_eq!(tick_for(&mut sensor, &mut time, 10, Distance::MILLIMETER), 1);
+		assert_eq!(sensor.get_state(), FilamentSensorState::RunOut);
+
+		sensor.rearm();
+		assert_eq!(tick_for(&mut sensor, &mut time, 5, Distance::MILLIMETER), 0);
+		assert_eq!(tick_for(&mut sensor, &mut time, 10, Distance::MILLIMETER), 1);
+	}
+
+	#[test]
+	fn switch_ignores_glitches_shorter_than_the_debounce_time()
+	{
+		let (mut sensor, mut pin) = sensor(FilamentSensorKind::Switch);
+		let mut time = Duration::ZERO;
+
+		pin.set_level(true);
+		tick_for(&mut sensor, &mut time, 10, Distance::ZERO);
+		for _ in 0..50
+		{
+			pin.set_level(false);
+			assert_eq!(tick_for(&mut sensor, &mut time, 2, Distance::MILLIMETER), 0);
+			pin.set_level(true);
+			assert_eq!(tick_for(&mut sensor, &mut time, 1, Distance::MILLIMETER), 0);
+		}
+		assert_eq!(sensor.get_state(), FilamentSensorState::Ok);
+	}
+
+	#[test]
+	fn motion_encoder_detects_jams()
+	{
+		let (mut sensor, mut pin) = sensor(FilamentSensorKind::MotionEncoder {
+			distance_per_pulse: Distance::from_millimeters(3),
+		});
+		let mut time = Duration::ZERO;
+
+		// The filament moves the wheel as much as it's extruded
+		for _ in 0..20
+		{
+			pin.set_level(!pin.clone().is_high().unwrap());
+			assert_eq!(tick_for(&mut sensor, &mut time, 6, Distance::from_micrometers(500)), 0);
+		}
+		assert_eq!(sensor.get_state(), FilamentSensorState::Ok);
+
+		// The wheel doesn't move anymore
+		assert_eq!(tick_for(&mut sensor, &mut time, 30, Distance::from_micrometers(500)), 1);
+		assert_eq!(sensor.get_state(), FilamentSensorState::Jammed);
+	}
+
+	#[test]
+	fn motion_encoder_does_not_bank_the_filament_detected_while_retracting()
+	{
+		let (mut sensor, mut pin) = sensor(FilamentSensorKind::MotionEncoder {
+			distance_per_pulse: Distance::from_millimeters(3),
+		});
+		let mut time = Duration::ZERO;
+
+		// A long retraction and its recovery move the wheel, but the net extruded filament is zero
+		for extruded_filament in [-Distance::from_millimeters(30), Distance::from_millimeters(30)]
+		{
+			for _ in 0..10
+			{
+				pin.set_level(!pin.clone().is_high().unwrap());
+				tick_for(&mut sensor, &mut time, 6, extruded_filament / 60);
+			}
+		}
+
+		assert_eq!(tick_for(&mut sensor, &mut time, 30, Distance::from_micrometers(500)), 1);
+	}
+}
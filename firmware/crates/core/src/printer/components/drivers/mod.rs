@@ -8,6 +8,7 @@ pub mod bl_touch;
 pub mod button;
 pub mod cartridge_heater;
 pub mod fan;
+pub mod filament_sensor;
 pub mod servo_motor;
 pub mod spi_flash_memory;
 pub mod stepper_motor;
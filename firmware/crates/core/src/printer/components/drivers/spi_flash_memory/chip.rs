@@ -1,6 +1,6 @@
 use embedded_hal::spi::{ErrorType, Mode, SpiDevice, MODE_0};
 
-use super::{address::RowAddress, FeatureRegister, SpiFlashMemory};
+use super::{address::RowAddress, EccStatus, FeatureRegister, SpiFlashMemory};
 use crate::utils::measurement::frequency::Frequency;
 
 /// A type that represents a [`flash memory chip`](https://en.wikipedia.org/wiki/Flash_memory).
@@ -40,6 +40,10 @@ pub trait FlashMemoryChip
 		block_index: u16, spi_flash_memory: &mut SpiFlashMemory<Self, Spi>,
 	) -> Result<bool, <Spi as ErrorType>::Error>
 	where Self: Sized;
+
+	/// Returns the [`EccStatus`] of the last page read, based on the value of the [`FeatureRegister::Status`] register
+	/// of the chip after the read.
+	fn get_ecc_status(status_register: u8) -> EccStatus;
 }
 
 /// Extra functionality provided automatically to every type that implements [`FlashMemoryChip`].
@@ -110,6 +114,19 @@ impl FlashMemoryChip for MT29F2G01ABAGDWB
 		let row_address = RowAddress::from_memory_address(block_index as u32 * Self::BLOCK_SIZE);
 		spi_flash_memory.read_ecc(row_address, core::slice::from_mut(&mut data))?;
 
-		Ok(data != 0)
+		// The manufacturer marks the bad blocks with a value different than 0xFF (the erased one)
+		Ok(data != 0xFF)
+	}
+
+	fn get_ecc_status(status_register: u8) -> EccStatus
+	{
+		// Check page 41 of the datasheet
+		match (status_register >> 4) & 0b111
+		{
+			0b000 => EccStatus::NoErrors,
+			// 1-3, 4-6 or 7-8 bits have been corrected
+			0b001 | 0b011 | 0b101 => EccStatus::Corrected,
+			_ => EccStatus::Uncorrectable,
+		}
 	}
 }
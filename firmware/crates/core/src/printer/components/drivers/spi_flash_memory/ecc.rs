@@ -0,0 +1,42 @@
+/// The result of the error correction the chip does on a page each time it's read.
+///
+/// The variants are ordered from the best to the worst result, so that the worst result of a read that spans
+/// multiple pages is their maximum.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Hash)]
+pub enum EccStatus
+{
+	/// The page didn't contain any flipped bit.
+	NoErrors,
+	/// The page contained some flipped bits, and they have been corrected.
+	Corrected,
+	/// The page contained too many flipped bits to be corrected, so the read data is corrupted.
+	Uncorrectable,
+}
+
+/// How many pages reported each [`EccStatus`] since the [`SpiFlashMemory`] has been created.
+///
+/// [`SpiFlashMemory`]: super::SpiFlashMemory
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct EccCounters
+{
+	/// The number of pages read from the chip.
+	pub read_pages_count: u64,
+	/// The number of read pages whose flipped bits have been corrected.
+	pub corrected_pages_count: u32,
+	/// The number of read pages whose flipped bits couldn't be corrected.
+	pub uncorrectable_pages_count: u32,
+}
+
+impl EccCounters
+{
+	pub(super) fn count(&mut self, ecc_status: EccStatus)
+	{
+		self.read_pages_count += 1;
+		match ecc_status
+		{
+			EccStatus::NoErrors => (),
+			EccStatus::Corrected => self.corrected_pages_count += 1,
+			EccStatus::Uncorrectable => self.uncorrectable_pages_count += 1,
+		}
+	}
+}
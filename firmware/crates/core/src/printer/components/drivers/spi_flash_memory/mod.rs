@@ -7,6 +7,8 @@
 //! # Features
 //! - **Programming and Reading**: Read and write data to flash memory pages.
 //! - **Erase Functionality**: Erase blocks of flash memory efficiently.
+//! - **ECC Checking**: Check the result of the error correction of each read page, and keep track of the pages and
+//!   blocks that reported errors.
 //! - **ID Validation**: Validate the manufacturer and device IDs to ensure proper communication with the chip.
 //! - **Feature Management**: Get and set various features of the flash memory chip.
 //!
@@ -30,9 +32,11 @@ use crate::utils::math::NumberExt;
 mod address;
 mod chip;
 mod commands;
+mod ecc;
 mod features;
 
 pub use chip::*;
+pub use ecc::*;
 pub use features::*;
 
 /// A flash memory connected to the microcontroller through a SPI interface.
@@ -46,13 +50,22 @@ pub struct SpiFlashMemory<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>
 {
 	spi: Spi,
 	chip: Chip,
+	ecc_counters: EccCounters,
+	/// The blocks that contained pages with flipped bits since the last call to [`Self::take_blocks_with_ecc_errors`]
+	/// (each one with the worst [`EccStatus`] of its pages).
+	blocks_with_ecc_errors: Vec<(u16, EccStatus)>,
 }
 
 impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> SpiFlashMemory<Chip, Spi>
 {
 	pub fn new(spi: Spi, chip: Chip) -> Self
 	{
-		Self { spi, chip }
+		Self {
+			spi,
+			chip,
+			ecc_counters: EccCounters::default(),
+			blocks_with_ecc_errors: Vec::new(),
+		}
 	}
 
 	/// Program the provided `data` in the flash memory starting from the provided `address`,
@@ -114,26 +127,49 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> SpiFlashMemory<Chip, Spi>
 
 	/// Reads [`data.len()`] bytes from the data areas of the pages starting from the specified `address`.
 	///
-	/// Returns `Ok(())` if all the bytes have been read successfully, otherwise returns `Err(...)`.
+	/// Returns `Ok(ecc_status)` if all the bytes have been read (where `ecc_status` is the worst [`EccStatus`] of the
+	/// read pages, so if it's [`EccStatus::Uncorrectable`] some of the read bytes are wrong), otherwise returns
+	/// `Err(...)`.
 	///
 	/// # Note
 	/// The read will only affect the data area of a page, not the ECC one.
-	pub fn read(&mut self, address: u32, data: &mut [u8]) -> Result<(), <Spi as ErrorType>::Error>
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::{mock::*, drivers::spi_flash_memory::*};
+	/// #
+	/// let mut memory = MockFlashMemory::<MT29F2G01ABAGDWB>::default();
+	/// let mut spi_flash_memory = SpiFlashMemory::new(memory.clone(), MT29F2G01ABAGDWB);
+	///
+	/// // The second page of the block 3 has 2 flipped bits
+	/// memory.inject_bit_flips(3 * 64 + 1, [0, 1]);
+	/// let mut data = [0; 4096];
+	/// assert_eq!(spi_flash_memory.read(3 * 131_072, &mut data), Ok(EccStatus::Corrected));
+	/// assert!(data.iter().all(|&byte| byte == 0xFF));
+	///
+	/// assert_eq!(spi_flash_memory.take_blocks_with_ecc_errors(), [(3, EccStatus::Corrected)]);
+	/// assert_eq!(spi_flash_memory.get_ecc_counters().corrected_pages_count, 1);
+	/// ```
+	pub fn read(&mut self, address: u32, data: &mut [u8]) -> Result<EccStatus, <Spi as ErrorType>::Error>
 	{
+		let mut worst_ecc_status = EccStatus::NoErrors;
 		if data.len() == 0
 		{
-			return Ok(());
+			return Ok(worst_ecc_status);
 		}
 
 		Self::cycle_pages(address, data.len() as u32, |parameters| {
-			self.read_internal(
+			let ecc_status = self.read_internal(
 				parameters.row_address,
 				parameters.column_address,
 				&mut data[parameters.data_range],
-			)
+			)?;
+			worst_ecc_status = worst_ecc_status.max(ecc_status);
+
+			Ok(())
 		})?;
 
-		Ok(())
+		Ok(worst_ecc_status)
 	}
 
 	/// Reads [`data.len()`] bytes from the ECC area of the page identified by the provided `row_address`.
@@ -154,7 +190,9 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> SpiFlashMemory<Chip, Spi>
 			row_address,
 			ColumnAddress::new(Chip::PAGE_SIZE as u16, row_address.get_plane_index()),
 			data,
-		)
+		)?;
+
+		Ok(())
 	}
 
 	/// Moves the bytes in the `from` address range to the address range of the same size that starts at
@@ -164,19 +202,26 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> SpiFlashMemory<Chip, Spi>
 	/// at the right location, but everything is done in the flash memory chip.
 	///
 	/// # Warning
+	/// The pages are moved entirely (even the bytes outside of `from`), and a page can be moved only to a page of the
+	/// same plane (the move goes through the cache of the plane).
+	///
 	/// Check [`struct's documentation`](Self#warning).
 	pub fn internal_data_move(
 		&mut self, from: RangeInclusive<u32>, to_start_address: u32,
 	) -> Result<(), <Spi as ErrorType>::Error>
 	{
-		Command::<Chip>::WriteEnable.execute(&mut self.spi)?;
-
-		Self::cycle_pages(*from.start(), from.end() - from.start(), |parameters| {
+		Self::cycle_pages(*from.start(), from.end() - from.start() + 1, |parameters| {
 			Command::PageRead::<Chip> {
 				row_address: parameters.row_address,
 			}
 			.execute(&mut self.spi)?;
 
+			let status = self.wait_for_operation_to_finish()?;
+			self.check_ecc_status(parameters.row_address, status);
+
+			// The write enable latch is reset after each program execute
+			Command::<Chip>::WriteEnable.execute(&mut self.spi)?;
+
 			Command::ProgramLoadRandomData::<Chip> {
 				column_address: parameters.column_address,
 				input: &[],
@@ -286,37 +331,84 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> SpiFlashMemory<Chip, Spi>
 		Ok(())
 	}
 
-	/// Returns a mutable reference to the underlying chip.
-	pub fn get_chip_mut(&mut self) -> &mut Chip
+	/// Returns how many pages reported each [`EccStatus`] since this struct has been created.
+	pub fn get_ecc_counters(&self) -> EccCounters
 	{
-		&mut self.chip
+		self.ecc_counters
+	}
+
+	/// Returns the indices of the blocks that contained pages with flipped bits (each one with the worst [`EccStatus`]
+	/// of its pages) since the last time you called this method.
+	///
+	/// A block whose pages repeatedly require a correction is wearing out, and its data should be moved to another
+	/// block before the flipped bits become too many to be corrected.
+	pub fn take_blocks_with_ecc_errors(&mut self) -> Vec<(u16, EccStatus)>
+	{
+		core::mem::take(&mut self.blocks_with_ecc_errors)
 	}
 
-	fn is_operation_in_progress(&mut self) -> Result<bool, <Spi as ErrorType>::Error>
+	/// Returns a mutable reference to the underlying chip.
+	pub fn get_chip_mut(&mut self) -> &mut Chip
 	{
-		let status = self.get_features(FeatureRegister::Status)?;
-		let is_in_progress = (status & 0b0000_0001) == 1;
-		Ok(is_in_progress)
+		&mut self.chip
 	}
 
-	fn wait_for_operation_to_finish(&mut self) -> Result<(), <Spi as ErrorType>::Error>
+	/// Returns the value of the [`FeatureRegister::Status`] register once the operation has finished.
+	fn wait_for_operation_to_finish(&mut self) -> Result<u8, <Spi as ErrorType>::Error>
 	{
-		while self.is_operation_in_progress()?
-		{}
-		Ok(())
+		loop
+		{
+			let status = self.get_features(FeatureRegister::Status)?;
+			if (status & 0b0000_0001) == 0
+			{
+				return Ok(status);
+			}
+		}
 	}
 
 	fn read_internal(
 		&mut self, row_address: RowAddress<Chip>, column_address: ColumnAddress, output: &mut [u8],
-	) -> Result<(), <Spi as ErrorType>::Error>
+	) -> Result<EccStatus, <Spi as ErrorType>::Error>
 	{
 		Command::PageRead::<Chip> { row_address }.execute(&mut self.spi)?;
 
-		self.wait_for_operation_to_finish()?;
+		let status = self.wait_for_operation_to_finish()?;
+		let ecc_status = self.check_ecc_status(row_address, status);
 
 		Command::ReadFromCache::<Chip> { column_address, output }.execute(&mut self.spi)?;
 
-		Ok(())
+		Ok(ecc_status)
+	}
+
+	/// Counts the [`EccStatus`] (based on the `status` register) of the page at `row_address` that has just been read,
+	/// and returns it.
+	fn check_ecc_status(&mut self, row_address: RowAddress<Chip>, status: u8) -> EccStatus
+	{
+		let ecc_status = Chip::get_ecc_status(status);
+		self.ecc_counters.count(ecc_status);
+
+		if ecc_status != EccStatus::NoErrors
+		{
+			let block_index = (row_address.get_page_index() / Chip::PAGES_PER_BLOCK) as u16;
+			match self
+				.blocks_with_ecc_errors
+				.iter_mut()
+				.find(|(other_block_index, _)| *other_block_index == block_index)
+			{
+				Some((_, worst_ecc_status)) => *worst_ecc_status = (*worst_ecc_status).max(ecc_status),
+				None => self.blocks_with_ecc_errors.push((block_index, ecc_status)),
+			}
+
+			if ecc_status == EccStatus::Uncorrectable
+			{
+				log::error!(
+					"The page {} of the flash memory is corrupted",
+					row_address.get_page_index()
+				);
+			}
+		}
+
+		ecc_status
 	}
 
 	/// Check the test module below for some examples.
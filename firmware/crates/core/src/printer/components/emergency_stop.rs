@@ -0,0 +1,91 @@
+//! This module lets the user halt the machine as soon as something goes wrong, with the `M112` G-code command, the
+//! `/v1/emergency-stop` HTTP request or the (optional) emergency stop button.
+//!
+//! [`stop`] immediately calls the same function that's called when the firmware panics (check [`PanicHandler`]), then
+//! in their next tick the [`Printer3DComponents`] turn off the heaters, stop the stepper motors and disable their
+//! drivers. Unlike a panic, the firmware keeps running (so it can still report its state), but it stays [`halted`]
+//! until the microcontroller is reset (which can be [`requested`](request_reset) remotely).
+//!
+//! [`PanicHandler`]: crate::printer::panic_handler::PanicHandler
+//! [`Printer3DComponents`]: super::Printer3DComponents
+//! [`halted`]: is_halted
+
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	OnceLock,
+};
+
+static IS_HALTED: AtomicBool = AtomicBool::new(false);
+static IS_RESET_REQUESTED: AtomicBool = AtomicBool::new(false);
+static DISABLE_ALL_PINS_FUNCTION: OnceLock<unsafe fn()> = OnceLock::new();
+
+/// Sets the function [`stop`] calls to pull low all the pins of the microcontroller (it can be set only once).
+pub(crate) fn set_disable_all_pins_function(disable_all_pins_function: unsafe fn())
+{
+	let _ = DISABLE_ALL_PINS_FUNCTION.set(disable_all_pins_function);
+}
+
+/// Halts the machine (check the [`module's`] documentation).
+///
+/// It's safe to call this function in an interrupt service routine.
+///
+/// [`module's`]: self
+pub fn stop()
+{
+	IS_HALTED.store(true, Ordering::Relaxed);
+
+	if let Some(disable_all_pins_function) = DISABLE_ALL_PINS_FUNCTION.get()
+	{
+		unsafe { disable_all_pins_function() }
+	}
+}
+
+/// Returns `true` if the machine has been halted by [`stop`].
+///
+/// # Examples
+/// ```
+/// # use firmware_core::printer::components::emergency_stop;
+/// #
+/// assert!(!emergency_stop::is_halted());
+///
+/// emergency_stop::stop();
+/// assert!(emergency_stop::is_halted());
+/// ```
+pub fn is_halted() -> bool
+{
+	IS_HALTED.load(Ordering::Relaxed)
+}
+
+/// Asks the `Communication` thread to reboot the microcontroller, so that the machine can work again after it has been
+/// [`halted`](stop).
+///
+/// Returns `false` (without doing anything) if the machine isn't [`halted`](is_halted).
+pub fn request_reset() -> bool
+{
+	let is_halted = is_halted();
+	if is_halted
+	{
+		IS_RESET_REQUESTED.store(true, Ordering::Relaxed);
+	}
+
+	is_halted
+}
+
+/// Returns `true` if [`request_reset`] has been called successfully since the last time this function returned `true`.
+pub fn take_reset_request() -> bool
+{
+	IS_RESET_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// Must be locked by the unit tests that halt the machine or that depend on it not being halted, since they share the
+/// same global state.
+#[cfg(test)]
+pub(crate) static TESTS_LOCK: spin::Mutex<()> = spin::Mutex::new(());
+
+/// Makes the machine work again after it has been [`halted`](stop), like when the microcontroller is reset.
+#[cfg(test)]
+pub(crate) fn reset()
+{
+	IS_HALTED.store(false, Ordering::Relaxed);
+	IS_RESET_REQUESTED.store(false, Ordering::Relaxed);
+}
@@ -0,0 +1,162 @@
+//! This module lets the user replace the filament loaded in the extruder.
+//!
+//! The `M702` and `M701` G-code commands respectively [`unload`] and [`load`] the filament, while `M600` makes the
+//! [`Pauser`] pause the print, park the nozzle and unload the filament, then wait until the user has inserted the new
+//! filament and [`confirmed`] it, and finally load it and resume the print.
+//!
+//! [`unload`]: unload_moves
+//! [`load`]: load_moves
+//! [`Pauser`]: super::pauser::Pauser
+//! [`confirmed`]: confirm
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::{
+	config::FilamentChangeConfig,
+	motion::{
+		planner::{MoveId, PlanMoveError},
+		MotionController,
+	},
+	pauser, Peripherals,
+};
+use crate::utils::measurement::distance::Distance;
+
+static IS_REQUESTED: AtomicBool = AtomicBool::new(false);
+static IS_WAITING_FOR_CONFIRMATION: AtomicBool = AtomicBool::new(false);
+static IS_CONFIRMED: AtomicBool = AtomicBool::new(false);
+
+/// Requests to change the filament, [`pausing`] the print (check the [`module's`] documentation).
+///
+/// [`pausing`]: pauser::pause
+/// [`module's`]: self
+pub fn request()
+{
+	IS_REQUESTED.store(true, Ordering::Relaxed);
+	pauser::pause();
+}
+
+/// Returns `true` if [`request`] has been called since the last time this function has been called.
+pub fn take_request() -> bool
+{
+	IS_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// Makes [`is_waiting_for_confirmation`] return `is_waiting` (discarding any previous confirmation).
+pub fn set_waiting_for_confirmation(is_waiting: bool)
+{
+	IS_CONFIRMED.store(false, Ordering::Relaxed);
+	IS_WAITING_FOR_CONFIRMATION.store(is_waiting, Ordering::Relaxed);
+}
+
+/// Returns `true` if the old filament has been unloaded and the machine is waiting for the user to insert the new one
+/// (check [`confirm`]).
+pub fn is_waiting_for_confirmation() -> bool
+{
+	IS_WAITING_FOR_CONFIRMATION.load(Ordering::Relaxed)
+}
+
+/// Tells the machine that the new filament has been inserted in the extruder, so that it can be loaded.
+///
+/// Returns `false` (without doing anything) if the machine isn't [`waiting`] for it.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::printer::components::filament_change;
+/// #
+/// assert!(!filament_change::confirm());
+///
+/// filament_change::set_waiting_for_confirmation(true);
+/// assert!(filament_change::confirm());
+/// assert!(filament_change::take_confirmation());
+/// assert!(!filament_change::take_confirmation());
+/// ```
+///
+/// [`waiting`]: is_waiting_for_confirmation
+pub fn confirm() -> bool
+{
+	let is_waiting = is_waiting_for_confirmation();
+	if is_waiting
+	{
+		IS_CONFIRMED.store(true, Ordering::Relaxed);
+	}
+
+	is_waiting
+}
+
+/// Returns `true` if the user has [`confirmed`] that the new filament has been inserted since the last time this
+/// function has been called.
+///
+/// [`confirmed`]: confirm
+pub fn take_confirmation() -> bool
+{
+	IS_CONFIRMED.swap(false, Ordering::Relaxed)
+}
+
+/// A move of the extruder made to load or unload the filament (check [`MotionController::plan_extruder_move`]).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExtruderMove
+{
+	/// Length of filament pushed into the extruder (it's negative if the filament is pulled back).
+	pub length: Distance,
+	pub speed_mm_s: f32,
+}
+
+/// Returns the moves that pull the filament out of the extruder.
+pub fn unload_moves(config: &FilamentChangeConfig) -> [ExtruderMove; 1]
+{
+	[ExtruderMove {
+		length: -config.unload_length,
+		speed_mm_s: config.unload_speed_mm_s,
+	}]
+}
+
+/// Returns the moves that push the filament into the extruder and then purge the nozzle.
+pub fn load_moves(config: &FilamentChangeConfig) -> [ExtruderMove; 2]
+{
+	[
+		ExtruderMove {
+			length: config.load_length,
+			speed_mm_s: config.load_speed_mm_s,
+		},
+		ExtruderMove {
+			length: config.purge_length,
+			speed_mm_s: config.purge_speed_mm_s,
+		},
+	]
+}
+
+/// Plans a sequence of [`ExtruderMove`]s, even when the buffer of the planner gets full in the middle of them.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct ExtruderMovesPlanner
+{
+	planned_moves_count: usize,
+	last_move_id: MoveId,
+}
+
+impl ExtruderMovesPlanner
+{
+	/// Plans the `moves` that haven't been planned yet by the previous calls to this method.
+	///
+	/// Returns the [`MoveId`] of the last move once all of them have been planned, or
+	/// `Err(PlanMoveError::BlocksBufferIsFull)` if not all of them could be planned, and you **MUST** call this method
+	/// again to plan the remaining ones.
+	pub fn plan<P: Peripherals>(
+		&mut self, motion_controller: &mut MotionController<P::StepperTickerTimer, P::Kinematics, P::ZAxisEndstop>,
+		moves: &[ExtruderMove],
+	) -> Result<MoveId, PlanMoveError>
+	{
+		for extruder_move in moves.iter().skip(self.planned_moves_count)
+		{
+			self.last_move_id = motion_controller.plan_extruder_move(extruder_move.length, extruder_move.speed_mm_s)?;
+			self.planned_moves_count += 1;
+		}
+
+		Ok(self.last_move_id)
+	}
+
+	/// Makes the next call to [`Self::plan`] start again from the first move.
+	pub fn reset(&mut self)
+	{
+		*self = Self::default();
+	}
+}
@@ -22,8 +22,7 @@ impl BadBlockTable
 
 		for block_index in 0..(Chip::MEMORY_SIZE / Chip::BLOCK_SIZE) as u16
 		{
-			let is_block_valid = Chip::contains_bad_block_mark(block_index, spi_flash_memory)?;
-			if !is_block_valid
+			if Chip::contains_bad_block_mark(block_index, spi_flash_memory)?
 			{
 				bad_blocks_indices.push(block_index);
 
@@ -36,14 +35,15 @@ impl BadBlockTable
 		Ok(Self { bad_blocks_indices })
 	}
 
-	/// Restores the bad block table from a previous one you [`converted`] to bytes.
+	/// Restores the bad block table from a previous one you [`converted`] to bytes (`bytes` must not contain the first
+	/// byte returned by the conversion, which is the number of bad blocks).
 	///
 	/// [`converted`]: `BadBlockTable::as_bytes`
 	pub fn from_bytes(bytes: &[u8]) -> Self
 	{
 		let mut bad_blocks_indices = Vec::with_capacity(bytes.len() / 2);
 		bytes
-			.windows(2)
+			.chunks_exact(2)
 			.for_each(|bytes| bad_blocks_indices.push(u16::from_le_bytes([bytes[0], bytes[1]])));
 
 		Self { bad_blocks_indices }
@@ -59,7 +59,7 @@ impl BadBlockTable
 
 	pub fn is_block_valid(&self, block_index: u16) -> bool
 	{
-		self.bad_blocks_indices.contains(&block_index)
+		!self.bad_blocks_indices.contains(&block_index)
 	}
 
 	pub fn as_bytes(&self) -> BadBlockTableBytes<'_>
@@ -94,15 +94,14 @@ impl<'a> Iterator for BadBlockTableBytes<'a>
 
 			Some(self.bad_blocks_table.bad_blocks_indices.len() as u8)
 		}
-		else if (self.i - 1) * 2 < self.bad_blocks_table.bad_blocks_indices.len()
+		else if self.i <= self.bad_blocks_table.bad_blocks_indices.len() * 2
 		{
+			let byte_index = self.i - 1;
 			self.i += 1;
 
-			let i = self.i - 1;
+			let block = self.bad_blocks_table.bad_blocks_indices[byte_index / 2];
 
-			let block = self.bad_blocks_table.bad_blocks_indices[i / 2];
-
-			Some(block.to_le_bytes()[i % 2])
+			Some(block.to_le_bytes()[byte_index % 2])
 		}
 		else
 		{
@@ -110,3 +109,41 @@ impl<'a> Iterator for BadBlockTableBytes<'a>
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::printer::components::mock::MockFlashMemory;
+
+	#[test]
+	fn first_powerup_finds_the_marked_blocks()
+	{
+		let mut memory = MockFlashMemory::<MT29F2G01ABAGDWB>::default();
+		memory.mark_block_as_bad(3);
+		memory.mark_block_as_bad(1500);
+		let mut spi_flash_memory = SpiFlashMemory::new(memory, MT29F2G01ABAGDWB);
+
+		let bad_block_table = BadBlockTable::from_first_powerup(&mut spi_flash_memory).unwrap();
+
+		assert_eq!(bad_block_table.indices(), [3, 1500]);
+		assert!(!bad_block_table.is_block_valid(3));
+		assert!(bad_block_table.is_block_valid(4));
+	}
+
+	#[test]
+	fn bytes_conversion_round_trip()
+	{
+		let mut bad_block_table = BadBlockTable::default();
+		for block_index in [7, 300, 2047]
+		{
+			bad_block_table.mark_block_as_invalid(block_index);
+		}
+
+		let bytes: Vec<u8> = bad_block_table.as_bytes().collect();
+		assert_eq!(bytes, [3, 7, 0, 44, 1, 255, 7]);
+
+		let restored_bad_block_table = BadBlockTable::from_bytes(&bytes[1..]);
+		assert_eq!(restored_bad_block_table.indices(), bad_block_table.indices());
+	}
+}
@@ -2,15 +2,28 @@ use std::fmt::Debug;
 
 use embedded_hal::spi::{ErrorType, SpiDevice};
 
-use self::regions::{
-	data::{FileReader, FileWriter, FilesRegion},
-	metadata::{FileDoesntExist, FileId, FileMetadata, FilesMetadatasRegion, NotEnoughSpaceAvailable},
-	RegionsConfig,
+use self::{
+	regions::{
+		checkpoint::{CheckpointRegion, StoreError as StoreCheckpointError},
+		data::{ContentChecksums, FileReader, FileWriter, FilesRegion, WriteError},
+		metadata::{
+			FileDoesntExist, FileId, FileMetadata, FilesMetadatasRegion, FindSpaceError,
+			ReadFromFlashError as ReadMetadatasRegionError, StoreError as StoreMetadatasError,
+		},
+		RegionsConfig,
+	},
+	wear_leveling::WearStatistics,
 };
-use super::drivers::spi_flash_memory::{FlashMemoryChip, SpiFlashMemory};
+use super::drivers::spi_flash_memory::{EccCounters, EccStatus, FlashMemoryChip, SpiFlashMemory};
 
 pub mod bad_blocks;
+pub mod path;
 pub mod regions;
+pub mod wear_leveling;
+
+/// The suffix added to the name of a file to get the name of the file where [`FileSystem::replace_file`] writes its
+/// new content.
+pub const REPLACEMENT_FILE_SUFFIX: &str = ".new";
 
 pub struct FileSystem<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>
 {
@@ -20,6 +33,7 @@ pub struct FileSystem<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>
 
 	metadatas_region: FilesMetadatasRegion,
 	files_region: FilesRegion,
+	checkpoint_region: CheckpointRegion,
 }
 
 impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileSystem<Chip, Spi>
@@ -30,16 +44,33 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileSystem<Chip, Spi>
 	{
 		Chip::initialize(&mut spi_flash_memory).map_err(CreationError::InitializeChip)?;
 
-		let metadatas_region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config)
-			.map_err(CreationError::MetadatasRegion)?;
+		let metadatas_region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).map_err(
+			|error| match error
+			{
+				ReadMetadatasRegionError::Store(error) => CreationError::MetadatasRegion(error),
+				ReadMetadatasRegionError::IncompatibleLayout { version } =>
+				{
+					CreationError::IncompatibleMetadatasRegionLayout { version }
+				},
+			},
+		)?;
 		let files_region = FilesRegion;
+		let checkpoint_region =
+			CheckpointRegion::read_from_flash(&mut spi_flash_memory, &regions_config, |block_index| {
+				Self::is_checkpoint_block_usable(&metadatas_region, block_index)
+			})
+			.map_err(CreationError::CheckpointRegion)?;
 
-		Ok(Self {
+		let mut self_ = Self {
 			spi_flash_memory,
 			regions_config,
 			metadatas_region,
 			files_region,
-		})
+			checkpoint_region,
+		};
+		self_.handle_ecc_errors().map_err(CreationError::MetadatasRegion)?;
+
+		Ok(self_)
 	}
 
 	/// Returns `true` if you [`created`] a file before that had the provided `file_id` and you didn't [`delete`] it.
@@ -52,6 +83,25 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileSystem<Chip, Spi>
 		self.metadatas_region.does_file_exist(file_id)
 	}
 
+	/// Returns `true` if the [`path`] is already used by a file you [`created`] (and didn't [`delete`]) or by a
+	/// directory, or if one of the directories in the path is actually a file. Otherwise returns `false`.
+	///
+	/// The file with the provided `ignored_file_id` (if it's `Some`) isn't taken into account.
+	///
+	/// [`created`]: Self::create_file
+	/// [`delete`]: Self::delete_file
+	pub fn is_path_taken(&self, path: &str, ignored_file_id: Option<FileId>) -> bool
+	{
+		self.get_existing_files_metadatas()
+			.iter()
+			.filter(|file_metadata| Some(file_metadata.id) != ignored_file_id)
+			.any(|file_metadata| {
+				file_metadata.name == path
+					|| path::strip_directory(&file_metadata.name, path).is_some()
+					|| path::strip_directory(path, &file_metadata.name).is_some()
+			})
+	}
+
 	/// Returns a slice of all the metadatas of all the files you [`created`] and didn't [`delete`].
 	///
 	/// [`created`]: Self::create_file
@@ -61,15 +111,114 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileSystem<Chip, Spi>
 		self.metadatas_region.get_files_metadatas()
 	}
 
+	/// Returns how much the blocks of the flash memory used by the file system have been worn out by the erases.
+	pub fn get_wear_statistics(&self) -> WearStatistics
+	{
+		self.metadatas_region.get_wear_statistics(&self.regions_config)
+	}
+
+	/// Returns the number of blocks of the flash memory that can't be used because they are bad.
+	pub fn get_bad_blocks_count(&self) -> u16
+	{
+		self.metadatas_region.get_bad_blocks_count()
+	}
+
+	/// Returns how many pages read from the flash memory contained flipped bits (that have been corrected or not) since
+	/// the file system has been created.
+	pub fn get_ecc_counters(&self) -> EccCounters
+	{
+		self.spi_flash_memory.get_ecc_counters()
+	}
+
+	/// Stops using the block of the flash memory at `block_index`, moving the file stored in it (if there's one) to
+	/// another place (check [`FilesMetadatasRegion::mark_block_as_invalid`]).
+	///
+	/// This is done automatically for the blocks whose pages repeatedly contain flipped bits when they are read, but you
+	/// can call it if you know that a block is wearing out.
+	///
+	/// Returns `Err(...)` if there has been an error in communicating with the flash memory, otherwise returns `Ok(())`.
+	pub fn mark_block_as_invalid(
+		&mut self, block_index: u16,
+	) -> Result<(), StoreMetadatasError<<Spi as ErrorType>::Error>>
+	{
+		self.metadatas_region
+			.mark_block_as_invalid(block_index, &mut self.spi_flash_memory, &self.regions_config)
+	}
+
+	/// Returns the last checkpoint [`stored`] in the dedicated region of the flash memory (check [`CheckpointRegion`]),
+	/// or `None` if there isn't one (or if it has been [`cleared`]).
+	///
+	/// [`stored`]: Self::store_checkpoint
+	/// [`cleared`]: Self::clear_checkpoint
+	pub fn get_checkpoint(&self) -> Option<&[u8]>
+	{
+		self.checkpoint_region.get_last_checkpoint()
+	}
+
+	/// Stores the provided `checkpoint` in the dedicated region of the flash memory, replacing the previous one (check
+	/// [`CheckpointRegion::store`]).
+	pub fn store_checkpoint(&mut self, checkpoint: &[u8]) -> Result<(), StoreCheckpointError<Spi>>
+	{
+		let metadatas_region = &self.metadatas_region;
+		self.checkpoint_region.store(
+			checkpoint,
+			&mut self.spi_flash_memory,
+			&self.regions_config,
+			|block_index| Self::is_checkpoint_block_usable(metadatas_region, block_index),
+		)
+	}
+
+	/// Makes [`Self::get_checkpoint`] return `None` (even after a reboot).
+	pub fn clear_checkpoint(&mut self) -> Result<(), StoreCheckpointError<Spi>>
+	{
+		let metadatas_region = &self.metadatas_region;
+		self.checkpoint_region
+			.clear(&mut self.spi_flash_memory, &self.regions_config, |block_index| {
+				Self::is_checkpoint_block_usable(metadatas_region, block_index)
+			})
+	}
+
+	/// Returns `false` if the block at `block_index` is bad or if it contains the data of a file (which can happen if
+	/// the file has been stored when the block was part of the data region), otherwise returns `true`.
+	fn is_checkpoint_block_usable(metadatas_region: &FilesMetadatasRegion, block_index: u16) -> bool
+	{
+		metadatas_region.is_block_valid(block_index)
+			&& !metadatas_region
+				.get_files_metadatas()
+				.iter()
+				.any(|file_metadata| file_metadata.block_range::<Chip>().contains(&block_index))
+	}
+
+	/// Handles the blocks whose pages contained flipped bits since the last call to this method (check
+	/// [`FilesMetadatasRegion::handle_ecc_error`]).
+	fn handle_ecc_errors(&mut self) -> Result<(), StoreMetadatasError<<Spi as ErrorType>::Error>>
+	{
+		for (block_index, ecc_status) in self.spi_flash_memory.take_blocks_with_ecc_errors()
+		{
+			self.metadatas_region.handle_ecc_error(
+				block_index,
+				ecc_status,
+				&mut self.spi_flash_memory,
+				&self.regions_config,
+			)?;
+		}
+
+		Ok(())
+	}
+
 	/// Opens the file with the provided `file_id` so that you can read it in the future.
 	///
-	/// Returns `Err(FileDoesntExist)` if a file with the provided `file_id` isn't stored in the file system,
-	/// otherwise returns `Ok(FileReader)` (check [`FileReader`] to understand how to read the file's content).
+	/// Returns `Err(FileDoesntExist)` if a file with the provided `file_id` isn't stored in the file system (or if it's
+	/// [`partial`]), otherwise returns `Ok(FileReader)` (check [`FileReader`] to understand how to read the file's
+	/// content).
+	///
+	/// [`partial`]: FileMetadata::partial_data_length
 	pub fn read_file(&self, file_id: FileId) -> Result<FileReader<Chip, Spi>, FileDoesntExist>
 	{
 		let metadata = self
 			.metadatas_region
 			.get_file_metadata(file_id)
+			.filter(|metadata| !metadata.is_partial())
 			.ok_or(FileDoesntExist)?;
 
 		Ok(self
@@ -77,54 +226,316 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileSystem<Chip, Spi>
 			.open_file_for_read(metadata, self.metadatas_region.get_file_validator()))
 	}
 
-	/// Creates a file with the name `file_name` and whose data will occupy `data_size` bytes.
+	/// Returns the files and the subdirectories directly inside the `directory` (check [`path`]), sorted by name.
+	///
+	/// An empty `directory` is the root directory.
+	pub fn list_directory(&self, directory: &str) -> DirectoryContent<'_>
+	{
+		let mut content = DirectoryContent {
+			files: Vec::new(),
+			directories: Vec::new(),
+		};
+		for file_metadata in self.get_existing_files_metadatas()
+		{
+			match path::strip_directory(&file_metadata.name, directory).map(|path| path.split_once(path::SEPARATOR))
+			{
+				Some(None) => content.files.push(file_metadata),
+				Some(Some((subdirectory, _))) if !content.directories.contains(&subdirectory) =>
+				{
+					content.directories.push(subdirectory)
+				},
+				_ => (),
+			}
+		}
+		content.files.sort_by(|a, b| a.name.cmp(&b.name));
+		content.directories.sort();
+
+		content
+	}
+
+	/// Creates a file with the name `file_name` (that can be a [`path`]) and whose data will occupy `data_size` bytes.
+	///
+	/// If there's enough free space in the flash memory but it's too fragmented to store the file, the file system is
+	/// [`compacted`] first.
+	///
+	/// Returns `Err(CreateFileError)` if the `file_name` isn't a [`valid path`] (or if it's [`already taken`]) or if
+	/// there's not enough space in the flash memory to store a file of the provided size (or if the compaction failed).
+	/// Otherwise returns `Ok(FileWriter)` (check [`FileWriter`] to understand how to write the file's content).
 	///
-	/// Returns `Err(NotEnoughSpaceAvailable)` if there's not enough space in the flash memory to store a file of the
-	/// the provided size. Otherwise returns `Ok(FileWriter)` (check [`FileWriter`] to understand how to write the file's content).
+	/// [`compacted`]: Self::compact
+	/// [`valid path`]: path::is_valid
+	/// [`already taken`]: Self::is_path_taken
 	pub fn create_file(
 		&mut self, file_name: impl Into<String>, data_size: u32,
-	) -> Result<FileWriter<Chip, Spi>, NotEnoughSpaceAvailable>
+	) -> Result<FileWriter<Chip, Spi>, CreateFileError<Spi>>
 	{
 		let file_name = Into::<String>::into(file_name);
-		let (file_id, start_address) = self
+		if !path::is_valid(&file_name)
+		{
+			return Err(CreateFileError::InvalidName);
+		}
+		if self.is_path_taken(&file_name, None)
+		{
+			return Err(CreateFileError::AlreadyExists);
+		}
+
+		let (file_id, start_address) = match self
 			.metadatas_region
-			.create_file::<Chip>(file_name.len() as u32 + data_size, &self.regions_config)?;
+			.create_file::<Chip>(data_size, &self.regions_config)
+		{
+			Err(FindSpaceError::RequiresCompacting) =>
+			{
+				self.compact().map_err(CreateFileError::Compact)?;
 
-		let file_metadata = FileMetadata {
-			id: file_id,
-			start_memory_address: start_address,
-			file_name_length: file_name.len() as u32,
-			file_data_length: data_size,
+				self.metadatas_region
+					.create_file::<Chip>(data_size, &self.regions_config)
+					.map_err(|_| CreateFileError::NotEnoughSpaceAvailable)?
+			},
+			result => result.map_err(|_| CreateFileError::NotEnoughSpaceAvailable)?,
 		};
 
+		let file_metadata = FileMetadata::new(file_id, start_address, data_size, file_name);
+
 		Ok(self
 			.files_region
-			.create_file(file_metadata, file_name, self.metadatas_region.get_file_validator()))
+			.create_file(file_metadata, self.metadatas_region.get_file_validator()))
+	}
+
+	/// Continues writing the [`partial`] file with the provided `file_id` (whose writing has been [`suspended`]) from
+	/// the first byte that hasn't been written.
+	///
+	/// The data already written is read to compute the checksums of the whole file when you [`finish writing`] it.
+	///
+	/// Returns `Err(ResumeWritingError)` if there has been a [problem] in resuming the writing of the file, otherwise
+	/// returns `Ok(FileWriter)`.
+	///
+	/// [`partial`]: FileMetadata::partial_data_length
+	/// [`suspended`]: FileWriter::suspend_writing
+	/// [`finish writing`]: FileWriter::finish_writing
+	/// [problem]: ResumeWritingError
+	pub fn resume_writing(&mut self, file_id: FileId) -> Result<FileWriter<Chip, Spi>, ResumeWritingError<Spi>>
+	{
+		let file_metadata = self
+			.metadatas_region
+			.get_file_metadata(file_id)
+			.ok_or(ResumeWritingError::FileDoesntExist)?;
+		let written_data_length = file_metadata
+			.partial_data_length
+			.ok_or(ResumeWritingError::NotPartial)?;
+
+		let mut content_checksums = ContentChecksums::default();
+		let mut buffer = vec![0; Chip::PAGE_SIZE as usize];
+		let mut read_data_length = 0;
+		let mut worst_ecc_status = EccStatus::NoErrors;
+		while read_data_length < written_data_length
+		{
+			let chunk = &mut buffer[..(written_data_length - read_data_length).min(Chip::PAGE_SIZE) as usize];
+			let ecc_status = self
+				.spi_flash_memory
+				.read(file_metadata.start_memory_address + read_data_length, chunk)
+				.map_err(ResumeWritingError::Spi)?;
+			worst_ecc_status = worst_ecc_status.max(ecc_status);
+			content_checksums.update(chunk);
+			read_data_length += chunk.len() as u32;
+		}
+		// The file may be moved if one of its blocks is worn out
+		self.handle_ecc_errors().map_err(ResumeWritingError::MoveFile)?;
+		if worst_ecc_status == EccStatus::Uncorrectable
+		{
+			return Err(ResumeWritingError::CorruptedData);
+		}
+
+		let file_metadata = self
+			.metadatas_region
+			.get_file_metadata(file_id)
+			.ok_or(ResumeWritingError::FileDoesntExist)?;
+		self.metadatas_region.resume_writing_file(file_id);
+
+		Ok(self.files_region.resume_writing_file(
+			file_metadata,
+			content_checksums,
+			self.metadatas_region.get_file_validator(),
+		))
+	}
+
+	/// Changes the name of the file with the provided `file_id` to `new_name` (that can be a [`path`], so this can also be
+	/// used to move the file to another directory).
+	///
+	/// Only the metadata of the file is updated, its data isn't copied.
+	///
+	/// Returns `Err(UpdateFileError)` if there has been a [problem] in renaming the file, otherwise returns `Ok(())`.
+	///
+	/// [problem]: UpdateFileError
+	pub fn rename_file(&mut self, file_id: FileId, new_name: impl Into<String>) -> Result<(), UpdateFileError<Spi>>
+	{
+		let new_name = Into::<String>::into(new_name);
+		if !path::is_valid(&new_name)
+		{
+			return Err(UpdateFileError::InvalidName);
+		}
+		if self.is_path_taken(&new_name, Some(file_id))
+		{
+			return Err(UpdateFileError::AlreadyExists);
+		}
+
+		self.update_file_metadata(file_id, |file_metadata| file_metadata.name = new_name)
+	}
+
+	/// Increments the print count of the file with the provided `file_id` and sets the time it has been printed the last
+	/// time to `print_time` (in seconds since the Unix epoch, if it's known).
+	///
+	/// Returns `Err(UpdateFileError)` if there has been a [problem] in updating the metadata of the file, otherwise
+	/// returns `Ok(())`.
+	///
+	/// [problem]: UpdateFileError
+	pub fn record_print(&mut self, file_id: FileId, print_time: Option<u64>) -> Result<(), UpdateFileError<Spi>>
+	{
+		self.update_file_metadata(file_id, |file_metadata| {
+			file_metadata.print_count = file_metadata.print_count.saturating_add(1);
+			if print_time.is_some()
+			{
+				file_metadata.last_print_time = print_time;
+			}
+		})
+	}
+
+	fn update_file_metadata(
+		&mut self, file_id: FileId, update: impl FnOnce(&mut FileMetadata),
+	) -> Result<(), UpdateFileError<Spi>>
+	{
+		self.metadatas_region
+			.update_file_metadata(file_id, update)
+			.map_err(|_| UpdateFileError::FileDoesntExist)?;
+
+		self.metadatas_region
+			.store_in_flash(&mut self.spi_flash_memory, &self.regions_config)
+			.map_err(UpdateFileError::Spi)
+	}
+
+	/// Moves all the files next to each other so that all the free space of the flash memory is contiguous (check
+	/// [`FilesMetadatasRegion::compact`]).
+	///
+	/// The [`FileReader`]s you opened before calling this method keep working, while the [`FileWriter`]s of files you
+	/// didn't start writing yet will return an error.
+	///
+	/// Returns `Err(...)` if there has been an error in communicating with the flash memory, otherwise returns `Ok(())`.
+	pub fn compact(&mut self) -> Result<(), StoreMetadatasError<<Spi as ErrorType>::Error>>
+	{
+		self.metadatas_region
+			.compact(&mut self.spi_flash_memory, &self.regions_config)
 	}
 
 	/// Tries to delete the file with the provided `file_id` from the file system.
 	///
-	/// Returns `Err(DeleteFileError)` if there has been a [problem] in deleting the file (which may also make it
-	/// corrupted), otherwise returns `Ok(())`.
+	/// The file is first removed from the metadatas stored in the flash memory and only then its data is erased, so if
+	/// the power is lost while deleting the file, the next time the file system is [`initialized`] the file is either
+	/// still there untouched or deleted (and its data erased).
+	///
+	/// Returns `Err(DeleteFileError)` if there has been a [problem] in deleting the file, otherwise returns `Ok(())`.
 	///
 	/// [problem]: DeleteFileError
+	/// [`initialized`]: Self::new
 	pub fn delete_file(&mut self, file_id: FileId) -> Result<(), DeleteFileError<Spi>>
 	{
-		let file_metadata = self
-			.metadatas_region
-			.delete_file(file_id)
+		self.metadatas_region
+			.delete_file::<Chip>(file_id)
 			.map_err(|_| DeleteFileError::FileDoesntExist)?;
 
-		self.files_region
-			.delete_file(file_metadata, &mut self.spi_flash_memory)
-			.map_err(DeleteFileError::CantDeleteFile)?;
-
 		self.metadatas_region
 			.store_in_flash(&mut self.spi_flash_memory, &self.regions_config)
 			.map_err(DeleteFileError::CantDeleteFileMetadata)?;
 
+		self.metadatas_region
+			.erase_pending_blocks(&mut self.spi_flash_memory)
+			.map_err(DeleteFileError::CantDeleteFile)?;
+
 		Ok(())
 	}
+
+	/// Replaces the content of the file named `file_name` with `content` (creating the file if it doesn't exist), so that
+	/// a power loss can't leave the file without a complete content. It's meant for small files that are always
+	/// rewritten completely, and that must be read with [`Self::read_replaced_file`].
+	///
+	/// The `content` is first written to a new file (whose name is `file_name` followed by [`REPLACEMENT_FILE_SUFFIX`]),
+	/// and only then the previous file is deleted and the new one is renamed to `file_name`.
+	///
+	/// Returns `Err(ReplaceFileError)` if there has been a [problem] in replacing the file, otherwise returns `Ok(())`.
+	///
+	/// [problem]: ReplaceFileError
+	pub fn replace_file(&mut self, file_name: &str, content: &[u8]) -> Result<(), ReplaceFileError<Spi>>
+	{
+		let replacement_file_name = format!("{file_name}{REPLACEMENT_FILE_SUFFIX}");
+		// The replacement of the file may have been interrupted by a power loss, and its new content must not be lost
+		if self.find_complete_file(&replacement_file_name).is_some()
+		{
+			self.finish_replacing_file(file_name, &replacement_file_name)?;
+		}
+
+		let mut file_writer = self
+			.create_file(replacement_file_name.as_str(), content.len() as u32)
+			.map_err(ReplaceFileError::CreateFile)?;
+		if let Err(error) = file_writer.write_data(self, content)
+		{
+			let _ = file_writer.discard(self);
+			return Err(ReplaceFileError::Write(error));
+		}
+		file_writer.finish_writing(self).map_err(ReplaceFileError::Write)?;
+
+		self.finish_replacing_file(file_name, &replacement_file_name)
+	}
+
+	/// Deletes the files named `file_name` and renames the file named `replacement_file_name` to `file_name`.
+	fn finish_replacing_file(
+		&mut self, file_name: &str, replacement_file_name: &str,
+	) -> Result<(), ReplaceFileError<Spi>>
+	{
+		let old_files_ids: Vec<_> = self
+			.get_existing_files_metadatas()
+			.iter()
+			.filter(|file_metadata| file_metadata.name == file_name)
+			.map(|file_metadata| file_metadata.id)
+			.collect();
+		for file_id in old_files_ids
+		{
+			self.delete_file(file_id).map_err(ReplaceFileError::DeleteOldFile)?;
+		}
+
+		let replacement_file_id = self
+			.find_complete_file(replacement_file_name)
+			.map(|file_metadata| file_metadata.id)
+			.ok_or(ReplaceFileError::Rename(UpdateFileError::FileDoesntExist))?;
+		self.rename_file(replacement_file_id, file_name)
+			.map_err(ReplaceFileError::Rename)
+	}
+
+	/// Reads the whole content of the file named `file_name` that has been written with [`Self::replace_file`].
+	///
+	/// If the power has been lost while the file was being replaced, the new content is returned if it had been
+	/// completely written, otherwise the previous one is returned.
+	///
+	/// Returns `None` if the file doesn't exist or if it has been impossible to read it.
+	pub fn read_replaced_file(&mut self, file_name: &str) -> Option<Vec<u8>>
+	{
+		let file_metadata = self
+			.find_complete_file(&format!("{file_name}{REPLACEMENT_FILE_SUFFIX}"))
+			.or_else(|| self.find_complete_file(file_name))?
+			.clone();
+
+		let mut file_reader = self.read_file(file_metadata.id).ok()?;
+		let mut content = vec![0; file_metadata.file_data_length as usize];
+		let read_bytes_count = file_reader.read_data(self, &mut content).ok()?;
+		content.truncate(read_bytes_count as usize);
+
+		Some(content)
+	}
+
+	/// Returns the metadata of the file named `file_name` whose writing has been finished (if there's one).
+	fn find_complete_file(&self, file_name: &str) -> Option<&FileMetadata>
+	{
+		self.get_existing_files_metadatas()
+			.iter()
+			.find(|file_metadata| file_metadata.name == file_name && !file_metadata.is_partial())
+	}
 }
 
 /// An error returned from [`FileSystem::delete_file`].
@@ -135,14 +546,165 @@ pub enum DeleteFileError<Spi: SpiDevice<u8>>
 	/// It has been impossible to erase the data portion of the file from the flash memory.
 	CantDeleteFile(<Spi as ErrorType>::Error),
 	/// It has been impossible to erase the metadata portion of the file from the flash memory.
-	CantDeleteFileMetadata(<Spi as ErrorType>::Error),
+	CantDeleteFileMetadata(StoreMetadatasError<<Spi as ErrorType>::Error>),
+}
+
+impl<Spi: SpiDevice<u8>> Debug for DeleteFileError<Spi>
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		match self
+		{
+			Self::FileDoesntExist => write!(f, "FileDoesntExist"),
+			Self::CantDeleteFile(arg0) => f.debug_tuple("CantDeleteFile").field(arg0).finish(),
+			Self::CantDeleteFileMetadata(arg0) => f.debug_tuple("CantDeleteFileMetadata").field(arg0).finish(),
+		}
+	}
+}
+
+/// An error returned from [`FileSystem::replace_file`].
+pub enum ReplaceFileError<Spi: SpiDevice<u8>>
+{
+	/// It has been impossible to create the file where the new content is written.
+	CreateFile(CreateFileError<Spi>),
+	/// It has been impossible to write the new content.
+	Write(WriteError<Spi>),
+	/// It has been impossible to delete the file with the previous content.
+	DeleteOldFile(DeleteFileError<Spi>),
+	/// It has been impossible to rename the file with the new content.
+	Rename(UpdateFileError<Spi>),
+}
+
+impl<Spi: SpiDevice<u8>> Debug for ReplaceFileError<Spi>
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		match self
+		{
+			Self::CreateFile(arg0) => f.debug_tuple("CreateFile").field(arg0).finish(),
+			Self::Write(arg0) => f.debug_tuple("Write").field(arg0).finish(),
+			Self::DeleteOldFile(arg0) => f.debug_tuple("DeleteOldFile").field(arg0).finish(),
+			Self::Rename(arg0) => f.debug_tuple("Rename").field(arg0).finish(),
+		}
+	}
+}
+
+/// The content of a directory of the file system (check [`FileSystem::list_directory`]).
+#[derive(Debug)]
+pub struct DirectoryContent<'a>
+{
+	/// The metadatas of the files directly inside the directory.
+	pub files: Vec<&'a FileMetadata>,
+	/// The names of the subdirectories directly inside the directory.
+	pub directories: Vec<&'a str>,
+}
+
+/// An error returned from [`FileSystem::resume_writing`].
+pub enum ResumeWritingError<Spi: SpiDevice<u8>>
+{
+	/// The file you tried to resume writing doesn't exist in the file system.
+	FileDoesntExist,
+	/// The file isn't [`partial`] (its writing has already been finished).
+	///
+	/// [`partial`]: FileMetadata::partial_data_length
+	NotPartial,
+	/// The data already written to the file has been corrupted, so the file should be deleted.
+	CorruptedData,
+	/// It has been impossible to read the data already written to the file from the flash memory.
+	Spi(<Spi as ErrorType>::Error),
+	/// It has been impossible to move the file away from a block that is wearing out.
+	MoveFile(StoreMetadatasError<<Spi as ErrorType>::Error>),
+}
+
+impl<Spi: SpiDevice<u8>> Debug for ResumeWritingError<Spi>
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		match self
+		{
+			Self::FileDoesntExist => write!(f, "FileDoesntExist"),
+			Self::NotPartial => write!(f, "NotPartial"),
+			Self::CorruptedData => write!(f, "CorruptedData"),
+			Self::Spi(arg0) => f.debug_tuple("Spi").field(arg0).finish(),
+			Self::MoveFile(arg0) => f.debug_tuple("MoveFile").field(arg0).finish(),
+		}
+	}
+}
+
+/// An error returned from [`FileSystem::rename_file`] and [`FileSystem::record_print`].
+pub enum UpdateFileError<Spi: SpiDevice<u8>>
+{
+	/// The file you tried to update doesn't exist in the file system.
+	FileDoesntExist,
+	/// The new name of the file isn't a [`valid path`].
+	///
+	/// [`valid path`]: path::is_valid
+	InvalidName,
+	/// The new name of the file is [`already taken`] by another file or by a directory.
+	///
+	/// [`already taken`]: FileSystem::is_path_taken
+	AlreadyExists,
+	/// It has been impossible to store the updated metadata of the file in the flash memory.
+	Spi(StoreMetadatasError<<Spi as ErrorType>::Error>),
+}
+
+impl<Spi: SpiDevice<u8>> Debug for UpdateFileError<Spi>
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		match self
+		{
+			Self::FileDoesntExist => write!(f, "FileDoesntExist"),
+			Self::InvalidName => write!(f, "InvalidName"),
+			Self::AlreadyExists => write!(f, "AlreadyExists"),
+			Self::Spi(arg0) => f.debug_tuple("Spi").field(arg0).finish(),
+		}
+	}
+}
+
+/// An error returned from [`FileSystem::create_file`].
+pub enum CreateFileError<Spi: SpiDevice<u8>>
+{
+	/// The name of the file isn't a [`valid path`].
+	///
+	/// [`valid path`]: path::is_valid
+	InvalidName,
+	/// The name of the file is [`already taken`] by another file or by a directory.
+	///
+	/// [`already taken`]: FileSystem::is_path_taken
+	AlreadyExists,
+	/// There's not enough space in the flash memory to store the file.
+	NotEnoughSpaceAvailable,
+	/// It has been impossible to compact the file system to make room for the file.
+	Compact(StoreMetadatasError<<Spi as ErrorType>::Error>),
+}
+
+impl<Spi: SpiDevice<u8>> Debug for CreateFileError<Spi>
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		match self
+		{
+			Self::InvalidName => write!(f, "InvalidName"),
+			Self::AlreadyExists => write!(f, "AlreadyExists"),
+			Self::NotEnoughSpaceAvailable => write!(f, "NotEnoughSpaceAvailable"),
+			Self::Compact(arg0) => f.debug_tuple("Compact").field(arg0).finish(),
+		}
+	}
 }
 
 /// An error returned from [`FileSystem::new`].
 pub enum CreationError<Spi: SpiDevice<u8>>
 {
 	InitializeChip(<Spi as ErrorType>::Error),
-	MetadatasRegion(<Spi as ErrorType>::Error),
+	MetadatasRegion(StoreMetadatasError<<Spi as ErrorType>::Error>),
+	/// The metadatas region has been stored by a firmware with a different layout, so the flash memory must be erased
+	/// to use it with this firmware (check [`ReadMetadatasRegionError::IncompatibleLayout`]).
+	IncompatibleMetadatasRegionLayout
+	{
+		version: u8,
+	},
+	CheckpointRegion(<Spi as ErrorType>::Error),
 }
 
 impl<Spi: SpiDevice<u8>> Debug for CreationError<Spi>
@@ -153,6 +715,645 @@ impl<Spi: SpiDevice<u8>> Debug for CreationError<Spi>
 		{
 			Self::MetadatasRegion(arg0) => f.debug_tuple("MetadatasRegion").field(arg0).finish(),
 			CreationError::InitializeChip(arg0) => f.debug_tuple("InitializeChip").field(arg0).finish(),
+			Self::IncompatibleMetadatasRegionLayout { version } => f
+				.debug_struct("IncompatibleMetadatasRegionLayout")
+				.field("version", version)
+				.finish(),
+			Self::CheckpointRegion(arg0) => f.debug_tuple("CheckpointRegion").field(arg0).finish(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use sha2::{Digest, Sha256};
+
+	use super::*;
+	use crate::{
+		printer::components::{
+			drivers::spi_flash_memory::{FlashMemoryChipExt, MT29F2G01ABAGDWB},
+			file_system::regions::{
+				data::{ExpectedChecksum, ReadError, WriteError},
+				metadata::CORRECTED_READS_BEFORE_RELOCATION,
+			},
+			mock::MockFlashMemory,
+		},
+		utils::crc::crc32,
+	};
+
+	type TestFileSystem = FileSystem<MT29F2G01ABAGDWB, MockFlashMemory<MT29F2G01ABAGDWB>>;
+
+	/// Returns a file system stored in `memory`, like it's created when the microcontroller boots.
+	fn boot(memory: &MockFlashMemory<MT29F2G01ABAGDWB>) -> TestFileSystem
+	{
+		let spi_flash_memory = SpiFlashMemory::new(memory.clone(), MT29F2G01ABAGDWB);
+		FileSystem::new(spi_flash_memory, RegionsConfig::default::<MT29F2G01ABAGDWB>()).unwrap()
+	}
+
+	fn file_content(length: usize) -> Vec<u8>
+	{
+		(0..length).map(|i| (i % 251) as u8).collect()
+	}
+
+	#[test]
+	fn written_file_is_read_back_after_reboot()
+	{
+		let memory = MockFlashMemory::default();
+		let content = file_content(5_000);
+
+		let mut file_system = boot(&memory);
+		let mut file_writer = file_system.create_file("benchy.gcode", content.len() as u32).unwrap();
+		for chunk in content.chunks(1_024)
+		{
+			file_writer.write_data(&mut file_system, chunk).unwrap();
+		}
+		file_writer.finish_writing(&mut file_system).unwrap();
+
+		let mut file_system = boot(&memory);
+		let file_id = file_system.get_existing_files_metadatas()[0].id;
+		assert_eq!(file_system.get_existing_files_metadatas()[0].name, "benchy.gcode");
+		let mut file_reader = file_system.read_file(file_id).unwrap();
+		let mut read_content = vec![0; content.len()];
+		for chunk in read_content.chunks_mut(700)
+		{
+			file_reader.read_data(&mut file_system, chunk).unwrap();
+		}
+		assert_eq!(read_content, content);
+		assert!(file_reader.has_reached_end_of_file());
+	}
+
+	#[test]
+	fn file_is_read_from_the_seeked_position()
+	{
+		let memory = MockFlashMemory::default();
+		let content = file_content(5_000);
+
+		let mut file_system = boot(&memory);
+		let file_id = create_file(&mut file_system, &content);
+
+		let mut file_reader = file_system.read_file(file_id).unwrap();
+		file_reader.seek(3_000);
+		let mut read_content = vec![0; 500];
+		file_reader.read_data(&mut file_system, &mut read_content).unwrap();
+		assert_eq!(read_content, content[3_000..3_500]);
+
+		file_reader.seek(10_000);
+		assert!(file_reader.has_reached_end_of_file());
+	}
+
+	#[test]
+	fn deleted_file_is_erased()
+	{
+		let memory = MockFlashMemory::default();
+
+		let mut file_system = boot(&memory);
+		let mut file_writer = file_system.create_file("cube.gcode", 10).unwrap();
+		file_writer.write_data(&mut file_system, &[0; 10]).unwrap();
+		file_writer.finish_writing(&mut file_system).unwrap();
+		let file_metadata = file_system.get_existing_files_metadatas()[0].clone();
+
+		file_system.delete_file(file_metadata.id).unwrap();
+
+		let first_page_index = file_metadata.start_memory_address / MT29F2G01ABAGDWB::PAGE_SIZE;
+		assert!(memory.get_page(first_page_index).iter().all(|&byte| byte == 0xFF));
+		assert!(!boot(&memory).does_file_exist(file_metadata.id));
+	}
+
+	#[test]
+	fn file_not_finished_before_a_reboot_is_deleted()
+	{
+		let memory = MockFlashMemory::default();
+
+		let mut file_system = boot(&memory);
+		let mut file_writer = file_system.create_file("cube.gcode", 5_000).unwrap();
+		file_writer.write_data(&mut file_system, &file_content(2_000)).unwrap();
+		// The power is lost while the file is being written
+		std::mem::forget(file_writer);
+
+		let file_system = boot(&memory);
+		assert!(file_system.get_existing_files_metadatas().is_empty());
+	}
+
+	#[test]
+	fn file_metadata_is_kept_after_a_rename_and_a_reboot()
+	{
+		let memory = MockFlashMemory::default();
+		let content = file_content(3_000);
+
+		let mut file_system = boot(&memory);
+		let mut file_writer = file_system.create_file("benchy.gcode", content.len() as u32).unwrap();
+		file_writer.set_upload_time(1_700_000_000);
+		file_writer.write_data(&mut file_system, &content).unwrap();
+		file_writer.finish_writing(&mut file_system).unwrap();
+		let file_id = file_system.get_existing_files_metadatas()[0].id;
+
+		file_system.rename_file(file_id, "models/boats/benchy.gcode").unwrap();
+		file_system.record_print(file_id, Some(1_700_000_500)).unwrap();
+		file_system.record_print(file_id, None).unwrap();
+		assert!(matches!(
+			file_system.rename_file(file_id, "models//benchy.gcode"),
+			Err(UpdateFileError::InvalidName)
+		));
+
+		let mut file_system = boot(&memory);
+		let file_metadata = file_system.get_existing_files_metadatas()[0].clone();
+		assert_eq!(file_metadata.name, "models/boats/benchy.gcode");
+		assert_eq!(file_metadata.upload_time, Some(1_700_000_000));
+		assert_eq!(file_metadata.last_print_time, Some(1_700_000_500));
+		assert_eq!(file_metadata.print_count, 2);
+		assert_eq!(file_metadata.content_sha256, Some(Sha256::digest(&content).into()));
+		assert_eq!(read_file(&mut file_system, file_id).unwrap(), content);
+	}
+
+	#[test]
+	fn taken_paths_are_rejected()
+	{
+		let memory = MockFlashMemory::default();
+
+		let mut file_system = boot(&memory);
+		let cube_id = create_file_with_name(&mut file_system, "models/cube.gcode");
+		let benchy_id = create_file_with_name(&mut file_system, "benchy.gcode");
+
+		for taken_path in ["models/cube.gcode", "models", "models/cube.gcode/cube.gcode"]
+		{
+			assert!(matches!(
+				file_system.create_file(taken_path, 10),
+				Err(CreateFileError::AlreadyExists)
+			));
+			assert!(matches!(
+				file_system.rename_file(benchy_id, taken_path),
+				Err(UpdateFileError::AlreadyExists)
+			));
+		}
+
+		// A file can be renamed to its own name and moved to an existing directory
+		file_system.rename_file(cube_id, "models/cube.gcode").unwrap();
+		file_system.rename_file(benchy_id, "models/benchy.gcode").unwrap();
+		create_file_with_name(&mut file_system, "models2");
+	}
+
+	#[test]
+	fn replaced_file_keeps_a_complete_content_after_a_power_loss()
+	{
+		let memory = MockFlashMemory::default();
+
+		let mut file_system = boot(&memory);
+		assert_eq!(file_system.read_replaced_file(".settings"), None);
+		file_system.replace_file(".settings", &[1; 10]).unwrap();
+		file_system.replace_file(".settings", &[2; 20]).unwrap();
+		assert_eq!(file_system.read_replaced_file(".settings"), Some(vec![2; 20]));
+		assert_eq!(file_system.get_existing_files_metadatas().len(), 1);
+
+		// The power is lost while the new content is being written
+		let mut file_writer = file_system.create_file(".settings.new", 30).unwrap();
+		file_writer.write_data(&mut file_system, &[3; 15]).unwrap();
+		std::mem::forget(file_writer);
+		let mut file_system = boot(&memory);
+		assert_eq!(file_system.read_replaced_file(".settings"), Some(vec![2; 20]));
+
+		// The power is lost after the new content has been written, but before the previous file has been deleted
+		let mut file_writer = file_system.create_file(".settings.new", 30).unwrap();
+		file_writer.write_data(&mut file_system, &[4; 30]).unwrap();
+		file_writer.finish_writing(&mut file_system).unwrap();
+		let mut file_system = boot(&memory);
+		assert_eq!(file_system.read_replaced_file(".settings"), Some(vec![4; 30]));
+
+		file_system.replace_file(".settings", &[5; 40]).unwrap();
+		assert_eq!(file_system.read_replaced_file(".settings"), Some(vec![5; 40]));
+		assert_eq!(file_system.get_existing_files_metadatas().len(), 1);
+		assert_eq!(file_system.get_existing_files_metadatas()[0].name, ".settings");
+	}
+
+	fn create_file_with_name(file_system: &mut TestFileSystem, name: &str) -> FileId
+	{
+		let mut file_writer = file_system.create_file(name, 10).unwrap();
+		file_writer.write_data(file_system, &[0; 10]).unwrap();
+		file_writer.finish_writing(file_system).unwrap();
+
+		file_system
+			.get_existing_files_metadatas()
+			.iter()
+			.find(|file_metadata| file_metadata.name == name)
+			.unwrap()
+			.id
+	}
+
+	#[test]
+	fn directory_lists_its_files_and_its_subdirectories()
+	{
+		let memory = MockFlashMemory::default();
+
+		let mut file_system = boot(&memory);
+		for file_name in [
+			"cube.gcode",
+			"models/benchy.gcode",
+			"models/calibration/cube.gcode",
+			"models/calibration/tower.gcode",
+			"models/vases/vase.gcode",
+			"models2/bracket.gcode",
+		]
+		{
+			let mut file_writer = file_system.create_file(file_name, 10).unwrap();
+			file_writer.write_data(&mut file_system, &[0; 10]).unwrap();
+			file_writer.finish_writing(&mut file_system).unwrap();
+		}
+		assert!(matches!(
+			file_system.create_file("models/", 10),
+			Err(CreateFileError::InvalidName)
+		));
+
+		let file_names = |content: &DirectoryContent| -> Vec<String> {
+			content
+				.files
+				.iter()
+				.map(|file_metadata| file_metadata.name.clone())
+				.collect()
+		};
+
+		let root = file_system.list_directory("");
+		assert_eq!(file_names(&root), ["cube.gcode"]);
+		assert_eq!(root.directories, ["models", "models2"]);
+
+		let models = file_system.list_directory("models");
+		assert_eq!(file_names(&models), ["models/benchy.gcode"]);
+		assert_eq!(models.directories, ["calibration", "vases"]);
+
+		let calibration = file_system.list_directory("models/calibration");
+		assert_eq!(
+			file_names(&calibration),
+			["models/calibration/cube.gcode", "models/calibration/tower.gcode"]
+		);
+		assert!(calibration.directories.is_empty());
+
+		assert!(file_system.list_directory("prints").files.is_empty());
+	}
+
+	#[test]
+	fn suspended_file_is_resumed_after_a_reboot()
+	{
+		let memory = MockFlashMemory::default();
+		let content = file_content(7_000);
+
+		let mut file_system = boot(&memory);
+		let mut file_writer = file_system.create_file("benchy.gcode", content.len() as u32).unwrap();
+		file_writer.write_data(&mut file_system, &content[..3_000]).unwrap();
+		file_writer.suspend_writing(&mut file_system).unwrap();
+
+		let mut file_system = boot(&memory);
+		let file_metadata = file_system.get_existing_files_metadatas()[0].clone();
+		assert_eq!(file_metadata.partial_data_length, Some(3_000));
+		assert!(file_system.read_file(file_metadata.id).is_err());
+
+		let mut file_writer = file_system.resume_writing(file_metadata.id).unwrap();
+		assert_eq!(file_writer.get_written_data_length(), 3_000);
+		file_writer.set_expected_checksum(ExpectedChecksum::Crc32(crc32(&content)));
+		file_writer.write_data(&mut file_system, &content[3_000..]).unwrap();
+		file_writer.finish_writing(&mut file_system).unwrap();
+
+		let mut file_system = boot(&memory);
+		let file_metadata = file_system.get_existing_files_metadatas()[0].clone();
+		assert!(!file_metadata.is_partial());
+		assert_eq!(file_metadata.content_sha256, Some(Sha256::digest(&content).into()));
+		assert_eq!(read_file(&mut file_system, file_metadata.id).unwrap(), content);
+		assert!(matches!(
+			file_system.resume_writing(file_metadata.id),
+			Err(ResumeWritingError::NotPartial)
+		));
+	}
+
+	#[test]
+	fn file_with_an_unexpected_checksum_is_deleted()
+	{
+		let memory = MockFlashMemory::default();
+		let content = file_content(3_000);
+
+		let mut file_system = boot(&memory);
+		let mut file_writer = file_system.create_file("cube.gcode", content.len() as u32).unwrap();
+		let mut wrong_sha256: [u8; 32] = Sha256::digest(&content).into();
+		wrong_sha256[0] ^= 1;
+		file_writer.set_expected_checksum(ExpectedChecksum::Sha256(wrong_sha256));
+		file_writer.write_data(&mut file_system, &content).unwrap();
+		let file_metadata = file_system.get_existing_files_metadatas()[0].clone();
+
+		assert!(matches!(
+			file_writer.finish_writing(&mut file_system),
+			Err(WriteError::ChecksumMismatch)
+		));
+		assert!(file_system.get_existing_files_metadatas().is_empty());
+		let first_page_index = file_metadata.start_memory_address / MT29F2G01ABAGDWB::PAGE_SIZE;
+		assert!(memory.get_page(first_page_index).iter().all(|&byte| byte == 0xFF));
+		assert!(boot(&memory).get_existing_files_metadatas().is_empty());
+	}
+
+	#[test]
+	fn region_stored_with_an_incompatible_layout_isnt_replaced()
+	{
+		let memory = MockFlashMemory::<MT29F2G01ABAGDWB>::default();
+		let regions_config = RegionsConfig::default::<MT29F2G01ABAGDWB>();
+		let address = MT29F2G01ABAGDWB::get_address_of_block_index(*regions_config.metadata_block_range.start());
+		let first_page_index = address / MT29F2G01ABAGDWB::PAGE_SIZE;
+
+		// A region stored without the header of the copies: no bad blocks, the first file ID and no files
+		let old_region = [0, 0, 1, 0, 0, 0, 0, 0];
+		let mut spi_flash_memory = SpiFlashMemory::new(memory.clone(), MT29F2G01ABAGDWB);
+		MT29F2G01ABAGDWB::initialize(&mut spi_flash_memory).unwrap();
+		spi_flash_memory.program(&old_region, address).unwrap();
+		let page = memory.get_page(first_page_index);
+
+		assert!(matches!(
+			FileSystem::new(spi_flash_memory, regions_config),
+			Err(CreationError::IncompatibleMetadatasRegionLayout { version: 0 })
+		));
+		assert_eq!(memory.get_page(first_page_index), page);
+	}
+
+	#[test]
+	fn last_checkpoint_is_kept_after_a_reboot()
+	{
+		let memory = MockFlashMemory::default();
+		let mut file_system = boot(&memory);
+		assert_eq!(file_system.get_checkpoint(), None);
+
+		// More checkpoints than the pages of the region, so that its blocks are reused
+		let pages_count = 2 * MT29F2G01ABAGDWB::PAGES_PER_BLOCK;
+		for i in 0..pages_count + 10
+		{
+			file_system.store_checkpoint(&i.to_le_bytes()).unwrap();
+			if i % 50 == 0
+			{
+				file_system = boot(&memory);
+			}
+		}
+		let mut file_system = boot(&memory);
+		assert_eq!(file_system.get_checkpoint(), Some(&(pages_count + 9).to_le_bytes()[..]));
+
+		file_system.clear_checkpoint().unwrap();
+		assert_eq!(file_system.get_checkpoint(), None);
+		assert_eq!(boot(&memory).get_checkpoint(), None);
+	}
+
+	#[test]
+	fn checkpoint_is_consistent_after_a_power_loss_at_any_point()
+	{
+		let set_up_memory = || {
+			let memory = MockFlashMemory::<MT29F2G01ABAGDWB>::default();
+			let mut file_system = boot(&memory);
+			for _ in 0..MT29F2G01ABAGDWB::PAGES_PER_BLOCK
+			{
+				file_system.store_checkpoint(b"old").unwrap();
+			}
+
+			(memory, file_system)
+		};
+
+		let (memory, mut file_system) = set_up_memory();
+		let operations_count_before = memory.get_program_and_erase_count();
+		file_system.store_checkpoint(b"new").unwrap();
+		let operations_count = memory.get_program_and_erase_count() - operations_count_before;
+
+		for operations_before_power_loss in 0..=operations_count
+		{
+			let (mut memory, mut file_system) = set_up_memory();
+			memory.cut_power_after(operations_before_power_loss);
+			// The operations done after the power loss are ignored by the memory
+			let _ = file_system.store_checkpoint(b"new");
+			memory.restore_power();
+
+			// A page whose programming has been interrupted may contain the whole new checkpoint
+			let checkpoint = boot(&memory).get_checkpoint().map(<[u8]>::to_vec);
+			match operations_before_power_loss == operations_count
+			{
+				true => assert_eq!(checkpoint.as_deref(), Some(&b"new"[..])),
+				false => assert!(checkpoint.as_deref() == Some(b"old") || checkpoint.as_deref() == Some(b"new")),
+			}
+		}
+	}
+
+	/// Deletes the file with `file_id` and creates a new file with `content`, returning `None` if any of the operations
+	/// fails.
+	fn replace_file(file_system: &mut TestFileSystem, file_id: FileId, content: &[u8]) -> Option<()>
+	{
+		file_system.delete_file(file_id).ok()?;
+		let mut file_writer = file_system.create_file("new.gcode", content.len() as u32).ok()?;
+		file_writer.write_data(file_system, content).ok()?;
+		file_writer.finish_writing(file_system).ok()
+	}
+
+	#[test]
+	fn files_are_consistent_after_a_power_loss_at_any_point()
+	{
+		let old_content = file_content(3_000);
+		let new_content = file_content(5_000);
+		let set_up_memory = || {
+			let memory = MockFlashMemory::<MT29F2G01ABAGDWB>::default();
+			let mut file_system = boot(&memory);
+			let mut file_writer = file_system.create_file("old.gcode", old_content.len() as u32).unwrap();
+			file_writer.write_data(&mut file_system, &old_content).unwrap();
+			file_writer.finish_writing(&mut file_system).unwrap();
+			let old_file_id = file_system.get_existing_files_metadatas()[0].id;
+
+			(memory, file_system, old_file_id)
+		};
+
+		let (memory, mut file_system, old_file_id) = set_up_memory();
+		let operations_count_before = memory.get_program_and_erase_count();
+		replace_file(&mut file_system, old_file_id, &new_content).unwrap();
+		let operations_count = memory.get_program_and_erase_count() - operations_count_before;
+
+		for operations_before_power_loss in 0..operations_count
+		{
+			let (mut memory, mut file_system, old_file_id) = set_up_memory();
+			memory.cut_power_after(operations_before_power_loss);
+			// The operations done after the power loss are ignored by the memory
+			let _ = replace_file(&mut file_system, old_file_id, &new_content);
+			memory.restore_power();
+
+			let mut file_system = boot(&memory);
+			let files_metadatas = file_system.get_existing_files_metadatas().to_vec();
+			assert!(files_metadatas.len() <= 1);
+			for file_metadata in files_metadatas
+			{
+				let expected_content = match file_metadata.id == old_file_id
+				{
+					true => &old_content,
+					false => &new_content,
+				};
+				let mut file_reader = file_system.read_file(file_metadata.id).unwrap();
+				let mut read_content = vec![0; file_metadata.file_data_length as usize];
+				file_reader.read_data(&mut file_system, &mut read_content).unwrap();
+				assert_eq!(&read_content, expected_content);
+			}
+		}
+	}
+
+	#[test]
+	fn fragmented_space_is_compacted_to_create_a_file()
+	{
+		let memory = MockFlashMemory::default();
+		let block_size = MT29F2G01ABAGDWB::BLOCK_SIZE as usize;
+		let regions_config = RegionsConfig {
+			data_block_range: 2..=9,
+			..RegionsConfig::default::<MT29F2G01ABAGDWB>()
+		};
+		let mut file_system =
+			FileSystem::new(SpiFlashMemory::new(memory.clone(), MT29F2G01ABAGDWB), regions_config).unwrap();
+
+		// Each file fills its blocks entirely (the 5 bytes of the name included)
+		let mut files = Vec::new();
+		for (name, blocks_count) in [("a.gco", 1), ("b.gco", 2), ("c.gco", 1), ("d.gco", 3)]
+		{
+			let content = file_content(blocks_count * block_size - 5);
+			let mut file_writer = file_system.create_file(name, content.len() as u32).unwrap();
+			file_writer.write_data(&mut file_system, &content).unwrap();
+			file_writer.finish_writing(&mut file_system).unwrap();
+			files.push((file_system.get_existing_files_metadatas().last().unwrap().id, content));
+		}
+		let (d_file_id, d_content) = files.pop().unwrap();
+		let (c_file_id, _) = files.pop().unwrap();
+		let (b_file_id, b_content) = files.pop().unwrap();
+		let (a_file_id, _) = files.pop().unwrap();
+		file_system.delete_file(a_file_id).unwrap();
+		file_system.delete_file(c_file_id).unwrap();
+
+		let mut d_file_reader = file_system.read_file(d_file_id).unwrap();
+		let mut read_d_content = vec![0; d_content.len()];
+		let (first_half, second_half) = read_d_content.split_at_mut(d_content.len() / 2);
+		d_file_reader.read_data(&mut file_system, first_half).unwrap();
+
+		// There are 3 free blocks but they aren't contiguous
+		let e_content = file_content(3 * block_size - 5);
+		let mut file_writer = file_system.create_file("e.gco", e_content.len() as u32).unwrap();
+		file_writer.write_data(&mut file_system, &e_content).unwrap();
+		file_writer.finish_writing(&mut file_system).unwrap();
+		assert!(file_system
+			.create_file("f.gco", 1)
+			.is_err_and(|error| matches!(error, CreateFileError::NotEnoughSpaceAvailable)));
+
+		// The reader opened before the compaction reads the file from its new address
+		d_file_reader.read_data(&mut file_system, second_half).unwrap();
+		assert_eq!(read_d_content, d_content);
+
+		let mut file_system = boot(&memory);
+		for (file_id, content) in [(b_file_id, b_content), (d_file_id, d_content)]
+		{
+			let mut file_reader = file_system.read_file(file_id).unwrap();
+			let mut read_content = vec![0; content.len()];
+			file_reader.read_data(&mut file_system, &mut read_content).unwrap();
+			assert_eq!(read_content, content);
+		}
+	}
+
+	#[test]
+	fn files_are_allocated_on_the_least_worn_blocks()
+	{
+		let memory = MockFlashMemory::default();
+
+		let mut file_system = boot(&memory);
+		let mut start_addresses = Vec::new();
+		for _ in 0..3
+		{
+			let mut file_writer = file_system.create_file("cube.gcode", 10).unwrap();
+			file_writer.write_data(&mut file_system, &[0; 10]).unwrap();
+			file_writer.finish_writing(&mut file_system).unwrap();
+
+			let file_metadata = file_system.get_existing_files_metadatas()[0].clone();
+			start_addresses.push(file_metadata.start_memory_address);
+			file_system.delete_file(file_metadata.id).unwrap();
 		}
+
+		// Each deleted file has worn out its block, so the next file is stored in the following one
+		let first_block_address = MT29F2G01ABAGDWB::get_address_of_block_index(2);
+		let block_size = MT29F2G01ABAGDWB::BLOCK_SIZE;
+		assert_eq!(
+			start_addresses,
+			[
+				first_block_address,
+				first_block_address + block_size,
+				first_block_address + 2 * block_size
+			]
+		);
+
+		let wear_statistics = file_system.get_wear_statistics();
+		assert_eq!(wear_statistics.min_erase_count, 0);
+		assert_eq!(boot(&memory).get_wear_statistics(), wear_statistics);
+	}
+
+	/// Creates a file with `content` and returns its ID.
+	fn create_file(file_system: &mut TestFileSystem, content: &[u8]) -> FileId
+	{
+		let mut file_writer = file_system.create_file("cube.gcode", content.len() as u32).unwrap();
+		file_writer.write_data(file_system, content).unwrap();
+		file_writer.finish_writing(file_system).unwrap();
+
+		file_system.get_existing_files_metadatas().last().unwrap().id
+	}
+
+	fn read_file(
+		file_system: &mut TestFileSystem, file_id: FileId,
+	) -> Result<Vec<u8>, ReadError<MockFlashMemory<MT29F2G01ABAGDWB>>>
+	{
+		let file_metadata = file_system.metadatas_region.get_file_metadata(file_id).unwrap();
+		let mut file_reader = file_system.read_file(file_id).unwrap();
+		let mut content = vec![0; file_metadata.file_data_length as usize];
+		file_reader.read_data(file_system, &mut content)?;
+
+		Ok(content)
+	}
+
+	#[test]
+	fn file_in_a_block_with_repeated_corrections_is_moved()
+	{
+		let mut memory = MockFlashMemory::default();
+		let content = file_content(3_000);
+
+		let mut file_system = boot(&memory);
+		let file_id = create_file(&mut file_system, &content);
+		let file_metadata = file_system.metadatas_region.get_file_metadata(file_id).unwrap();
+		let first_page_index = file_metadata.start_memory_address / MT29F2G01ABAGDWB::PAGE_SIZE;
+		memory.inject_bit_flips(first_page_index, [100, 2_000]);
+
+		for _ in 0..CORRECTED_READS_BEFORE_RELOCATION
+		{
+			assert_eq!(file_system.get_bad_blocks_count(), 0);
+			assert_eq!(read_file(&mut file_system, file_id).unwrap(), content);
+		}
+
+		assert_eq!(file_system.get_bad_blocks_count(), 1);
+		assert_eq!(
+			file_system.get_ecc_counters().corrected_pages_count,
+			CORRECTED_READS_BEFORE_RELOCATION as u32 + 1
+		);
+		let moved_file_metadata = file_system.metadatas_region.get_file_metadata(file_id).unwrap();
+		assert_ne!(
+			moved_file_metadata.start_memory_address,
+			file_metadata.start_memory_address
+		);
+
+		let mut file_system = boot(&memory);
+		assert_eq!(file_system.get_bad_blocks_count(), 1);
+		assert_eq!(
+			file_system.metadatas_region.get_file_metadata(file_id),
+			Some(moved_file_metadata)
+		);
+		assert_eq!(read_file(&mut file_system, file_id).unwrap(), content);
+		assert_eq!(file_system.get_ecc_counters().corrected_pages_count, 0);
+	}
+
+	#[test]
+	fn uncorrectable_read_is_reported_and_its_block_is_marked_as_invalid()
+	{
+		let mut memory = MockFlashMemory::default();
+
+		let mut file_system = boot(&memory);
+		let file_id = create_file(&mut file_system, &file_content(3_000));
+		let file_metadata = file_system.metadatas_region.get_file_metadata(file_id).unwrap();
+		let first_page_index = file_metadata.start_memory_address / MT29F2G01ABAGDWB::PAGE_SIZE;
+		memory.inject_bit_flips(first_page_index, 200..220);
+
+		assert!(read_file(&mut file_system, file_id).is_err_and(|error| matches!(error, ReadError::CorruptedData)));
+		// The page is read again when the file is moved away from its block
+		assert_eq!(file_system.get_ecc_counters().uncorrectable_pages_count, 2);
+		assert_eq!(boot(&memory).get_bad_blocks_count(), 1);
 	}
 }
@@ -0,0 +1,91 @@
+//! The files of the [`FileSystem`] can be organized in directories by giving them path-style names, like
+//! `models/calibration/cube.gcode`.
+//!
+//! The directories aren't stored in the flash memory: a directory exists as long as there is a file whose path
+//! contains it.
+//!
+//! [`FileSystem`]: super::FileSystem
+
+/// The character that separates the names of the directories (and of the file) in a path.
+pub const SEPARATOR: char = '/';
+
+/// The maximum number of bytes of a path.
+pub const MAX_LENGTH: usize = 255;
+
+/// Returns `true` if `path` is a valid path for a file, otherwise returns `false`.
+///
+/// A path is valid if it's not longer than [`MAX_LENGTH`] bytes and if it's made of non empty names separated by
+/// [`SEPARATOR`] (so it can't start or end with a separator).
+///
+/// # Examples
+/// ```
+/// # use firmware_core::printer::components::file_system::path;
+/// #
+/// assert!(path::is_valid("cube.gcode"));
+/// assert!(path::is_valid("models/calibration/cube.gcode"));
+///
+/// assert!(!path::is_valid(""));
+/// assert!(!path::is_valid("/cube.gcode"));
+/// assert!(!path::is_valid("models/"));
+/// assert!(!path::is_valid("models//cube.gcode"));
+/// ```
+pub fn is_valid(path: &str) -> bool
+{
+	path.len() <= MAX_LENGTH && path.split(SEPARATOR).all(|name| !name.is_empty())
+}
+
+/// Returns the path of the directory that contains the file at `path` (an empty string if the file isn't in a
+/// directory).
+///
+/// # Examples
+/// ```
+/// # use firmware_core::printer::components::file_system::path;
+/// #
+/// assert_eq!(path::get_directory("models/calibration/cube.gcode"), "models/calibration");
+/// assert_eq!(path::get_directory("cube.gcode"), "");
+/// ```
+pub fn get_directory(path: &str) -> &str
+{
+	path.rsplit_once(SEPARATOR)
+		.map(|(directory, _)| directory)
+		.unwrap_or_default()
+}
+
+/// Returns the name of the file at `path` (without the directories that contain it).
+///
+/// # Examples
+/// ```
+/// # use firmware_core::printer::components::file_system::path;
+/// #
+/// assert_eq!(path::get_file_name("models/calibration/cube.gcode"), "cube.gcode");
+/// assert_eq!(path::get_file_name("cube.gcode"), "cube.gcode");
+/// ```
+pub fn get_file_name(path: &str) -> &str
+{
+	path.rsplit_once(SEPARATOR)
+		.map(|(_, file_name)| file_name)
+		.unwrap_or(path)
+}
+
+/// If `path` is inside the `directory` (even indirectly, in one of its subdirectories), returns the part of the path
+/// relative to the `directory`. Otherwise returns `None`.
+///
+/// An empty `directory` is the root directory, that contains all the paths.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::printer::components::file_system::path;
+/// #
+/// assert_eq!(path::strip_directory("models/calibration/cube.gcode", "models"), Some("calibration/cube.gcode"));
+/// assert_eq!(path::strip_directory("cube.gcode", ""), Some("cube.gcode"));
+/// assert_eq!(path::strip_directory("models2/cube.gcode", "models"), None);
+/// ```
+pub fn strip_directory<'a>(path: &'a str, directory: &str) -> Option<&'a str>
+{
+	if directory.is_empty()
+	{
+		return Some(path);
+	}
+
+	path.strip_prefix(directory)?.strip_prefix(SEPARATOR)
+}
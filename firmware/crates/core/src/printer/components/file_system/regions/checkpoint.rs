@@ -0,0 +1,214 @@
+use embedded_hal::spi::{ErrorType, SpiDevice};
+
+use super::RegionsConfig;
+use crate::{
+	printer::components::drivers::spi_flash_memory::{EccStatus, FlashMemoryChip, FlashMemoryChipExt, SpiFlashMemory},
+	utils::{crc::crc32, slice_to_array},
+};
+
+/// A small region of the flash memory that contains the last checkpoint stored in it (a few bytes that must survive a
+/// power loss, like the progress of the current print).
+///
+/// Each checkpoint is programmed in the page after the one of the previous checkpoint, so a block is erased only when
+/// all its pages have been used (and the region then moves to the next block of the range). Each page contains a
+/// sequence number and a CRC, so that the last checkpoint is found when the region is read and a checkpoint whose
+/// programming has been interrupted by a power loss is ignored.
+pub struct CheckpointRegion
+{
+	last_checkpoint: Option<Vec<u8>>,
+	next_sequence_number: u32,
+	/// The block whose pages are being used and the index of the next page to program in it, or `None` if the next
+	/// checkpoint must be stored in a new block.
+	next_page: Option<(u16, u32)>,
+	/// The block that contains the last checkpoint (if there's one).
+	last_block_index: Option<u16>,
+}
+
+impl CheckpointRegion
+{
+	const MARKER: u8 = 0xC7;
+	const HEADER_SIZE: usize = 1 + 4 + 2 + 4;
+
+	/// Returns the maximum length of the checkpoints that can be [`stored`](Self::store).
+	pub const fn max_checkpoint_length<Chip: FlashMemoryChip>() -> usize
+	{
+		Chip::PAGE_SIZE as usize - Self::HEADER_SIZE
+	}
+
+	/// Reads all the pages of the blocks in `regions_config.checkpoint_block_range` for which `is_block_usable` returns
+	/// `true`, keeping the last valid checkpoint.
+	///
+	/// The pages of the block that contains the last checkpoint are never programmed again, because a page whose
+	/// programming has been interrupted may not look erased: the next checkpoint is stored in the next block.
+	pub fn read_from_flash<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+		spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, regions_config: &RegionsConfig,
+		is_block_usable: impl Fn(u16) -> bool,
+	) -> Result<Self, <Spi as ErrorType>::Error>
+	{
+		let mut self_ = Self {
+			last_checkpoint: None,
+			next_sequence_number: 0,
+			next_page: None,
+			last_block_index: None,
+		};
+
+		let mut last_sequence_number = None;
+		let mut page = vec![0; Chip::PAGE_SIZE as usize];
+		for block_index in regions_config
+			.checkpoint_block_range
+			.clone()
+			.filter(|&block_index| (is_block_usable)(block_index))
+		{
+			for page_index in 0..Chip::PAGES_PER_BLOCK
+			{
+				let address = Chip::get_address_of_block_index(block_index) + page_index * Chip::PAGE_SIZE;
+				if spi_flash_memory.read(address, &mut page[..1])? == EccStatus::NoErrors && page[0] == 0xFF
+				{
+					// The pages are programmed in order, so the next ones are erased too
+					break;
+				}
+
+				if spi_flash_memory.read(address, &mut page)? == EccStatus::Uncorrectable
+				{
+					continue;
+				}
+				if let Some((sequence_number, checkpoint)) = Self::parse_page(&page)
+				{
+					if last_sequence_number.is_none_or(|last_sequence_number| sequence_number > last_sequence_number)
+					{
+						last_sequence_number = Some(sequence_number);
+						self_.last_checkpoint = (!checkpoint.is_empty()).then(|| checkpoint.to_vec());
+						self_.last_block_index = Some(block_index);
+					}
+				}
+			}
+		}
+		self_.next_sequence_number = last_sequence_number.map_or(0, |sequence_number| sequence_number.wrapping_add(1));
+
+		Ok(self_)
+	}
+
+	/// Returns the last checkpoint [`stored`](Self::store) in the region, or `None` if there isn't one (or if it has
+	/// been [`cleared`](Self::clear)).
+	pub fn get_last_checkpoint(&self) -> Option<&[u8]>
+	{
+		self.last_checkpoint.as_deref()
+	}
+
+	/// Stores the provided `checkpoint` in the next page of the region, so that it's returned by
+	/// [`Self::get_last_checkpoint`] (even after a reboot).
+	///
+	/// `is_block_usable` is used to skip the blocks of the range that must not be erased.
+	pub fn store<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+		&mut self, checkpoint: &[u8], spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, regions_config: &RegionsConfig,
+		is_block_usable: impl Fn(u16) -> bool,
+	) -> Result<(), StoreError<Spi>>
+	{
+		if checkpoint.len() > Self::max_checkpoint_length::<Chip>()
+		{
+			return Err(StoreError::CheckpointTooLong);
+		}
+
+		let (block_index, page_index) = match self.next_page
+		{
+			Some((block_index, page_index)) if page_index < Chip::PAGES_PER_BLOCK => (block_index, page_index),
+			_ =>
+			{
+				let block_index = self
+					.get_next_block_index(regions_config, is_block_usable)
+					.ok_or(StoreError::NoUsableBlock)?;
+				spi_flash_memory
+					.erase_blocks(block_index..=block_index)
+					.map_err(StoreError::Spi)?;
+				(block_index, 0)
+			},
+		};
+
+		let sequence_number = self.next_sequence_number;
+		let mut page = Vec::with_capacity(Self::HEADER_SIZE + checkpoint.len());
+		page.push(Self::MARKER);
+		page.extend_from_slice(&sequence_number.to_le_bytes());
+		page.extend_from_slice(&(checkpoint.len() as u16).to_le_bytes());
+		page.extend_from_slice(&crc32(&[&page[1..], checkpoint].concat()).to_le_bytes());
+		page.extend_from_slice(checkpoint);
+
+		let address = Chip::get_address_of_block_index(block_index) + page_index * Chip::PAGE_SIZE;
+		self.next_page = Some((block_index, page_index + 1));
+		spi_flash_memory.program(&page, address).map_err(StoreError::Spi)?;
+
+		self.next_sequence_number = sequence_number.wrapping_add(1);
+		self.last_checkpoint = (!checkpoint.is_empty()).then(|| checkpoint.to_vec());
+		self.last_block_index = Some(block_index);
+
+		Ok(())
+	}
+
+	/// Makes [`Self::get_last_checkpoint`] return `None` (even after a reboot), storing an empty checkpoint if there's
+	/// a checkpoint in the region.
+	pub fn clear<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+		&mut self, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, regions_config: &RegionsConfig,
+		is_block_usable: impl Fn(u16) -> bool,
+	) -> Result<(), StoreError<Spi>>
+	{
+		match self.last_checkpoint
+		{
+			Some(_) => self.store(&[], spi_flash_memory, regions_config, is_block_usable),
+			None => Ok(()),
+		}
+	}
+
+	/// Returns the sequence number and the checkpoint contained in the provided `page`, or `None` if the page doesn't
+	/// contain a valid checkpoint.
+	fn parse_page(page: &[u8]) -> Option<(u32, &[u8])>
+	{
+		if page.len() < Self::HEADER_SIZE || page[0] != Self::MARKER
+		{
+			return None;
+		}
+
+		let sequence_number = u32::from_le_bytes(slice_to_array(&page[1..]));
+		let checkpoint_length = u16::from_le_bytes(slice_to_array(&page[5..])) as usize;
+		let crc = u32::from_le_bytes(slice_to_array(&page[7..]));
+		let checkpoint = page.get(Self::HEADER_SIZE..Self::HEADER_SIZE + checkpoint_length)?;
+
+		(crc32(&[&page[1..7], checkpoint].concat()) == crc).then_some((sequence_number, checkpoint))
+	}
+
+	/// Returns the first usable block of the range after the one that contains the last checkpoint (wrapping around
+	/// the range).
+	fn get_next_block_index(&self, regions_config: &RegionsConfig, is_block_usable: impl Fn(u16) -> bool)
+		-> Option<u16>
+	{
+		let checkpoint_block_range = &regions_config.checkpoint_block_range;
+		let blocks_count = checkpoint_block_range.len() as u16;
+		let previous_block_offset = self.last_block_index.map_or(blocks_count - 1, |block_index| {
+			block_index - checkpoint_block_range.start()
+		});
+		(1..=blocks_count)
+			.map(|offset| checkpoint_block_range.start() + (previous_block_offset + offset) % blocks_count)
+			.find(|&block_index| (is_block_usable)(block_index))
+	}
+}
+
+/// An error returned by [`CheckpointRegion::store`].
+pub enum StoreError<Spi: SpiDevice<u8>>
+{
+	/// The checkpoint is longer than [`CheckpointRegion::max_checkpoint_length`].
+	CheckpointTooLong,
+	/// All the blocks of the region are bad (or occupied by a file stored before the region existed).
+	NoUsableBlock,
+	Spi(<Spi as ErrorType>::Error),
+}
+
+impl<Spi: SpiDevice<u8>> std::fmt::Debug for StoreError<Spi>
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		match self
+		{
+			Self::CheckpointTooLong => write!(f, "CheckpointTooLong"),
+			Self::NoUsableBlock => write!(f, "NoUsableBlock"),
+			Self::Spi(arg0) => f.debug_tuple("Spi").field(arg0).finish(),
+		}
+	}
+}
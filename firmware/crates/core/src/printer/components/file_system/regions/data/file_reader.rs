@@ -1,11 +1,11 @@
-use std::{fmt::Debug, marker::PhantomData, string::FromUtf8Error};
+use std::{fmt::Debug, marker::PhantomData};
 
 use embedded_hal::spi::{ErrorType, SpiDevice};
 
 use crate::printer::components::{
-	drivers::spi_flash_memory::FlashMemoryChip,
+	drivers::spi_flash_memory::{EccStatus, FlashMemoryChip},
 	file_system::{
-		regions::metadata::{FileMetadata, FileMetadataValidator},
+		regions::metadata::{FileMetadata, FileMetadataValidator, StoreError},
 		FileSystem,
 	},
 };
@@ -26,6 +26,11 @@ pub enum ReadError<Spi: SpiDevice<u8>>
 	DoesntExistAnymore,
 	/// All the content of the file has been read and there's nothing else to read.
 	EndOfFile,
+	/// Some of the read bytes are wrong, because they contained too many flipped bits to be corrected by the ECC of the
+	/// flash memory.
+	CorruptedData,
+	/// It has been impossible to move a file away from a block that is wearing out.
+	MoveFile(StoreError<<Spi as ErrorType>::Error>),
 }
 
 impl<Spi: SpiDevice<u8>> Debug for ReadError<Spi>
@@ -37,26 +42,8 @@ impl<Spi: SpiDevice<u8>> Debug for ReadError<Spi>
 			Self::Spi(arg0) => f.debug_tuple("Spi").field(arg0).finish(),
 			Self::DoesntExistAnymore => write!(f, "DoesntExistAnymore"),
 			Self::EndOfFile => write!(f, "EndOfFile"),
-		}
-	}
-}
-
-pub enum ReadNameError<Spi: SpiDevice<u8>>
-{
-	Spi(<Spi as ErrorType>::Error),
-	DoesntExistAnymore,
-	InvalidUtf8String(FromUtf8Error),
-}
-
-impl<Spi: SpiDevice<u8>> Debug for ReadNameError<Spi>
-{
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
-	{
-		match self
-		{
-			Self::Spi(arg0) => f.debug_tuple("Spi").field(arg0).finish(),
-			Self::DoesntExistAnymore => write!(f, "DoesntExistAnymore"),
-			Self::InvalidUtf8String(arg0) => f.debug_tuple("InvalidUtf8String").field(arg0).finish(),
+			Self::CorruptedData => write!(f, "CorruptedData"),
+			Self::MoveFile(arg0) => f.debug_tuple("MoveFile").field(arg0).finish(),
 		}
 	}
 }
@@ -65,10 +52,16 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileReader<Chip, Spi>
 {
 	/// Reads `data.len()` bytes from the file at the current position. The current position will be moved after the read
 	/// by `data.len()` bytes (so you can sequentially read all the bytes in the file you provided to [`FileWriter::write_data`](super::FileWriter::write_data)).
+	///
+	/// If the read pages contain flipped bits, their blocks are [`handled`] by the file system (the file may be moved to
+	/// another address). If the bits couldn't be corrected, `Err(ReadError::CorruptedData)` is returned (and the current
+	/// position isn't moved).
+	///
+	/// [`handled`]: crate::printer::components::file_system::regions::metadata::FilesMetadatasRegion::handle_ecc_error
 	pub fn read_data(&mut self, file_system: &mut FileSystem<Chip, Spi>, data: &mut [u8])
 		-> Result<u32, ReadError<Spi>>
 	{
-		if !self.validate_file_still_exists(&file_system)
+		if !self.validate_file_still_exists(file_system)
 		{
 			return Err(ReadError::DoesntExistAnymore);
 		}
@@ -78,22 +71,34 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileReader<Chip, Spi>
 			return Err(ReadError::EndOfFile);
 		}
 
-		let address = self.file_metadata.start_memory_address + self.file_metadata.file_name_length + self.cursor;
+		let address = self.file_metadata.start_memory_address + self.cursor;
 		let read_bytes_count = data
 			.len()
 			.min((self.file_metadata.file_data_length - self.cursor) as usize);
-		file_system
+		let ecc_status = file_system
 			.spi_flash_memory
 			.read(address, &mut data[..read_bytes_count])
 			.map_err(ReadError::Spi)?;
-
-		// TODO: Check if a bad block has been developed with the use of the memory and mark it as invalid in the BadBlockTable.
+		file_system.handle_ecc_errors().map_err(ReadError::MoveFile)?;
+		if ecc_status == EccStatus::Uncorrectable
+		{
+			return Err(ReadError::CorruptedData);
+		}
 
 		self.cursor += read_bytes_count as u32;
 
 		Ok(read_bytes_count as u32)
 	}
 
+	/// Moves the current position to the byte of the file at index `position` (or to the end of the file, if `position`
+	/// is greater than the length of the file), so that the next [`read`] starts from there.
+	///
+	/// [`read`]: `Self::read_data`
+	pub fn seek(&mut self, position: u32)
+	{
+		self.cursor = position.min(self.file_metadata.file_data_length);
+	}
+
 	/// Returns `true` if you have [`read`] all the data present in the file, otherwise returns `false`.
 	///
 	/// [`read`]: `Self::read_data`
@@ -102,27 +107,24 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileReader<Chip, Spi>
 		self.cursor == self.file_metadata.file_data_length
 	}
 
-	/// Reads the `file_name` you provided to [`FileSystem::create_file`] from the flash memory.
-	pub fn read_name(&mut self, file_system: &mut FileSystem<Chip, Spi>) -> Result<String, ReadNameError<Spi>>
+	/// Returns true if the file read by this struct still exists.
+	///
+	/// If the file has been moved to another address (because the file system has been [`compacted`]), its metadata is
+	/// updated.
+	///
+	/// [`compacted`]: FileSystem::compact
+	fn validate_file_still_exists(&mut self, file_system: &FileSystem<Chip, Spi>) -> bool
 	{
-		if !self.validate_file_still_exists(&file_system)
+		if !self.validator.validate(&file_system.metadatas_region)
 		{
-			return Err(ReadNameError::DoesntExistAnymore);
+			match file_system.metadatas_region.get_file_metadata(self.file_metadata.id)
+			{
+				Some(file_metadata) => self.file_metadata = file_metadata,
+				None => return false,
+			}
+			self.validator = file_system.metadatas_region.get_file_validator();
 		}
 
-		let mut name_bytes = vec![0; self.file_metadata.file_name_length as usize];
-		file_system
-			.spi_flash_memory
-			.read(self.file_metadata.start_memory_address, &mut name_bytes)
-			.map_err(ReadNameError::Spi)?;
-		let name = String::from_utf8(name_bytes).map_err(ReadNameError::InvalidUtf8String)?;
-
-		Ok(name)
-	}
-
-	/// Returns true if the file read by this struct still exists and the memory address in the metadata is still valid.
-	fn validate_file_still_exists(&self, file_system: &FileSystem<Chip, Spi>) -> bool
-	{
-		self.validator.validate(&file_system.metadatas_region) && file_system.does_file_exist(self.file_metadata.id)
+		file_system.does_file_exist(self.file_metadata.id)
 	}
 }
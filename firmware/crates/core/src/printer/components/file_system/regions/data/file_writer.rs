@@ -1,19 +1,26 @@
 use std::marker::PhantomData;
 
 use embedded_hal::spi::{ErrorType, SpiDevice};
+use sha2::{Digest, Sha256};
 
-use crate::printer::components::{
-	drivers::spi_flash_memory::FlashMemoryChip,
-	file_system::{
-		regions::metadata::{FileMetadata, FileMetadataValidator},
-		FileSystem,
+use crate::{
+	printer::components::{
+		drivers::spi_flash_memory::FlashMemoryChip,
+		file_system::{
+			regions::metadata::{FileMetadata, FileMetadataValidator, StoreError},
+			FileSystem,
+		},
 	},
+	utils::crc::Crc32,
 };
 
 pub struct FileWriter<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>
 {
 	pub(super) file_metadata: FileMetadata,
-	pub(super) name: Option<String>,
+	pub(super) has_started_writing: bool,
+	/// The checksums of the data written so far.
+	pub(super) content_checksums: ContentChecksums,
+	pub(super) expected_checksum: Option<ExpectedChecksum>,
 	pub(super) cursor: u32,
 	pub(super) has_finished_writing: bool,
 	pub(super) validator: FileMetadataValidator,
@@ -25,10 +32,17 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileWriter<Chip, Spi>
 	pub fn write_data(&mut self, file_system: &mut FileSystem<Chip, Spi>, data: &[u8]) -> Result<(), WriteError<Spi>>
 	{
 		// If it's the first time you try to write to this file
-		if let Some(name) = self.name.take()
+		if !self.has_started_writing
 		{
+			// The space reserved for the file may have been occupied by another file while compacting the file system
+			if !self.validator.validate(&file_system.metadatas_region)
+			{
+				return Err(WriteError::DoesntExistAnymore);
+			}
+
 			log::info!(
-				"Start writing a file with name \"{name}\" and with a size of {} bytes",
+				"Start writing a file with name \"{}\" and with a size of {} bytes",
+				self.file_metadata.name,
 				self.file_metadata.file_data_length
 			);
 			file_system
@@ -38,33 +52,90 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileWriter<Chip, Spi>
 					&mut file_system.spi_flash_memory,
 					&file_system.regions_config,
 				)
-				.map_err(WriteError::Spi)?;
-
-			file_system
-				.spi_flash_memory
-				.program(name.as_bytes(), self.file_metadata.start_memory_address)
-				.map_err(WriteError::Spi)?;
+				.map_err(WriteError::from)?;
+			self.has_started_writing = true;
 		}
 
 		file_system
 			.spi_flash_memory
-			.program(
-				data,
-				self.file_metadata.start_memory_address + self.file_metadata.file_name_length + self.cursor,
-			)
+			.program(data, self.file_metadata.start_memory_address + self.cursor)
 			.map_err(WriteError::Spi)?;
 
 		// TODO: Check if a bad block has been developed with the use of the memory and mark it as invalid in the BadBlockTable.
 
+		self.content_checksums.update(data);
 		self.cursor += data.len() as u32;
 
 		Ok(())
 	}
 
+	/// Sets when the file has been uploaded (in seconds since the Unix epoch), that is stored in its metadata when you
+	/// [`finish writing`] it.
+	///
+	/// [`finish writing`]: Self::finish_writing
+	pub fn set_upload_time(&mut self, upload_time: u64)
+	{
+		self.file_metadata.upload_time = Some(upload_time);
+	}
+
+	/// Sets the checksum that the data of the whole file must have. When you [`finish writing`] the file, if the data you
+	/// wrote (even before suspending and resuming the writing) has a different checksum the file is deleted.
+	///
+	/// [`finish writing`]: Self::finish_writing
+	pub fn set_expected_checksum(&mut self, expected_checksum: ExpectedChecksum)
+	{
+		self.expected_checksum = Some(expected_checksum);
+	}
+
+	/// Returns how many bytes of data have been written to the file (including the ones written before its writing has
+	/// been suspended and resumed).
+	pub fn get_written_data_length(&self) -> u32
+	{
+		self.cursor
+	}
+
+	/// Stores the metadata of the file (with the SHA-256 hash of the data you [`wrote`]) in the flash memory, so that the
+	/// file isn't deleted the next time the file system is created.
+	///
+	/// Returns `Err(WriteError::ChecksumMismatch)` if the data you wrote doesn't have the [`expected checksum`] (the
+	/// file is deleted in this case).
+	///
+	/// [`wrote`]: Self::write_data
+	/// [`expected checksum`]: Self::set_expected_checksum
 	pub fn finish_writing(mut self, file_system: &mut FileSystem<Chip, Spi>) -> Result<(), WriteError<Spi>>
 	{
 		self.validate_file_still_exists(file_system)?;
 
+		let content_checksums = core::mem::take(&mut self.content_checksums);
+		let content_crc32 = content_checksums.crc32.finish();
+		let content_sha256 = content_checksums.sha256.finalize().into();
+		let is_checksum_valid = match self.expected_checksum
+		{
+			Some(ExpectedChecksum::Sha256(expected_sha256)) => expected_sha256 == content_sha256,
+			Some(ExpectedChecksum::Crc32(expected_crc32)) => expected_crc32 == content_crc32,
+			None => true,
+		};
+		if !is_checksum_valid
+		{
+			log::warn!(
+				"The file with name \"{}\" doesn't have the expected checksum, so it's deleted",
+				self.file_metadata.name
+			);
+			self.discard(file_system)?;
+
+			return Err(WriteError::ChecksumMismatch);
+		}
+
+		let upload_time = self.file_metadata.upload_time;
+		// The file doesn't exist in the metadatas region only if no data has been written to it
+		let _ = file_system
+			.metadatas_region
+			.update_file_metadata(self.file_metadata.id, |file_metadata| {
+				file_metadata.content_sha256 = Some(content_sha256);
+				file_metadata.upload_time = upload_time;
+				file_metadata.partial_data_length = None;
+			});
+
 		file_system
 			.metadatas_region
 			.finish_writing_file(
@@ -72,12 +143,65 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileWriter<Chip, Spi>
 				&mut file_system.spi_flash_memory,
 				&file_system.regions_config,
 			)
-			.map_err(WriteError::Spi)?;
+			.map_err(WriteError::from)?;
 		self.has_finished_writing = true;
 
 		Ok(())
 	}
 
+	/// Stops writing the file before all its data has been written, keeping the data you wrote in the file system (the
+	/// file becomes [`partial`]), so that you can [`resume`] writing it later (even after a reboot).
+	///
+	/// [`partial`]: FileMetadata::partial_data_length
+	/// [`resume`]: FileSystem::resume_writing
+	pub fn suspend_writing(mut self, file_system: &mut FileSystem<Chip, Spi>) -> Result<(), WriteError<Spi>>
+	{
+		self.validate_file_still_exists(file_system)?;
+
+		log::info!(
+			"Suspend writing the file with name \"{}\" after {} bytes",
+			self.file_metadata.name,
+			self.cursor
+		);
+		file_system
+			.metadatas_region
+			.suspend_writing_file(
+				self.file_metadata.id,
+				self.cursor,
+				&mut file_system.spi_flash_memory,
+				&file_system.regions_config,
+			)
+			.map_err(WriteError::from)?;
+		self.has_finished_writing = true;
+
+		Ok(())
+	}
+
+	/// Stops writing the file and deletes it (with all the data you wrote, even before suspending and resuming the
+	/// writing).
+	pub fn discard(mut self, file_system: &mut FileSystem<Chip, Spi>) -> Result<(), WriteError<Spi>>
+	{
+		self.has_finished_writing = true;
+
+		// The file doesn't exist in the metadatas region only if no data has been written to it
+		if file_system
+			.metadatas_region
+			.delete_file::<Chip>(self.file_metadata.id)
+			.is_ok()
+		{
+			file_system
+				.metadatas_region
+				.store_in_flash(&mut file_system.spi_flash_memory, &file_system.regions_config)
+				.map_err(WriteError::from)?;
+			file_system
+				.metadatas_region
+				.erase_pending_blocks(&mut file_system.spi_flash_memory)
+				.map_err(WriteError::Spi)?;
+		}
+
+		Ok(())
+	}
+
 	fn validate_file_still_exists(&self, file_system: &FileSystem<Chip, Spi>) -> Result<(), WriteError<Spi>>
 	{
 		if self.validator.validate(&file_system.metadatas_region) && !file_system.does_file_exist(self.file_metadata.id)
@@ -91,10 +215,57 @@ impl<Chip: FlashMemoryChip, Spi: SpiDevice<u8>> FileWriter<Chip, Spi>
 	}
 }
 
+/// The checksums of the data of a file, computed while it's written.
+#[derive(Clone, Default)]
+pub struct ContentChecksums
+{
+	pub sha256: Sha256,
+	pub crc32: Crc32,
+}
+
+impl ContentChecksums
+{
+	/// Adds the `data` to the data whose checksums are computed.
+	pub fn update(&mut self, data: &[u8])
+	{
+		self.sha256.update(data);
+		self.crc32.update(data);
+	}
+}
+
+/// The checksum that the data of a file must have when you [`finish writing`] it.
+///
+/// [`finish writing`]: FileWriter::finish_writing
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExpectedChecksum
+{
+	Sha256([u8; 32]),
+	/// Check [`Crc32`].
+	Crc32(u32),
+}
+
 pub enum WriteError<Spi: SpiDevice<u8>>
 {
 	Spi(<Spi as ErrorType>::Error),
 	DoesntExistAnymore,
+	/// The data written to the file doesn't have the [`expected checksum`], so the file has been deleted.
+	///
+	/// [`expected checksum`]: FileWriter::set_expected_checksum
+	ChecksumMismatch,
+	/// The metadatas of the files don't fit in the metadatas region anymore (check [`StoreError::DoesntFitInBlock`]).
+	MetadatasRegionFull,
+}
+
+impl<Spi: SpiDevice<u8>> From<StoreError<<Spi as ErrorType>::Error>> for WriteError<Spi>
+{
+	fn from(error: StoreError<<Spi as ErrorType>::Error>) -> Self
+	{
+		match error
+		{
+			StoreError::Spi(error) => Self::Spi(error),
+			StoreError::DoesntFitInBlock => Self::MetadatasRegionFull,
+		}
+	}
 }
 
 impl<Spi: SpiDevice<u8>> std::fmt::Debug for WriteError<Spi>
@@ -105,6 +276,8 @@ impl<Spi: SpiDevice<u8>> std::fmt::Debug for WriteError<Spi>
 		{
 			Self::Spi(arg0) => f.debug_tuple("Spi").field(arg0).finish(),
 			Self::DoesntExistAnymore => write!(f, "DoesntExistAnymore"),
+			Self::ChecksumMismatch => write!(f, "ChecksumMismatch"),
+			Self::MetadatasRegionFull => write!(f, "MetadatasRegionFull"),
 		}
 	}
 }
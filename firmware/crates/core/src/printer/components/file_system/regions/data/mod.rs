@@ -8,7 +8,10 @@ pub use file_reader::*;
 pub use file_writer::*;
 
 use super::metadata::{FileMetadata, FileMetadataValidator};
-use crate::printer::components::drivers::spi_flash_memory::{FlashMemoryChip, FlashMemoryChipExt, SpiFlashMemory};
+use crate::printer::components::{
+	drivers::spi_flash_memory::{FlashMemoryChip, SpiFlashMemory},
+	file_system::wear_leveling::EraseCountTable,
+};
 
 /// There are 2 assumptions used in all the methods of this struct that must be held:
 /// - 2 files can't partially occupy the same block (basically the allocation unit size of the file system is the size
@@ -19,15 +22,14 @@ pub struct FilesRegion;
 impl FilesRegion
 {
 	pub fn create_file<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
-		&self, mut file_metadata: FileMetadata, name: impl Into<String>, file_validator: FileMetadataValidator,
+		&self, file_metadata: FileMetadata, file_validator: FileMetadataValidator,
 	) -> FileWriter<Chip, Spi>
 	{
-		let name = name.into();
-		file_metadata.file_name_length = name.len() as u32;
-
 		FileWriter {
 			file_metadata,
-			name: Some(name),
+			has_started_writing: false,
+			content_checksums: ContentChecksums::default(),
+			expected_checksum: None,
 			cursor: 0,
 			has_finished_writing: false,
 			_chip_and_spi: PhantomData,
@@ -35,6 +37,26 @@ impl FilesRegion
 		}
 	}
 
+	/// Returns a [`FileWriter`] that continues writing the [`partial`] file with the provided `file_metadata`, whose
+	/// already written data has the provided `content_checksums`.
+	///
+	/// [`partial`]: FileMetadata::partial_data_length
+	pub fn resume_writing_file<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+		&self, file_metadata: FileMetadata, content_checksums: ContentChecksums, file_validator: FileMetadataValidator,
+	) -> FileWriter<Chip, Spi>
+	{
+		FileWriter {
+			cursor: file_metadata.partial_data_length.unwrap_or_default(),
+			file_metadata,
+			has_started_writing: true,
+			content_checksums,
+			expected_checksum: None,
+			has_finished_writing: false,
+			_chip_and_spi: PhantomData,
+			validator: file_validator,
+		}
+	}
+
 	pub fn open_file_for_read<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
 		&self, file_metadata: FileMetadata, file_validator: FileMetadataValidator,
 	) -> FileReader<Chip, Spi>
@@ -49,11 +71,10 @@ impl FilesRegion
 
 	pub fn delete_file<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
 		&self, file_metadata: FileMetadata, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>,
+		erase_count_table: &mut EraseCountTable,
 	) -> Result<(), <Spi as ErrorType>::Error>
 	{
-		let start_block_index = Chip::get_block_index_of_address(file_metadata.start_memory_address);
-		let end_block_index = Chip::get_block_index_of_address(file_metadata.end_memory_address());
-		spi_flash_memory.erase_blocks(start_block_index..=end_block_index)?;
+		erase_count_table.erase_blocks(spi_flash_memory, file_metadata.block_range::<Chip>())?;
 
 		Ok(())
 	}
@@ -2,7 +2,10 @@ use std::{fmt::Debug, marker::PhantomData, ops::RangeInclusive};
 
 use super::FilesMetadatasRegion;
 use crate::{
-	printer::components::{drivers::spi_flash_memory::*, file_system::RegionsConfig},
+	printer::components::{
+		drivers::spi_flash_memory::*,
+		file_system::{wear_leveling::EraseCountTable, RegionsConfig},
+	},
 	utils::math::NumberExt,
 };
 
@@ -23,30 +26,29 @@ impl<Chip: FlashMemoryChip> DataHoles<Chip>
 			.iter()
 			.map(|value| *value..=*value)
 			.chain(core::iter::once(regions_config.metadata_block_range.clone()))
-			.chain(files_metadatas_region.files_metadatas.iter().map(|metadata| {
-				let start_block_index = Chip::get_block_index_of_address(metadata.start_memory_address);
-				let end_block_index = Chip::get_block_index_of_address(metadata.end_memory_address());
-				start_block_index..=end_block_index
-			}))
+			.chain(
+				files_metadatas_region
+					.files_metadatas
+					.iter()
+					.map(|metadata| metadata.block_range::<Chip>()),
+			)
 			.collect();
 
 		used_slots.sort_by(|a, b| a.start().cmp(b.start()));
 
+		// Only the blocks of the data region can contain files
+		let data_block_range = &regions_config.data_block_range;
 		let mut holes = Vec::with_capacity(used_slots.len() + 1);
-
-		if *used_slots[0].start() != 0
-		{
-			holes.push(0..=(*used_slots[0].start() - 1));
-		}
-
-		for i in 0..(used_slots.len() - 1)
+		let mut hole_start = *data_block_range.start();
+		for used_slot in used_slots
 		{
-			holes.push((used_slots[i].end() + 1)..=(used_slots[i + 1].start() - 1));
-		}
-		if let Some(last_hole) = used_slots.last()
-		{
-			holes.push((*last_hole.end() + 1)..=Chip::get_block_index_of_address(Chip::MEMORY_SIZE));
+			if *used_slot.start() > hole_start
+			{
+				holes.push(hole_start..=(*used_slot.start() - 1).min(*data_block_range.end()));
+			}
+			hole_start = hole_start.max(used_slot.end().saturating_add(1));
 		}
+		holes.push(hole_start..=*data_block_range.end());
 
 		holes.retain(|hole| !hole.is_empty());
 
@@ -57,28 +59,55 @@ impl<Chip: FlashMemoryChip> DataHoles<Chip>
 	}
 
 	/// Check if there's enough space in the data holes to contain `data_size` bytes.
-	pub fn find_space_for_new_data(&self, data_size: u32) -> FreeSpace
+	///
+	/// If there is, the space whose blocks have been erased the least times (based on the `erase_count_table`) is
+	/// chosen, so that all the blocks wear out evenly (if more spaces are equally worn, the one with the lowest address
+	/// is chosen).
+	pub fn find_space_for_new_data(&self, data_size: u32, erase_count_table: &EraseCountTable) -> FreeSpace
 	{
-		let required_blocks = data_size.ceil_div(Chip::BLOCK_SIZE) as u16;
+		// Even an empty file occupies a block
+		let required_blocks = (data_size.ceil_div(Chip::BLOCK_SIZE) as u16).max(1);
 
+		let mut least_worn_space: Option<(u64, u16)> = None;
 		let mut total_available_space = 0;
 		for hole in &self.block_holes
 		{
 			let hole_size = hole.len() as u16;
 			if hole_size >= required_blocks
 			{
-				return FreeSpace::Available {
-					start_address: Chip::get_address_of_block_index(*hole.start()),
-				};
+				// Slide the space along the hole, updating its erase count with the block that enters and the one that
+				// exits
+				let mut start_block_index = *hole.start();
+				let mut erase_count =
+					erase_count_table.get_total_erase_count(start_block_index..(start_block_index + required_blocks));
+				loop
+				{
+					if least_worn_space.is_none_or(|(least_erase_count, _)| erase_count < least_erase_count)
+					{
+						least_worn_space = Some((erase_count, start_block_index));
+					}
+
+					let end_block_index = start_block_index + required_blocks;
+					if end_block_index > *hole.end()
+					{
+						break;
+					}
+					erase_count += erase_count_table.get_erase_count(end_block_index) as u64;
+					erase_count -= erase_count_table.get_erase_count(start_block_index) as u64;
+					start_block_index += 1;
+				}
 			}
 
 			total_available_space += hole_size;
 		}
 
-		match total_available_space >= required_blocks
+		match least_worn_space
 		{
-			true => FreeSpace::AvailableButRequiresCompacting,
-			false => FreeSpace::NotAvailable,
+			Some((_, start_block_index)) => FreeSpace::Available {
+				start_address: Chip::get_address_of_block_index(start_block_index),
+			},
+			None if total_available_space >= required_blocks => FreeSpace::AvailableButRequiresCompacting,
+			None => FreeSpace::NotAvailable,
 		}
 	}
 }
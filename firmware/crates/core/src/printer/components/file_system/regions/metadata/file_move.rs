@@ -0,0 +1,59 @@
+use super::id::FileId;
+use crate::utils::slice_to_array;
+
+/// A file that is being moved to another location of the flash memory by [`FilesMetadatasRegion::compact`].
+///
+/// It's stored in the flash memory with the rest of the region after each moved chunk of blocks, so that if the
+/// printer loses power during the move, the move can be resumed the next time the region is read.
+///
+/// [`FilesMetadatasRegion::compact`]: super::FilesMetadatasRegion::compact
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct FileMove
+{
+	/// The ID of the file that is being moved.
+	pub file_id: FileId,
+	/// The address where the file will start after the move.
+	pub destination_address: u32,
+	/// How many blocks of the file (starting from the first one) have already been copied to the destination.
+	pub moved_blocks_count: u16,
+}
+
+impl FileMove
+{
+	/// The number of bytes returned by [`Self::to_bytes`].
+	pub const SERIALIZED_SIZE: usize = 10;
+
+	pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_SIZE]
+	{
+		let mut bytes = self
+			.file_id
+			.to_bytes()
+			.into_iter()
+			.chain(self.destination_address.to_le_bytes())
+			.chain(self.moved_blocks_count.to_le_bytes());
+
+		std::array::from_fn(|_| bytes.next().unwrap())
+	}
+
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::file_system::regions::metadata::*;
+	/// #
+	/// let file_move = FileMove
+	/// {
+	///     file_id: FileId::FIRST,
+	///     destination_address: 0x40000,
+	///     moved_blocks_count: 3,
+	/// };
+	///
+	/// assert_eq!(file_move, FileMove::from_bytes(&file_move.to_bytes()));
+	/// ```
+	pub fn from_bytes(bytes: &[u8]) -> Self
+	{
+		Self {
+			file_id: FileId::from_bytes(slice_to_array(&bytes[0..])),
+			destination_address: u32::from_le_bytes(slice_to_array(&bytes[4..])),
+			moved_blocks_count: u16::from_le_bytes(slice_to_array(&bytes[8..])),
+		}
+	}
+}
@@ -1,56 +1,162 @@
-use super::id::FileId;
-use crate::utils::slice_to_array;
+use std::ops::RangeInclusive;
+
+use super::{id::FileId, take, take_slice};
+use crate::printer::components::drivers::spi_flash_memory::{FlashMemoryChip, FlashMemoryChipExt};
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct FileMetadata
 {
 	pub id: FileId,
 	pub start_memory_address: u32,
-	pub file_name_length: u32,
 	pub file_data_length: u32,
+	/// The path of the file in the file system (check [`path`]).
+	///
+	/// It's stored only in the metadatas region, so the file can be renamed without copying its data.
+	///
+	/// [`path`]: crate::printer::components::file_system::path
+	pub name: String,
+	/// When the file has been uploaded (in seconds since the Unix epoch), if it's known.
+	pub upload_time: Option<u64>,
+	/// When the file has been printed the last time (in seconds since the Unix epoch), if it's known.
+	pub last_print_time: Option<u64>,
+	/// How many times the file has been printed.
+	pub print_count: u32,
+	/// The SHA-256 hash of the data of the file, computed while the file was written (it's `None` until the file has
+	/// been completely written).
+	pub content_sha256: Option<[u8; 32]>,
+	/// If the writing of the file has been [`suspended`] before all its data was written, how many bytes of its data have
+	/// been written (the file can't be read until its writing is [`resumed`] and finished).
+	///
+	/// [`suspended`]: crate::printer::components::file_system::regions::data::FileWriter::suspend_writing
+	/// [`resumed`]: crate::printer::components::file_system::FileSystem::resume_writing
+	pub partial_data_length: Option<u32>,
 }
 
 impl FileMetadata
 {
-	pub fn to_bytes(&self) -> [u8; core::mem::size_of::<Self>()]
+	/// Returns the metadata of a file that has never been printed and whose upload time and content hash are unknown.
+	pub fn new(id: FileId, start_memory_address: u32, file_data_length: u32, name: impl Into<String>) -> Self
+	{
+		Self {
+			id,
+			start_memory_address,
+			file_data_length,
+			name: name.into(),
+			upload_time: None,
+			last_print_time: None,
+			print_count: 0,
+			content_sha256: None,
+			partial_data_length: None,
+		}
+	}
+
+	/// Returns `true` if the writing of the file has been suspended before all its data was written (check
+	/// [`Self::partial_data_length`]), otherwise returns `false`.
+	pub fn is_partial(&self) -> bool
+	{
+		self.partial_data_length.is_some()
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8>
 	{
-		let mut bytes = self
-			.id
+		let optional_time_to_bytes = |time: Option<u64>| time.unwrap_or(u64::MAX).to_le_bytes();
+
+		self.id
 			.to_bytes()
 			.into_iter()
 			.chain(self.start_memory_address.to_le_bytes())
-			.chain(self.file_name_length.to_le_bytes())
-			.chain(self.file_data_length.to_le_bytes());
-
-		std::array::from_fn(|_| bytes.next().unwrap())
+			.chain(self.file_data_length.to_le_bytes())
+			.chain(optional_time_to_bytes(self.upload_time))
+			.chain(optional_time_to_bytes(self.last_print_time))
+			.chain(self.print_count.to_le_bytes())
+			.chain(self.partial_data_length.unwrap_or(u32::MAX).to_le_bytes())
+			.chain(core::iter::once(self.content_sha256.is_some() as u8))
+			.chain(self.content_sha256.into_iter().flatten())
+			.chain((self.name.len() as u16).to_le_bytes())
+			.chain(self.name.bytes())
+			.collect()
 	}
 
+	/// Reads a file metadata you [`converted`] to bytes from the start of `bytes`, and removes the read bytes from
+	/// `bytes`.
+	///
+	/// Returns `None` if `bytes` doesn't start with a valid file metadata.
+	///
 	/// # Examples
 	/// ```
 	/// # use firmware_core::printer::components::file_system::regions::metadata::*;
 	/// #
-	/// let file_metadata = FileMetadata
-	/// {
-	///     id: FileId::FIRST,
-	///     start_memory_address: 0x3000,
-	///     file_name_length: 20,
-	///     file_data_length: 500_000
-	/// };
-	///
-	/// assert_eq!(file_metadata, FileMetadata::from_bytes(&file_metadata.to_bytes()));
+	/// let mut file_metadata = FileMetadata::new(FileId::FIRST, 0x3000, 500_000, "models/benchy.gcode");
+	/// file_metadata.upload_time = Some(1_700_000_000);
+	/// file_metadata.print_count = 2;
+	/// file_metadata.content_sha256 = Some([7; 32]);
+	/// file_metadata.partial_data_length = Some(1_000);
+	///
+	/// let bytes = file_metadata.to_bytes();
+	/// let mut bytes_to_read = &bytes[..];
+	/// assert_eq!(FileMetadata::from_bytes(&mut bytes_to_read), Some(file_metadata));
+	/// assert!(bytes_to_read.is_empty());
 	/// ```
-	pub fn from_bytes(bytes: &[u8]) -> Self
+	///
+	/// [`converted`]: Self::to_bytes
+	pub fn from_bytes(bytes: &mut &[u8]) -> Option<Self>
 	{
-		Self {
-			id: FileId::from_bytes(slice_to_array(&bytes[0..])),
-			start_memory_address: u32::from_le_bytes(slice_to_array(&bytes[4..])),
-			file_name_length: u32::from_le_bytes(slice_to_array(&bytes[8..])),
-			file_data_length: u32::from_le_bytes(slice_to_array(&bytes[12..])),
-		}
+		let optional_time_from_bytes =
+			|bytes: [u8; 8]| Some(u64::from_le_bytes(bytes)).filter(|&time| time != u64::MAX);
+
+		let id = FileId::from_bytes(take(bytes)?);
+		let start_memory_address = u32::from_le_bytes(take(bytes)?);
+		let file_data_length = u32::from_le_bytes(take(bytes)?);
+		let upload_time = optional_time_from_bytes(take(bytes)?);
+		let last_print_time = optional_time_from_bytes(take(bytes)?);
+		let print_count = u32::from_le_bytes(take(bytes)?);
+		let partial_data_length = Some(u32::from_le_bytes(take(bytes)?)).filter(|&length| length != u32::MAX);
+		let content_sha256 = match take::<1>(bytes)?[0]
+		{
+			0 => None,
+			_ => Some(take(bytes)?),
+		};
+		let name_length = u16::from_le_bytes(take(bytes)?);
+		let name = String::from_utf8(take_slice(bytes, name_length as usize)?.to_vec()).ok()?;
+
+		Some(Self {
+			id,
+			start_memory_address,
+			file_data_length,
+			name,
+			upload_time,
+			last_print_time,
+			print_count,
+			content_sha256,
+			partial_data_length,
+		})
 	}
 
 	pub fn end_memory_address(&self) -> u32
 	{
-		self.start_memory_address + self.file_name_length + self.file_data_length
+		self.start_memory_address + self.file_data_length
+	}
+
+	/// Returns the range of the indices of the blocks of the flash memory occupied by the file (an empty file occupies
+	/// a block anyway).
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::{file_system::regions::metadata::*, drivers::spi_flash_memory::*};
+	/// #
+	/// let file_metadata = FileMetadata::new(
+	///     FileId::FIRST,
+	///     3 * MT29F2G01ABAGDWB::BLOCK_SIZE,
+	///     2 * MT29F2G01ABAGDWB::BLOCK_SIZE,
+	///     "cube.gcode",
+	/// );
+	///
+	/// assert_eq!(file_metadata.block_range::<MT29F2G01ABAGDWB>(), 3..=4);
+	/// ```
+	pub fn block_range<Chip: FlashMemoryChip>(&self) -> RangeInclusive<u16>
+	{
+		let last_memory_address = self.end_memory_address().max(self.start_memory_address + 1) - 1;
+		Chip::get_block_index_of_address(self.start_memory_address)
+			..=Chip::get_block_index_of_address(last_memory_address)
 	}
 }
@@ -1,9 +1,13 @@
 mod data_holes;
+mod file_move;
 mod id;
 mod metadata;
 mod validator;
 
+use std::{fmt::Debug, ops::RangeInclusive};
+
 use embedded_hal::spi::{ErrorType, SpiDevice};
+pub use file_move::*;
 pub use id::*;
 pub use metadata::*;
 pub use validator::*;
@@ -12,12 +16,36 @@ pub(crate) use self::data_holes::*;
 use super::data::FilesRegion;
 use crate::{
 	printer::components::{
-		drivers::spi_flash_memory::{FlashMemoryChip, SpiFlashMemory},
-		file_system::{bad_blocks::BadBlockTable, RegionsConfig},
+		drivers::spi_flash_memory::{EccStatus, FlashMemoryChip, FlashMemoryChipExt, SpiFlashMemory},
+		file_system::{
+			bad_blocks::BadBlockTable,
+			wear_leveling::{EraseCountTable, WearStatistics},
+			RegionsConfig,
+		},
 	},
-	utils::slice_to_array,
+	utils::{crc::crc32, slice_to_array},
 };
 
+/// The size of the header of a copy of the region stored in the flash memory: the [`layout version`] of the copy (or
+/// `0xFF` if the block doesn't contain a copy), the sequence number of the copy, the length of the stored region and its
+/// CRC.
+///
+/// [`layout version`]: STORED_COPY_LAYOUT_VERSION
+const STORED_COPY_HEADER_SIZE: usize = 1 + 3 * core::mem::size_of::<u32>();
+
+/// The version of the layout of the copies of the region stored in the flash memory, written in the first byte of their
+/// header.
+///
+/// The firmwares that stored a single copy of the region without this header wrote `0` there, and their copy can't be
+/// read by this firmware (check [`ReadFromFlashError::IncompatibleLayout`]).
+const STORED_COPY_LAYOUT_VERSION: u8 = 1;
+
+/// How many times the pages of a block can require a correction of their flipped bits before the block is considered
+/// worn out and [`marked as invalid`].
+///
+/// [`marked as invalid`]: FilesMetadatasRegion::mark_block_as_invalid
+pub const CORRECTED_READS_BEFORE_RELOCATION: u8 = 3;
+
 pub struct FilesMetadatasRegion
 {
 	files_metadatas: Vec<FileMetadata>,
@@ -25,6 +53,16 @@ pub struct FilesMetadatasRegion
 	writing_to_files_with_id: Vec<FileId>,
 	bad_block_table: BadBlockTable,
 	metadata_validator_master: FileMetadataValidatorMaster,
+	file_move: Option<FileMove>,
+	/// The ranges of the blocks of the deleted files that may have not been erased yet.
+	blocks_to_erase: Vec<RangeInclusive<u16>>,
+	erase_count_table: EraseCountTable,
+	/// The block of the metadata region where the region has been stored the last time.
+	metadata_block_index: u16,
+	/// Incremented each time the region is stored, to find the most recent copy of the region.
+	metadata_sequence_number: u32,
+	/// How many times the pages of each block required a correction since the region has been read.
+	corrected_reads_counts: Vec<(u16, u8)>,
 }
 
 impl FilesMetadatasRegion
@@ -33,156 +71,307 @@ impl FilesMetadatasRegion
 	/// at the address specified in `regions_config.metadata_block_range` or creates a default one
 	/// if it's the first time the flash memory is used.
 	///
+	/// The region is stored in a different block of the metadata region each time (check [`Self::store_in_flash`]), so
+	/// the most recent copy of it whose CRC is valid is read (if the power was lost while storing the most recent copy,
+	/// the previous one is read).
+	///
+	/// If the printer lost power while [`compacting`] the region, the interrupted file move is completed, and if it lost
+	/// power while deleting a file, the blocks of the file are erased.
+	///
 	/// Returns `Ok(Self)` if the region was correctly read or if it was missing and the default
-	/// one was succesfully created and automatically stored in the chip. Otherwise returns
-	/// `Err(...)` (if there has been an error in communicating with the `spi_flash_memory`).
+	/// one was succesfully created and automatically stored in the chip. Returns
+	/// `Err(ReadFromFlashError::IncompatibleLayout)` without touching the flash memory if the region has only been
+	/// stored by a firmware with a different [`layout`] (so that the files and the list of the bad blocks aren't lost
+	/// by replacing it with an empty region), otherwise returns `Err(ReadFromFlashError::Store(...))` (if there has
+	/// been an error in communicating with the `spi_flash_memory`).
 	///
+	/// [`layout`]: STORED_COPY_LAYOUT_VERSION
 	/// [`stored`]: `Self::store_in_flash`
+	/// [`compacting`]: `Self::compact`
 	pub fn read_from_flash<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
 		spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, regions_config: &RegionsConfig,
-	) -> Result<Self, <Spi as ErrorType>::Error>
+	) -> Result<Self, ReadFromFlashError<<Spi as ErrorType>::Error>>
 	{
-		let mut needs_to_store_in_flash = false;
-
-		let address_offset = *regions_config.metadata_address_range::<Chip>().start();
-
-		const READ_DATA_SIZE: usize = 2048;
-		assert_eq!(READ_DATA_SIZE, Chip::PAGE_SIZE as usize);
-		let mut data = ArrayIterator::new([0; READ_DATA_SIZE]);
-
-		spi_flash_memory.read(address_offset, data.reset_and_get_slice_mut())?;
-
-		let self_ = if data.next() == 0xFF
+		let mut stored_copies = Vec::with_capacity(regions_config.metadata_block_range.len());
+		let mut incompatible_layout_version = None;
+		for block_index in regions_config.metadata_block_range.clone()
 		{
-			needs_to_store_in_flash = true;
-
-			let bad_block_table = BadBlockTable::from_first_powerup(spi_flash_memory)?;
+			if Chip::contains_bad_block_mark(block_index, spi_flash_memory)?
+			{
+				continue;
+			}
 
-			Self {
-				files_metadatas: Vec::with_capacity(5),
-				highest_used_file_id: FileId::FIRST,
-				writing_to_files_with_id: Vec::with_capacity(2),
-				bad_block_table,
-				metadata_validator_master: FileMetadataValidatorMaster::new(),
+			let mut header = [0; STORED_COPY_HEADER_SIZE];
+			spi_flash_memory.read(Chip::get_address_of_block_index(block_index), &mut header)?;
+			match header[0]
+			{
+				0xFF => (),
+				STORED_COPY_LAYOUT_VERSION => stored_copies.push((block_index, header)),
+				version => incompatible_layout_version = Some(version),
 			}
 		}
-		else
+		if stored_copies.len() == 1 && regions_config.metadata_block_range.len() == 1
 		{
-			let bad_blocks_size = core::mem::size_of::<u16>() * (data.next() as usize);
-			let bad_block_table = BadBlockTable::from_bytes(data.take(bad_blocks_size));
+			log::warn!("The metadata region has a single block, so a power loss while storing it may corrupt it");
+		}
 
-			let highest_used_file_id = FileId::from_bytes(data.take_as_array());
+		// The most recent copy is the one with the highest sequence number
+		stored_copies.sort_by_key(|(_, header)| core::cmp::Reverse(u32::from_le_bytes(slice_to_array(&header[1..]))));
+		let has_stored_copies = !stored_copies.is_empty();
+		let mut read_copy = None;
+		for (block_index, header) in stored_copies
+		{
+			let sequence_number = u32::from_le_bytes(slice_to_array(&header[1..]));
+			let length = u32::from_le_bytes(slice_to_array(&header[5..]));
+			let crc = u32::from_le_bytes(slice_to_array(&header[9..]));
 
-			let files_count_stored_in_flash = u16::from_be_bytes(data.take_as_array());
-			let mut files_metadatas = Vec::with_capacity(files_count_stored_in_flash as usize);
-			let mut current_page_index = 0;
-			let mut data_reserve = [0_u8; core::mem::size_of::<FileMetadata>()];
-			let mut data_reserve_len = 0;
-			for _ in 0..files_count_stored_in_flash
+			if length <= Chip::BLOCK_SIZE - STORED_COPY_HEADER_SIZE as u32
 			{
-				let bytes = if data_reserve_len == 0
+				let mut bytes = vec![0; length as usize];
+				spi_flash_memory.read(
+					Chip::get_address_of_block_index(block_index) + STORED_COPY_HEADER_SIZE as u32,
+					&mut bytes,
+				)?;
+
+				if let Some(region) = (crc32(&bytes) == crc)
+					.then(|| Self::from_bytes::<Chip>(&bytes, block_index, sequence_number))
+					.flatten()
 				{
-					data.take(core::mem::size_of::<FileMetadata>())
+					read_copy = Some(region);
+					break;
 				}
-				else
-				{
-					data_reserve[data_reserve_len..]
-						.copy_from_slice(data.take(core::mem::size_of::<FileMetadata>() - data_reserve_len));
-					&data_reserve
-				};
+			}
+
+			log::warn!("The copy of the metadatas region stored in the block {block_index} is corrupted");
+		}
 
-				let file_metadata = FileMetadata::from_bytes(bytes);
-				if file_metadata.id == FileId::WRITING_FILE
+		let mut self_ = match read_copy
+		{
+			Some(region) => region,
+			None =>
+			{
+				if let Some(version) = incompatible_layout_version
 				{
-					// Delete the corrupted files
-					FilesRegion.delete_file(file_metadata, spi_flash_memory)?;
+					log::error!(
+						"The metadatas region has been stored with the layout {version}, but this firmware can only read \
+						 the layout {STORED_COPY_LAYOUT_VERSION}: erase the flash memory to use it (all the files will be \
+						 deleted)"
+					);
+					return Err(ReadFromFlashError::IncompatibleLayout { version });
 				}
-				else
+				if has_stored_copies
 				{
-					files_metadatas.push(file_metadata);
+					log::error!("All the copies of the metadatas region are corrupted, so all the files are lost");
 				}
 
-				if data.len() < core::mem::size_of::<FileMetadata>()
-				{
-					current_page_index += 1;
-
-					data_reserve_len = data.len();
-					data_reserve.copy_from_slice(data.take(data_reserve_len));
+				let bad_block_table = BadBlockTable::from_first_powerup(spi_flash_memory)?;
 
-					spi_flash_memory.read(
-						address_offset + current_page_index * Chip::PAGE_SIZE,
-						data.reset_and_get_slice_mut(),
-					)?;
-				}
-			}
+				let mut self_ = Self {
+					files_metadatas: Vec::with_capacity(5),
+					highest_used_file_id: FileId::FIRST,
+					writing_to_files_with_id: Vec::with_capacity(2),
+					bad_block_table,
+					metadata_validator_master: FileMetadataValidatorMaster::new(),
+					file_move: None,
+					blocks_to_erase: Vec::new(),
+					erase_count_table: EraseCountTable::new::<Chip>(),
+					// So that the first copy is stored in the first block of the metadata region
+					metadata_block_index: *regions_config.metadata_block_range.end(),
+					metadata_sequence_number: 0,
+					corrected_reads_counts: Vec::new(),
+				};
+				self_.store_in_flash(spi_flash_memory, regions_config)?;
 
-			Self {
-				files_metadatas,
-				highest_used_file_id,
-				writing_to_files_with_id: Vec::with_capacity(2),
-				bad_block_table,
-				metadata_validator_master: FileMetadataValidatorMaster::new(),
-			}
+				self_
+			},
 		};
 
-		if needs_to_store_in_flash
+		// Delete the corrupted files (the ones whose writing has been interrupted by a power loss)
+		let corrupted_files: Vec<FileMetadata> = self_
+			.files_metadatas
+			.iter()
+			.filter(|file_metadata| file_metadata.id == FileId::WRITING_FILE)
+			.cloned()
+			.collect();
+		if !corrupted_files.is_empty()
 		{
+			for file_metadata in corrupted_files
+			{
+				FilesRegion.delete_file(file_metadata, spi_flash_memory, &mut self_.erase_count_table)?;
+			}
+			self_
+				.files_metadatas
+				.retain(|file_metadata| file_metadata.id != FileId::WRITING_FILE);
 			self_.store_in_flash(spi_flash_memory, regions_config)?;
 		}
 
+		self_.erase_pending_blocks(spi_flash_memory)?;
+
+		if self_.file_move.is_some()
+		{
+			log::info!("Resume the file move interrupted by a power loss");
+
+			self_.resume_file_move(spi_flash_memory, regions_config)?;
+		}
+
 		Ok(self_)
 	}
 
-	/// Stores this region in the provided `spi_flash_memory` at the address specified in
+	/// Stores this region in the provided `spi_flash_memory` in one of the blocks specified in
 	/// `regions_config.metadata_block_range` so that you can later recover it
 	/// (even after shutting down the microcontrooler) using [`Self::read_from_flash`].
 	///
-	/// Returns `Err(...)` if there has been an error in communicating with the flash memory,
-	/// otherwise returns `Ok(())`.
+	/// Each time the region is stored in the block after the one used the previous time (skipping the bad blocks and
+	/// restarting from the first block after the last one), so that the erases are spread on all the blocks of the
+	/// metadata region instead of always wearing out the same block. The previous copy of the region is left untouched,
+	/// so if the power is lost while storing the region, the previous copy is read the next time (the copies have a
+	/// sequence number and a CRC to find the most recent one that is complete).
+	///
+	/// Returns `Err(StoreError::DoesntFitInBlock)` without touching the flash memory if the region is bigger than a
+	/// block, `Err(StoreError::Spi(...))` if there has been an error in communicating with the flash memory, otherwise
+	/// returns `Ok(())`.
 	pub fn store_in_flash<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
-		&self, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, regions_config: &RegionsConfig,
-	) -> Result<(), <Spi as ErrorType>::Error>
+		&mut self, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, regions_config: &RegionsConfig,
+	) -> Result<(), StoreError<<Spi as ErrorType>::Error>>
 	{
-		spi_flash_memory.erase_blocks(regions_config.metadata_block_range.clone())?;
+		// The size of the region doesn't change when the erase count of the block is incremented
+		if self.to_bytes().len() + STORED_COPY_HEADER_SIZE > Chip::BLOCK_SIZE as usize
+		{
+			return Err(StoreError::DoesntFitInBlock);
+		}
+
+		let block_index = self.get_next_metadata_block_index(regions_config);
+		self.erase_count_table
+			.erase_blocks(spi_flash_memory, block_index..=block_index)?;
+		self.metadata_block_index = block_index;
+		self.metadata_sequence_number = self.metadata_sequence_number.wrapping_add(1);
+
+		let bytes = self.to_bytes();
+		let stored_copy: Vec<u8> = core::iter::once(STORED_COPY_LAYOUT_VERSION)
+			.chain(self.metadata_sequence_number.to_le_bytes())
+			.chain((bytes.len() as u32).to_le_bytes())
+			.chain(crc32(&bytes).to_le_bytes())
+			.chain(bytes)
+			.collect();
+
+		spi_flash_memory.program(&stored_copy, Chip::get_address_of_block_index(block_index))?;
 
-		let self_as_bytes: Vec<u8> = core::iter::once(0_u8)
-			.chain(self.bad_block_table.as_bytes())
-			.chain(self.highest_used_file_id.to_bytes().into_iter())
-			.chain((self.files_metadatas.len() as u16).to_le_bytes().into_iter())
+		Ok(())
+	}
+
+	/// Returns the bytes of this region stored in the flash memory (after the header with the sequence number and the
+	/// CRC).
+	fn to_bytes(&self) -> Vec<u8>
+	{
+		self.bad_block_table
+			.as_bytes()
+			.chain(self.erase_count_table.as_bytes())
+			.chain(self.highest_used_file_id.to_bytes())
+			.chain((self.files_metadatas.len() as u16).to_be_bytes())
 			.chain(self.files_metadatas.iter().flat_map(|file_metadata| {
 				let mut file_metadata_to_serialize = file_metadata.clone();
-				if self.writing_to_files_with_id.contains(&file_metadata.id)
+				// A partial file whose writing has been resumed is kept if the power is lost while it's being written
+				if self.writing_to_files_with_id.contains(&file_metadata.id) && !file_metadata.is_partial()
 				{
 					file_metadata_to_serialize.id = FileId::WRITING_FILE;
 				}
-				file_metadata_to_serialize.to_bytes().into_iter()
+				file_metadata_to_serialize.to_bytes()
 			}))
-			.collect();
+			.chain(core::iter::once(self.file_move.is_some() as u8))
+			.chain(self.file_move.iter().flat_map(FileMove::to_bytes))
+			.chain((self.blocks_to_erase.len() as u16).to_le_bytes())
+			.chain(self.blocks_to_erase.iter().flat_map(|block_range| {
+				block_range
+					.start()
+					.to_le_bytes()
+					.into_iter()
+					.chain(block_range.end().to_le_bytes())
+			}))
+			.collect()
+	}
 
-		spi_flash_memory.program(&self_as_bytes, *regions_config.metadata_address_range::<Chip>().start())?;
+	/// Restores a region from the `bytes` returned by [`Self::to_bytes`], or returns `None` if the `bytes` are too few.
+	fn from_bytes<Chip: FlashMemoryChip>(
+		mut bytes: &[u8], metadata_block_index: u16, metadata_sequence_number: u32,
+	) -> Option<Self>
+	{
+		let bad_blocks_count = take::<1>(&mut bytes)?[0] as usize;
+		let bad_block_table = BadBlockTable::from_bytes(take_slice(&mut bytes, bad_blocks_count * 2)?);
 
-		Ok(())
+		let erase_count_table =
+			EraseCountTable::from_bytes(take_slice(&mut bytes, EraseCountTable::bytes_count::<Chip>())?);
+
+		let highest_used_file_id = FileId::from_bytes(take(&mut bytes)?);
+
+		let files_count = u16::from_be_bytes(take(&mut bytes)?);
+		let files_metadatas = (0..files_count)
+			.map(|_| FileMetadata::from_bytes(&mut bytes))
+			.collect::<Option<Vec<_>>>()?;
+
+		let file_move = match take::<1>(&mut bytes)?[0]
+		{
+			0 => None,
+			_ => Some(FileMove::from_bytes(&take::<{ FileMove::SERIALIZED_SIZE }>(
+				&mut bytes,
+			)?)),
+		};
+
+		let blocks_to_erase_count = u16::from_le_bytes(take(&mut bytes)?);
+		let blocks_to_erase = (0..blocks_to_erase_count)
+			.map(|_| {
+				let start = u16::from_le_bytes(take(&mut bytes)?);
+				let end = u16::from_le_bytes(take(&mut bytes)?);
+				Some(start..=end)
+			})
+			.collect::<Option<Vec<_>>>()?;
+
+		Some(Self {
+			files_metadatas,
+			highest_used_file_id,
+			writing_to_files_with_id: Vec::with_capacity(2),
+			bad_block_table,
+			metadata_validator_master: FileMetadataValidatorMaster::new(),
+			file_move,
+			blocks_to_erase,
+			erase_count_table,
+			metadata_block_index,
+			metadata_sequence_number,
+			corrected_reads_counts: Vec::new(),
+		})
+	}
+
+	/// Returns the index of the first valid block of the metadata region after the one where the region has been stored
+	/// the last time.
+	fn get_next_metadata_block_index(&self, regions_config: &RegionsConfig) -> u16
+	{
+		let metadata_block_range = &regions_config.metadata_block_range;
+		let blocks_count = metadata_block_range.len() as u16;
+		let previous_block_offset = self.metadata_block_index - metadata_block_range.start();
+
+		(1..=blocks_count)
+			.map(|offset| metadata_block_range.start() + (previous_block_offset + offset) % blocks_count)
+			.find(|&block_index| self.bad_block_table.is_block_valid(block_index))
+			.unwrap_or(self.metadata_block_index)
 	}
 
 	/// Finds a space large enough to store `data_size` bytes contiguously.
 	///
 	/// Returns `Ok((new_file_id, new_file_start_address))` if the space has been found,
-	/// otherwise returns `Err(NotEnoughSpaceAvailable)`
+	/// otherwise returns `Err(FindSpaceError)`.
 	///
 	/// # Warning
 	/// This won't store the metadata of the newly created file in the flash memory, a call
 	/// to [`Self::finish_writing_file`] is required.
 	pub fn create_file<Chip: FlashMemoryChip>(
 		&mut self, bytes_count: u32, regions_config: &RegionsConfig,
-	) -> Result<(FileId, u32), NotEnoughSpaceAvailable>
+	) -> Result<(FileId, u32), FindSpaceError>
 	{
-		let data_holes = DataHoles::<Chip>::from_metadatas_region(&self, regions_config);
+		let data_holes = DataHoles::<Chip>::from_metadatas_region(self, regions_config);
 
-		match data_holes.find_space_for_new_data(bytes_count)
+		match data_holes.find_space_for_new_data(bytes_count, &self.erase_count_table)
 		{
-			data_holes::FreeSpace::NotAvailable => Err(NotEnoughSpaceAvailable),
-			// Memory compaction is not implemented yet
-			data_holes::FreeSpace::AvailableButRequiresCompacting => Err(NotEnoughSpaceAvailable),
+			data_holes::FreeSpace::NotAvailable => Err(FindSpaceError::NotEnoughSpaceAvailable),
+			data_holes::FreeSpace::AvailableButRequiresCompacting => Err(FindSpaceError::RequiresCompacting),
 			data_holes::FreeSpace::Available { start_address } =>
 			{
 				let id = self.highest_used_file_id;
@@ -196,7 +385,7 @@ impl FilesMetadatasRegion
 	pub fn start_writing_file<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
 		&mut self, file_metadata: FileMetadata, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>,
 		regions_config: &RegionsConfig,
-	) -> Result<(), <Spi as ErrorType>::Error>
+	) -> Result<(), StoreError<<Spi as ErrorType>::Error>>
 	{
 		self.writing_to_files_with_id.push(file_metadata.id);
 
@@ -208,7 +397,7 @@ impl FilesMetadatasRegion
 
 	pub fn finish_writing_file<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
 		&mut self, file_id: FileId, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, regions_config: &RegionsConfig,
-	) -> Result<(), <Spi as ErrorType>::Error>
+	) -> Result<(), StoreError<<Spi as ErrorType>::Error>>
 	{
 		if let Some(position) = self
 			.writing_to_files_with_id
@@ -223,6 +412,47 @@ impl FilesMetadatasRegion
 		Ok(())
 	}
 
+	/// Marks the file with the specified `file_id` (that is being written) as [`partial`], with `written_data_length`
+	/// bytes of data written, and stores the region in the flash memory.
+	///
+	/// The file is kept in the file system, and its writing can be resumed with [`Self::resume_writing_file`].
+	///
+	/// [`partial`]: FileMetadata::partial_data_length
+	pub fn suspend_writing_file<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+		&mut self, file_id: FileId, written_data_length: u32, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>,
+		regions_config: &RegionsConfig,
+	) -> Result<(), StoreError<<Spi as ErrorType>::Error>>
+	{
+		self.writing_to_files_with_id
+			.retain(|&writing_file_id| writing_file_id != file_id);
+		if self
+			.update_file_metadata(file_id, |file_metadata| {
+				file_metadata.partial_data_length = Some(written_data_length)
+			})
+			.is_ok()
+		{
+			self.store_in_flash(spi_flash_memory, regions_config)?;
+		}
+
+		Ok(())
+	}
+
+	/// Marks the [`partial`] file with the specified `file_id` as being written again.
+	///
+	/// Its metadata isn't changed in the flash memory until its writing is [`suspended`] again or [`finished`], so if the
+	/// power is lost in the meantime the file is still partial, with the data length it had before.
+	///
+	/// [`partial`]: FileMetadata::partial_data_length
+	/// [`suspended`]: Self::suspend_writing_file
+	/// [`finished`]: Self::finish_writing_file
+	pub fn resume_writing_file(&mut self, file_id: FileId)
+	{
+		if !self.writing_to_files_with_id.contains(&file_id)
+		{
+			self.writing_to_files_with_id.push(file_id);
+		}
+	}
+
 	/// If a file with the specified `file_id` is stored in this region, returns its metadata
 	/// wrapped in `Some`. Otherwise returns `None`.
 	pub fn get_file_metadata(&self, file_id: FileId) -> Option<FileMetadata>
@@ -233,6 +463,28 @@ impl FilesMetadatasRegion
 			.cloned()
 	}
 
+	/// Calls `update` with the metadata of the file with the specified `file_id` so that you can change it (only the
+	/// fields that don't affect where the file is stored in the flash memory must be changed, like its name).
+	///
+	/// Returns `Err(FileDoesntExist)` if there's no file with `file_id` in this region, otherwise returns `Ok(())`.
+	///
+	/// # Warning
+	/// A call to `Self::store_in_flash` is required after calling this method to actually
+	/// store the modified region in the flash memory.
+	pub fn update_file_metadata(
+		&mut self, file_id: FileId, update: impl FnOnce(&mut FileMetadata),
+	) -> Result<(), FileDoesntExist>
+	{
+		let file_metadata = self
+			.files_metadatas
+			.iter_mut()
+			.find(|file_metadata| file_metadata.id == file_id)
+			.ok_or(FileDoesntExist)?;
+		(update)(file_metadata);
+
+		Ok(())
+	}
+
 	/// Returns `true` if a file with the specified `file_id` is stored in this region,
 	/// otherwise returns `false`.
 	pub fn does_file_exist(&self, file_id: FileId) -> bool
@@ -242,12 +494,16 @@ impl FilesMetadatasRegion
 			.any(|file_metadata| file_metadata.id == file_id)
 	}
 
-	/// Removes the file with the specified `file_id` from the region.
+	/// Removes the file with the specified `file_id` from the region, and marks its blocks as to be erased.
 	///
 	/// # Warning
 	/// A call to `Self::store_in_flash` is required after calling this method to actually
-	/// store the modified region in the flash memory.
-	pub fn delete_file(&mut self, file_id: FileId) -> Result<FileMetadata, FileDoesntExist>
+	/// store the modified region in the flash memory, and then a call to [`Self::erase_pending_blocks`] is required to
+	/// erase the data of the file (if the power is lost before the data is erased, it's erased the next time the region
+	/// is [`read`]).
+	///
+	/// [`read`]: Self::read_from_flash
+	pub fn delete_file<Chip: FlashMemoryChip>(&mut self, file_id: FileId) -> Result<FileMetadata, FileDoesntExist>
 	{
 		if let Some(index_to_remove) = self
 			.files_metadatas
@@ -256,6 +512,9 @@ impl FilesMetadatasRegion
 		{
 			let file_metadata = self.files_metadatas[index_to_remove].clone();
 			self.files_metadatas.swap_remove(index_to_remove);
+			self.writing_to_files_with_id
+				.retain(|&writing_file_id| writing_file_id != file_id);
+			self.blocks_to_erase.push(file_metadata.block_range::<Chip>());
 
 			Ok(file_metadata)
 		}
@@ -265,82 +524,804 @@ impl FilesMetadatasRegion
 		}
 	}
 
+	/// Erases the blocks of the files you [`deleted`].
+	///
+	/// The blocks are removed from the ones to erase stored in the flash memory the next time the region is [`stored`].
+	///
+	/// [`deleted`]: Self::delete_file
+	/// [`stored`]: Self::store_in_flash
+	pub fn erase_pending_blocks<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+		&mut self, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>,
+	) -> Result<(), <Spi as ErrorType>::Error>
+	{
+		while let Some(block_range) = self.blocks_to_erase.last().cloned()
+		{
+			self.erase_count_table.erase_blocks(spi_flash_memory, block_range)?;
+			self.blocks_to_erase.pop();
+		}
+
+		Ok(())
+	}
+
 	/// Returns a reference to the metadatas of all the files stored in this region.
 	pub fn get_files_metadatas(&self) -> &[FileMetadata]
 	{
 		&self.files_metadatas
 	}
 
+	/// Returns `false` if the block at `block_index` is in the [`BadBlockTable`], otherwise returns `true`.
+	pub fn is_block_valid(&self, block_index: u16) -> bool
+	{
+		self.bad_block_table.is_block_valid(block_index)
+	}
+
+	/// Returns the number of blocks in the [`BadBlockTable`].
+	pub fn get_bad_blocks_count(&self) -> u16
+	{
+		self.bad_block_table.indices().len() as u16
+	}
+
+	/// Returns the [`WearStatistics`] of the valid blocks used by the file system.
+	pub fn get_wear_statistics(&self, regions_config: &RegionsConfig) -> WearStatistics
+	{
+		self.erase_count_table.get_statistics(
+			regions_config
+				.metadata_block_range
+				.clone()
+				.chain(regions_config.data_block_range.clone()),
+			&self.bad_block_table,
+		)
+	}
+
+	/// Handles a block of the flash memory whose pages contained some flipped bits when they have been read (check
+	/// [`SpiFlashMemory::take_blocks_with_ecc_errors`]).
+	///
+	/// If the flipped bits couldn't be corrected, or if the block required a correction
+	/// [`CORRECTED_READS_BEFORE_RELOCATION`] times, the block is [`marked as invalid`].
+	///
+	/// Returns `Err(...)` if there has been an error in communicating with the flash memory, otherwise returns `Ok(())`.
+	///
+	/// [`marked as invalid`]: Self::mark_block_as_invalid
+	pub fn handle_ecc_error<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+		&mut self, block_index: u16, ecc_status: EccStatus, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>,
+		regions_config: &RegionsConfig,
+	) -> Result<(), StoreError<<Spi as ErrorType>::Error>>
+	{
+		let corrected_reads_count = match self
+			.corrected_reads_counts
+			.iter_mut()
+			.find(|(other_block_index, _)| *other_block_index == block_index)
+		{
+			Some((_, corrected_reads_count)) =>
+			{
+				*corrected_reads_count = corrected_reads_count.saturating_add(1);
+				*corrected_reads_count
+			},
+			None =>
+			{
+				self.corrected_reads_counts.push((block_index, 1));
+				1
+			},
+		};
+
+		if ecc_status == EccStatus::Uncorrectable || corrected_reads_count >= CORRECTED_READS_BEFORE_RELOCATION
+		{
+			log::warn!("The block {block_index} of the flash memory is wearing out ({ecc_status:?})");
+
+			self.mark_block_as_invalid(block_index, spi_flash_memory, regions_config)?;
+		}
+
+		Ok(())
+	}
+
+	/// Adds the block at `block_index` to the [`BadBlockTable`] (that is then stored in the flash memory), so that it's
+	/// never used again.
+	///
+	/// If the block contains the data of a file, the file is moved to the least worn free space large enough to contain it
+	/// (the move is resumed after a power loss like the ones done while [`compacting`]). If there isn't such a space or if
+	/// the file is being written, the file is left where it is.
+	///
+	/// The last valid block of the metadata region is never marked as invalid, because the region must be stored
+	/// somewhere.
+	///
+	/// Returns `Err(...)` if there has been an error in communicating with the flash memory, otherwise returns `Ok(())`.
+	///
+	/// [`compacting`]: Self::compact
+	pub fn mark_block_as_invalid<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+		&mut self, block_index: u16, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, regions_config: &RegionsConfig,
+	) -> Result<(), StoreError<<Spi as ErrorType>::Error>>
+	{
+		if !self.bad_block_table.is_block_valid(block_index)
+		{
+			return Ok(());
+		}
+
+		if regions_config.metadata_block_range.contains(&block_index)
+		{
+			let valid_metadata_blocks_count = regions_config
+				.metadata_block_range
+				.clone()
+				.filter(|&block_index| self.bad_block_table.is_block_valid(block_index))
+				.count();
+			if valid_metadata_blocks_count <= 1
+			{
+				log::error!("The block {block_index} is the last valid block of the metadata region");
+				return Ok(());
+			}
+			if valid_metadata_blocks_count == 2
+			{
+				log::warn!("The metadata region will be left with a single valid block");
+			}
+		}
+
+		log::info!("Mark the block {block_index} as invalid");
+		self.bad_block_table.mark_block_as_invalid(block_index);
+		self.corrected_reads_counts
+			.retain(|(other_block_index, _)| *other_block_index != block_index);
+
+		let file_to_move = self
+			.files_metadatas
+			.iter()
+			.find(|file_metadata| {
+				file_metadata.block_range::<Chip>().contains(&block_index)
+					&& !self.writing_to_files_with_id.contains(&file_metadata.id)
+			})
+			.cloned();
+		if let Some(file_metadata) = file_to_move
+		{
+			let data_holes = DataHoles::<Chip>::from_metadatas_region(self, regions_config);
+			match data_holes.find_space_for_new_data(file_metadata.file_data_length, &self.erase_count_table)
+			{
+				data_holes::FreeSpace::Available { start_address } =>
+				{
+					log::info!("Move the file {:?} away from the invalid block", file_metadata.id);
+
+					self.metadata_validator_master.invalidate_all_the_instances();
+					self.file_move = Some(FileMove {
+						file_id: file_metadata.id,
+						destination_address: start_address,
+						moved_blocks_count: 0,
+					});
+					self.store_in_flash(spi_flash_memory, regions_config)?;
+
+					return self.resume_file_move(spi_flash_memory, regions_config);
+				},
+				_ => log::warn!(
+					"There's no space to move the file {:?} away from the invalid block",
+					file_metadata.id
+				),
+			}
+		}
+
+		self.store_in_flash(spi_flash_memory, regions_config)
+	}
+
 	/// Returns the current file validator.
 	pub fn get_file_validator(&self) -> FileMetadataValidator
 	{
 		self.metadata_validator_master.create_validator()
 	}
 
-	#[allow(dead_code, unused_variables)]
 	/// Collect all the fragmented data holes present in the memory in one big chunk, moving all the files
-	/// next to each other.
+	/// next to each other (the files that are being written and the blocks that can't be used are left where they are).
 	///
 	/// Check [`this`] for more info.
 	///
+	/// The files are moved one block at a time (the [`internal_data_move`] of the flash memory is used when possible) and
+	/// the progress of the move is stored in the flash memory after each chunk of moved blocks, so if the printer loses
+	/// power while compacting, the move is resumed the next time the region is [`read`] and no file is lost.
+	///
+	/// # Warning
+	/// All the [`FileMetadataValidator`]s created before calling this method are invalidated (the files may have been
+	/// moved to another address).
+	///
 	/// [`this`]: <https://www.geeksforgeeks.org/compaction-in-operating-system/>
+	/// [`internal_data_move`]: SpiFlashMemory::internal_data_move
+	/// [`read`]: Self::read_from_flash
 	pub fn compact<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
-		&mut self, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>,
-	) -> Result<(), <Spi as ErrorType>::Error>
+		&mut self, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, regions_config: &RegionsConfig,
+	) -> Result<(), StoreError<<Spi as ErrorType>::Error>>
 	{
 		self.metadata_validator_master.invalidate_all_the_instances();
 
-		todo!();
+		log::info!("Start compacting the file system");
+
+		let mut files_ids: Vec<FileId> = self
+			.files_metadatas
+			.iter()
+			.map(|file_metadata| file_metadata.id)
+			.collect();
+		files_ids.sort_by_key(|&file_id| self.get_file_metadata(file_id).unwrap().start_memory_address);
+
+		// All the blocks before this one are either occupied or unusable
+		let mut first_free_block_index = *regions_config.data_block_range.start();
+		for file_id in files_ids
+		{
+			let block_range = self.get_file_metadata(file_id).unwrap().block_range::<Chip>();
+			let blocks_count = block_range.len() as u16;
+
+			if !self.writing_to_files_with_id.contains(&file_id)
+			{
+				if let Some(destination_block_index) = self.find_destination_block::<Chip>(
+					first_free_block_index,
+					*block_range.start(),
+					blocks_count,
+					regions_config,
+				)
+				{
+					self.file_move = Some(FileMove {
+						file_id,
+						destination_address: Chip::get_address_of_block_index(destination_block_index),
+						moved_blocks_count: 0,
+					});
+					self.store_in_flash(spi_flash_memory, regions_config)?;
+
+					self.resume_file_move(spi_flash_memory, regions_config)?;
+				}
+			}
+
+			let block_range = self.get_file_metadata(file_id).unwrap().block_range::<Chip>();
+			first_free_block_index = first_free_block_index.max(*block_range.end() + 1);
+		}
+
+		log::info!("Finished compacting the file system");
+
+		Ok(())
+	}
+
+	/// Returns the index of the lowest block (between `first_free_block_index` and `file_start_block_index`) where a file
+	/// that starts at `file_start_block_index` and occupies `blocks_count` blocks can be moved to, or `None` if the file
+	/// can't be moved to a lower address.
+	fn find_destination_block<Chip: FlashMemoryChip>(
+		&self, first_free_block_index: u16, file_start_block_index: u16, blocks_count: u16,
+		regions_config: &RegionsConfig,
+	) -> Option<u16>
+	{
+		let is_block_usable = |block_index: u16| {
+			regions_config.data_block_range.contains(&block_index)
+				&& !regions_config.metadata_block_range.contains(&block_index)
+				&& self.bad_block_table.is_block_valid(block_index)
+				&& !self.writing_to_files_with_id.iter().any(|&file_id| {
+					self.get_file_metadata(file_id)
+						.is_some_and(|file_metadata| file_metadata.block_range::<Chip>().contains(&block_index))
+				})
+		};
+
+		let mut destination_block_index = first_free_block_index;
+		while destination_block_index < file_start_block_index
+		{
+			let destination_range = destination_block_index..(destination_block_index + blocks_count);
+			match destination_range
+				.clone()
+				.find(|&block_index| !is_block_usable(block_index))
+			{
+				Some(unusable_block_index) => destination_block_index = unusable_block_index + 1,
+				None => return Some(destination_block_index),
+			}
+		}
+
+		None
+	}
+
+	/// Completes the move of the file stored in `self.file_move` (if there's one).
+	///
+	/// The file is moved in chunks of blocks as big as the distance between its source and its destination, so that the
+	/// blocks that are erased to make room for a chunk have already been copied (and the move can be resumed from the
+	/// last stored chunk if the power is lost).
+	///
+	/// The destination must either start before the source or not overlap with it.
+	fn resume_file_move<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+		&mut self, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, regions_config: &RegionsConfig,
+	) -> Result<(), StoreError<<Spi as ErrorType>::Error>>
+	{
+		let Some(mut file_move) = self.file_move.clone()
+		else
+		{
+			return Ok(());
+		};
+		let Some(file_index) = self
+			.files_metadatas
+			.iter()
+			.position(|file_metadata| file_metadata.id == file_move.file_id)
+		else
+		{
+			self.file_move = None;
+			return self.store_in_flash(spi_flash_memory, regions_config);
+		};
+
+		let source_block_range = self.files_metadatas[file_index].block_range::<Chip>();
+		let source_block_index = *source_block_range.start();
+		let destination_block_index = Chip::get_block_index_of_address(file_move.destination_address);
+		let blocks_count = source_block_range.len() as u16;
+		let distance = source_block_index.abs_diff(destination_block_index);
+
+		while file_move.moved_blocks_count < blocks_count
+		{
+			let chunk = file_move.moved_blocks_count..(file_move.moved_blocks_count + distance).min(blocks_count);
+
+			self.erase_count_table.erase_blocks(
+				spi_flash_memory,
+				(destination_block_index + chunk.start)..=(destination_block_index + chunk.end - 1),
+			)?;
+			for block_offset in chunk.clone()
+			{
+				Self::copy_block(
+					spi_flash_memory,
+					source_block_index + block_offset,
+					destination_block_index + block_offset,
+				)?;
+			}
+
+			file_move.moved_blocks_count = chunk.end;
+			self.file_move = Some(file_move.clone());
+			self.store_in_flash(spi_flash_memory, regions_config)?;
+		}
+
+		// Erase the blocks of the source that haven't been overwritten by the destination (this is done before storing
+		// the new address of the file, so that the freed blocks are always left erased even if the power is lost). The
+		// invalid blocks are left as they are, because erasing them would only wear them out more
+		let first_freed_block_index = match destination_block_index < source_block_index
+		{
+			true => source_block_index.max(destination_block_index + blocks_count),
+			false => source_block_index,
+		};
+		for freed_block_index in first_freed_block_index..=*source_block_range.end()
+		{
+			if self.bad_block_table.is_block_valid(freed_block_index)
+			{
+				self.erase_count_table
+					.erase_blocks(spi_flash_memory, freed_block_index..=freed_block_index)?;
+			}
+		}
+
+		self.files_metadatas[file_index].start_memory_address = file_move.destination_address;
+		self.file_move = None;
+		self.store_in_flash(spi_flash_memory, regions_config)
+	}
+
+	/// Copies the content of the block at `source_block_index` to the (erased) block at `destination_block_index`.
+	fn copy_block<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+		spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, source_block_index: u16, destination_block_index: u16,
+	) -> Result<(), <Spi as ErrorType>::Error>
+	{
+		let source_address = Chip::get_address_of_block_index(source_block_index);
+		let destination_address = Chip::get_address_of_block_index(destination_block_index);
+
+		// The pages can be moved inside the flash memory chip only if the 2 blocks are in the same plane
+		if source_block_index as u32 % Chip::PLANES_PER_LUN == destination_block_index as u32 % Chip::PLANES_PER_LUN
+		{
+			spi_flash_memory.internal_data_move(
+				source_address..=(source_address + Chip::BLOCK_SIZE - 1),
+				destination_address,
+			)
+		}
+		else
+		{
+			let mut page = vec![0; Chip::PAGE_SIZE as usize];
+			for page_offset in (0..Chip::BLOCK_SIZE).step_by(Chip::PAGE_SIZE as usize)
+			{
+				spi_flash_memory.read(source_address + page_offset, &mut page)?;
+				spi_flash_memory.program(&page, destination_address + page_offset)?;
+			}
+
+			Ok(())
+		}
 	}
 }
 
 #[derive(Debug)]
 pub struct FileDoesntExist;
-#[derive(Debug)]
-pub struct NotEnoughSpaceAvailable;
 
-struct ArrayIterator<const N: usize, T>
+/// An error returned from [`FilesMetadatasRegion::create_file`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FindSpaceError
+{
+	/// There's not enough space in the flash memory to store the file.
+	NotEnoughSpaceAvailable,
+	/// There's enough space to store the file only after [`compacting`] the region.
+	///
+	/// [`compacting`]: FilesMetadatasRegion::compact
+	RequiresCompacting,
+}
+
+/// An error returned when the [`FilesMetadatasRegion`] can't be [`stored`] in the flash memory.
+///
+/// [`stored`]: FilesMetadatasRegion::store_in_flash
+pub enum StoreError<E>
+{
+	/// It has been impossible to communicate with the flash memory.
+	Spi(E),
+	/// The region is bigger than a block of the flash memory (there are too many files, or their names are too long).
+	DoesntFitInBlock,
+}
+
+impl<E> From<E> for StoreError<E>
+{
+	fn from(error: E) -> Self
+	{
+		Self::Spi(error)
+	}
+}
+
+/// An error returned from [`FilesMetadatasRegion::read_from_flash`].
+pub enum ReadFromFlashError<E>
 {
-	array: [T; N],
-	current_element: usize,
+	/// The region couldn't be stored in the flash memory (check [`StoreError`]).
+	Store(StoreError<E>),
+	/// The region has only been stored by a firmware with a different [`layout`], which this firmware can't read.
+	///
+	/// [`layout`]: STORED_COPY_LAYOUT_VERSION
+	IncompatibleLayout
+	{
+		version: u8
+	},
 }
-impl<const N: usize, T> ArrayIterator<N, T>
+
+impl<E> From<E> for ReadFromFlashError<E>
 {
-	fn new(array: [T; N]) -> Self
+	fn from(error: E) -> Self
 	{
-		Self {
-			array,
-			current_element: 0,
+		Self::Store(StoreError::Spi(error))
+	}
+}
+
+impl<E> From<StoreError<E>> for ReadFromFlashError<E>
+{
+	fn from(error: StoreError<E>) -> Self
+	{
+		Self::Store(error)
+	}
+}
+
+impl<E: Debug> Debug for ReadFromFlashError<E>
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		match self
+		{
+			Self::Store(arg0) => f.debug_tuple("Store").field(arg0).finish(),
+			Self::IncompatibleLayout { version } =>
+			{
+				f.debug_struct("IncompatibleLayout").field("version", version).finish()
+			},
 		}
 	}
+}
 
-	fn next(&mut self) -> T
-	where T: Clone
+impl<E: Debug> Debug for StoreError<E>
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
 	{
-		self.take(1)[0].clone()
+		match self
+		{
+			Self::Spi(arg0) => f.debug_tuple("Spi").field(arg0).finish(),
+			Self::DoesntFitInBlock => write!(f, "DoesntFitInBlock"),
+		}
 	}
+}
+
+/// Removes the first `N` bytes from `bytes` and returns them, or returns `None` if there are less than `N` bytes.
+pub(crate) fn take<const N: usize>(bytes: &mut &[u8]) -> Option<[u8; N]>
+{
+	let (taken_bytes, remaining_bytes) = bytes.split_first_chunk::<N>()?;
+	*bytes = remaining_bytes;
+
+	Some(*taken_bytes)
+}
 
-	fn take(&mut self, n: usize) -> &[T]
+/// Removes the first `length` bytes from `bytes` and returns them, or returns `None` if there are less than `length`
+/// bytes.
+pub(crate) fn take_slice<'a>(bytes: &mut &'a [u8], length: usize) -> Option<&'a [u8]>
+{
+	let (taken_bytes, remaining_bytes) = bytes.split_at_checked(length)?;
+	*bytes = remaining_bytes;
+
+	Some(taken_bytes)
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::printer::components::{
+		drivers::spi_flash_memory::{FlashMemoryChipExt, MT29F2G01ABAGDWB},
+		mock::MockFlashMemory,
+	};
+
+	type Memory = SpiFlashMemory<MT29F2G01ABAGDWB, MockFlashMemory<MT29F2G01ABAGDWB>>;
+
+	fn new_memory() -> (MockFlashMemory<MT29F2G01ABAGDWB>, Memory)
 	{
-		self.current_element += n;
+		let memory = MockFlashMemory::default();
+		let mut spi_flash_memory = SpiFlashMemory::new(memory.clone(), MT29F2G01ABAGDWB);
+		MT29F2G01ABAGDWB::initialize(&mut spi_flash_memory).unwrap();
 
-		&self.array[(self.current_element - n)..self.current_element]
+		(memory, spi_flash_memory)
 	}
 
-	fn take_as_array<const A: usize>(&mut self) -> [T; A]
-	where T: Copy
+	#[test]
+	fn metadatas_spanning_multiple_pages_are_read_back()
 	{
-		slice_to_array(self.take(A))
+		let (mut memory, mut spi_flash_memory) = new_memory();
+		memory.mark_block_as_bad(10);
+		let regions_config = RegionsConfig::default::<MT29F2G01ABAGDWB>();
+
+		let mut region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		// The metadatas of 150 files don't fit in a single page
+		for i in 0..150
+		{
+			let mut file_metadata = FileMetadata::new(
+				region.highest_used_file_id,
+				(i + 2) * MT29F2G01ABAGDWB::BLOCK_SIZE,
+				1_000 + i,
+				format!("models/model {i}.gcode"),
+			);
+			file_metadata.upload_time = Some(1_700_000_000 + i as u64);
+			region.files_metadatas.push(file_metadata);
+			region.highest_used_file_id = FileId::next(region.highest_used_file_id);
+		}
+		region.store_in_flash(&mut spi_flash_memory, &regions_config).unwrap();
+
+		let read_region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		assert_eq!(read_region.get_files_metadatas(), region.get_files_metadatas());
+		assert_eq!(read_region.highest_used_file_id, region.highest_used_file_id);
+		assert_eq!(read_region.bad_block_table.indices(), [10]);
+	}
+
+	#[test]
+	fn metadatas_that_dont_fit_in_a_block_arent_stored()
+	{
+		let (_, mut spi_flash_memory) = new_memory();
+		let regions_config = RegionsConfig::default::<MT29F2G01ABAGDWB>();
+
+		let mut region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		let (file_id, start_address) = region.create_file::<MT29F2G01ABAGDWB>(100, &regions_config).unwrap();
+		region
+			.files_metadatas
+			.push(FileMetadata::new(file_id, start_address, 100, "cube.gcode"));
+		region.store_in_flash(&mut spi_flash_memory, &regions_config).unwrap();
+
+		let long_name = "a".repeat(200);
+		for i in 0..MT29F2G01ABAGDWB::BLOCK_SIZE / 200
+		{
+			region.files_metadatas.push(FileMetadata::new(
+				FileId::next(region.highest_used_file_id),
+				start_address,
+				100,
+				format!("{long_name} {i}.gcode"),
+			));
+			region.highest_used_file_id = FileId::next(region.highest_used_file_id);
+		}
+		assert!(matches!(
+			region.store_in_flash(&mut spi_flash_memory, &regions_config),
+			Err(StoreError::DoesntFitInBlock)
+		));
+
+		// The metadatas stored before are left untouched
+		let read_region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		assert_eq!(read_region.get_files_metadatas().len(), 1);
+		assert!(read_region.does_file_exist(file_id));
 	}
 
-	fn len(&self) -> usize
+	#[test]
+	fn files_left_being_written_are_deleted()
 	{
-		N - self.current_element
+		let (memory, mut spi_flash_memory) = new_memory();
+		let regions_config = RegionsConfig::default::<MT29F2G01ABAGDWB>();
+
+		let mut region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		let (file_id, start_address) = region.create_file::<MT29F2G01ABAGDWB>(100, &regions_config).unwrap();
+		let file_metadata = FileMetadata::new(file_id, start_address, 100, "cube.gcode");
+		region
+			.start_writing_file(file_metadata, &mut spi_flash_memory, &regions_config)
+			.unwrap();
+		spi_flash_memory.program(&[0x55; 100], start_address).unwrap();
+
+		// The power is lost before the file is finished
+		let read_region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		assert!(!read_region.does_file_exist(file_id));
+		let first_page_index = start_address / MT29F2G01ABAGDWB::PAGE_SIZE;
+		assert!(memory.get_page(first_page_index).iter().all(|&byte| byte == 0xFF));
 	}
 
-	fn reset_and_get_slice_mut(&mut self) -> &mut [T; N]
+	/// Programs `blocks_count` blocks starting from `start_block_index` with some content derived from `seed` and adds
+	/// the metadata of the file to the `region`.
+	fn add_file(
+		region: &mut FilesMetadatasRegion, spi_flash_memory: &mut Memory, start_block_index: u16, blocks_count: u16,
+		seed: u8,
+	) -> (FileMetadata, Vec<u8>)
 	{
-		self.current_element = 0;
-		&mut self.array
+		let file_metadata = FileMetadata::new(
+			region.highest_used_file_id,
+			MT29F2G01ABAGDWB::get_address_of_block_index(start_block_index),
+			blocks_count as u32 * MT29F2G01ABAGDWB::BLOCK_SIZE,
+			format!("file {seed}.gcode"),
+		);
+		region.highest_used_file_id = FileId::next(region.highest_used_file_id);
+
+		let content: Vec<u8> = (0..file_metadata.file_data_length)
+			.map(|i| (i % 239) as u8 ^ seed)
+			.collect();
+		spi_flash_memory
+			.program(&content, file_metadata.start_memory_address)
+			.unwrap();
+		region.files_metadatas.push(file_metadata.clone());
+
+		(file_metadata, content)
+	}
+
+	fn read_file(spi_flash_memory: &mut Memory, file_metadata: &FileMetadata) -> Vec<u8>
+	{
+		let mut content = vec![0; file_metadata.file_data_length as usize];
+		spi_flash_memory
+			.read(file_metadata.start_memory_address, &mut content)
+			.unwrap();
+
+		content
+	}
+
+	#[test]
+	fn compact_skips_bad_blocks_and_files_being_written()
+	{
+		let (mut memory, mut spi_flash_memory) = new_memory();
+		memory.mark_block_as_bad(3);
+		let regions_config = RegionsConfig::default::<MT29F2G01ABAGDWB>();
+
+		let mut region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		let (writing_file, _) = add_file(&mut region, &mut spi_flash_memory, 7, 1, 0x01);
+		region.writing_to_files_with_id.push(writing_file.id);
+		let (first_file, first_content) = add_file(&mut region, &mut spi_flash_memory, 5, 2, 0x02);
+		let (second_file, second_content) = add_file(&mut region, &mut spi_flash_memory, 10, 3, 0x03);
+		region.store_in_flash(&mut spi_flash_memory, &regions_config).unwrap();
+
+		let validator = region.get_file_validator();
+		region.compact(&mut spi_flash_memory, &regions_config).unwrap();
+		assert!(!validator.validate(&region));
+
+		// The block 3 is bad and the block 7 is occupied by the file being written
+		let first_file = region.get_file_metadata(first_file.id).unwrap();
+		assert_eq!(first_file.block_range::<MT29F2G01ABAGDWB>(), 4..=5);
+		let second_file = region.get_file_metadata(second_file.id).unwrap();
+		assert_eq!(second_file.block_range::<MT29F2G01ABAGDWB>(), 8..=10);
+		assert_eq!(region.get_file_metadata(writing_file.id), Some(writing_file));
+
+		assert_eq!(read_file(&mut spi_flash_memory, &first_file), first_content);
+		assert_eq!(read_file(&mut spi_flash_memory, &second_file), second_content);
+		for freed_block_index in [6, 11, 12]
+		{
+			let first_page_index = freed_block_index * MT29F2G01ABAGDWB::PAGES_PER_BLOCK;
+			assert!(memory.get_page(first_page_index).iter().all(|&byte| byte == 0xFF));
+		}
+
+		let read_region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		assert_eq!(read_region.get_file_metadata(second_file.id), Some(second_file));
+	}
+
+	#[test]
+	fn file_move_interrupted_by_a_power_loss_is_resumed()
+	{
+		let (memory, mut spi_flash_memory) = new_memory();
+		let regions_config = RegionsConfig::default::<MT29F2G01ABAGDWB>();
+
+		let mut region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		let (file_metadata, content) = add_file(&mut region, &mut spi_flash_memory, 6, 3, 0x04);
+
+		// The power is lost after the first block of the file has been copied from the block 6 to the block 5
+		let first_block_content = &content[..MT29F2G01ABAGDWB::BLOCK_SIZE as usize];
+		spi_flash_memory
+			.program(first_block_content, MT29F2G01ABAGDWB::get_address_of_block_index(5))
+			.unwrap();
+		region.file_move = Some(FileMove {
+			file_id: file_metadata.id,
+			destination_address: MT29F2G01ABAGDWB::get_address_of_block_index(5),
+			moved_blocks_count: 1,
+		});
+		region.store_in_flash(&mut spi_flash_memory, &regions_config).unwrap();
+
+		let read_region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		assert_eq!(read_region.file_move, None);
+		let moved_file_metadata = read_region.get_file_metadata(file_metadata.id).unwrap();
+		assert_eq!(moved_file_metadata.block_range::<MT29F2G01ABAGDWB>(), 5..=7);
+		assert_eq!(read_file(&mut spi_flash_memory, &moved_file_metadata), content);
+		let first_freed_page_index = 8 * MT29F2G01ABAGDWB::PAGES_PER_BLOCK;
+		assert!(memory.get_page(first_freed_page_index).iter().all(|&byte| byte == 0xFF));
+
+		let read_region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		assert_eq!(
+			read_region.get_file_metadata(file_metadata.id),
+			Some(moved_file_metadata)
+		);
+	}
+
+	#[test]
+	fn region_is_stored_in_rotating_blocks()
+	{
+		let (mut memory, mut spi_flash_memory) = new_memory();
+		memory.mark_block_as_bad(1);
+		let regions_config = RegionsConfig {
+			metadata_block_range: 0..=2,
+			data_block_range: 3..=2045,
+			checkpoint_block_range: 2046..=2047,
+		};
+
+		let mut region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		assert_eq!(region.metadata_block_index, 0);
+		for _ in 0..3
+		{
+			region.store_in_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		}
+		add_file(&mut region, &mut spi_flash_memory, 3, 1, 0x05);
+		region.store_in_flash(&mut spi_flash_memory, &regions_config).unwrap();
+
+		// The bad block is skipped
+		assert_eq!(region.metadata_block_index, 0);
+		assert_eq!(region.erase_count_table.get_erase_count(0), 3);
+		assert_eq!(region.erase_count_table.get_erase_count(1), 0);
+		assert_eq!(region.erase_count_table.get_erase_count(2), 2);
+
+		// The most recent copy is read
+		let read_region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		assert_eq!(read_region.get_files_metadatas(), region.get_files_metadatas());
+		assert_eq!(read_region.metadata_sequence_number, 5);
+		assert_eq!(
+			read_region.get_wear_statistics(&regions_config),
+			region.get_wear_statistics(&regions_config)
+		);
+	}
+
+	#[test]
+	fn corrupted_copy_is_ignored_in_favour_of_the_previous_one()
+	{
+		let (mut memory, mut spi_flash_memory) = new_memory();
+		let regions_config = RegionsConfig::default::<MT29F2G01ABAGDWB>();
+
+		let mut region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		add_file(&mut region, &mut spi_flash_memory, 3, 1, 0x06);
+		region.store_in_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		let previous_files_metadatas = region.get_files_metadatas().to_vec();
+		add_file(&mut region, &mut spi_flash_memory, 4, 1, 0x07);
+		region.store_in_flash(&mut spi_flash_memory, &regions_config).unwrap();
+
+		let first_page_index = region.metadata_block_index as u32 * MT29F2G01ABAGDWB::PAGES_PER_BLOCK;
+		memory.inject_bit_flips(first_page_index, 200..300);
+
+		let read_region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+		assert_eq!(read_region.get_files_metadatas(), previous_files_metadatas);
+	}
+
+	#[test]
+	fn compaction_interrupted_by_a_power_loss_at_any_point_keeps_the_file()
+	{
+		let regions_config = RegionsConfig::default::<MT29F2G01ABAGDWB>();
+		let set_up_memory = || {
+			let (memory, mut spi_flash_memory) = new_memory();
+			let mut region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+			let (file_metadata, content) = add_file(&mut region, &mut spi_flash_memory, 4, 3, 0x08);
+			region.store_in_flash(&mut spi_flash_memory, &regions_config).unwrap();
+
+			(memory, spi_flash_memory, region, file_metadata, content)
+		};
+
+		let (memory, mut spi_flash_memory, mut region, ..) = set_up_memory();
+		let operations_count_before = memory.get_program_and_erase_count();
+		region.compact(&mut spi_flash_memory, &regions_config).unwrap();
+		let operations_count = memory.get_program_and_erase_count() - operations_count_before;
+
+		for operations_before_power_loss in 0..operations_count
+		{
+			let (mut memory, mut spi_flash_memory, mut region, file_metadata, content) = set_up_memory();
+			memory.cut_power_after(operations_before_power_loss);
+			// The operations done after the power loss are ignored by the memory
+			let _ = region.compact(&mut spi_flash_memory, &regions_config);
+			memory.restore_power();
+			MT29F2G01ABAGDWB::initialize(&mut spi_flash_memory).unwrap();
+
+			let read_region = FilesMetadatasRegion::read_from_flash(&mut spi_flash_memory, &regions_config).unwrap();
+			assert_eq!(read_region.file_move, None);
+			let read_file_metadata = read_region.get_file_metadata(file_metadata.id).unwrap();
+			// The file is either still in its original blocks (if the move didn't start) or already moved
+			assert!([2..=4, 4..=6].contains(&read_file_metadata.block_range::<MT29F2G01ABAGDWB>()));
+			assert_eq!(read_file(&mut spi_flash_memory, &read_file_metadata), content);
+		}
 	}
 }
@@ -2,6 +2,7 @@ use std::ops::RangeInclusive;
 
 use crate::printer::components::drivers::spi_flash_memory::*;
 
+pub mod checkpoint;
 pub mod data;
 pub mod metadata;
 
@@ -9,18 +10,27 @@ pub mod metadata;
 pub struct RegionsConfig
 {
 	/// The range of the indices of the flash memory's blocks used by the [`FilesMetadatasRegion`](metadata::FilesMetadatasRegion).
+	///
+	/// The region is stored in one block at a time, moving to the next block of the range each time it's stored, so the
+	/// more blocks there are in the range, the slower they wear out.
 	pub metadata_block_range: RangeInclusive<u16>,
 	/// The range of the indices of the flash memory's blocks used by the [`FilesRegion`](data::FilesRegion).
 	pub data_block_range: RangeInclusive<u16>,
+	/// The range of the indices of the flash memory's blocks used by the [`CheckpointRegion`](checkpoint::CheckpointRegion).
+	///
+	/// It must not overlap the other ranges.
+	pub checkpoint_block_range: RangeInclusive<u16>,
 }
 
 impl RegionsConfig
 {
 	pub const fn default<Chip: FlashMemoryChip>() -> Self
 	{
+		let last_block_index = (Chip::MEMORY_SIZE / Chip::BLOCK_SIZE) as u16 - 1;
 		Self {
-			metadata_block_range: 0..=0,
-			data_block_range: 2..=(Chip::MEMORY_SIZE / Chip::BLOCK_SIZE) as u16,
+			metadata_block_range: 0..=1,
+			data_block_range: 2..=(last_block_index - 2),
+			checkpoint_block_range: (last_block_index - 1)..=last_block_index,
 		}
 	}
 
@@ -0,0 +1,177 @@
+use std::ops::{Range, RangeInclusive};
+
+use embedded_hal::spi::{ErrorType, SpiDevice};
+
+use super::bad_blocks::BadBlockTable;
+use crate::printer::components::drivers::spi_flash_memory::*;
+
+/// Keeps track of how many times each block of the flash memory has been erased, so that the file system can spread
+/// the erases on all the blocks (each block can only be erased a limited number of times before wearing out).
+pub struct EraseCountTable
+{
+	erase_counts: Vec<u32>,
+}
+
+impl EraseCountTable
+{
+	/// Creates a table of a flash memory whose blocks have never been erased.
+	pub fn new<Chip: FlashMemoryChip>() -> Self
+	{
+		Self {
+			erase_counts: vec![0; (Chip::MEMORY_SIZE / Chip::BLOCK_SIZE) as usize],
+		}
+	}
+
+	/// Restores the erase count table from a previous one you [`converted`] to bytes.
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::{file_system::wear_leveling::*, drivers::spi_flash_memory::*};
+	/// #
+	/// let mut erase_count_table = EraseCountTable::new::<MT29F2G01ABAGDWB>();
+	/// erase_count_table.count_erases(5..=6);
+	///
+	/// let bytes: Vec<u8> = erase_count_table.as_bytes().collect();
+	/// let restored_erase_count_table = EraseCountTable::from_bytes(&bytes);
+	/// assert_eq!(restored_erase_count_table.get_erase_count(6), 1);
+	/// assert_eq!(restored_erase_count_table.get_erase_count(7), 0);
+	/// ```
+	///
+	/// [`converted`]: `EraseCountTable::as_bytes`
+	pub fn from_bytes(bytes: &[u8]) -> Self
+	{
+		Self {
+			erase_counts: bytes
+				.chunks_exact(4)
+				.map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+				.collect(),
+		}
+	}
+
+	/// Returns the number of bytes returned by [`Self::as_bytes`] for the table of a `Chip`.
+	pub const fn bytes_count<Chip: FlashMemoryChip>() -> usize
+	{
+		(Chip::MEMORY_SIZE / Chip::BLOCK_SIZE) as usize * core::mem::size_of::<u32>()
+	}
+
+	pub fn as_bytes(&self) -> impl Iterator<Item = u8> + '_
+	{
+		self.erase_counts
+			.iter()
+			.flat_map(|erase_count| erase_count.to_le_bytes())
+	}
+
+	/// Erases the blocks in the `block_indices_to_erase` range of the provided `spi_flash_memory` (check
+	/// [`SpiFlashMemory::erase_blocks`]) and counts the erases.
+	pub fn erase_blocks<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+		&mut self, spi_flash_memory: &mut SpiFlashMemory<Chip, Spi>, block_indices_to_erase: RangeInclusive<u16>,
+	) -> Result<(), <Spi as ErrorType>::Error>
+	{
+		self.count_erases(block_indices_to_erase.clone());
+
+		spi_flash_memory.erase_blocks(block_indices_to_erase)
+	}
+
+	/// Increments the erase count of each block in the `block_indices` range.
+	pub fn count_erases(&mut self, block_indices: RangeInclusive<u16>)
+	{
+		for block_index in block_indices
+		{
+			self.erase_counts[block_index as usize] = self.erase_counts[block_index as usize].saturating_add(1);
+		}
+	}
+
+	pub fn get_erase_count(&self, block_index: u16) -> u32
+	{
+		self.erase_counts[block_index as usize]
+	}
+
+	/// Returns the sum of the erase counts of the blocks in the `block_indices` range.
+	pub fn get_total_erase_count(&self, block_indices: Range<u16>) -> u64
+	{
+		self.erase_counts[block_indices.start as usize..block_indices.end as usize]
+			.iter()
+			.map(|&erase_count| erase_count as u64)
+			.sum()
+	}
+
+	/// Returns the [`WearStatistics`] of the blocks in `block_indices` that aren't bad (based on the
+	/// `bad_block_table`).
+	pub fn get_statistics(
+		&self, block_indices: impl IntoIterator<Item = u16>, bad_block_table: &BadBlockTable,
+	) -> WearStatistics
+	{
+		let mut statistics = WearStatistics {
+			blocks_count: 0,
+			min_erase_count: u32::MAX,
+			max_erase_count: 0,
+			most_erased_block_index: 0,
+			total_erase_count: 0,
+		};
+		for block_index in block_indices
+			.into_iter()
+			.filter(|&block_index| bad_block_table.is_block_valid(block_index))
+		{
+			let erase_count = self.get_erase_count(block_index);
+
+			statistics.blocks_count += 1;
+			statistics.min_erase_count = statistics.min_erase_count.min(erase_count);
+			if erase_count > statistics.max_erase_count || statistics.blocks_count == 1
+			{
+				statistics.max_erase_count = erase_count;
+				statistics.most_erased_block_index = block_index;
+			}
+			statistics.total_erase_count += erase_count as u64;
+		}
+
+		if statistics.blocks_count == 0
+		{
+			statistics.min_erase_count = 0;
+		}
+
+		statistics
+	}
+}
+
+/// A summary of how much the (valid) blocks of the flash memory used by the file system have been worn out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WearStatistics
+{
+	/// The number of blocks these statistics are about.
+	pub blocks_count: u16,
+	/// The erase count of the least erased block.
+	pub min_erase_count: u32,
+	/// The erase count of the most erased block.
+	pub max_erase_count: u32,
+	/// The index of the most erased block.
+	pub most_erased_block_index: u16,
+	/// The sum of the erase counts of all the blocks.
+	pub total_erase_count: u64,
+}
+
+impl WearStatistics
+{
+	/// Returns the average of the erase counts of the blocks.
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::{file_system::{bad_blocks::*, wear_leveling::*}, drivers::spi_flash_memory::*};
+	/// #
+	/// let mut erase_count_table = EraseCountTable::new::<MT29F2G01ABAGDWB>();
+	/// erase_count_table.count_erases(0..=1);
+	/// erase_count_table.count_erases(1..=1);
+	///
+	/// let statistics = erase_count_table.get_statistics(0..4, &BadBlockTable::default());
+	/// assert_eq!(statistics.max_erase_count, 2);
+	/// assert_eq!(statistics.most_erased_block_index, 1);
+	/// assert_eq!(statistics.get_average_erase_count(), 0.75);
+	/// ```
+	pub fn get_average_erase_count(&self) -> f32
+	{
+		match self.blocks_count
+		{
+			0 => 0.,
+			blocks_count => self.total_erase_count as f32 / blocks_count as f32,
+		}
+	}
+}
@@ -4,6 +4,8 @@
 //!
 //! [`RepRap documentation`]: <https://reprap.org/wiki/G-code>
 
+use std::time::Duration;
+
 use super::{
 	execute::{GCodeExecuter, PositionMode},
 	parameters::{identifier, value::NoValue, Param},
@@ -12,11 +14,17 @@ use super::{
 use crate::{
 	printer::components::{
 		drivers::fan::Fan,
+		emergency_stop,
+		filament_change::{self, ExtruderMovesPlanner},
 		hal::{
 			adc::{Adc, AdcPin},
 			pwm::PwmPin,
 		},
-		motion::{axes::Axis, planner::MoveId},
+		motion::{
+			axes::Axis,
+			planner::{MoveId, PlanMoveError},
+		},
+		pauser, persisted_settings, print_process,
 		temperature::TemperaturePidController,
 		Peripherals, Printer3DComponents,
 	},
@@ -37,6 +45,17 @@ fn convert_feed_rate(feed_rate: Param<identifier::F, f32>) -> Option<f32>
 	feed_rate.value.map(|feed_rate| feed_rate / 60.)
 }
 
+/// Returns the [`Status`] of a command whose moves couldn't be planned because of `error`.
+fn plan_move_error_to_status(error: PlanMoveError) -> Status
+{
+	match error
+	{
+		// The command tries again to plan its moves when it's prepared the next time
+		PlanMoveError::BlocksBufferIsFull => Status::Working,
+		PlanMoveError::UnreachablePosition => Status::Error("The tool can't reach the target position".to_string()),
+	}
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct G0
 {
@@ -75,20 +94,13 @@ impl<P: Peripherals> GCodeCommand<P> for G0
 		&mut self, printer_components: &mut Printer3DComponents<P>, g_code_executer: &mut GCodeExecuter<P>,
 	) -> Status
 	{
-		g_code_executer.calculate_position_based_on_mode(
-			printer_components,
-			&mut self.x.value,
-			&mut self.y.value,
-			&mut self.z.value,
-			&mut self.e.value,
-		);
-		match printer_components.motion_controller.plan_move(
-			self.x.value,
-			self.y.value,
-			self.z.value,
-			self.e.value,
-			convert_feed_rate(self.feed_rate),
-		)
+		// The parameters are kept as they were written, so that the command can be prepared again
+		// after being unprepared (relative moves would be offset twice otherwise).
+		let (mut x, mut y, mut z, mut e) = (self.x.value, self.y.value, self.z.value, self.e.value);
+		g_code_executer.calculate_position_based_on_mode(printer_components, &mut x, &mut y, &mut z, &mut e);
+		match printer_components
+			.motion_controller
+			.plan_move(x, y, z, e, convert_feed_rate(self.feed_rate))
 		{
 			Ok(move_id) =>
 			{
@@ -96,9 +108,14 @@ impl<P: Peripherals> GCodeCommand<P> for G0
 
 				Status::Finished
 			},
-			Err(_) => Status::Working,
+			Err(error) => plan_move_error_to_status(error),
 		}
 	}
+
+	fn unprepare(&mut self)
+	{
+		self.is_move_ready_to_go = false;
+	}
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
@@ -157,6 +174,81 @@ impl<P: Peripherals> GCodeCommand<P> for G1
 
 		status
 	}
+
+	fn unprepare(&mut self)
+	{
+		self.is_move_ready_to_go = false;
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Retracts the filament (and lifts the nozzle) using the [`retraction settings`] of the machine.
+///
+/// [`retraction settings`]: crate::printer::components::motion::retraction::RetractionSettings
+pub struct G10
+{
+	pub move_id: MoveId,
+}
+impl<P: Peripherals> GCodeCommand<P> for G10
+{
+	fn execute(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		match printer_components
+			.motion_controller
+			.has_move_been_executed(self.move_id)
+		{
+			true => Status::Finished,
+			false => Status::Working,
+		}
+	}
+
+	fn prepare(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		match printer_components.motion_controller.retract()
+		{
+			Ok(move_id) =>
+			{
+				self.move_id = move_id;
+
+				Status::Finished
+			},
+			Err(error) => plan_move_error_to_status(error),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Recovers from the retraction done by the last [`G10`] (lowering the nozzle and priming the filament).
+pub struct G11
+{
+	pub move_id: MoveId,
+}
+impl<P: Peripherals> GCodeCommand<P> for G11
+{
+	fn execute(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		match printer_components
+			.motion_controller
+			.has_move_been_executed(self.move_id)
+		{
+			true => Status::Finished,
+			false => Status::Working,
+		}
+	}
+
+	fn prepare(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		match printer_components.motion_controller.recover_retraction()
+		{
+			Ok(move_id) =>
+			{
+				self.move_id = move_id;
+
+				Status::Finished
+			},
+			Err(error) => plan_move_error_to_status(error),
+		}
+	}
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
@@ -184,6 +276,8 @@ impl<P: Peripherals> GCodeCommand<P> for G21
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Homes the axes of the machine. If only `X` and/or `Y` are provided, the Z axis isn't homed (so the tool doesn't hit
+/// what's on the bed), and the X and Y axes are always homed together.
 pub struct G28
 {
 	pub x: Param<identifier::X, NoValue>,
@@ -198,9 +292,10 @@ impl<P: Peripherals> GCodeCommand<P> for G28
 	{
 		if !self.has_started_homing
 		{
-			if printer_components.motion_controller.start_homing().is_err()
+			let home_z_axis = self.z.value.is_some() || (self.x.value.is_none() && self.y.value.is_none());
+			if let Err(error) = printer_components.motion_controller.start_homing(home_z_axis)
 			{
-				return Status::Working;
+				return plan_move_error_to_status(error);
 			}
 			self.has_started_homing = true;
 		}
@@ -216,6 +311,38 @@ impl<P: Peripherals> GCodeCommand<P> for G28
 	}
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Auto-calibrates the kinematics of the machine (check [`Kinematics::auto_calibrate`]).
+///
+/// [`Kinematics::auto_calibrate`]: crate::printer::components::motion::kinematics::Kinematics::auto_calibrate
+pub struct G33
+{
+	pub has_started_calibration: bool,
+}
+impl<P: Peripherals> GCodeCommand<P> for G33
+{
+	fn prepare(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		if !self.has_started_calibration
+		{
+			if let Err(error) = printer_components.motion_controller.start_auto_calibration()
+			{
+				return plan_move_error_to_status(error);
+			}
+			self.has_started_calibration = true;
+		}
+
+		if printer_components.motion_controller.is_auto_calibrating()
+		{
+			Status::Working
+		}
+		else
+		{
+			Status::Finished
+		}
+	}
+}
+
 const DEFAULT_MEMORY_SLOT: usize = 0;
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
 /// TODO: Find a way to get the current position (because now I can't save it since I don't know how to retrieve it).
@@ -267,7 +394,7 @@ impl<P: Peripherals> GCodeCommand<P> for G61
 					.plan_move(x, y, z, e, convert_feed_rate(self.feed_rate))
 				{
 					Ok(_) => Status::Finished,
-					Err(_) => Status::Working,
+					Err(error) => plan_move_error_to_status(error),
 				}
 			},
 			Err(_) => Status::Error(format!(
@@ -364,6 +491,23 @@ impl<P: Peripherals> GCodeCommand<P> for M83
 	}
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Disables the stepper motors when they have been idle for `S` seconds (as soon as they are idle if `S` is missing).
+pub struct M84
+{
+	pub idle_time_in_seconds: Param<identifier::S, u16>,
+}
+impl<P: Peripherals> GCodeCommand<P> for M84
+{
+	fn execute(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		let idle_time = Duration::from_secs(self.idle_time_in_seconds.value.unwrap_or(0) as u64);
+		printer_components.disable_steppers_when_idle_for(idle_time);
+
+		Status::Finished
+	}
+}
+
 fn set_target_temperature<CHP: PwmPin, TADC: Adc, TP: AdcPin<TADC>>(
 	temperature: &Option<u16>, pid_controller: &mut TemperaturePidController<CHP, TADC, TP>,
 ) -> Status
@@ -455,6 +599,23 @@ impl<P: Peripherals> GCodeCommand<P> for M109
 	}
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Halts the machine (check [`emergency_stop`]) once the previous commands have been prepared, without waiting for
+/// them to be executed. When it's received with the `/v1/gcode-commands` HTTP request it doesn't even wait for them to
+/// be prepared, because it's handled as soon as the request is received (check [`ImmediateCommand`]).
+///
+/// [`ImmediateCommand`]: super::parser::ImmediateCommand
+pub struct M112;
+impl<P: Peripherals> GCodeCommand<P> for M112
+{
+	fn prepare(&mut self, _: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		emergency_stop::stop();
+
+		Status::Finished
+	}
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct M140
 {
@@ -592,3 +753,412 @@ impl<P: Peripherals> GCodeCommand<P> for M105
 		Status::Finished
 	}
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Reports the current position of the tool (in the logical space, so without the [`skew correction`] and the
+/// [`backlash compensation`]).
+///
+/// [`skew correction`]: crate::printer::components::motion::skew::SkewCorrection
+/// [`backlash compensation`]: crate::printer::components::motion::backlash::BacklashSettings
+pub struct M114;
+impl<P: Peripherals> GCodeCommand<P> for M114
+{
+	fn execute(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		let position = printer_components.motion_controller.get_position();
+		log::info!(
+			"M114: Report position, X: {:#?}, Y: {:#?}, Z: {:#?}, E: {:#?}",
+			position[Axis::X as usize],
+			position[Axis::Y as usize],
+			position[Axis::Z as usize],
+			position[Axis::E as usize]
+		);
+
+		Status::Finished
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Sets the length, the speed and the Z-hop of the retraction done by [`G10`] (and logs all the [`retraction
+/// settings`]).
+///
+/// [`retraction settings`]: crate::printer::components::motion::retraction::RetractionSettings
+pub struct M207
+{
+	pub length: Param<identifier::S, Distance>,
+	pub feed_rate: Param<identifier::F, f32>,
+	pub z_hop: Param<identifier::Z, Distance>,
+}
+impl<P: Peripherals> GCodeCommand<P> for M207
+{
+	fn prepare(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		let settings = printer_components.motion_controller.get_retraction_settings_mut();
+		settings.length = self.length.value.unwrap_or(settings.length);
+		settings.speed_mm_s = convert_feed_rate(self.feed_rate).unwrap_or(settings.speed_mm_s);
+		settings.z_hop = self.z_hop.value.unwrap_or(settings.z_hop);
+
+		log::info!("M207: Retraction: {:#?}", settings);
+
+		Status::Finished
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Sets the extra length and the speed of the prime done by [`G11`] (and logs all the [`retraction settings`]).
+///
+/// [`retraction settings`]: crate::printer::components::motion::retraction::RetractionSettings
+pub struct M208
+{
+	pub extra_prime_length: Param<identifier::S, Distance>,
+	pub feed_rate: Param<identifier::F, f32>,
+}
+impl<P: Peripherals> GCodeCommand<P> for M208
+{
+	fn prepare(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		let settings = printer_components.motion_controller.get_retraction_settings_mut();
+		settings.extra_prime_length = self.extra_prime_length.value.unwrap_or(settings.extra_prime_length);
+		settings.prime_speed_mm_s = convert_feed_rate(self.feed_rate).unwrap_or(settings.prime_speed_mm_s);
+
+		log::info!("M208: Retraction: {:#?}", settings);
+
+		Status::Finished
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Sets the [`backlash compensation`] settings (and logs them).
+///
+/// [`backlash compensation`]: crate::printer::components::motion::backlash::BacklashSettings
+pub struct M425
+{
+	pub correction: Param<identifier::F, f32>,
+	pub smoothing_distance: Param<identifier::S, Distance>,
+	pub x: Param<identifier::X, Distance>,
+	pub y: Param<identifier::Y, Distance>,
+	pub z: Param<identifier::Z, Distance>,
+}
+impl<P: Peripherals> GCodeCommand<P> for M425
+{
+	fn prepare(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		let settings = printer_components.motion_controller.get_backlash_settings_mut();
+		if let Some(correction) = self.correction.value
+		{
+			settings.correction = correction.clamp(0., 1.);
+		}
+		if let Some(smoothing_distance) = self.smoothing_distance.value
+		{
+			settings.smoothing_distance = smoothing_distance;
+		}
+		for (distance, axis) in [self.x.value, self.y.value, self.z.value]
+			.into_iter()
+			.zip([Axis::X, Axis::Y, Axis::Z])
+		{
+			if let Some(distance) = distance
+			{
+				settings.distances[axis as usize] = distance;
+			}
+		}
+
+		log::info!("M425: Backlash compensation: {:#?}", settings);
+
+		Status::Finished
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Stores the current [`persisted settings`] in the flash memory, so that they are loaded the next time the machine
+/// is turned on.
+///
+/// [`persisted settings`]: crate::printer::components::persisted_settings::PersistedSettings
+pub struct M500;
+impl<P: Peripherals> GCodeCommand<P> for M500
+{
+	fn prepare(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		persisted_settings::request_save(printer_components.get_persisted_settings());
+
+		Status::Finished
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Cancels the print in execution (check [`print_process::request_cancel`]) once the previous commands have been
+/// prepared. When it's received with the `/v1/gcode-commands` HTTP request it's handled as soon as the request is
+/// received instead (check [`ImmediateCommand`]).
+///
+/// [`ImmediateCommand`]: super::parser::ImmediateCommand
+pub struct M524;
+impl<P: Peripherals> GCodeCommand<P> for M524
+{
+	fn prepare(&mut self, _: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		print_process::request_cancel();
+
+		Status::Finished
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Changes the filament (check the [`filament_change`] module), and finishes once the print has been resumed.
+pub struct M600
+{
+	pub has_requested_filament_change: bool,
+}
+impl<P: Peripherals> GCodeCommand<P> for M600
+{
+	fn execute(&mut self, _: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		if !self.has_requested_filament_change
+		{
+			filament_change::request();
+			self.has_requested_filament_change = true;
+		}
+
+		match pauser::is_paused()
+		{
+			true => Status::Working,
+			false => Status::Finished,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Loads the filament in the extruder, purging the nozzle (check [`filament_change::load_moves`]).
+pub struct M701
+{
+	pub moves_planner: ExtruderMovesPlanner,
+	pub move_id: MoveId,
+}
+impl<P: Peripherals> GCodeCommand<P> for M701
+{
+	fn execute(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		match printer_components
+			.motion_controller
+			.has_move_been_executed(self.move_id)
+		{
+			true => Status::Finished,
+			false => Status::Working,
+		}
+	}
+
+	fn prepare(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		let moves = filament_change::load_moves(printer_components.get_filament_change_config());
+		match self
+			.moves_planner
+			.plan::<P>(&mut printer_components.motion_controller, &moves)
+		{
+			Ok(move_id) =>
+			{
+				self.move_id = move_id;
+
+				Status::Finished
+			},
+			Err(error) => plan_move_error_to_status(error),
+		}
+	}
+
+	fn unprepare(&mut self)
+	{
+		self.moves_planner.reset();
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Unloads the filament from the extruder (check [`filament_change::unload_moves`]).
+pub struct M702
+{
+	pub moves_planner: ExtruderMovesPlanner,
+	pub move_id: MoveId,
+}
+impl<P: Peripherals> GCodeCommand<P> for M702
+{
+	fn execute(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		match printer_components
+			.motion_controller
+			.has_move_been_executed(self.move_id)
+		{
+			true => Status::Finished,
+			false => Status::Working,
+		}
+	}
+
+	fn prepare(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		let moves = filament_change::unload_moves(printer_components.get_filament_change_config());
+		match self
+			.moves_planner
+			.plan::<P>(&mut printer_components.motion_controller, &moves)
+		{
+			Ok(move_id) =>
+			{
+				self.move_id = move_id;
+
+				Status::Finished
+			},
+			Err(error) => plan_move_error_to_status(error),
+		}
+	}
+
+	fn unprepare(&mut self)
+	{
+		self.moves_planner.reset();
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Sets the [`skew correction`] factors (and logs them).
+///
+/// [`skew correction`]: crate::printer::components::motion::skew::SkewCorrection
+pub struct M852
+{
+	pub xy: Param<identifier::I, f32>,
+	pub xz: Param<identifier::J, f32>,
+	pub yz: Param<identifier::K, f32>,
+}
+impl<P: Peripherals> GCodeCommand<P> for M852
+{
+	fn prepare(&mut self, printer_components: &mut Printer3DComponents<P>, _: &mut GCodeExecuter<P>) -> Status
+	{
+		let mut skew_correction = printer_components.motion_controller.get_skew_correction();
+		skew_correction.xy = self.xy.value.unwrap_or(skew_correction.xy);
+		skew_correction.xz = self.xz.value.unwrap_or(skew_correction.xz);
+		skew_correction.yz = self.yz.value.unwrap_or(skew_correction.yz);
+		printer_components
+			.motion_controller
+			.set_skew_correction(skew_correction);
+
+		log::info!("M852: Skew correction: {:#?}", skew_correction);
+
+		Status::Finished
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::printer::components::{
+		drivers::{cartridge_heater::CartridgeHeater, thermistor::Thermistor},
+		mock::{MockAdc, MockAdcPin, MockPwmPin, MockThermalPlant, ThermalPlantConfig},
+		temperature::{
+			safety::{temperature_change::TemperatureChangeConfig, TemperatureSafety},
+			TemperaturePidGains,
+		},
+	};
+
+	const DELTA_TIME: f64 = 0.1;
+
+	fn pid_controller_and_plant(
+		config: ThermalPlantConfig, pid_gains: TemperaturePidGains,
+	) -> (
+		TemperaturePidController<MockPwmPin, MockAdc, MockAdcPin>,
+		MockThermalPlant,
+		MockAdc,
+	)
+	{
+		let heater_pin = MockPwmPin::default();
+		let thermistor_pin = MockAdcPin::default();
+		let adc = MockAdc::default();
+
+		let plant = MockThermalPlant::new(config, &heater_pin, &thermistor_pin, &adc);
+		let pid_controller = TemperaturePidController::new(
+			Thermistor::new(
+				thermistor_pin,
+				config.thermistor.beta,
+				config.thermistor.resistance_at_t0,
+				config.thermistor.other_resistance,
+			),
+			CartridgeHeater::new(heater_pin),
+			pid_gains,
+			TemperatureSafety::new(
+				Temperature::from_celsius(0.)..=Temperature::from_celsius(260.),
+				TemperatureChangeConfig {
+					period_in_seconds: 40.,
+					hysteresis: 4.,
+				},
+				TemperatureChangeConfig {
+					period_in_seconds: 90.,
+					hysteresis: 2.,
+				},
+				45,
+			),
+		);
+
+		(pid_controller, plant, adc)
+	}
+
+	/// Ticks the plant and the PID controller until [`wait_for_target_temperature`] returns [`Status::Finished`] (or
+	/// until `timeout_seconds` have passed), and returns the seconds it took.
+	fn seconds_waited_for_target_temperature(
+		pid_controller: &mut TemperaturePidController<MockPwmPin, MockAdc, MockAdcPin>, plant: &mut MockThermalPlant,
+		adc: &mut MockAdc, target_temperature_cooling_and_heating: Param<identifier::R, u16>, timeout_seconds: f64,
+	) -> Option<f64>
+	{
+		let mut seconds = 0.;
+		while seconds < timeout_seconds
+		{
+			match wait_for_target_temperature(pid_controller, adc, &target_temperature_cooling_and_heating)
+			{
+				Status::Working => (),
+				Status::Finished => return Some(seconds),
+				Status::Error(error) => panic!("{error}"),
+			}
+
+			plant.tick(DELTA_TIME);
+			pid_controller.tick(DELTA_TIME, adc).unwrap();
+			seconds += DELTA_TIME;
+		}
+
+		None
+	}
+
+	#[test]
+	fn m190_waits_for_heated_bed_to_heat()
+	{
+		let (mut pid_controller, mut plant, mut adc) = pid_controller_and_plant(
+			ThermalPlantConfig::heated_bed(),
+			TemperaturePidGains {
+				p: 1000.,
+				i: 10.,
+				d: 10.,
+			},
+		);
+		set_target_temperature(&Some(60), &mut pid_controller);
+
+		let seconds =
+			seconds_waited_for_target_temperature(&mut pid_controller, &mut plant, &mut adc, None.into(), 600.)
+				.unwrap();
+		assert!(seconds > 60., "{seconds}");
+		assert!(pid_controller.get_last_sample_of_current_temperature().unwrap() >= Temperature::from_celsius(60.));
+	}
+
+	#[test]
+	fn m109_waits_for_hotend_to_cool_only_with_r()
+	{
+		let hotend_pid_gains = TemperaturePidGains {
+			p: 100.,
+			i: 10.,
+			d: 750.,
+		};
+		let (mut pid_controller, mut plant, mut adc) =
+			pid_controller_and_plant(ThermalPlantConfig::hotend(), hotend_pid_gains);
+		set_target_temperature(&Some(200), &mut pid_controller);
+		seconds_waited_for_target_temperature(&mut pid_controller, &mut plant, &mut adc, None.into(), 300.).unwrap();
+
+		set_target_temperature(&Some(150), &mut pid_controller);
+		assert_eq!(
+			seconds_waited_for_target_temperature(&mut pid_controller, &mut plant, &mut adc, None.into(), 300.),
+			Some(0.)
+		);
+		let seconds =
+			seconds_waited_for_target_temperature(&mut pid_controller, &mut plant, &mut adc, 150.into(), 300.).unwrap();
+		assert!(seconds > 10., "{seconds}");
+		assert!(pid_controller.get_last_sample_of_current_temperature().unwrap() <= Temperature::from_celsius(153.));
+	}
+}
@@ -2,7 +2,9 @@ use std::collections::VecDeque;
 
 use super::{GCodeCommand, Status};
 use crate::{
-	printer::components::{print_process, Peripherals, Printer3DComponents},
+	printer::components::{
+		file_system::regions::metadata::FileId, motion::PlanningState, print_process, Peripherals, Printer3DComponents,
+	},
 	utils::{
 		math::vectors::*,
 		measurement::distance::{Distance, Units},
@@ -15,14 +17,15 @@ pub const SAVED_POSITIONS_COUNT: usize = 1;
 pub struct GCodeExecuter<P: Peripherals>
 {
 	/// All the commands added to the executer first go in this queue to be prepared.
-	commands_to_prepare: VecDeque<Box<dyn GCodeCommand<P>>>,
+	commands_to_prepare: VecDeque<QueuedCommand<P>>,
 	/// When a command is successfully prepared (it returns [`Status::Finished`]), it goes in this queue.
-	command_buffer: VecDeque<Box<dyn GCodeCommand<P>>>,
+	command_buffer: VecDeque<QueuedCommand<P>>,
 	/// This is the first command taken from the `command_buffer` queue and it's constantly executed. When the execution is finished
 	/// (it returns [`Status::Finished`]) this field becomes `None` and a new command is taken (if possible) from the `command_buffer`.
-	current_command: Option<Box<dyn GCodeCommand<P>>>,
+	current_command: Option<QueuedCommand<P>>,
 
 	current_command_being_executed_index: u32,
+	last_executed_file_command: Option<FileCommandState>,
 
 	position_mode: PositionMode,
 	extruder_position_mode: PositionMode,
@@ -39,6 +42,7 @@ impl<P: Peripherals> core::fmt::Debug for GCodeExecuter<P>
 			.field("command_buffer", &self.command_buffer.len())
 			.field("current_command", &self.current_command.is_some())
 			.field("current_command_index", &self.current_command_being_executed_index)
+			.field("last_executed_file_command", &self.last_executed_file_command)
 			.field("position_mode", &self.position_mode)
 			.field("extruder_position_mode", &self.extruder_position_mode)
 			.field("saved_positions", &self.saved_positions)
@@ -49,25 +53,56 @@ impl<P: Peripherals> core::fmt::Debug for GCodeExecuter<P>
 impl<P: Peripherals> GCodeExecuter<P>
 {
 	pub fn tick(&mut self, printer_components: &mut Printer3DComponents<P>) -> Result<(), TickError>
+	{
+		self.tick_commands(printer_components, true)
+	}
+
+	/// Ticks the executer, and if `prepare_commands` is `false` only the commands that have already been prepared are
+	/// executed.
+	fn tick_commands(
+		&mut self, printer_components: &mut Printer3DComponents<P>, prepare_commands: bool,
+	) -> Result<(), TickError>
 	{
 		print_process::set_commands_in_buffer_count(
 			(self.commands_to_prepare.len() + self.command_buffer.len()) as u16,
 		);
 
 		let mut prepare_another_command = true;
-		while prepare_another_command && (!self.commands_to_prepare.is_empty() || !self.command_buffer.is_empty())
+		while prepare_another_command && self.has_command_to_execute()
 		{
-			if let Some(mut command) = self.commands_to_prepare.pop_front()
+			if let Some(mut queued_command) = prepare_commands.then(|| self.commands_to_prepare.pop_front()).flatten()
 			{
-				match command.prepare(printer_components, self)
+				if queued_command.state_before_preparation.is_none()
+				{
+					queued_command.state_before_preparation = Some(PreparationState {
+						planning_state: printer_components.motion_controller.get_planning_state(),
+						position_mode: self.position_mode,
+						extruder_position_mode: self.extruder_position_mode,
+					});
+				}
+
+				match queued_command.command.prepare(printer_components, self)
 				{
 					Status::Working =>
 					{
-						self.commands_to_prepare.push_front(command);
+						self.commands_to_prepare.push_front(queued_command);
 
 						prepare_another_command = false;
 					},
-					Status::Finished => self.command_buffer.push_back(command),
+					Status::Finished =>
+					{
+						// The moves are planned while the commands are prepared, so this is the position the tool will
+						// be at when the command has been executed
+						queued_command.file_command_state =
+							queued_command.file_position.map(|file_position| FileCommandState {
+								file_position,
+								position: printer_components.motion_controller.get_position(),
+								feed_rate_mm_s: printer_components.motion_controller.get_feed_rate(),
+								position_mode: self.position_mode,
+								extruder_position_mode: self.extruder_position_mode,
+							});
+						self.command_buffer.push_back(queued_command);
+					},
 					Status::Error(error) => return Err(TickError::PreparingCommand { error }),
 				}
 			}
@@ -77,17 +112,17 @@ impl<P: Peripherals> GCodeExecuter<P>
 				self.current_command = self.command_buffer.pop_front();
 			}
 
-			if let Some(mut command) = self.current_command.take()
+			if let Some(mut queued_command) = self.current_command.take()
 			{
-				let status = command.execute(printer_components, self);
+				let status = queued_command.command.execute(printer_components, self);
 
 				match status
 				{
 					Status::Working =>
 					{
-						self.current_command = Some(command);
+						self.current_command = Some(queued_command);
 
-						if self.commands_to_prepare.is_empty()
+						if !prepare_commands || self.commands_to_prepare.is_empty()
 						{
 							prepare_another_command = false;
 						}
@@ -95,10 +130,66 @@ impl<P: Peripherals> GCodeExecuter<P>
 					Status::Finished =>
 					{
 						self.current_command_being_executed_index += 1;
+						if let Some(file_command_state) = queued_command.file_command_state
+						{
+							print_process::set_last_executed_file_position(file_command_state.file_position);
+							self.last_executed_file_command = Some(file_command_state);
+						}
 					},
 					Status::Error(error) => return Err(TickError::ExecutingCommand { error }),
 				}
 			}
+			else if !prepare_commands
+			{
+				prepare_another_command = false;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Makes the machine stop as soon as possible without losing any command: the commands whose moves have already
+	/// been sent to the stepper motors are executed, then the [`planned moves`] that haven't started yet are discarded
+	/// and the commands that planned them are moved back to the queue of the commands to prepare (restoring the state
+	/// the executer and the motion controller had before they were prepared).
+	///
+	/// The commands are prepared again (starting from the position where the machine has stopped) the next time this
+	/// executer is [`ticked`].
+	///
+	/// [`planned moves`]: crate::printer::components::motion::MotionController::plan_move
+	/// [`ticked`]: Self::tick
+	pub fn unprepare_commands(&mut self, printer_components: &mut Printer3DComponents<P>) -> Result<(), TickError>
+	{
+		self.tick_commands(printer_components, false)?;
+
+		printer_components.motion_controller.discard_planned_moves();
+
+		while let Some(queued_command) = self.command_buffer.pop_back()
+		{
+			self.commands_to_prepare.push_front(queued_command);
+		}
+		if let Some(queued_command) = self.current_command.take()
+		{
+			self.commands_to_prepare.push_front(queued_command);
+		}
+
+		for (index, queued_command) in self.commands_to_prepare.iter_mut().enumerate()
+		{
+			if let Some(state) = queued_command.state_before_preparation.take()
+			{
+				queued_command.command.unprepare();
+
+				// The commands are prepared again starting from the first one
+				if index == 0
+				{
+					printer_components
+						.motion_controller
+						.set_planning_state(state.planning_state);
+					self.position_mode = state.position_mode;
+					self.extruder_position_mode = state.extruder_position_mode;
+				}
+			}
+			queued_command.file_command_state = None;
 		}
 
 		Ok(())
@@ -111,6 +202,31 @@ impl<P: Peripherals> GCodeExecuter<P>
 		self.current_command.is_some() || !self.command_buffer.is_empty() || !self.commands_to_prepare.is_empty()
 	}
 
+	/// Returns `true` if at least 1 of the commands that haven't been executed yet has been [`read from a file`].
+	///
+	/// [`read from a file`]: Self::add_file_command_to_buffer
+	pub fn has_file_command_to_execute(&self) -> bool
+	{
+		self.current_command
+			.iter()
+			.chain(self.command_buffer.iter())
+			.chain(self.commands_to_prepare.iter())
+			.any(|queued_command| queued_command.file_position.is_some())
+	}
+
+	/// Removes all the commands from the executer (the one being executed is interrupted) and sets the position modes
+	/// back to [`PositionMode::Absolute`], so that the next added commands are executed as if the previous ones never
+	/// existed.
+	pub fn clear(&mut self)
+	{
+		self.commands_to_prepare.clear();
+		self.command_buffer.clear();
+		self.current_command = None;
+		self.last_executed_file_command = None;
+		self.position_mode = PositionMode::Absolute;
+		self.extruder_position_mode = PositionMode::Absolute;
+	}
+
 	pub fn set_units(&mut self, units: Units)
 	{
 		todo!("{:#?}", units)
@@ -154,12 +270,30 @@ impl<P: Peripherals> GCodeExecuter<P>
 
 	pub fn add_command_to_buffer(&mut self, command: Box<dyn GCodeCommand<P>>)
 	{
-		self.commands_to_prepare.push_back(command);
+		self.commands_to_prepare.push_back(QueuedCommand::new(command, None));
 	}
 
 	pub fn add_commands_to_buffer(&mut self, commands: Vec<Box<dyn GCodeCommand<P>>>)
 	{
-		self.commands_to_prepare.extend(commands);
+		self.commands_to_prepare
+			.extend(commands.into_iter().map(|command| QueuedCommand::new(command, None)));
+	}
+
+	/// Adds the provided `command` (that has been read from a file at `file_position`) to the buffer, so that when it
+	/// has been executed it's returned by [`Self::get_last_executed_file_command`].
+	pub fn add_file_command_to_buffer(&mut self, command: Box<dyn GCodeCommand<P>>, file_position: FilePosition)
+	{
+		self.commands_to_prepare
+			.push_back(QueuedCommand::new(command, Some(file_position)));
+	}
+
+	/// Returns the state of the machine after the execution of the last command [`read from a file`] that has been
+	/// executed, or `None` if a command read from a file has never been executed.
+	///
+	/// [`read from a file`]: Self::add_file_command_to_buffer
+	pub fn get_last_executed_file_command(&self) -> Option<&FileCommandState>
+	{
+		self.last_executed_file_command.as_ref()
 	}
 
 	/// Save the provided `position` at the specified `slot`, so that you can later retrieve it using [`Self::get_position(slot)`].
@@ -243,6 +377,7 @@ impl<P: Peripherals> Default for GCodeExecuter<P>
 			command_buffer: VecDeque::with_capacity(100),
 			current_command: Default::default(),
 			current_command_being_executed_index: 0,
+			last_executed_file_command: None,
 			position_mode: Default::default(),
 			extruder_position_mode: Default::default(),
 			saved_positions: Default::default(),
@@ -253,6 +388,69 @@ impl<P: Peripherals> Default for GCodeExecuter<P>
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct InvalidPositionSlot;
 
+/// A command added to a [`GCodeExecuter`].
+struct QueuedCommand<P: Peripherals>
+{
+	command: Box<dyn GCodeCommand<P>>,
+	/// Where the command is in the file it has been read from (it's `None` if it hasn't been read from a file).
+	file_position: Option<FilePosition>,
+	/// The state before the command started being prepared, which is restored if it has to be prepared again (check
+	/// [`GCodeExecuter::unprepare_commands`]). It's `None` if the command hasn't started being prepared yet.
+	state_before_preparation: Option<PreparationState>,
+	/// It's `Some` only if the command has been read from a file and it has been prepared.
+	file_command_state: Option<FileCommandState>,
+}
+
+impl<P: Peripherals> QueuedCommand<P>
+{
+	fn new(command: Box<dyn GCodeCommand<P>>, file_position: Option<FilePosition>) -> Self
+	{
+		Self {
+			command,
+			file_position,
+			state_before_preparation: None,
+			file_command_state: None,
+		}
+	}
+}
+
+/// The state of a [`GCodeExecuter`] and of the [`MotionController`] that determines how the next commands are
+/// prepared.
+///
+/// [`MotionController`]: crate::printer::components::motion::MotionController
+struct PreparationState
+{
+	planning_state: PlanningState,
+	position_mode: PositionMode,
+	extruder_position_mode: PositionMode,
+}
+
+/// Where a G-code command is in the file it has been read from.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FilePosition
+{
+	pub file_id: FileId,
+	/// Index of the first byte of the file after the line that contains the command (so the file must be read from
+	/// here to execute the commands that come after it).
+	pub offset: u32,
+}
+
+/// The state of the machine right after a command read from a file has been executed (check
+/// [`GCodeExecuter::get_last_executed_file_command`]).
+#[derive(Debug, PartialEq, Clone)]
+pub struct FileCommandState
+{
+	pub file_position: FilePosition,
+	/// The position of the tool in the coordinate system of the G-code (check [`MotionController::get_position`]).
+	///
+	/// [`MotionController::get_position`]: crate::printer::components::motion::MotionController::get_position
+	pub position: VectorN<4>,
+	/// The speed of the next moves that don't specify their feed rate.
+	pub feed_rate_mm_s: f32,
+	pub position_mode: PositionMode,
+	pub extruder_position_mode: PositionMode,
+}
+
 #[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum PositionMode
 {
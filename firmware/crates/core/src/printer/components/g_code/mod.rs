@@ -36,6 +36,11 @@ pub trait GCodeCommand<P: Peripherals>: Send + Sync + Debug + AsAny<P>
 	{
 		Status::Finished
 	}
+
+	/// This method is called when the moves planned while preparing the G-code command have been discarded, before
+	/// the command is prepared again (check [`GCodeExecuter::unprepare_commands`]).
+	/// The command must forget what it has done in [`Self::prepare`], but not what it has done in [`Self::execute`].
+	fn unprepare(&mut self) {}
 }
 
 /// This trait is only used for testing purposes, and it's automatically implemented by every [`GCodeCommand`].
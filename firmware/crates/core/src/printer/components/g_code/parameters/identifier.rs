@@ -51,7 +51,7 @@ macro_rules! impl_g_code_letter_identifiers {
     };
 }
 
-impl_g_code_letter_identifiers!(E, F, P, R, S, X, Y, Z);
+impl_g_code_letter_identifiers!(E, F, I, J, K, P, R, S, X, Y, Z);
 
 #[derive(Debug, Clone, Copy)]
 /// A list of identifiers that could be present in the string of the parameter in any order.
@@ -157,6 +157,40 @@ pub enum GCodeLine<'a, P: Peripherals>
 	Error,
 }
 
+/// A command that is handled as soon as it's received (for example with the `/v1/gcode-commands` HTTP request),
+/// instead of waiting for the commands that have been received before it to be prepared.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImmediateCommand
+{
+	/// [`M112`], which halts the machine.
+	EmergencyStop,
+	/// [`M524`], which cancels the print in execution.
+	CancelPrint,
+}
+
+impl ImmediateCommand
+{
+	/// Returns the immediate command contained in `line`, or `None` if the line contains another command (or none).
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::g_code::parser::ImmediateCommand;
+	/// #
+	/// assert_eq!(ImmediateCommand::parse("M112 ;Stop!"), Some(ImmediateCommand::EmergencyStop));
+	/// assert_eq!(ImmediateCommand::parse("G28"), None);
+	/// ```
+	pub fn parse(line: &str) -> Option<Self>
+	{
+		let line_without_comment = line.split(';').next().unwrap_or_default();
+		match line_without_comment.split_whitespace().next()?
+		{
+			"M112" => Some(Self::EmergencyStop),
+			"M524" => Some(Self::CancelPrint),
+			_ => None,
+		}
+	}
+}
+
 /// **This function is generated at compile time by the build.rs file and you shouldn't touch it.**
 fn parse<P: Peripherals>(command: &str, parameters: SplitWhitespace, units: Units) -> Option<Box<dyn GCodeCommand<P>>>
 {
@@ -179,3 +213,44 @@ fn find_identifier<'a, I: GCodeParameterIdentifier>(words: SplitWhitespace<'a>)
 
 	None
 }
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn emergency_stop_is_an_immediate_command()
+	{
+		assert_eq!(ImmediateCommand::parse("M112"), Some(ImmediateCommand::EmergencyStop));
+		assert_eq!(
+			ImmediateCommand::parse("  M112  "),
+			Some(ImmediateCommand::EmergencyStop)
+		);
+		assert_eq!(
+			ImmediateCommand::parse("M112;Something went wrong"),
+			Some(ImmediateCommand::EmergencyStop)
+		);
+	}
+
+	#[test]
+	fn cancel_print_is_an_immediate_command()
+	{
+		assert_eq!(ImmediateCommand::parse("M524"), Some(ImmediateCommand::CancelPrint));
+		assert_eq!(
+			ImmediateCommand::parse("M524 ;Cancel"),
+			Some(ImmediateCommand::CancelPrint)
+		);
+	}
+
+	#[test]
+	fn other_lines_arent_immediate_commands()
+	{
+		assert_eq!(ImmediateCommand::parse(""), None);
+		assert_eq!(ImmediateCommand::parse(";M112"), None);
+		assert_eq!(ImmediateCommand::parse("M1120"), None);
+		assert_eq!(ImmediateCommand::parse("G1 X10 ;M112"), None);
+		assert_eq!(ImmediateCommand::parse("M104 S200"), None);
+		assert_eq!(ImmediateCommand::parse("M5240"), None);
+	}
+}
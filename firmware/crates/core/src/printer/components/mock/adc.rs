@@ -1,31 +1,134 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use spin::Mutex;
+
 use crate::{
 	printer::components::hal::adc::{Adc, AdcPin},
 	utils::math::Percentage,
 };
 
-pub struct MockAdcPin;
+/// An [`AdcPin`] that returns scripted values.
+///
+/// The values [`pushed`] are returned (in order) by the next reads, and when there are no more of them the pin keeps
+/// returning the last [`set value`] (or the last pushed one). If no value has ever been provided, the read fails.
+///
+/// Cloning a `MockAdcPin` returns a handle to the same pin, so you can keep a clone to change the values read after
+/// you gave the pin to the code under test.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::printer::components::{mock::*, hal::adc::*};
+/// #
+/// let mut adc = MockAdc::default();
+/// let mut pin = MockAdcPin::default();
+/// assert!(pin.read(&mut adc).is_err());
+///
+/// pin.set_value(100);
+/// pin.push_values([1, 2]);
+///
+/// assert_eq!(pin.read(&mut adc).unwrap(), MockAdcReading(1));
+/// assert_eq!(pin.read(&mut adc).unwrap(), MockAdcReading(2));
+/// assert_eq!(pin.read(&mut adc).unwrap(), MockAdcReading(2));
+///
+/// pin.set_value(100);
+/// assert_eq!(pin.read(&mut adc).unwrap(), MockAdcReading(100));
+/// assert_eq!(pin.read_count(), 4);
+/// ```
+///
+/// [`pushed`]: Self::push_values
+/// [`set value`]: Self::set_value
+#[derive(Clone, Default)]
+pub struct MockAdcPin
+{
+	state: Arc<Mutex<MockAdcPinState>>,
+}
+
+#[derive(Default)]
+struct MockAdcPinState
+{
+	scripted_values: VecDeque<u16>,
+	value: Option<u16>,
+	read_count: usize,
+}
+
+impl MockAdcPin
+{
+	/// Makes all the next reads return `value` (after the values [`pushed`] before, if there are any).
+	///
+	/// [`pushed`]: Self::push_values
+	pub fn set_value(&mut self, value: u16)
+	{
+		let mut state = self.state.lock();
+		state.scripted_values.clear();
+		state.value = Some(value);
+	}
+
+	/// Adds the `values` to the ones that will be returned by the next reads.
+	pub fn push_values(&mut self, values: impl IntoIterator<Item = u16>)
+	{
+		self.state.lock().scripted_values.extend(values);
+	}
+
+	/// Returns how many times the pin has been read successfully.
+	pub fn read_count(&self) -> usize
+	{
+		self.state.lock().read_count
+	}
+}
+
 impl AdcPin<MockAdc> for MockAdcPin
 {
 	type Error = ();
 
 	fn read(&mut self, _: &mut MockAdc) -> Result<<MockAdc as Adc>::ReadableValue, Self::Error>
 	{
-		todo!()
+		let mut state = self.state.lock();
+		if let Some(value) = state.scripted_values.pop_front()
+		{
+			state.value = Some(value);
+		}
+		let value = state.value.ok_or(())?;
+		state.read_count += 1;
+
+		Ok(MockAdcReading(value))
+	}
+}
+
+/// An [`Adc`] whose readings go from `0` to the `max_readable_value` provided to [`MockAdc::new`] (by default it's a
+/// 12 bit ADC).
+pub struct MockAdc
+{
+	max_readable_value: u16,
+}
+
+impl MockAdc
+{
+	pub fn new(max_readable_value: u16) -> Self
+	{
+		Self { max_readable_value }
+	}
+}
+
+impl Default for MockAdc
+{
+	fn default() -> Self
+	{
+		Self::new(4095)
 	}
 }
 
-pub struct MockAdc;
 impl Adc for MockAdc
 {
 	type ReadableValue = MockAdcReading;
 
 	fn max_readable_value(&self) -> Self::ReadableValue
 	{
-		todo!()
+		MockAdcReading(self.max_readable_value)
 	}
 }
 
-pub struct MockAdcReading(u16);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MockAdcReading(pub u16);
 impl std::ops::Div<MockAdcReading> for MockAdcReading
 {
 	type Output = Result<Percentage, ()>;
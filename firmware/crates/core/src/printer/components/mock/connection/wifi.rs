@@ -1,5 +1,5 @@
 use embedded_svc::{
-	http::{server::Connection, Headers, Query},
+	http::{server::Connection, Headers, Method, Query},
 	io::{ErrorType, Read, Write},
 	wifi::asynch::Wifi,
 };
@@ -94,12 +94,51 @@ impl HttpServer for MockHttpServer
 	}
 }
 
-pub struct MockHttpConnection;
+/// A [`Connection`] that receives the request provided in [`new`](Self::new) and records the response sent to it.
+pub struct MockHttpConnection
+{
+	headers: MockHeaders,
+	body: MockRequestBody,
+	response_status: Option<u16>,
+	response_body: Vec<u8>,
+}
+impl MockHttpConnection
+{
+	/// Returns a connection that receives a request with the provided `method`, `uri`, `headers` and `body`.
+	pub fn new(method: Method, uri: &str, headers: &[(&str, &str)], body: &[u8]) -> Self
+	{
+		Self {
+			headers: MockHeaders {
+				method,
+				uri: uri.to_string(),
+				headers: headers
+					.iter()
+					.map(|(name, value)| (name.to_string(), value.to_string()))
+					.collect(),
+			},
+			body: MockRequestBody(body.to_vec()),
+			response_status: None,
+			response_body: Vec::new(),
+		}
+	}
+
+	/// Returns the status of the response, or `None` if the response hasn't been initiated.
+	pub fn get_response_status(&self) -> Option<u16>
+	{
+		self.response_status
+	}
+
+	/// Returns the bytes written in the body of the response.
+	pub fn get_response_body(&self) -> &[u8]
+	{
+		&self.response_body
+	}
+}
 impl Connection for MockHttpConnection
 {
 	type Headers = MockHeaders;
 
-	type Read = Self;
+	type Read = MockRequestBody;
 
 	type RawConnectionError = MockError;
 
@@ -107,62 +146,71 @@ impl Connection for MockHttpConnection
 
 	fn split(&mut self) -> (&Self::Headers, &mut Self::Read)
 	{
-		todo!()
+		(&self.headers, &mut self.body)
 	}
 
 	fn initiate_response<'a>(
-		&'a mut self, status: u16, message: Option<&'a str>, headers: &'a [(&'a str, &'a str)],
+		&'a mut self, status: u16, _: Option<&'a str>, _: &'a [(&'a str, &'a str)],
 	) -> Result<(), Self::Error>
 	{
-		todo!()
+		self.response_status = Some(status);
+
+		Ok(())
 	}
 
 	fn is_response_initiated(&self) -> bool
 	{
-		todo!()
+		self.response_status.is_some()
 	}
 
 	fn raw_connection(&mut self) -> Result<&mut Self::RawConnection, Self::Error>
 	{
-		todo!()
+		Ok(self)
 	}
 }
 impl Headers for MockHttpConnection
 {
 	fn header(&self, name: &str) -> Option<&'_ str>
 	{
-		todo!()
+		self.headers.header(name)
 	}
 }
 impl Query for MockHttpConnection
 {
 	fn uri(&self) -> &'_ str
 	{
-		todo!()
+		self.headers.uri()
 	}
 
-	fn method(&self) -> embedded_svc::http::Method
+	fn method(&self) -> Method
 	{
-		todo!()
+		self.headers.method()
 	}
 }
 impl Read for MockHttpConnection
 {
 	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>
 	{
-		todo!()
+		self.body.read(buf)
 	}
 }
 impl Write for MockHttpConnection
 {
 	fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error>
 	{
-		todo!()
+		if !self.is_response_initiated()
+		{
+			return Err(MockError);
+		}
+
+		self.response_body.extend_from_slice(buf);
+
+		Ok(buf.len())
 	}
 
 	fn flush(&mut self) -> Result<(), Self::Error>
 	{
-		todo!()
+		Ok(())
 	}
 }
 impl ErrorType for MockHttpConnection
@@ -170,23 +218,49 @@ impl ErrorType for MockHttpConnection
 	type Error = MockError;
 }
 
-pub struct MockHeaders;
+pub struct MockHeaders
+{
+	method: Method,
+	uri: String,
+	headers: Vec<(String, String)>,
+}
 impl Headers for MockHeaders
 {
 	fn header(&self, name: &str) -> Option<&'_ str>
 	{
-		todo!()
+		self.headers
+			.iter()
+			.find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
 	}
 }
 impl Query for MockHeaders
 {
 	fn uri(&self) -> &'_ str
 	{
-		todo!()
+		&self.uri
 	}
 
-	fn method(&self) -> embedded_svc::http::Method
+	fn method(&self) -> Method
 	{
-		todo!()
+		self.method
 	}
 }
+
+/// The body of the request received by a [`MockHttpConnection`], which is consumed while it's read.
+pub struct MockRequestBody(Vec<u8>);
+impl Read for MockRequestBody
+{
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>
+	{
+		let read_bytes_count = buf.len().min(self.0.len());
+		buf[..read_bytes_count].copy_from_slice(&self.0[..read_bytes_count]);
+		self.0.drain(..read_bytes_count);
+
+		Ok(read_bytes_count)
+	}
+}
+impl ErrorType for MockRequestBody
+{
+	type Error = MockError;
+}
@@ -0,0 +1,607 @@
+use std::{
+	collections::{HashMap, HashSet},
+	fs::{File, OpenOptions},
+	io::{Read, Seek, SeekFrom, Write},
+	marker::PhantomData,
+	path::Path,
+	sync::Arc,
+};
+
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+use spin::Mutex;
+
+use super::MockError;
+use crate::printer::components::drivers::spi_flash_memory::{FeatureRegister, FlashMemoryChip};
+
+/// A [`SpiDevice`] that emulates a SPI NAND flash memory with the geometry of `Chip` and the command set of the
+/// [`MT29F2G01ABAGDWB`] (the one sent by [`SpiFlashMemory`]).
+///
+/// The memory follows the rules of a real NAND flash:
+/// - a page is read in a cache before its bytes can be read, and it's programmed from that cache (each plane has its
+///   own cache, so a page can be moved inside the memory only to another page of the same plane);
+/// - programming can only change bits from `1` to `0`, so you need to erase a block (setting all its bits to `1`)
+///   before you can write new data to it;
+/// - programming and erasing require the write enable latch to be set, and they are ignored while the blocks are locked
+///   (all of them are locked at power up until the `BlockLock` feature register is cleared);
+/// - the status register reports the result of the last program or erase operation and the ECC status of the last page
+///   read.
+///
+/// You can also simulate the defects of a real memory, marking some blocks as [`bad`] and [`flipping`] some bits, and
+/// [`cut the power`] while the memory is being programmed or erased.
+///
+/// The content of the memory is either kept in RAM (a memory created with [`Default::default`]) or stored in a
+/// [`file`]. Cloning a `MockFlashMemory` returns a handle to the same memory, so you can keep a clone to inspect (or
+/// damage) the memory after you gave it to the code under test.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::printer::components::{mock::*, drivers::spi_flash_memory::*};
+/// #
+/// let memory = MockFlashMemory::<MT29F2G01ABAGDWB>::default();
+/// let mut spi_flash_memory = SpiFlashMemory::new(memory.clone(), MT29F2G01ABAGDWB);
+/// MT29F2G01ABAGDWB::initialize(&mut spi_flash_memory).unwrap();
+///
+/// let mut data = [0; 4];
+/// spi_flash_memory.read(0, &mut data).unwrap();
+/// assert_eq!(data, [0xFF; 4]);
+///
+/// spi_flash_memory.program(&[0b1010, 0b1100, 0, 0xFF], 0).unwrap();
+/// // Programming can only clear bits
+/// spi_flash_memory.program(&[0b0110, 0xFF, 0xFF, 0x0F], 0).unwrap();
+/// spi_flash_memory.read(0, &mut data).unwrap();
+/// assert_eq!(data, [0b0010, 0b1100, 0, 0x0F]);
+///
+/// spi_flash_memory.erase_blocks(0..=0).unwrap();
+/// spi_flash_memory.read(0, &mut data).unwrap();
+/// assert_eq!(data, [0xFF; 4]);
+/// assert_eq!(memory.get_page(0)[..4], [0xFF; 4]);
+/// ```
+///
+/// [`MT29F2G01ABAGDWB`]: crate::printer::components::drivers::spi_flash_memory::MT29F2G01ABAGDWB
+/// [`SpiFlashMemory`]: crate::printer::components::drivers::spi_flash_memory::SpiFlashMemory
+/// [`bad`]: Self::mark_block_as_bad
+/// [`flipping`]: Self::inject_bit_flips
+/// [`cut the power`]: Self::cut_power_after
+/// [`file`]: Self::from_file
+pub struct MockFlashMemory<Chip: FlashMemoryChip>
+{
+	state: Arc<Mutex<MockFlashMemoryState>>,
+	_chip: PhantomData<Chip>,
+}
+
+struct MockFlashMemoryState
+{
+	storage: Storage,
+	full_page_size: usize,
+	pages_per_block: u32,
+	caches: Vec<Vec<u8>>,
+	block_lock: u8,
+	configuration: u8,
+	status: u8,
+	bad_blocks: HashSet<u32>,
+	bit_flips: HashMap<u32, Vec<u32>>,
+	program_and_erase_count: usize,
+	power_loss_at_operation: Option<usize>,
+}
+
+impl<Chip: FlashMemoryChip> MockFlashMemory<Chip>
+{
+	const FULL_PAGE_SIZE: usize = (Chip::PAGE_SIZE + Chip::PAGE_ECC_SIZE) as usize;
+
+	/// Returns a memory whose content is stored in the file at `path` (which is created if it doesn't exist), so that
+	/// it's still there the next time you open the same file.
+	///
+	/// The bytes are stored inverted in the file, so that an empty (or sparse) file is a completely erased memory.
+	pub fn from_file(path: impl AsRef<Path>) -> std::io::Result<Self>
+	{
+		let file = OpenOptions::new()
+			.read(true)
+			.write(true)
+			.create(true)
+			.truncate(false)
+			.open(path)?;
+		Ok(Self::new(Storage::File(file)))
+	}
+
+	/// Marks the block at `block_index` as bad, like the manufacturer does before shipping the chip: the first byte of
+	/// the ECC area of the first page of the block is set to `0`, and from now on programming and erasing that block
+	/// will fail (leaving its content untouched).
+	///
+	/// You can call this at any time to simulate a block that wears out while the memory is being used.
+	pub fn mark_block_as_bad(&mut self, block_index: u16)
+	{
+		let mut state = self.state.lock();
+		let first_page_index = block_index as u32 * Chip::PAGES_PER_BLOCK;
+		let mut page = state.read_page(first_page_index);
+		page[Chip::PAGE_SIZE as usize] = 0;
+		state.write_page(first_page_index, &page);
+		state.bad_blocks.insert(block_index as u32);
+	}
+
+	/// Flips the bits at `bits_indices` (counted from the first bit of the data area, the most significant bit of a
+	/// byte first) of the page at `page_index` each time the page is read, until its block is erased.
+	///
+	/// While the ECC of the memory is enabled (it is by default) up to 8 flipped bits per page are corrected, and the
+	/// status register reports how many bits have been corrected (or that they couldn't be corrected).
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::{mock::*, drivers::spi_flash_memory::*};
+	/// #
+	/// let mut memory = MockFlashMemory::<MT29F2G01ABAGDWB>::default();
+	/// let mut spi_flash_memory = SpiFlashMemory::new(memory.clone(), MT29F2G01ABAGDWB);
+	/// let ecc_status = |spi_flash_memory: &mut SpiFlashMemory<_, _>| {
+	///     (spi_flash_memory.get_features(FeatureRegister::Status).unwrap() >> 4) & 0b111
+	/// };
+	///
+	/// let mut data = [0; 2];
+	/// memory.inject_bit_flips(0, [0, 9]);
+	/// spi_flash_memory.read(0, &mut data).unwrap();
+	/// assert_eq!(data, [0xFF, 0xFF]);
+	/// assert_eq!(ecc_status(&mut spi_flash_memory), 0b001);
+	///
+	/// memory.inject_bit_flips(0, 10..20);
+	/// spi_flash_memory.read(0, &mut data).unwrap();
+	/// assert_eq!(data, [0b0111_1111, 0b1000_0000]);
+	/// assert_eq!(ecc_status(&mut spi_flash_memory), 0b010);
+	/// ```
+	pub fn inject_bit_flips(&mut self, page_index: u32, bits_indices: impl IntoIterator<Item = u32>)
+	{
+		self.state
+			.lock()
+			.bit_flips
+			.entry(page_index)
+			.or_default()
+			.extend(bits_indices);
+	}
+
+	/// Simulates a power loss that happens after `operations_count` more program or erase operations: the operation in
+	/// progress when the power is lost is only half done (half of the page is programmed, or half of the pages of the
+	/// block are erased), and all the following program and erase operations are ignored until you
+	/// [`restore the power`].
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::{mock::*, drivers::spi_flash_memory::*};
+	/// #
+	/// let mut memory = MockFlashMemory::<MT29F2G01ABAGDWB>::default();
+	/// let mut spi_flash_memory = SpiFlashMemory::new(memory.clone(), MT29F2G01ABAGDWB);
+	/// MT29F2G01ABAGDWB::initialize(&mut spi_flash_memory).unwrap();
+	///
+	/// memory.cut_power_after(1);
+	/// spi_flash_memory.program(&[0; 2048], 0).unwrap();
+	/// spi_flash_memory.program(&[0; 2048], 2048).unwrap();
+	/// spi_flash_memory.program(&[0; 2048], 4096).unwrap();
+	/// memory.restore_power();
+	///
+	/// assert!(memory.get_page(0)[..2048].iter().all(|&byte| byte == 0));
+	/// // Half of the page (data and ECC area) has been programmed
+	/// assert_eq!(memory.get_page(1)[1087..1089], [0, 0xFF]);
+	/// assert!(memory.get_page(2)[..2048].iter().all(|&byte| byte == 0xFF));
+	/// ```
+	///
+	/// [`restore the power`]: Self::restore_power
+	pub fn cut_power_after(&mut self, operations_count: usize)
+	{
+		let mut state = self.state.lock();
+		state.power_loss_at_operation = Some(state.program_and_erase_count + operations_count);
+	}
+
+	/// Powers the memory again after you [`cut the power`], leaving it in the same state it's in at power up (with all
+	/// the blocks locked).
+	///
+	/// [`cut the power`]: Self::cut_power_after
+	pub fn restore_power(&mut self)
+	{
+		let mut state = self.state.lock();
+		state.power_loss_at_operation = None;
+		state.block_lock = MockFlashMemoryState::BLOCK_LOCK_AT_POWER_UP;
+		state.configuration = MockFlashMemoryState::ECC_ENABLED;
+		state.status = 0;
+		state.caches.iter_mut().for_each(|cache| cache.fill(0xFF));
+	}
+
+	/// Returns how many program and erase operations have been done on the memory since it has been created (including
+	/// the ones ignored because the power was cut).
+	pub fn get_program_and_erase_count(&self) -> usize
+	{
+		self.state.lock().program_and_erase_count
+	}
+
+	/// Returns the bytes stored in the page at `page_index` (its data area followed by its ECC area), without the
+	/// [`flipped bits`].
+	///
+	/// [`flipped bits`]: Self::inject_bit_flips
+	pub fn get_page(&self, page_index: u32) -> Vec<u8>
+	{
+		self.state.lock().read_page(page_index)
+	}
+
+	fn new(storage: Storage) -> Self
+	{
+		Self {
+			state: Arc::new(Mutex::new(MockFlashMemoryState {
+				storage,
+				full_page_size: Self::FULL_PAGE_SIZE,
+				pages_per_block: Chip::PAGES_PER_BLOCK,
+				caches: vec![vec![0xFF; Self::FULL_PAGE_SIZE]; Chip::PLANES_PER_LUN as usize],
+				block_lock: MockFlashMemoryState::BLOCK_LOCK_AT_POWER_UP,
+				configuration: MockFlashMemoryState::ECC_ENABLED,
+				status: 0,
+				bad_blocks: HashSet::new(),
+				bit_flips: HashMap::new(),
+				program_and_erase_count: 0,
+				power_loss_at_operation: None,
+			})),
+			_chip: PhantomData,
+		}
+	}
+}
+
+impl MockFlashMemoryState
+{
+	/// All the blocks are locked (`BP0`, `BP1` and `BP2` bits set).
+	const BLOCK_LOCK_AT_POWER_UP: u8 = 0b0011_1000;
+	const BLOCK_PROTECTION_BITS: u8 = 0b0111_1000;
+	const ECC_ENABLED: u8 = 0b0001_0000;
+
+	const WRITE_ENABLE_LATCH: u8 = 0b0000_0010;
+	const ERASE_FAIL: u8 = 0b0000_0100;
+	const PROGRAM_FAIL: u8 = 0b0000_1000;
+	const ECC_STATUS: u8 = 0b0111_0000;
+
+	/// Highest number of flipped bits in a page the ECC is able to correct.
+	const MAX_CORRECTABLE_BIT_FLIPS: usize = 8;
+
+	fn execute(&mut self, op_code: u8, arguments: &[u8], output: &mut Vec<u8>)
+	{
+		match op_code
+		{
+			// Reset
+			0xFF => self.status = 0,
+			// Write enable
+			0x06 => self.status |= Self::WRITE_ENABLE_LATCH,
+			// Write disable
+			0x04 => self.status &= !Self::WRITE_ENABLE_LATCH,
+			// Page read
+			0x13 => self.read_page_into_cache(Self::row_address(arguments)),
+			// Read from cache (the first byte after the column address is a dummy one)
+			0x03 =>
+			{
+				let (plane_index, column) = Self::column_address(arguments);
+				let cache = &self.caches[plane_index % self.caches.len()];
+				output.extend_from_slice(&cache[column.min(cache.len())..]);
+			},
+			// Program load
+			0x02 =>
+			{
+				let (plane_index, _) = Self::column_address(arguments);
+				let plane_count = self.caches.len();
+				self.caches[plane_index % plane_count].fill(0xFF);
+				self.load_into_cache(arguments);
+			},
+			// Program load random data
+			0x84 => self.load_into_cache(arguments),
+			// Program execute
+			0x10 =>
+			{
+				let page_index = Self::row_address(arguments);
+				if let Some(completion) =
+					self.start_program_or_erase(page_index / self.pages_per_block, Self::PROGRAM_FAIL)
+				{
+					let mut page = self.read_page(page_index);
+					let cache = &self.caches[self.plane_of_page(page_index)];
+					let programmed_bytes_count = (page.len() as f32 * completion) as usize;
+					for (byte, cache_byte) in page.iter_mut().zip(cache).take(programmed_bytes_count)
+					{
+						*byte &= cache_byte;
+					}
+					self.write_page(page_index, &page);
+				}
+			},
+			// Block erase
+			0xD8 =>
+			{
+				let block_index = Self::row_address(arguments) / self.pages_per_block;
+				if let Some(completion) = self.start_program_or_erase(block_index, Self::ERASE_FAIL)
+				{
+					let erased_page = vec![0xFF; self.full_page_size];
+					let erased_pages_count = (self.pages_per_block as f32 * completion) as u32;
+					let first_page_index = block_index * self.pages_per_block;
+					for page_index in first_page_index..(first_page_index + erased_pages_count)
+					{
+						self.write_page(page_index, &erased_page);
+						self.bit_flips.remove(&page_index);
+					}
+				}
+			},
+			// Get features
+			0x0F =>
+			{
+				let address = arguments.first().copied().unwrap_or_default();
+				output.push(match address
+				{
+					_ if address == FeatureRegister::BlockLock.address() => self.block_lock,
+					_ if address == FeatureRegister::Configuration.address() => self.configuration,
+					// No operation is ever in progress, because each command is executed immediately
+					_ if address == FeatureRegister::Status.address() => self.status,
+					_ => 0,
+				});
+			},
+			// Set features
+			0x1F =>
+			{
+				if let [address, value, ..] = *arguments
+				{
+					match address
+					{
+						_ if address == FeatureRegister::BlockLock.address() => self.block_lock = value,
+						_ if address == FeatureRegister::Configuration.address() => self.configuration = value,
+						// The status register is read only
+						_ => (),
+					}
+				}
+			},
+			_ => log::warn!("The mock flash memory received the unknown command {op_code:#04X}"),
+		}
+	}
+
+	/// Returns the fraction (between `0` and `1`) of the program or erase operation on the block at `block_index` that
+	/// can be done before the power is lost (`1` if the power isn't lost), or `None` if the operation can't be done (and
+	/// in that case sets the `fail_flag` in the status register if the operation failed).
+	fn start_program_or_erase(&mut self, block_index: u32, fail_flag: u8) -> Option<f32>
+	{
+		let is_write_enabled = (self.status & Self::WRITE_ENABLE_LATCH) != 0;
+		self.status &= !(Self::WRITE_ENABLE_LATCH | Self::ERASE_FAIL | Self::PROGRAM_FAIL);
+
+		// A command sent without enabling the writes is ignored
+		if !is_write_enabled
+		{
+			return None;
+		}
+
+		let is_locked = (self.block_lock & Self::BLOCK_PROTECTION_BITS) != 0;
+		if is_locked || self.bad_blocks.contains(&block_index)
+		{
+			self.status |= fail_flag;
+			return None;
+		}
+
+		let operation_index = self.program_and_erase_count;
+		self.program_and_erase_count += 1;
+		match self.power_loss_at_operation
+		{
+			Some(power_loss_at_operation) if operation_index > power_loss_at_operation => None,
+			Some(power_loss_at_operation) if operation_index == power_loss_at_operation => Some(0.5),
+			_ => Some(1.),
+		}
+	}
+
+	fn read_page_into_cache(&mut self, page_index: u32)
+	{
+		let plane_index = self.plane_of_page(page_index);
+		self.caches[plane_index] = self.read_page(page_index);
+
+		let bit_flips = self.bit_flips.get(&page_index).map(Vec::as_slice).unwrap_or_default();
+		let is_ecc_enabled = (self.configuration & Self::ECC_ENABLED) != 0;
+		let ecc_status: u8 = match (is_ecc_enabled, bit_flips.len())
+		{
+			(false, _) | (true, 0) => 0b000,
+			(true, 1..=3) => 0b001,
+			(true, 4..=6) => 0b011,
+			(true, 7..=Self::MAX_CORRECTABLE_BIT_FLIPS) => 0b101,
+			(true, _) => 0b010,
+		};
+		self.status = (self.status & !Self::ECC_STATUS) | (ecc_status << 4);
+
+		// The ECC corrects the flipped bits unless they are too many
+		if !is_ecc_enabled || bit_flips.len() > Self::MAX_CORRECTABLE_BIT_FLIPS
+		{
+			for &bit_index in bit_flips
+			{
+				if let Some(byte) = self.caches[plane_index].get_mut(bit_index as usize / 8)
+				{
+					*byte ^= 0b1000_0000 >> (bit_index % 8);
+				}
+			}
+		}
+	}
+
+	fn load_into_cache(&mut self, arguments: &[u8])
+	{
+		let (plane_index, column) = Self::column_address(arguments);
+		let plane_count = self.caches.len();
+		let cache = &mut self.caches[plane_index % plane_count];
+		let column = column.min(cache.len());
+		let data = &arguments[2.min(arguments.len())..];
+		let length = data.len().min(cache.len() - column);
+		cache[column..(column + length)].copy_from_slice(&data[..length]);
+	}
+
+	fn plane_of_page(&self, page_index: u32) -> usize
+	{
+		(page_index / self.pages_per_block) as usize % self.caches.len()
+	}
+
+	fn read_page(&mut self, page_index: u32) -> Vec<u8>
+	{
+		self.storage
+			.read_page(page_index, self.full_page_size)
+			.unwrap_or_else(|error| {
+				log::error!("Couldn't read the page {page_index} of the mock flash memory: {error}");
+				vec![0xFF; self.full_page_size]
+			})
+	}
+
+	fn write_page(&mut self, page_index: u32, page: &[u8])
+	{
+		if let Err(error) = self.storage.write_page(page_index, page)
+		{
+			log::error!("Couldn't write the page {page_index} of the mock flash memory: {error}");
+		}
+	}
+
+	fn row_address(arguments: &[u8]) -> u32
+	{
+		match *arguments
+		{
+			[high, middle, low, ..] => u32::from_be_bytes([0, high, middle, low]),
+			_ => 0,
+		}
+	}
+
+	/// Returns the index of the plane and the column address.
+	fn column_address(arguments: &[u8]) -> (usize, usize)
+	{
+		match *arguments
+		{
+			// The 13th bit selects the plane
+			[high, low, ..] =>
+			{
+				let address = u16::from_be_bytes([high, low]);
+				((address >> 12) as usize, (address & 0x0FFF) as usize)
+			},
+			_ => (0, 0),
+		}
+	}
+}
+
+/// Where the pages of a [`MockFlashMemory`] are stored.
+enum Storage
+{
+	/// Only the pages that aren't erased are stored.
+	Ram(HashMap<u32, Vec<u8>>),
+	/// The pages are stored inverted one after the other.
+	File(File),
+}
+
+impl Storage
+{
+	fn read_page(&mut self, page_index: u32, full_page_size: usize) -> std::io::Result<Vec<u8>>
+	{
+		match self
+		{
+			Storage::Ram(pages) => Ok(pages
+				.get(&page_index)
+				.cloned()
+				.unwrap_or_else(|| vec![0xFF; full_page_size])),
+			Storage::File(file) =>
+			{
+				let mut page = vec![0; full_page_size];
+				file.seek(SeekFrom::Start(page_index as u64 * full_page_size as u64))?;
+
+				// The part of the page after the end of the file is erased
+				let mut read_bytes_count = 0;
+				while read_bytes_count < page.len()
+				{
+					match file.read(&mut page[read_bytes_count..])?
+					{
+						0 => break,
+						count => read_bytes_count += count,
+					}
+				}
+
+				page.iter_mut().for_each(|byte| *byte = !*byte);
+				Ok(page)
+			},
+		}
+	}
+
+	fn write_page(&mut self, page_index: u32, page: &[u8]) -> std::io::Result<()>
+	{
+		match self
+		{
+			Storage::Ram(pages) =>
+			{
+				match page.iter().all(|&byte| byte == 0xFF)
+				{
+					true => pages.remove(&page_index),
+					false => pages.insert(page_index, page.to_vec()),
+				};
+				Ok(())
+			},
+			Storage::File(file) =>
+			{
+				let inverted_page: Vec<u8> = page.iter().map(|byte| !byte).collect();
+				file.seek(SeekFrom::Start(page_index as u64 * page.len() as u64))?;
+				file.write_all(&inverted_page)
+			},
+		}
+	}
+}
+
+impl<Chip: FlashMemoryChip> Default for MockFlashMemory<Chip>
+{
+	fn default() -> Self
+	{
+		Self::new(Storage::Ram(HashMap::new()))
+	}
+}
+
+impl<Chip: FlashMemoryChip> Clone for MockFlashMemory<Chip>
+{
+	fn clone(&self) -> Self
+	{
+		Self {
+			state: Arc::clone(&self.state),
+			_chip: PhantomData,
+		}
+	}
+}
+
+impl<Chip: FlashMemoryChip> std::fmt::Debug for MockFlashMemory<Chip>
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		let state = self.state.lock();
+		f.debug_struct("MockFlashMemory")
+			.field("status", &state.status)
+			.field("bad_blocks", &state.bad_blocks)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<Chip: FlashMemoryChip> SpiDevice<u8> for MockFlashMemory<Chip>
+{
+	fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error>
+	{
+		// The commands are made of an operation code followed by its arguments, and then the bytes to read
+		let mut written_bytes = Vec::new();
+		for operation in operations.iter()
+		{
+			match operation
+			{
+				Operation::Write(bytes) | Operation::Transfer(_, bytes) => written_bytes.extend_from_slice(bytes),
+				Operation::TransferInPlace(bytes) => written_bytes.extend_from_slice(bytes),
+				Operation::Read(_) | Operation::DelayUs(_) => (),
+			}
+		}
+		let Some((&op_code, arguments)) = written_bytes.split_first()
+		else
+		{
+			return Err(MockError);
+		};
+
+		let mut output = Vec::new();
+		self.state.lock().execute(op_code, arguments, &mut output);
+
+		let mut output = output.into_iter();
+		for operation in operations
+		{
+			if let Operation::Read(buffer) | Operation::Transfer(buffer, _) | Operation::TransferInPlace(buffer) =
+				operation
+			{
+				buffer
+					.iter_mut()
+					.for_each(|byte| *byte = output.next().unwrap_or_default());
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl<Chip: FlashMemoryChip> ErrorType for MockFlashMemory<Chip>
+{
+	type Error = MockError;
+}
@@ -1,18 +1,96 @@
+use std::sync::Arc;
+
 use embedded_hal::digital::{ErrorType, InputPin};
+use spin::Mutex;
 
 use super::MockError;
+use crate::printer::components::hal::interrupt::{InterruptPin, Trigger};
+
+/// An [`InputPin`] whose level (initially low) is chosen with [`MockInputPin::set_level`].
+///
+/// The callback you subscribe with [`InterruptPin::subscribe_to_interrupt`] is called by [`MockInputPin::set_level`]
+/// when the new level matches the [`Trigger`].
+///
+/// Cloning a `MockInputPin` returns a handle to the same pin, so you can keep a clone to change its level after you
+/// gave it to the code under test.
+///
+/// # Examples
+/// ```
+/// # use std::sync::{Arc, atomic::{AtomicU32, Ordering}};
+/// # use embedded_hal::digital::InputPin;
+/// # use firmware_core::printer::components::{mock::*, hal::interrupt::*};
+/// #
+/// let mut pin = MockInputPin::default();
+/// let mut pin_clone = pin.clone();
+///
+/// let interrupts_count = Arc::new(AtomicU32::new(0));
+/// let interrupts_count_clone = Arc::clone(&interrupts_count);
+/// unsafe {
+///     pin.subscribe_to_interrupt(Trigger::PositiveEdge, move || {
+///         interrupts_count_clone.fetch_add(1, Ordering::Relaxed);
+///     }).unwrap();
+/// }
+///
+/// pin_clone.set_level(true);
+/// // The level didn't change, so there's no edge
+/// pin_clone.set_level(true);
+/// pin_clone.set_level(false);
+///
+/// assert_eq!(pin.is_low(), Ok(true));
+/// assert_eq!(interrupts_count.load(Ordering::Relaxed), 1);
+/// ```
+#[derive(Clone, Default)]
+pub struct MockInputPin
+{
+	state: Arc<Mutex<MockInputPinState>>,
+}
+
+#[derive(Default)]
+struct MockInputPinState
+{
+	is_high: bool,
+	interrupt: Option<(Trigger, Box<dyn FnMut() + Send>)>,
+}
+
+impl MockInputPin
+{
+	pub fn set_level(&mut self, is_high: bool)
+	{
+		let interrupt = {
+			let mut state = self.state.lock();
+			let was_high = std::mem::replace(&mut state.is_high, is_high);
+
+			let should_trigger = state.interrupt.as_ref().is_some_and(|(trigger, _)| match trigger
+			{
+				Trigger::PositiveEdge => !was_high && is_high,
+				Trigger::NegativeEdge => was_high && !is_high,
+				Trigger::AnyEdge => was_high != is_high,
+				Trigger::LowLevel => !is_high,
+				Trigger::HighLevel => is_high,
+			});
+			should_trigger.then(|| state.interrupt.take()).flatten()
+		};
+
+		if let Some((trigger, mut callback)) = interrupt
+		{
+			callback();
+
+			// The callback may have subscribed a new callback
+			self.state.lock().interrupt.get_or_insert((trigger, callback));
+		}
+	}
+}
 
-pub struct MockInputPin;
 impl InputPin for MockInputPin
 {
 	fn is_high(&self) -> Result<bool, Self::Error>
 	{
-		todo!()
+		Ok(self.state.lock().is_high)
 	}
 
 	fn is_low(&self) -> Result<bool, Self::Error>
 	{
-		todo!()
+		Ok(!self.state.lock().is_high)
 	}
 }
 
@@ -20,3 +98,17 @@ impl ErrorType for MockInputPin
 {
 	type Error = MockError;
 }
+
+impl InterruptPin for MockInputPin
+{
+	type Error = MockError;
+
+	unsafe fn subscribe_to_interrupt(
+		&mut self, when_to_trigger: Trigger, callback: impl FnMut() + Send + 'static,
+	) -> Result<(), Self::Error>
+	{
+		self.state.lock().interrupt = Some((when_to_trigger, Box::new(callback)));
+
+		Ok(())
+	}
+}
@@ -11,11 +11,14 @@
 
 mod adc;
 mod connection;
+mod flash_memory;
 mod input;
 mod output;
 mod peripherals;
 mod pwm;
 mod spi;
+mod step_trace;
+mod thermal_plant;
 mod time;
 mod timer;
 mod uart;
@@ -24,37 +27,40 @@ mod z_axis_probe;
 
 pub use adc::*;
 pub use connection::*;
+pub use flash_memory::*;
 pub use input::*;
 pub use output::*;
 pub use peripherals::*;
 pub use pwm::*;
 pub use spi::*;
+pub use step_trace::*;
+pub use thermal_plant::*;
 pub use time::*;
 pub use timer::*;
 pub use uart::*;
 pub use watchdog::*;
 pub use z_axis_probe::*;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct MockError;
 impl embedded_hal::spi::Error for MockError
 {
 	fn kind(&self) -> embedded_hal::spi::ErrorKind
 	{
-		todo!()
+		embedded_hal::spi::ErrorKind::Other
 	}
 }
 impl embedded_hal::digital::Error for MockError
 {
 	fn kind(&self) -> embedded_hal::digital::ErrorKind
 	{
-		todo!()
+		embedded_hal::digital::ErrorKind::Other
 	}
 }
 impl embedded_svc::io::Error for MockError
 {
 	fn kind(&self) -> embedded_svc::io::ErrorKind
 	{
-		todo!()
+		embedded_svc::io::ErrorKind::Other
 	}
 }
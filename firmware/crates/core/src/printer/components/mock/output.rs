@@ -1,18 +1,131 @@
-use std::convert::Infallible;
+use std::{convert::Infallible, sync::Arc};
 
 use embedded_hal::digital::{ErrorType, OutputPin};
+use spin::Mutex;
+
+use super::MockTimer;
+
+/// An [`OutputPin`] that records all its edges (the level of the pin is initially low).
+///
+/// If the pin is [`linked to a timer`], each edge also records the time at which it happened.
+///
+/// Cloning a `MockOutputPin` returns a handle to the same pin, so you can keep a clone to inspect the pin after you
+/// gave it to the code under test.
+///
+/// # Examples
+/// ```
+/// # use std::time::Duration;
+/// # use embedded_hal::digital::OutputPin;
+/// # use firmware_core::printer::components::mock::*;
+/// #
+/// let mut timer = MockTimer::default();
+/// let mut pin = MockOutputPin::linked_to_timer(&timer);
+///
+/// pin.set_high().unwrap();
+/// timer.advance_time(Duration::from_micros(2));
+/// // Setting the same level again is not an edge
+/// pin.set_high().unwrap();
+/// pin.set_low().unwrap();
+///
+/// assert!(!pin.is_high());
+/// assert_eq!(pin.get_edges(), vec![
+///     Edge { is_rising: true, time_in_ticks: 0 },
+///     Edge { is_rising: false, time_in_ticks: 2 },
+/// ]);
+/// assert_eq!(pin.rising_edges_count(), 1);
+/// ```
+///
+/// [`linked to a timer`]: Self::linked_to_timer
+#[derive(Clone, Default)]
+pub struct MockOutputPin
+{
+	state: Arc<Mutex<MockOutputPinState>>,
+	timer: Option<MockTimer>,
+}
+
+#[derive(Default)]
+struct MockOutputPinState
+{
+	is_high: bool,
+	edges: Vec<Edge>,
+}
+
+/// A change of the level of a [`MockOutputPin`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge
+{
+	/// `true` if the pin went from low to high, `false` if it went from high to low.
+	pub is_rising: bool,
+	/// Time of the [`MockTimer`] the pin is linked to when the edge happened (it's `0` if the pin isn't linked to any
+	/// timer).
+	pub time_in_ticks: u64,
+}
+
+impl MockOutputPin
+{
+	/// Returns a `MockOutputPin` whose edges record the time of the provided `timer`.
+	pub fn linked_to_timer(timer: &MockTimer) -> Self
+	{
+		Self {
+			timer: Some(timer.clone()),
+			..Default::default()
+		}
+	}
+
+	/// Returns `true` if the current level of the pin is high.
+	pub fn is_high(&self) -> bool
+	{
+		self.state.lock().is_high
+	}
+
+	/// Returns all the edges of the pin, from the oldest to the newest.
+	pub fn get_edges(&self) -> Vec<Edge>
+	{
+		self.state.lock().edges.clone()
+	}
+
+	/// Returns how many times the pin went from low to high.
+	pub fn rising_edges_count(&self) -> usize
+	{
+		self.state.lock().edges.iter().filter(|edge| edge.is_rising).count()
+	}
+
+	/// Forgets all the edges of the pin until now (the current level doesn't change).
+	pub fn clear_edges(&mut self)
+	{
+		self.state.lock().edges.clear();
+	}
+
+	fn set_level(&mut self, is_high: bool)
+	{
+		let time_in_ticks = self.timer.as_ref().map_or(0, |timer| timer.get_time_in_ticks());
+
+		let mut state = self.state.lock();
+		if state.is_high != is_high
+		{
+			state.is_high = is_high;
+			state.edges.push(Edge {
+				is_rising: is_high,
+				time_in_ticks,
+			});
+		}
+	}
+}
 
-pub struct MockOutputPin;
 impl OutputPin for MockOutputPin
 {
 	fn set_low(&mut self) -> Result<(), Self::Error>
 	{
-		todo!()
+		self.set_level(false);
+
+		Ok(())
 	}
 
 	fn set_high(&mut self) -> Result<(), Self::Error>
 	{
-		todo!()
+		self.set_level(true);
+
+		Ok(())
 	}
 }
 
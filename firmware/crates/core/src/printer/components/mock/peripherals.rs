@@ -1,15 +1,14 @@
-use std::net::IpAddr;
+use std::{fmt::Debug, net::IpAddr};
 
-#[cfg(feature = "usb")]
-use super::input::MockInputPin;
 use super::{
 	adc::{MockAdc, MockAdcPin},
 	connection::*,
+	input::MockInputPin,
 	pwm::MockPwmPin,
 	time::MockSystemTime,
 	uart::MockUart,
 	z_axis_probe::MockZAxisProbe,
-	MockError, MockOutputPin, MockSpi, MockTimer, MockWatchdogCreator,
+	MockError, MockFlashMemory, MockOutputPin, MockTimer, MockWatchdogCreator,
 };
 use crate::printer::components::{
 	drivers::spi_flash_memory::MT29F2G01ABAGDWB,
@@ -17,8 +16,103 @@ use crate::printer::components::{
 	Peripherals,
 };
 
-#[derive(Debug)]
-pub struct MockPeripherals;
+/// [`Peripherals`] made of functional mocks, that you can use to run the components of the printer without any
+/// hardware.
+///
+/// All the mocks of the components are public, and since cloning a mock returns a handle to the same mock, you can keep
+/// a clone of the ones you want to control or inspect before the printer takes them (the connection related
+/// peripherals are not available).
+///
+/// # Examples
+/// ```
+/// # use firmware_core::printer::components::{mock::*, Peripherals};
+/// #
+/// let mut peripherals = MockPeripherals::default();
+/// let hotend_heater_pin = peripherals.hotend_cartridge_heater_pin.clone().unwrap();
+///
+/// assert!(peripherals.take_hotend_cartridge_heater_pin().is_some());
+/// assert!(peripherals.take_hotend_cartridge_heater_pin().is_none());
+/// assert!(hotend_heater_pin.get_duty_cycle_history().is_empty());
+/// ```
+pub struct MockPeripherals
+{
+	pub stepper_ticker_timer: Option<MockTimer>,
+
+	pub left_motor_dir_pin: Option<MockOutputPin>,
+	pub left_motor_step_pin: Option<MockOutputPin>,
+	pub right_motor_dir_pin: Option<MockOutputPin>,
+	pub right_motor_step_pin: Option<MockOutputPin>,
+	pub z_axis_motor_dir_pin: Option<MockOutputPin>,
+	pub z_axis_motor_step_pin: Option<MockOutputPin>,
+	pub extruder_motor_dir_pin: Option<MockOutputPin>,
+	pub extruder_motor_step_pin: Option<MockOutputPin>,
+
+	pub uart_driver: Option<MockUart>,
+
+	pub x_axis_endstop: Option<ManualEndstop>,
+	pub y_axis_endstop: Option<ManualEndstop>,
+	pub z_axis_endstop: Option<MockZAxisProbe>,
+
+	pub filament_sensor_pin: Option<MockInputPin>,
+	pub emergency_stop_button_pin: Option<MockInputPin>,
+
+	pub hotend_cartridge_heater_pin: Option<MockPwmPin>,
+	pub hotend_thermistor_pin: Option<MockAdcPin>,
+	pub bed_cartridge_heater_pin: Option<MockPwmPin>,
+	pub bed_thermistor_pin: Option<MockAdcPin>,
+	pub adc: Option<MockAdc>,
+
+	pub layer_fan_pin: Option<MockPwmPin>,
+	pub hotend_fan_pin: Option<MockPwmPin>,
+
+	pub flash_spi: Option<MockFlashMemory<MT29F2G01ABAGDWB>>,
+
+	pub system_time: Option<MockSystemTime>,
+}
+
+impl Default for MockPeripherals
+{
+	fn default() -> Self
+	{
+		let stepper_ticker_timer = MockTimer::default();
+		let step_pin = || Some(MockOutputPin::linked_to_timer(&stepper_ticker_timer));
+
+		Self {
+			left_motor_dir_pin: Some(MockOutputPin::default()),
+			left_motor_step_pin: step_pin(),
+			right_motor_dir_pin: Some(MockOutputPin::default()),
+			right_motor_step_pin: step_pin(),
+			z_axis_motor_dir_pin: Some(MockOutputPin::default()),
+			z_axis_motor_step_pin: step_pin(),
+			extruder_motor_dir_pin: Some(MockOutputPin::default()),
+			extruder_motor_step_pin: step_pin(),
+			stepper_ticker_timer: Some(stepper_ticker_timer),
+			uart_driver: Some(MockUart::default()),
+			x_axis_endstop: Some(ManualEndstop::new()),
+			y_axis_endstop: Some(ManualEndstop::new()),
+			z_axis_endstop: Some(MockZAxisProbe::default()),
+			filament_sensor_pin: None,
+			emergency_stop_button_pin: None,
+			hotend_cartridge_heater_pin: Some(MockPwmPin::default()),
+			hotend_thermistor_pin: Some(MockAdcPin::default()),
+			bed_cartridge_heater_pin: Some(MockPwmPin::default()),
+			bed_thermistor_pin: Some(MockAdcPin::default()),
+			adc: Some(MockAdc::default()),
+			layer_fan_pin: Some(MockPwmPin::default()),
+			hotend_fan_pin: Some(MockPwmPin::default()),
+			flash_spi: Some(MockFlashMemory::default()),
+			system_time: Some(MockSystemTime::default()),
+		}
+	}
+}
+
+impl Debug for MockPeripherals
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		f.debug_struct("MockPeripherals").finish_non_exhaustive()
+	}
+}
 
 impl Peripherals for MockPeripherals
 {
@@ -43,6 +137,9 @@ impl Peripherals for MockPeripherals
 	type YAxisEndstop = ManualEndstop;
 	type ZAxisEndstop = MockZAxisProbe;
 
+	type FilamentSensorPin = MockInputPin;
+	type EmergencyStopButtonPin = MockInputPin;
+
 	type CartridgeHeaterPin = MockPwmPin;
 	type HotendAdcPin = MockAdcPin;
 
@@ -50,7 +147,7 @@ impl Peripherals for MockPeripherals
 	type HeatedBedAdcPin = MockAdcPin;
 
 	type FlashChip = MT29F2G01ABAGDWB;
-	type FlashSpi = MockSpi;
+	type FlashSpi = MockFlashMemory<MT29F2G01ABAGDWB>;
 
 	type Adc = MockAdc;
 
@@ -75,159 +172,169 @@ impl Peripherals for MockPeripherals
 
 	fn take_x_axis_endstop(&mut self) -> Option<Self::XAxisEndstop>
 	{
-		todo!()
+		self.x_axis_endstop.take()
 	}
 
 	fn take_y_axis_endstop(&mut self) -> Option<Self::YAxisEndstop>
 	{
-		todo!()
+		self.y_axis_endstop.take()
 	}
 
 	fn take_z_axis_endstop(&mut self) -> Option<Self::ZAxisEndstop>
 	{
-		todo!()
+		self.z_axis_endstop.take()
+	}
+
+	fn take_filament_sensor_pin(&mut self) -> Option<Self::FilamentSensorPin>
+	{
+		self.filament_sensor_pin.take()
+	}
+
+	fn take_emergency_stop_button_pin(&mut self) -> Option<Self::EmergencyStopButtonPin>
+	{
+		self.emergency_stop_button_pin.take()
 	}
 
 	fn take_bed_cartridge_heater_pin(&mut self) -> Option<Self::CartridgeHeaterPin>
 	{
-		todo!()
+		self.bed_cartridge_heater_pin.take()
 	}
 
 	fn take_hotend_cartridge_heater_pin(&mut self) -> Option<Self::CartridgeHeaterPin>
 	{
-		todo!()
+		self.hotend_cartridge_heater_pin.take()
 	}
 
 	fn take_layer_fan_pin(&mut self) -> Option<Self::FanPin>
 	{
-		todo!()
+		self.layer_fan_pin.take()
 	}
 
 	fn take_hotend_fan_pin(&mut self) -> Option<Self::FanPin>
 	{
-		todo!()
+		self.hotend_fan_pin.take()
 	}
 
 	fn take_system_time(&mut self) -> Option<Self::SystemTime>
 	{
-		todo!()
+		self.system_time.take()
 	}
 
 	fn take_adc(&mut self) -> Option<Self::Adc>
 	{
-		todo!()
+		self.adc.take()
 	}
 
 	fn take_bed_thermistor_pin(&mut self) -> Option<Self::HeatedBedAdcPin>
 	{
-		todo!()
+		self.bed_thermistor_pin.take()
 	}
 
 	fn take_hotend_thermistor_pin(&mut self) -> Option<Self::HotendAdcPin>
 	{
-		todo!()
+		self.hotend_thermistor_pin.take()
 	}
 
 	fn take_kinematics(&mut self) -> Option<Self::Kinematics>
 	{
-		todo!()
+		Some(CoreXYKinematics)
 	}
 
 	fn take_stepper_ticker_timer(&mut self) -> Option<Self::StepperTickerTimer>
 	{
-		todo!()
+		self.stepper_ticker_timer.take()
 	}
 
 	fn take_left_motor_dir_pin(&mut self) -> Option<Self::LeftDirPin>
 	{
-		todo!()
+		self.left_motor_dir_pin.take()
 	}
 
 	fn take_left_motor_step_pin(&mut self) -> Option<Self::LeftStepPin>
 	{
-		todo!()
+		self.left_motor_step_pin.take()
 	}
 
 	fn take_right_motor_dir_pin(&mut self) -> Option<Self::RightDirPin>
 	{
-		todo!()
+		self.right_motor_dir_pin.take()
 	}
 
 	fn take_right_motor_step_pin(&mut self) -> Option<Self::RightStepPin>
 	{
-		todo!()
+		self.right_motor_step_pin.take()
 	}
 
 	fn take_z_axis_motor_dir_pin(&mut self) -> Option<Self::ZAxisDirPin>
 	{
-		todo!()
+		self.z_axis_motor_dir_pin.take()
 	}
 
 	fn take_z_axis_motor_step_pin(&mut self) -> Option<Self::ZAxisStepPin>
 	{
-		todo!()
+		self.z_axis_motor_step_pin.take()
 	}
 
 	fn take_extruder_motor_dir_pin(&mut self) -> Option<Self::ExtruderDirPin>
 	{
-		todo!()
+		self.extruder_motor_dir_pin.take()
 	}
 
 	fn take_extruder_motor_step_pin(&mut self) -> Option<Self::ExtruderStepPin>
 	{
-		todo!()
+		self.extruder_motor_step_pin.take()
 	}
 
 	fn take_uart_driver(&mut self) -> Option<Self::UartDriver>
 	{
-		todo!()
+		self.uart_driver.take()
 	}
 
 	fn take_flash_chip(&mut self) -> Option<Self::FlashChip>
 	{
-		todo!()
+		Some(MT29F2G01ABAGDWB)
 	}
 
 	fn take_flash_spi(&mut self) -> Option<Self::FlashSpi>
 	{
-		todo!()
+		self.flash_spi.take()
 	}
 
 	fn take_wifi_driver(&mut self) -> Option<Self::WifiDriver>
 	{
-		todo!()
+		None
 	}
 
 	fn get_ip_address_from_wifi_driver_function() -> fn(&Self::WifiDriver) -> Option<IpAddr>
 	{
-		todo!()
+		|_| None
 	}
 
 	fn take_http_server(&mut self) -> Option<Box<dyn FnOnce() -> Result<Self::Server, Self::ServerError> + Send>>
 	{
-		todo!()
+		None
 	}
 
 	fn take_ota(&mut self) -> Option<Self::Ota>
 	{
-		todo!()
+		None
 	}
 
 	fn reboot_fn() -> fn()
 	{
-		todo!()
+		|| ()
 	}
 
 	#[cfg(feature = "usb")]
 	fn take_usb_sense_pin(&mut self) -> Option<Self::UsbSensePin>
 	{
-		todo!()
+		None
 	}
 
 	#[cfg(feature = "usb")]
 	fn take_usb_bus(&mut self) -> Option<Self::UsbBus>
 	{
-		todo!()
+		None
 	}
 
 	type ServerError = MockError;
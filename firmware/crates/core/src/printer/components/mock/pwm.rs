@@ -1,22 +1,90 @@
-use crate::printer::components::hal::pwm::PwmPin;
+use std::sync::Arc;
+
+use spin::Mutex;
+
+use crate::{
+	printer::components::hal::pwm::PwmPin,
+	utils::{math::Percentage, measurement::frequency::Frequency},
+};
+
+/// A [`PwmPin`] that records all the duty cycles that have been set.
+///
+/// Cloning a `MockPwmPin` returns a handle to the same pin, so you can keep a clone to inspect the pin after you gave
+/// it to the code under test.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::{utils::math::Percentage, printer::components::{mock::*, hal::pwm::*}};
+/// #
+/// let mut pin = MockPwmPin::default();
+/// let pin_clone = pin.clone();
+///
+/// pin.set_duty_cycle(Percentage::from_0_to_1(0.5).unwrap()).unwrap();
+/// pin.set_duty_cycle(Percentage::from_0_to_1(0.2).unwrap()).unwrap();
+///
+/// assert_eq!(pin_clone.get_duty_cycle(), Percentage::from_0_to_1(0.2).unwrap());
+/// assert_eq!(pin_clone.get_duty_cycle_history(), vec![
+///     Percentage::from_0_to_1(0.5).unwrap(),
+///     Percentage::from_0_to_1(0.2).unwrap(),
+/// ]);
+/// ```
+#[derive(Clone, Default)]
+pub struct MockPwmPin
+{
+	state: Arc<Mutex<MockPwmPinState>>,
+}
+
+#[derive(Default)]
+struct MockPwmPinState
+{
+	duty_cycle: Percentage,
+	duty_cycle_history: Vec<Percentage>,
+	frequency: Option<Frequency>,
+}
+
+impl MockPwmPin
+{
+	/// Returns all the duty cycles set on this pin, from the oldest to the newest.
+	pub fn get_duty_cycle_history(&self) -> Vec<Percentage>
+	{
+		self.state.lock().duty_cycle_history.clone()
+	}
+
+	/// Forgets all the duty cycles set on this pin until now (the current duty cycle doesn't change).
+	pub fn clear_duty_cycle_history(&mut self)
+	{
+		self.state.lock().duty_cycle_history.clear();
+	}
+
+	/// Returns the last frequency set on this pin, or `None` if it has never been set.
+	pub fn get_frequency(&self) -> Option<Frequency>
+	{
+		self.state.lock().frequency
+	}
+}
 
-pub struct MockPwmPin;
 impl PwmPin for MockPwmPin
 {
 	type Error = ();
 
-	fn get_duty_cycle(&self) -> crate::utils::math::Percentage
+	fn get_duty_cycle(&self) -> Percentage
 	{
-		todo!()
+		self.state.lock().duty_cycle
 	}
 
-	fn set_duty_cycle(&mut self, _: crate::utils::math::Percentage) -> Result<(), Self::Error>
+	fn set_duty_cycle(&mut self, percentage: Percentage) -> Result<(), Self::Error>
 	{
-		todo!()
+		let mut state = self.state.lock();
+		state.duty_cycle = percentage;
+		state.duty_cycle_history.push(percentage);
+
+		Ok(())
 	}
 
-	fn set_frequency(&mut self, _: crate::utils::measurement::frequency::Frequency) -> Result<(), Self::Error>
+	fn set_frequency(&mut self, frequency: Frequency) -> Result<(), Self::Error>
 	{
-		todo!()
+		self.state.lock().frequency = Some(frequency);
+
+		Ok(())
 	}
 }
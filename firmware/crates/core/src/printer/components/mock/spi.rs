@@ -1,13 +1,113 @@
-use embedded_hal::spi::{ErrorType, SpiDevice};
+use std::{collections::VecDeque, sync::Arc};
+
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+use spin::Mutex;
 
 use super::MockError;
 
-pub struct MockSpi;
+/// A [`SpiDevice`] that records the bytes written in each transaction and answers the reads with scripted bytes.
+///
+/// The bytes [`pushed`] are returned (in order) by the next reads, and when there are no more of them the reads return
+/// `0`s. You can also make the next transactions [`fail`].
+///
+/// Cloning a `MockSpi` returns a handle to the same device, so you can keep a clone to inspect the device after you
+/// gave it to the code under test.
+///
+/// # Examples
+/// ```
+/// # use embedded_hal::spi::{SpiDevice, Operation};
+/// # use firmware_core::printer::components::mock::*;
+/// #
+/// let mut spi = MockSpi::default();
+/// spi.push_read_bytes([5, 6]);
+///
+/// let mut read = [0; 3];
+/// spi.transaction(&mut [Operation::Write(&[1, 2]), Operation::Read(&mut read)]).unwrap();
+/// assert_eq!(read, [5, 6, 0]);
+/// assert_eq!(spi.get_written_transactions(), vec![vec![1, 2]]);
+///
+/// spi.fail_next_transactions(1);
+/// assert!(spi.transaction(&mut [Operation::Write(&[3])]).is_err());
+/// assert!(spi.transaction(&mut [Operation::Write(&[3])]).is_ok());
+/// ```
+///
+/// [`pushed`]: Self::push_read_bytes
+/// [`fail`]: Self::fail_next_transactions
+#[derive(Clone, Default)]
+pub struct MockSpi
+{
+	state: Arc<Mutex<MockSpiState>>,
+}
+
+#[derive(Default)]
+struct MockSpiState
+{
+	read_bytes: VecDeque<u8>,
+	written_transactions: Vec<Vec<u8>>,
+	transactions_to_fail_count: usize,
+}
+
+impl MockSpi
+{
+	/// Adds the `bytes` to the ones that will be returned by the next reads.
+	pub fn push_read_bytes(&mut self, bytes: impl IntoIterator<Item = u8>)
+	{
+		self.state.lock().read_bytes.extend(bytes);
+	}
+
+	/// Returns the bytes written in each successful transaction, from the oldest to the newest.
+	pub fn get_written_transactions(&self) -> Vec<Vec<u8>>
+	{
+		self.state.lock().written_transactions.clone()
+	}
+
+	/// Makes the next `count` transactions return `Err(MockError)` without reading or writing anything.
+	pub fn fail_next_transactions(&mut self, count: usize)
+	{
+		self.state.lock().transactions_to_fail_count = count;
+	}
+}
+
 impl SpiDevice<u8> for MockSpi
 {
-	fn transaction(&mut self, _: &mut [embedded_hal::spi::Operation<'_, u8>]) -> Result<(), Self::Error>
+	fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error>
 	{
-		todo!()
+		let mut state = self.state.lock();
+		if state.transactions_to_fail_count > 0
+		{
+			state.transactions_to_fail_count -= 1;
+			return Err(MockError);
+		}
+
+		let mut written_bytes = Vec::new();
+		let read = |buffer: &mut [u8], read_bytes: &mut VecDeque<u8>| {
+			for byte in buffer
+			{
+				*byte = read_bytes.pop_front().unwrap_or(0);
+			}
+		};
+		for operation in operations
+		{
+			match operation
+			{
+				Operation::Read(buffer) => read(buffer, &mut state.read_bytes),
+				Operation::Write(buffer) => written_bytes.extend_from_slice(buffer),
+				Operation::Transfer(read_buffer, write_buffer) =>
+				{
+					written_bytes.extend_from_slice(write_buffer);
+					read(read_buffer, &mut state.read_bytes);
+				},
+				Operation::TransferInPlace(buffer) =>
+				{
+					written_bytes.extend_from_slice(buffer);
+					read(buffer, &mut state.read_bytes);
+				},
+				Operation::DelayUs(_) => (),
+			}
+		}
+		state.written_transactions.push(written_bytes);
+
+		Ok(())
 	}
 }
 
@@ -0,0 +1,356 @@
+use std::{fmt::Write, path::Path};
+
+use super::{Edge, MockOutputPin, MockTimer};
+use crate::{
+	printer::components::{
+		drivers::stepper_motor::{RotationalDirection, StepperMotor},
+		hal::timer::Timer,
+		motion::N_MOTORS,
+	},
+	utils::measurement::frequency::Frequency,
+};
+
+/// Name of the environment variable that makes [`StepTrace::assert_eq_golden_file`] overwrite the golden files
+/// instead of comparing them (run the tests with `UPDATE_GOLDEN_FILES=1` after an intended change of behaviour).
+pub const UPDATE_GOLDEN_FILES_ENV_VAR: &str = "UPDATE_GOLDEN_FILES";
+
+/// Provides [`StepperMotor`]s whose `DIR` and `STEP` pins are [`MockOutputPin`]s linked to a [`MockTimer`], and
+/// rebuilds from their edges the [`StepTrace`] of the steps taken by each motor.
+///
+/// The motors are indexed like the axes of the motion module (`0` is the left motor, `1` the right one, `2` the Z axis
+/// one and `3` the extruder one).
+///
+/// A change of direction that happens at the same time of a step is considered to happen before the step (like in the
+/// [`StepperMotorsTicker`], which sets the `DIR` pin before pulsing the `STEP` pin).
+///
+/// # Examples
+/// ```
+/// # use std::time::Duration;
+/// # use firmware_core::printer::components::{mock::*, drivers::stepper_motor::*};
+/// #
+/// let mut timer = MockTimer::default();
+/// let recorder = StepTraceRecorder::new(&timer);
+/// let mut motor = recorder.get_stepper_motor(0);
+///
+/// motor.set_rotation_direction(RotationalDirection::CW).unwrap();
+/// for _ in 0..3
+/// {
+///     timer.advance_time(Duration::from_micros(100));
+///     motor.start_step_pulse().unwrap();
+///     motor.end_step_pulse().unwrap();
+/// }
+/// timer.advance_time(Duration::from_micros(50));
+/// motor.set_rotation_direction(RotationalDirection::CCW).unwrap();
+/// motor.start_step_pulse().unwrap();
+/// motor.end_step_pulse().unwrap();
+///
+/// let trace = recorder.get_trace();
+/// assert_eq!(trace.get_steps(0).len(), 4);
+/// assert_eq!(trace.get_position(0), 2);
+/// assert_eq!(trace.get_steps(0)[3], Step { time_in_ticks: 350, direction: RotationalDirection::CCW });
+/// // 1 step every 100µs
+/// assert_eq!(trace.get_velocity_profile(0)[0].value, 10_000.);
+/// ```
+///
+/// [`StepperMotorsTicker`]: crate::printer::components::motion::ticker::StepperMotorsTicker
+pub struct StepTraceRecorder
+{
+	timer_frequency: Frequency,
+	motors_pins: [MotorPins; N_MOTORS],
+}
+
+struct MotorPins
+{
+	dir_pin: MockOutputPin,
+	step_pin: MockOutputPin,
+}
+
+impl StepTraceRecorder
+{
+	/// Returns a recorder whose pins record the time of the provided `timer`.
+	pub fn new(timer: &MockTimer) -> Self
+	{
+		Self {
+			timer_frequency: timer.get_clock_frequency(),
+			motors_pins: std::array::from_fn(|_| MotorPins {
+				dir_pin: MockOutputPin::linked_to_timer(timer),
+				step_pin: MockOutputPin::linked_to_timer(timer),
+			}),
+		}
+	}
+
+	/// Returns a [`StepperMotor`] that drives the pins of the motor with the provided `index` (you can call it more
+	/// than once, all the returned motors share the same pins).
+	pub fn get_stepper_motor(&self, index: usize) -> StepperMotor<MockOutputPin, MockOutputPin>
+	{
+		let (dir_pin, step_pin) = self.get_motor_pins(index);
+		StepperMotor::new(dir_pin, step_pin)
+	}
+
+	/// Returns the `DIR` and `STEP` pins of the motor with the provided `index`, for when you need to give them to
+	/// something that builds its own [`StepperMotor`] (like a [`Peripherals`] implementation).
+	///
+	/// [`Peripherals`]: crate::printer::components::Peripherals
+	pub fn get_motor_pins(&self, index: usize) -> (MockOutputPin, MockOutputPin)
+	{
+		let pins = &self.motors_pins[index];
+		(pins.dir_pin.clone(), pins.step_pin.clone())
+	}
+
+	/// Returns the steps taken by all the motors since the recorder was created (or since the last call to
+	/// [`Self::clear`]).
+	pub fn get_trace(&self) -> StepTrace
+	{
+		StepTrace {
+			timer_frequency: self.timer_frequency,
+			motors_steps: std::array::from_fn(|index| {
+				let pins = &self.motors_pins[index];
+				Self::steps_from_edges(
+					&pins.dir_pin.get_edges(),
+					&pins.step_pin.get_edges(),
+					pins.dir_pin.is_high(),
+				)
+			}),
+		}
+	}
+
+	/// Forgets all the steps recorded until now.
+	pub fn clear(&mut self)
+	{
+		for pins in &mut self.motors_pins
+		{
+			pins.dir_pin.clear_edges();
+			pins.step_pin.clear_edges();
+		}
+	}
+
+	fn steps_from_edges(dir_edges: &[Edge], step_edges: &[Edge], is_dir_pin_high_now: bool) -> Vec<Step>
+	{
+		// The level the DIR pin had before its first recorded edge
+		let mut is_dir_pin_high = dir_edges
+			.first()
+			.map_or(is_dir_pin_high_now, |first_edge| !first_edge.is_rising);
+		let mut dir_edges = dir_edges.iter().peekable();
+
+		step_edges
+			.iter()
+			.filter(|edge| edge.is_rising)
+			.map(|step_edge| {
+				// The direction is always set before the step pulse, even if they happen at the same time
+				while let Some(dir_edge) =
+					dir_edges.next_if(|dir_edge| dir_edge.time_in_ticks <= step_edge.time_in_ticks)
+				{
+					is_dir_pin_high = dir_edge.is_rising;
+				}
+
+				Step {
+					time_in_ticks: step_edge.time_in_ticks,
+					direction: match is_dir_pin_high
+					{
+						true => RotationalDirection::CW,
+						false => RotationalDirection::CCW,
+					},
+				}
+			})
+			.collect()
+	}
+}
+
+/// A step taken by a motor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step
+{
+	/// Time of the timer when the pulse on the `STEP` pin started.
+	pub time_in_ticks: u64,
+	pub direction: RotationalDirection,
+}
+
+/// A sample of a profile returned by [`StepTrace::get_velocity_profile`] or [`StepTrace::get_acceleration_profile`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProfileSample
+{
+	pub time_in_seconds: f64,
+	pub value: f64,
+}
+
+/// The steps taken by each motor, returned by [`StepTraceRecorder::get_trace`].
+pub struct StepTrace
+{
+	timer_frequency: Frequency,
+	motors_steps: [Vec<Step>; N_MOTORS],
+}
+
+impl StepTrace
+{
+	/// Returns the steps taken by the motor with the provided `index`, from the oldest to the newest.
+	pub fn get_steps(&self, motor_index: usize) -> &[Step]
+	{
+		&self.motors_steps[motor_index]
+	}
+
+	/// Returns the number of steps taken clockwise minus the number of steps taken counterclockwise by the motor with
+	/// the provided `index`.
+	pub fn get_position(&self, motor_index: usize) -> i32
+	{
+		self.motors_steps[motor_index]
+			.iter()
+			.map(|step| step.direction as i32)
+			.sum()
+	}
+
+	/// Returns the velocity (in steps per second, negative if counterclockwise) of the motor with the provided
+	/// `index` between each pair of consecutive steps, sampled in the middle of the two steps.
+	pub fn get_velocity_profile(&self, motor_index: usize) -> Vec<ProfileSample>
+	{
+		self.motors_steps[motor_index]
+			.windows(2)
+			.map(|steps| {
+				let interval = self.ticks_to_seconds(steps[1].time_in_ticks - steps[0].time_in_ticks);
+				ProfileSample {
+					time_in_seconds: self.ticks_to_seconds(steps[0].time_in_ticks) + interval / 2.,
+					value: steps[1].direction as i32 as f64 / interval,
+				}
+			})
+			.collect()
+	}
+
+	/// Returns the acceleration (in steps per second squared) of the motor with the provided `index`, calculated from
+	/// each pair of consecutive samples of [`Self::get_velocity_profile`].
+	pub fn get_acceleration_profile(&self, motor_index: usize) -> Vec<ProfileSample>
+	{
+		self.get_velocity_profile(motor_index)
+			.windows(2)
+			.map(|samples| ProfileSample {
+				time_in_seconds: (samples[0].time_in_seconds + samples[1].time_in_seconds) / 2.,
+				value: (samples[1].value - samples[0].value)
+					/ (samples[1].time_in_seconds - samples[0].time_in_seconds),
+			})
+			.collect()
+	}
+
+	/// Returns a textual representation of the trace that can be compared with [`Self::assert_eq_golden_file`].
+	///
+	/// For each motor there's the time of its first step, followed by the interval (in ticks) between each step and
+	/// the previous one, with a sign that represents the direction of the step. Consecutive equal intervals are
+	/// written once followed by how many times they're repeated.
+	///
+	/// # Examples
+	/// ```
+	/// # use std::time::Duration;
+	/// # use firmware_core::printer::components::{mock::*, drivers::stepper_motor::*};
+	/// #
+	/// let mut timer = MockTimer::default();
+	/// let recorder = StepTraceRecorder::new(&timer);
+	/// let mut motor = recorder.get_stepper_motor(3);
+	/// for interval in [100, 100, 100, 70]
+	/// {
+	///     timer.advance_time(Duration::from_micros(interval));
+	///     motor.start_step_pulse().unwrap();
+	///     motor.end_step_pulse().unwrap();
+	/// }
+	///
+	/// assert_eq!(recorder.get_trace().to_golden_string(), "\
+	/// timer frequency: 1000000Hz
+	/// motor 0: 0 steps, position 0
+	/// motor 1: 0 steps, position 0
+	/// motor 2: 0 steps, position 0
+	/// motor 3: 4 steps, position -4
+	/// start 100
+	/// -100 x2
+	/// -70
+	/// ");
+	/// ```
+	pub fn to_golden_string(&self) -> String
+	{
+		let mut string = String::new();
+		writeln!(string, "timer frequency: {}Hz", self.timer_frequency.as_hertz()).unwrap();
+		for (index, steps) in self.motors_steps.iter().enumerate()
+		{
+			writeln!(
+				string,
+				"motor {index}: {} steps, position {}",
+				steps.len(),
+				self.get_position(index)
+			)
+			.unwrap();
+
+			if let Some(first_step) = steps.first()
+			{
+				writeln!(string, "start {}", first_step.time_in_ticks).unwrap();
+			}
+
+			let mut intervals = steps
+				.windows(2)
+				.map(|steps| (steps[1].direction as i64) * (steps[1].time_in_ticks - steps[0].time_in_ticks) as i64)
+				.peekable();
+			while let Some(interval) = intervals.next()
+			{
+				let mut repetitions = 1;
+				while intervals.next_if_eq(&interval).is_some()
+				{
+					repetitions += 1;
+				}
+
+				let sign = if interval < 0 { '-' } else { '+' };
+				match repetitions
+				{
+					1 => writeln!(string, "{sign}{}", interval.abs()),
+					_ => writeln!(string, "{sign}{} x{repetitions}", interval.abs()),
+				}
+				.unwrap();
+			}
+		}
+
+		string
+	}
+
+	/// Panics if the [`golden string`] of this trace is different from the content of the file at `path`.
+	///
+	/// If the [`UPDATE_GOLDEN_FILES_ENV_VAR`] environment variable is set, the file is overwritten with the golden
+	/// string instead.
+	///
+	/// [`golden string`]: Self::to_golden_string
+	pub fn assert_eq_golden_file(&self, path: impl AsRef<Path>)
+	{
+		let path = path.as_ref();
+		let golden_string = self.to_golden_string();
+
+		if std::env::var_os(UPDATE_GOLDEN_FILES_ENV_VAR).is_some()
+		{
+			if let Some(directory) = path.parent()
+			{
+				std::fs::create_dir_all(directory).unwrap();
+			}
+			std::fs::write(path, golden_string).unwrap();
+		}
+		else
+		{
+			let expected = std::fs::read_to_string(path).unwrap_or_else(|error| {
+				panic!(
+					"Couldn't read the golden file {path:?} ({error}), run the test with {UPDATE_GOLDEN_FILES_ENV_VAR}=1 \
+					 to create it"
+				)
+			});
+			// Comparing the lines gives a more readable message and ignores the line endings
+			for (line_index, (actual_line, expected_line)) in golden_string.lines().zip(expected.lines()).enumerate()
+			{
+				assert_eq!(
+					actual_line,
+					expected_line,
+					"The step trace differs from the golden file {path:?} at line {}",
+					line_index + 1
+				);
+			}
+			assert_eq!(
+				golden_string.lines().count(),
+				expected.lines().count(),
+				"The step trace has a different length than the golden file {path:?}"
+			);
+		}
+	}
+
+	fn ticks_to_seconds(&self, ticks: u64) -> f64
+	{
+		ticks as f64 / self.timer_frequency.as_hertz() as f64
+	}
+}
@@ -0,0 +1,214 @@
+use super::{MockAdc, MockAdcPin, MockPwmPin};
+use crate::{
+	printer::components::{
+		config::temperature::ThermistorConfig,
+		drivers::thermistor::T0,
+		hal::{adc::Adc, pwm::PwmPin},
+	},
+	utils::{math::Percentage, measurement::temperature::Temperature},
+};
+
+/// Physical parameters of a [`MockThermalPlant`].
+#[derive(Clone, Copy, Debug)]
+pub struct ThermalPlantConfig
+{
+	/// Power of the heater when its duty cycle is `100%`.
+	pub heater_power_watts: f32,
+	/// Energy required to raise the temperature of the heated body by `1°C`.
+	pub heat_capacity_joules_per_kelvin: f32,
+	/// Power lost to the ambient for each degree of difference between the heated body and the ambient.
+	pub heat_loss_watts_per_kelvin: f32,
+	pub ambient_temperature: Temperature,
+	/// Time constant of the thermistor (how slowly its temperature follows the one of the heated body).
+	pub thermistor_time_constant_seconds: f32,
+	/// Parameters of the thermistor (they should be the same ones provided to the [`Thermistor`] that reads it).
+	///
+	/// [`Thermistor`]: crate::printer::components::drivers::thermistor::Thermistor
+	pub thermistor: ThermistorConfig,
+}
+
+impl ThermalPlantConfig
+{
+	/// Returns a config similar to a common hotend with a `40W` cartridge heater.
+	pub fn hotend() -> Self
+	{
+		Self {
+			heater_power_watts: 40.,
+			heat_capacity_joules_per_kelvin: 15.,
+			heat_loss_watts_per_kelvin: 0.15,
+			ambient_temperature: Temperature::from_celsius(25.),
+			thermistor_time_constant_seconds: 2.,
+			thermistor: ThermistorConfig {
+				beta: 3_950,
+				resistance_at_t0: 100_000,
+				other_resistance: 4_700,
+			},
+		}
+	}
+
+	/// Returns a config similar to a common `220x220mm` heated bed.
+	pub fn heated_bed() -> Self
+	{
+		Self {
+			heater_power_watts: 200.,
+			heat_capacity_joules_per_kelvin: 600.,
+			heat_loss_watts_per_kelvin: 1.5,
+			ambient_temperature: Temperature::from_celsius(25.),
+			thermistor_time_constant_seconds: 5.,
+			thermistor: ThermistorConfig {
+				beta: 3_950,
+				resistance_at_t0: 100_000,
+				other_resistance: 4_700,
+			},
+		}
+	}
+}
+
+/// A first-order thermal model of a heater and of the thermistor that measures its temperature.
+///
+/// Each time you [`tick`] it, the plant reads the duty cycle of the heater's [`MockPwmPin`], updates the temperature
+/// of the heated body and of the thermistor, and sets the value read by the thermistor's [`MockAdcPin`] (converting
+/// the temperature with the inverse of the beta equation used by the [`Thermistor`]).
+///
+/// # Examples
+/// ```
+/// # use firmware_core::{utils::{math::Percentage, measurement::temperature::*}, printer::components::{
+/// #     mock::*, hal::pwm::*, drivers::thermistor::*, config::temperature::*}};
+/// #
+/// let mut heater_pin = MockPwmPin::default();
+/// let thermistor_pin = MockAdcPin::default();
+/// let mut adc = MockAdc::default();
+///
+/// let config = ThermalPlantConfig::hotend();
+/// let mut plant = MockThermalPlant::new(config, &heater_pin, &thermistor_pin, &adc);
+/// let mut thermistor = Thermistor::new(thermistor_pin, config.thermistor.beta, config.thermistor.resistance_at_t0,
+///     config.thermistor.other_resistance);
+///
+/// let read_celsius = |thermistor: &mut Thermistor<_, _>, adc: &mut _| thermistor.read_temperature(adc).unwrap().as_celsius();
+/// assert!((read_celsius(&mut thermistor, &mut adc) - 25.).abs() < 0.5);
+///
+/// heater_pin.set_duty_cycle(Percentage::from_0_to_1(1.).unwrap()).unwrap();
+/// for _ in 0..100
+/// {
+///     plant.tick(0.1);
+/// }
+/// assert!(read_celsius(&mut thermistor, &mut adc) > 40.);
+/// ```
+///
+/// [`tick`]: Self::tick
+/// [`Thermistor`]: crate::printer::components::drivers::thermistor::Thermistor
+pub struct MockThermalPlant
+{
+	config: ThermalPlantConfig,
+	heater_pin: MockPwmPin,
+	thermistor_pin: MockAdcPin,
+	max_adc_value: u16,
+
+	heated_body_temperature: Temperature,
+	thermistor_temperature: Temperature,
+	heater_efficiency: f32,
+	forced_heater_duty_cycle: Option<Percentage>,
+}
+
+impl MockThermalPlant
+{
+	/// Maximum time simulated in a single integration step (longer ticks are split in multiple steps).
+	const MAX_STEP_SECONDS: f64 = 0.05;
+
+	/// Returns a plant at the ambient temperature, driven by the `heater_pin` and read through the `thermistor_pin`
+	/// (which is connected to the `adc`).
+	pub fn new(config: ThermalPlantConfig, heater_pin: &MockPwmPin, thermistor_pin: &MockAdcPin, adc: &MockAdc)
+		-> Self
+	{
+		let mut self_ = Self {
+			config,
+			heater_pin: heater_pin.clone(),
+			thermistor_pin: thermistor_pin.clone(),
+			max_adc_value: adc.max_readable_value().0,
+			heated_body_temperature: config.ambient_temperature,
+			thermistor_temperature: config.ambient_temperature,
+			heater_efficiency: 1.,
+			forced_heater_duty_cycle: None,
+		};
+		self_.update_thermistor_pin();
+
+		self_
+	}
+
+	/// Simulates `delta_time` seconds.
+	pub fn tick(&mut self, delta_time: f64)
+	{
+		let heater_duty_cycle = self
+			.forced_heater_duty_cycle
+			.unwrap_or_else(|| self.heater_pin.get_duty_cycle());
+		let heater_power = self.config.heater_power_watts * self.heater_efficiency * heater_duty_cycle.into_0_to_1();
+
+		let mut remaining_time = delta_time;
+		while remaining_time > 0.
+		{
+			let step = remaining_time.min(Self::MAX_STEP_SECONDS) as f32;
+			remaining_time -= step as f64;
+
+			let heat_loss = self.config.heat_loss_watts_per_kelvin
+				* (self.heated_body_temperature.as_kelvin() - self.config.ambient_temperature.as_kelvin());
+			let heated_body_kelvin = self.heated_body_temperature.as_kelvin()
+				+ (heater_power - heat_loss) / self.config.heat_capacity_joules_per_kelvin * step;
+
+			let thermistor_kelvin = self.thermistor_temperature.as_kelvin()
+				+ (self.heated_body_temperature.as_kelvin() - self.thermistor_temperature.as_kelvin())
+					* (step / self.config.thermistor_time_constant_seconds).min(1.);
+
+			self.heated_body_temperature = Temperature::from_kelvin(heated_body_kelvin);
+			self.thermistor_temperature = Temperature::from_kelvin(thermistor_kelvin);
+		}
+
+		self.update_thermistor_pin();
+	}
+
+	/// Returns the actual temperature of the heated body.
+	pub fn get_heated_body_temperature(&self) -> Temperature
+	{
+		self.heated_body_temperature
+	}
+
+	/// Returns the temperature of the thermistor (which lags behind the one of the heated body).
+	pub fn get_thermistor_temperature(&self) -> Temperature
+	{
+		self.thermistor_temperature
+	}
+
+	/// Sets the fraction (from `0` to `1`) of the heater's power that actually heats the body (it's `1` by default).
+	///
+	/// You can use it to simulate faults, like a heater that fell out of the heater block (`0`).
+	pub fn set_heater_efficiency(&mut self, efficiency: f32)
+	{
+		self.heater_efficiency = efficiency.clamp(0., 1.);
+	}
+
+	/// Makes the heater ignore the duty cycle of its pin and always use `duty_cycle` instead (for example to simulate a
+	/// shorted MOSFET that keeps the heater always on). Pass `None` to use the duty cycle of the pin again.
+	pub fn force_heater_duty_cycle(&mut self, duty_cycle: Option<Percentage>)
+	{
+		self.forced_heater_duty_cycle = duty_cycle;
+	}
+
+	/// Sets the temperature of the ambient around the heated body (for example to simulate a draft of cold air).
+	pub fn set_ambient_temperature(&mut self, ambient_temperature: Temperature)
+	{
+		self.config.ambient_temperature = ambient_temperature;
+	}
+
+	fn update_thermistor_pin(&mut self)
+	{
+		let thermistor = self.config.thermistor;
+		let resistance = thermistor.resistance_at_t0 as f64
+			* f64::exp(
+				thermistor.beta as f64
+					* (1. / self.thermistor_temperature.as_kelvin() as f64 - 1. / T0.as_kelvin() as f64),
+			);
+		let adc_sample_percentage = resistance / (resistance + thermistor.other_resistance as f64);
+
+		self.thermistor_pin
+			.set_value((adc_sample_percentage * self.max_adc_value as f64).round() as u16);
+	}
+}
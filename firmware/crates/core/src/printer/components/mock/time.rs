@@ -1,15 +1,36 @@
+use std::{sync::Arc, time::Duration};
+
+use spin::Mutex;
+
 use crate::printer::components::time::SystemTime;
 
-pub struct MockSystemTime;
+/// A [`SystemTime`] whose time is virtual: it only moves forward when you call [`MockSystemTime::advance`] or when
+/// the code under test calls [`SystemTime::delay`] (which returns immediately).
+///
+/// Cloning a `MockSystemTime` returns a handle to the same time.
+#[derive(Clone, Default)]
+pub struct MockSystemTime
+{
+	now: Arc<Mutex<Duration>>,
+}
+
+impl MockSystemTime
+{
+	pub fn advance(&self, duration: Duration)
+	{
+		*self.now.lock() += duration;
+	}
+}
+
 impl SystemTime for MockSystemTime
 {
-	fn now(&self) -> std::time::Duration
+	fn now(&self) -> Duration
 	{
-		todo!()
+		*self.now.lock()
 	}
 
-	fn delay(&self, _: std::time::Duration)
+	fn delay(&self, duration: Duration)
 	{
-		todo!()
+		self.advance(duration);
 	}
 }
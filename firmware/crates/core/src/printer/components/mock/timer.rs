@@ -1,9 +1,170 @@
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use spin::Mutex;
+
 use crate::{
-	printer::components::hal::timer::{Timer, TimerAdditionalFunctionality},
+	printer::components::hal::timer::{duration_to_counter, ticks_to_duration, Timer, TimerAdditionalFunctionality},
 	utils::measurement::frequency::Frequency,
 };
 
-pub struct MockTimer;
+/// A [`Timer`] whose time is virtual: it only moves forward when you call [`MockTimer::advance_time`] (or
+/// [`MockTimer::advance_ticks`]), and the callback registered with [`Timer::on_alarm`] is called when the time reaches
+/// the alarm (or when you call [`MockTimer::fire_alarm`]).
+///
+/// Cloning a `MockTimer` returns a handle to the same timer, so you can keep a clone to control the timer after you
+/// gave it to the code under test.
+///
+/// The alarm is one-shot like in a real timer: after it fires it must be set again (for example by the callback
+/// itself) to fire another time.
+///
+/// # Examples
+/// ```
+/// # use std::{time::Duration, sync::{Arc, atomic::{AtomicU32, Ordering}}};
+/// # use firmware_core::printer::components::{mock::*, hal::timer::*};
+/// #
+/// let mut timer = MockTimer::default();
+///
+/// let alarms_count = Arc::new(AtomicU32::new(0));
+/// let alarms_count_clone = Arc::clone(&alarms_count);
+/// let mut additional_functionality = timer.get_additional_functionality();
+/// unsafe {
+///     timer.on_alarm(move || {
+///         alarms_count_clone.fetch_add(1, Ordering::Relaxed);
+///     }).unwrap();
+/// }
+/// timer.enable_alarm(true).unwrap();
+/// additional_functionality.set_alarm(Duration::from_millis(10)).unwrap();
+///
+/// timer.advance_time(Duration::from_millis(5));
+/// assert_eq!(alarms_count.load(Ordering::Relaxed), 0);
+///
+/// timer.advance_time(Duration::from_millis(5));
+/// assert_eq!(alarms_count.load(Ordering::Relaxed), 1);
+///
+/// // The alarm has not been set again, so it doesn't fire anymore...
+/// timer.advance_time(Duration::from_millis(50));
+/// assert_eq!(alarms_count.load(Ordering::Relaxed), 1);
+///
+/// // ...unless you fire it manually
+/// timer.fire_alarm();
+/// assert_eq!(alarms_count.load(Ordering::Relaxed), 2);
+/// assert_eq!(additional_functionality.get_time().unwrap(), Duration::from_millis(60));
+/// ```
+#[derive(Clone)]
+pub struct MockTimer
+{
+	state: Arc<Mutex<MockTimerState>>,
+}
+
+struct MockTimerState
+{
+	clock_frequency: Frequency,
+	time_in_ticks: u64,
+	alarm_in_ticks: u64,
+	is_alarm_armed: bool,
+	is_alarm_enabled: bool,
+	callback: Option<Box<dyn FnMut() + Send>>,
+}
+
+impl MockTimer
+{
+	/// Returns a `MockTimer` whose time is `0` and that counts at `clock_frequency`.
+	pub fn new(clock_frequency: Frequency) -> Self
+	{
+		Self {
+			state: Arc::new(Mutex::new(MockTimerState {
+				clock_frequency,
+				time_in_ticks: 0,
+				alarm_in_ticks: 0,
+				is_alarm_armed: false,
+				is_alarm_enabled: false,
+				callback: None,
+			})),
+		}
+	}
+
+	/// Moves the time of the timer forward by `duration`, calling the alarm callback each time the alarm is reached.
+	pub fn advance_time(&mut self, duration: Duration)
+	{
+		let clock_frequency = self.state.lock().clock_frequency;
+		self.advance_ticks(duration_to_counter(duration, clock_frequency));
+	}
+
+	/// Like [`Self::advance_time`], but the time is expressed in ticks of the timer.
+	pub fn advance_ticks(&mut self, ticks: u64)
+	{
+		let target_time_in_ticks = self.state.lock().time_in_ticks + ticks;
+		loop
+		{
+			let mut state = self.state.lock();
+			if state.is_alarm_enabled && state.is_alarm_armed && state.alarm_in_ticks <= target_time_in_ticks
+			{
+				state.time_in_ticks = state.time_in_ticks.max(state.alarm_in_ticks);
+				drop(state);
+				self.fire_alarm();
+			}
+			else
+			{
+				state.time_in_ticks = target_time_in_ticks;
+				break;
+			}
+		}
+	}
+
+	/// Calls the callback registered with [`Timer::on_alarm`] (if there's one) without moving the time forward.
+	pub fn fire_alarm(&mut self)
+	{
+		let callback = {
+			let mut state = self.state.lock();
+			state.is_alarm_armed = false;
+			state.callback.take()
+		};
+
+		if let Some(mut callback) = callback
+		{
+			callback();
+
+			let mut state = self.state.lock();
+			// The callback may have registered a new callback
+			if state.callback.is_none()
+			{
+				state.callback = Some(callback);
+			}
+		}
+	}
+
+	/// Returns the current time of the timer in ticks.
+	pub fn get_time_in_ticks(&self) -> u64
+	{
+		self.state.lock().time_in_ticks
+	}
+
+	/// Returns `true` if the alarm is enabled and has been set but it hasn't fired yet.
+	pub fn is_alarm_pending(&self) -> bool
+	{
+		let state = self.state.lock();
+		state.is_alarm_enabled && state.is_alarm_armed
+	}
+}
+
+impl Default for MockTimer
+{
+	fn default() -> Self
+	{
+		Self::new(Frequency::from_hertz(1_000_000))
+	}
+}
+
+impl Debug for MockTimer
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		f.debug_struct("MockTimer")
+			.field("time_in_ticks", &self.get_time_in_ticks())
+			.finish_non_exhaustive()
+	}
+}
+
 impl Timer for MockTimer
 {
 	type Error = ();
@@ -11,52 +172,67 @@ impl Timer for MockTimer
 
 	fn get_additional_functionality(&self) -> Self::AdditionalFunctionality
 	{
-		MockTimerAdditionalFunctionality
+		MockTimerAdditionalFunctionality {
+			state: Arc::clone(&self.state),
+		}
 	}
 
-	fn get_clock_frequency(&self) -> crate::utils::measurement::frequency::Frequency
+	fn get_clock_frequency(&self) -> Frequency
 	{
-		Frequency::from_hertz(1_000_000)
+		self.state.lock().clock_frequency
 	}
 
-	unsafe fn on_alarm(&mut self, _: impl FnMut() + 'static) -> Result<(), Self::Error>
+	unsafe fn on_alarm(&mut self, callback: impl FnMut() + Send + 'static) -> Result<(), Self::Error>
 	{
-		todo!()
+		self.state.lock().callback = Some(Box::new(callback));
+
+		Ok(())
 	}
 
-	fn enable_alarm(&mut self, _: bool) -> Result<(), Self::Error>
+	fn enable_alarm(&mut self, enable: bool) -> Result<(), Self::Error>
 	{
-		todo!()
+		self.state.lock().is_alarm_enabled = enable;
+
+		Ok(())
 	}
 
 	fn get_alarm_in_ticks(&self) -> Result<u64, Self::Error>
 	{
-		todo!()
+		Ok(self.state.lock().alarm_in_ticks)
 	}
 }
 
-pub struct MockTimerAdditionalFunctionality;
+pub struct MockTimerAdditionalFunctionality
+{
+	state: Arc<Mutex<MockTimerState>>,
+}
 impl TimerAdditionalFunctionality for MockTimerAdditionalFunctionality
 {
 	type Error = ();
 
-	fn set_alarm(&mut self, _: std::time::Duration) -> Result<(), Self::Error>
+	fn set_alarm(&mut self, time: Duration) -> Result<(), Self::Error>
 	{
-		todo!()
+		let clock_frequency = self.state.lock().clock_frequency;
+		self.set_alarm_in_ticks(duration_to_counter(time, clock_frequency))
 	}
 
-	fn get_time(&self) -> Result<std::time::Duration, Self::Error>
+	fn get_time(&self) -> Result<Duration, Self::Error>
 	{
-		todo!()
+		let state = self.state.lock();
+		Ok(ticks_to_duration(state.time_in_ticks, state.clock_frequency))
 	}
 
 	fn set_alarm_in_ticks(&mut self, ticks: u64) -> Result<(), Self::Error>
 	{
-		todo!()
+		let mut state = self.state.lock();
+		state.alarm_in_ticks = ticks;
+		state.is_alarm_armed = true;
+
+		Ok(())
 	}
 
 	fn get_time_in_ticks(&self) -> Result<u64, Self::Error>
 	{
-		todo!()
+		Ok(self.state.lock().time_in_ticks)
 	}
 }
@@ -1,24 +1,68 @@
-use crate::printer::components::hal::uart::Uart;
+use std::{collections::VecDeque, sync::Arc};
+
+use spin::Mutex;
+
+use crate::{printer::components::hal::uart::Uart, utils::measurement::duration::SmallDuration};
+
+/// A [`Uart`] that records all the written bytes and returns scripted bytes when it's read (when there are no more
+/// scripted bytes, the reads time out returning `0` read bytes).
+///
+/// Cloning a `MockUart` returns a handle to the same UART.
+#[derive(Clone, Default, Debug)]
+pub struct MockUart
+{
+	state: Arc<Mutex<MockUartState>>,
+}
+
+#[derive(Default, Debug)]
+struct MockUartState
+{
+	read_bytes: VecDeque<u8>,
+	written_bytes: Vec<u8>,
+}
+
+impl MockUart
+{
+	/// Adds the `bytes` to the ones that will be returned by the next reads.
+	pub fn push_read_bytes(&mut self, bytes: impl IntoIterator<Item = u8>)
+	{
+		self.state.lock().read_bytes.extend(bytes);
+	}
+
+	/// Returns all the bytes written until now.
+	pub fn get_written_bytes(&self) -> Vec<u8>
+	{
+		self.state.lock().written_bytes.clone()
+	}
+}
 
-pub struct MockUart;
 impl Uart for MockUart
 {
 	type Error = ();
 
-	fn read(
-		&mut self, buf: &mut [u8], timeout: crate::utils::measurement::duration::SmallDuration,
-	) -> Result<usize, Self::Error>
+	fn read(&mut self, buf: &mut [u8], _: SmallDuration) -> Result<usize, Self::Error>
 	{
-		todo!()
+		let mut state = self.state.lock();
+		let read_bytes_count = buf.len().min(state.read_bytes.len());
+		for (byte, read_byte) in buf.iter_mut().zip(state.read_bytes.drain(..read_bytes_count))
+		{
+			*byte = read_byte;
+		}
+
+		Ok(read_bytes_count)
 	}
 
 	fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error>
 	{
-		todo!()
+		self.state.lock().written_bytes.extend_from_slice(buf);
+
+		Ok(buf.len())
 	}
 
 	fn flush_read(&mut self) -> Result<(), Self::Error>
 	{
-		todo!()
+		self.state.lock().read_bytes.clear();
+
+		Ok(())
 	}
 }
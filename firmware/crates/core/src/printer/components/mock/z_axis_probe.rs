@@ -1,6 +1,64 @@
+use std::{fmt::Debug, sync::Arc};
+
+use spin::Mutex;
+
 use crate::printer::components::motion::bed_leveling::ZAxisProbe;
 
-pub struct MockZAxisProbe;
+/// A [`ZAxisProbe`] that has to be triggered via software calling [`MockZAxisProbe::trigger`] (like a
+/// [`ManualEndstop`]).
+///
+/// Cloning a `MockZAxisProbe` returns a handle to the same probe, so you can keep a clone to trigger the probe after
+/// you gave it to the code under test.
+///
+/// [`ManualEndstop`]: crate::printer::components::motion::homing::endstop::ManualEndstop
+#[derive(Clone, Default)]
+pub struct MockZAxisProbe
+{
+	state: Arc<Mutex<MockZAxisProbeState>>,
+}
+
+#[derive(Default)]
+struct MockZAxisProbeState
+{
+	is_triggered: bool,
+	callback: Option<Box<dyn FnMut() + Send>>,
+}
+
+impl MockZAxisProbe
+{
+	/// Sets the value returned by [`ZAxisProbe::is_end_reached`] to `true` and calls the registered callback (if
+	/// there's one).
+	pub fn trigger(&mut self)
+	{
+		let callback = {
+			let mut state = self.state.lock();
+			state.is_triggered = true;
+			state.callback.take()
+		};
+
+		if let Some(mut callback) = callback
+		{
+			callback();
+			self.state.lock().callback.get_or_insert(callback);
+		}
+	}
+
+	/// Sets the value returned by [`ZAxisProbe::is_end_reached`] to `false`.
+	pub fn release(&mut self)
+	{
+		self.state.lock().is_triggered = false;
+	}
+}
+
+impl Debug for MockZAxisProbe
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		f.debug_struct("MockZAxisProbe")
+			.field("is_triggered", &self.state.lock().is_triggered)
+			.finish_non_exhaustive()
+	}
+}
 
 impl ZAxisProbe for MockZAxisProbe
 {
@@ -10,21 +68,24 @@ impl ZAxisProbe for MockZAxisProbe
 
 	fn is_end_reached(&self) -> Result<bool, Self::IsEndReachedError>
 	{
-		todo!()
+		Ok(self.state.lock().is_triggered)
 	}
 
-	unsafe fn on_end_reached(&mut self, _: impl FnMut() + 'static) -> Result<(), Self::OnEndReachedError>
+	unsafe fn on_end_reached(&mut self, callback: impl FnMut() + Send + 'static)
+		-> Result<(), Self::OnEndReachedError>
 	{
-		todo!()
+		self.state.lock().callback = Some(Box::new(callback));
+
+		Ok(())
 	}
 
 	fn prepare_for_homing(&mut self) -> Result<(), Self::HomingError>
 	{
-		todo!()
+		Ok(())
 	}
 
 	fn finish_homing(&mut self) -> Result<(), Self::HomingError>
 	{
-		todo!()
+		Ok(())
 	}
 }
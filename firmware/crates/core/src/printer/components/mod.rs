@@ -8,6 +8,8 @@
 
 pub mod config;
 pub mod drivers;
+pub mod emergency_stop;
+pub mod filament_change;
 pub mod file_system;
 pub mod g_code;
 pub mod hal;
@@ -15,28 +17,46 @@ pub mod mock;
 pub mod motion;
 pub mod pauser;
 mod peripherals;
+pub mod persisted_settings;
+pub mod power_loss_recovery;
+pub mod print_history;
 pub mod print_process;
+pub mod print_queue;
 pub mod temperature;
 pub mod time;
 
-use std::fmt::Debug;
+use std::{fmt::Debug, time::Duration};
 
+use embedded_hal::digital::{Error as _, ErrorKind as InputPinErrorKind};
 use motion::planner::communicate_to_ticker;
 pub use peripherals::*;
 
 use self::{
-	config::ComponentsConfig,
-	drivers::{cartridge_heater::CartridgeHeater, fan::Fan, stepper_motor::StepperMotor, thermistor::Thermistor},
-	g_code::execute::GCodeExecuter,
-	hal::{timer::Timer as TimerTrait, uart::Uart as UartTrait},
+	config::{ComponentsConfig, EndPrintSequence, FilamentChangeConfig},
+	drivers::{
+		button::Button, cartridge_heater::CartridgeHeater, fan::Fan, filament_sensor::FilamentSensor,
+		stepper_motor::StepperMotor, thermistor::Thermistor,
+	},
+	g_code::{
+		commands::{G1, M84},
+		execute::GCodeExecuter,
+		parameters::Param,
+		GCodeCommand,
+	},
+	hal::{pwm::PwmPin, timer::Timer as TimerTrait, uart::Uart as UartTrait},
 	motion::{
+		axes::Axis,
 		bed_leveling::{Probe, ZAxisProbe},
 		MotionController,
 	},
+	pauser::Pauser,
+	persisted_settings::PersistedSettings,
+	power_loss_recovery::{Checkpointer, PrintCheckpoint},
 	temperature::{safety::TemperatureSafety, TemperaturePidController},
 	time::Clock,
 };
 use super::communication::http::other::printer_state;
+use crate::utils::{math::Percentage, measurement::distance::Distance};
 
 /// This struct encapsulates all the elements required to make a 3D print possible, including fans,
 /// motion control, temperature controllers, and the G-code executer.
@@ -58,6 +78,25 @@ pub struct Printer3DComponents<P: Peripherals>
 	pub clock: Clock<P::SystemTime>,
 
 	pub g_code_executer: Option<GCodeExecuter<P>>,
+
+	/// The sensor that detects if the filament has run out (it's optional).
+	filament_sensor: Option<FilamentSensor<P::FilamentSensorPin>>,
+	/// The button that triggers an [`emergency stop`](emergency_stop) when pressed (it's optional).
+	///
+	/// It's never read, but it must be kept alive to keep receiving its interrupts.
+	_emergency_stop_button: Option<Button<P::EmergencyStopButtonPin>>,
+	/// `true` if the machine has already been stopped after an [`emergency stop`](emergency_stop).
+	has_halted: bool,
+
+	print_checkpointer: Checkpointer,
+	end_print_sequence: EndPrintSequence,
+	pauser: Pauser,
+
+	/// How long the stepper motors keep holding their position after the last move (check
+	/// [`Self::disable_steppers_when_idle_for`]).
+	steppers_idle_time: Duration,
+	/// The time at which the stepper motors have stopped moving, if they are idle and enabled.
+	steppers_idle_since: Option<Duration>,
 }
 
 impl<P: Peripherals> Printer3DComponents<P>
@@ -225,6 +264,24 @@ impl<P: Peripherals> Printer3DComponents<P>
 			.map_err(CreationError::MotionController)?,
 			uart_driver,
 			g_code_executer: Some(GCodeExecuter::default()),
+			filament_sensor: peripherals
+				.take_filament_sensor_pin()
+				.map(|pin| FilamentSensor::new(Button::new(pin), config.filament_sensor)),
+			_emergency_stop_button: peripherals
+				.take_emergency_stop_button_pin()
+				.map(|pin| {
+					let mut button = Button::new(pin);
+					// SAFETY: emergency_stop::stop only stores an atomic flag and pulls the pins low
+					unsafe { button.on_pressed(emergency_stop::stop) }.map(|_| button)
+				})
+				.transpose()
+				.map_err(|_| CreationError::EmergencyStopButton)?,
+			has_halted: false,
+			print_checkpointer: Checkpointer::new(config.print_checkpoint_interval),
+			end_print_sequence: config.end_print_sequence,
+			pauser: Pauser::new(config.pause_sequence, config.filament_change),
+			steppers_idle_time: Duration::ZERO,
+			steppers_idle_since: None,
 		})
 	}
 
@@ -239,15 +296,67 @@ impl<P: Peripherals> Printer3DComponents<P>
 		let delta_time = self.clock.get_delta_time().as_secs_f64();
 		self.clock.tick();
 
-		if !pauser::is_paused()
+		if emergency_stop::is_halted()
 		{
-			if let Some(mut g_code_executer) = self.g_code_executer.take()
+			return self.tick_halted(delta_time);
+		}
+
+		let is_printing = print_process::is_reading_file()
+			|| self
+				.g_code_executer
+				.as_ref()
+				.is_some_and(|g_code_executer| g_code_executer.has_file_command_to_execute());
+		let was_running = !self.pauser.is_active();
+		self.pauser.tick::<P>(
+			&mut self.motion_controller,
+			&mut self.hotend_pid_controller,
+			is_printing,
+		);
+
+		if let Some(mut g_code_executer) = self.g_code_executer.take()
+		{
+			if !self.pauser.is_active()
 			{
 				g_code_executer.tick(self).map_err(TickError::GCodeExecuter)?;
-				self.g_code_executer = Some(g_code_executer);
 			}
+			else if was_running
+			{
+				g_code_executer
+					.unprepare_commands(self)
+					.map_err(TickError::GCodeExecuter)?;
+			}
+			self.g_code_executer = Some(g_code_executer);
 		}
 
+		let extruded_filament = self.motion_controller.take_extruded_filament();
+		print_history::add_extruded_filament(extruded_filament);
+		if let Some(filament_sensor) = self.filament_sensor.as_mut()
+		{
+			// The filament is expected to move only while a print is running, and it's replaced while it's paused
+			let is_detecting_runout = is_printing && !self.pauser.is_active();
+			let extruded_filament = match is_detecting_runout
+			{
+				true => extruded_filament,
+				false =>
+				{
+					filament_sensor.rearm();
+					Distance::ZERO
+				},
+			};
+			if filament_sensor
+				.tick(extruded_filament, self.clock.get_elapsed_time())
+				.map_err(|error| TickError::FilamentSensor(error.kind()))?
+			{
+				log::warn!(
+					"The filament sensor has detected that the filament is {:?}, so it's going to be changed",
+					filament_sensor.get_state()
+				);
+				filament_change::request();
+			}
+		}
+
+		self.checkpoint_print();
+
 		self.heated_bed_pid_controller
 			.tick(delta_time, &mut self.adc)
 			.map_err(TickError::HeatedBedPidController)?;
@@ -255,24 +364,215 @@ impl<P: Peripherals> Printer3DComponents<P>
 			.tick(delta_time, &mut self.adc)
 			.map_err(TickError::HotendPidController)?;
 
-		printer_state::tick::<P>(&self.hotend_pid_controller, &self.heated_bed_pid_controller);
+		printer_state::tick::<P>(
+			&self.hotend_pid_controller,
+			&self.heated_bed_pid_controller,
+			self.filament_sensor.as_ref().map(FilamentSensor::get_state),
+		);
 
-		let mut is_moving = !pauser::is_paused();
+		let mut is_moving = true;
+		if let Some(g_code_executer) = self.g_code_executer.as_ref()
+		{
+			is_moving = g_code_executer.has_command_to_execute() || communicate_to_ticker::is_block_available();
+		}
 		if is_moving
 		{
-			if let Some(g_code_executer) = self.g_code_executer.as_ref()
-			{
-				is_moving = g_code_executer.has_command_to_execute() || communicate_to_ticker::is_block_available();
-			}
+			self.steppers_idle_since = None;
 		}
+		// While the print is paused the stepper motors must keep holding their position, otherwise the nozzle would
+		// not go back exactly where it was
+		let are_steppers_holding = !is_moving && (self.pauser.is_active() || self.should_steppers_hold_position());
 
 		self.motion_controller
-			.set_paused(!is_moving, &mut self.uart_driver)
+			.set_paused(!is_moving && !are_steppers_holding, &mut self.uart_driver)
 			.map_err(TickError::PausingMotionController)?;
 		self.motion_controller.tick().map_err(TickError::MotionController)?;
 
 		Ok(())
 	}
+
+	/// Keeps the machine safe after an [`emergency stop`](emergency_stop): the first time it's called the G-code
+	/// commands and the planned moves are discarded and the stepper motors are [`halted`], then the heaters are kept
+	/// off, and nothing else is done until the microcontroller is reset.
+	///
+	/// [`halted`]: MotionController::halt
+	fn tick_halted(
+		&mut self, delta_time: f64,
+	) -> Result<(), TickError<P::ZAxisEndstop, P::UartDriver, P::StepperTickerTimer>>
+	{
+		if !self.has_halted
+		{
+			log::error!("Emergency stop: turning off the heaters and the stepper motors");
+			self.has_halted = true;
+
+			pauser::resume();
+			self.pauser.abort();
+			if let Some(g_code_executer) = self.g_code_executer.as_mut()
+			{
+				g_code_executer.clear();
+			}
+			// The print can't be resumed after an emergency stop
+			power_loss_recovery::request_clear();
+
+			self.motion_controller
+				.halt(&mut self.uart_driver)
+				.map_err(TickError::PausingMotionController)?;
+		}
+
+		// The commands sent by the `Communication` thread could have set a target temperature again
+		self.heated_bed_pid_controller.set_target_temperature(None);
+		self.hotend_pid_controller.set_target_temperature(None);
+		self.heated_bed_pid_controller
+			.tick(delta_time, &mut self.adc)
+			.map_err(TickError::HeatedBedPidController)?;
+		self.hotend_pid_controller
+			.tick(delta_time, &mut self.adc)
+			.map_err(TickError::HotendPidController)?;
+
+		printer_state::tick::<P>(
+			&self.hotend_pid_controller,
+			&self.heated_bed_pid_controller,
+			self.filament_sensor.as_ref().map(FilamentSensor::get_state),
+		);
+
+		Ok(())
+	}
+
+	/// Returns how the filament is moved when it's [`changed`](filament_change).
+	pub fn get_filament_change_config(&self) -> &FilamentChangeConfig
+	{
+		self.pauser.get_filament_change_config()
+	}
+
+	/// Makes the stepper motors keep holding their position for `idle_time` after the last move, before being
+	/// disabled (this only applies to the next time they stop moving).
+	pub fn disable_steppers_when_idle_for(&mut self, idle_time: Duration)
+	{
+		self.steppers_idle_time = idle_time;
+		self.steppers_idle_since = None;
+	}
+
+	/// Returns `true` if the stepper motors aren't moving but they must still hold their position (check
+	/// [`Self::disable_steppers_when_idle_for`]).
+	fn should_steppers_hold_position(&mut self) -> bool
+	{
+		let current_time = self.clock.get_elapsed_time();
+		let idle_since = *self.steppers_idle_since.get_or_insert(current_time);
+		let should_hold = current_time < idle_since + self.steppers_idle_time;
+		if !should_hold
+		{
+			self.steppers_idle_time = Duration::ZERO;
+		}
+
+		should_hold
+	}
+
+	/// Stops the print in execution as soon as possible: all the G-code commands that haven't been executed yet and
+	/// the moves that haven't started yet are discarded, the heaters and the layer fan are turned off, then the
+	/// [`EndPrintSequence`] of the [`ComponentsConfig`] is executed.
+	///
+	/// The commands are not read from the printed file anymore only when the `Communication` thread stops the
+	/// [`PrintProcess`](print_process::PrintProcess), so this must be called after that.
+	pub fn cancel_print(&mut self) -> Result<(), <P::FanPin as PwmPin>::Error>
+	{
+		pauser::resume();
+		self.pauser.abort();
+		self.motion_controller.discard_planned_moves();
+		self.hotend_pid_controller.set_target_temperature(None);
+		self.heated_bed_pid_controller.set_target_temperature(None);
+		// The print will never be resumed, so its checkpoint is useless
+		power_loss_recovery::request_clear();
+
+		if let Some(g_code_executer) = self.g_code_executer.as_mut()
+		{
+			g_code_executer.clear();
+
+			let sequence = &self.end_print_sequence.parking;
+			let position = self.motion_controller.get_position();
+			let to_mm_min = |speed_mm_s: f32| Param::from(speed_mm_s * 60.);
+			// The nozzle is raised as much as possible without crashing into the top of the Z axis (and it's never lowered)
+			let z = position[Axis::Z as usize];
+			let lifted_z = (z + sequence.z_lift).min(self.motion_controller.get_max_z()).max(z);
+			let mut commands: Vec<Box<dyn GCodeCommand<P>>> = vec![
+				Box::new(G1 {
+					e: Param::from(position[Axis::E as usize] - sequence.retraction_length),
+					feed_rate: (to_mm_min)(sequence.retraction_speed_mm_s),
+					..Default::default()
+				}),
+				Box::new(G1 {
+					z: Param::from(lifted_z),
+					feed_rate: (to_mm_min)(sequence.z_lift_speed_mm_s),
+					..Default::default()
+				}),
+			];
+			if let Some(park_position) = sequence.park_position.as_ref()
+			{
+				commands.push(Box::new(G1 {
+					x: Param::from(park_position.x()),
+					y: Param::from(park_position.y()),
+					feed_rate: (to_mm_min)(sequence.park_speed_mm_s),
+					..Default::default()
+				}));
+			}
+			commands.push(Box::new(M84 {
+				idle_time_in_seconds: Param::from(self.end_print_sequence.disable_steppers_after.as_secs() as u16),
+			}));
+			g_code_executer.add_commands_to_buffer(commands);
+		}
+
+		self.layer_fan.set_speed(Percentage::ZERO)
+	}
+
+	/// Requests to store a [`PrintCheckpoint`] of the print in execution when needed, and to clear it when the print
+	/// has ended.
+	fn checkpoint_print(&mut self)
+	{
+		if let Some(g_code_executer) = self.g_code_executer.as_ref()
+		{
+			if let Some(file_command_state) = g_code_executer.get_last_executed_file_command()
+			{
+				if self
+					.print_checkpointer
+					.should_store(file_command_state.file_position, self.clock.get_elapsed_time())
+				{
+					power_loss_recovery::request_store(PrintCheckpoint::new(
+						file_command_state,
+						self.hotend_pid_controller.get_target_temperature(),
+						self.heated_bed_pid_controller.get_target_temperature(),
+						self.layer_fan.get_speed(),
+					));
+				}
+			}
+
+			let has_print_ended = !g_code_executer.has_command_to_execute() && !print_process::is_reading_file();
+			if has_print_ended && self.print_checkpointer.should_clear()
+			{
+				power_loss_recovery::request_clear();
+			}
+		}
+	}
+
+	/// Returns the current value of all the settings that can be [`persisted`] in the flash memory.
+	///
+	/// [`persisted`]: persisted_settings
+	pub fn get_persisted_settings(&self) -> PersistedSettings
+	{
+		PersistedSettings {
+			skew: self.motion_controller.get_skew_correction(),
+			backlash: self.motion_controller.get_backlash_settings(),
+			retraction: self.motion_controller.get_retraction_settings(),
+		}
+	}
+
+	/// Replaces the current value of all the settings that can be [`persisted`] with the provided `settings`.
+	///
+	/// [`persisted`]: persisted_settings
+	pub fn apply_persisted_settings(&mut self, settings: PersistedSettings)
+	{
+		self.motion_controller.set_skew_correction(settings.skew);
+		*self.motion_controller.get_backlash_settings_mut() = settings.backlash;
+		*self.motion_controller.get_retraction_settings_mut() = settings.retraction;
+	}
 }
 
 #[derive(Debug)]
@@ -288,6 +588,9 @@ pub enum CreationError<Timer: TimerTrait, ZEndstop: ZAxisProbe, Uart: UartTrait>
 
 	Endstop,
 
+	/// It has been impossible to subscribe to the interrupt of the emergency stop button.
+	EmergencyStopButton,
+
 	MotionController(motion::CreationError<Timer, ZEndstop, Uart>),
 }
 
@@ -299,6 +602,7 @@ pub enum TickError<ZEndstop: ZAxisProbe, Uart: UartTrait, Timer: TimerTrait>
 	HotendPidController(temperature::PidUpdateError),
 	MotionController(motion::TickError<Probe<ZEndstop>>),
 	PausingMotionController(motion::SetPausedError<Uart, Timer>),
+	FilamentSensor(InputPinErrorKind),
 }
 
 impl<ZEndstop: ZAxisProbe, Uart: UartTrait, Timer: TimerTrait> Debug for TickError<ZEndstop, Uart, Timer>
@@ -312,6 +616,7 @@ impl<ZEndstop: ZAxisProbe, Uart: UartTrait, Timer: TimerTrait> Debug for TickErr
 			Self::HotendPidController(arg0) => f.debug_tuple("HotendPidController").field(arg0).finish(),
 			Self::MotionController(arg0) => f.debug_tuple("MotionController").field(arg0).finish(),
 			Self::PausingMotionController(arg0) => f.debug_tuple("PausingMotionController").field(arg0).finish(),
+			Self::FilamentSensor(arg0) => f.debug_tuple("FilamentSensor").field(arg0).finish(),
 		}
 	}
 }
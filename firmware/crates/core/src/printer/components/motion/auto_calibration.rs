@@ -0,0 +1,89 @@
+//! Module for managing the auto-calibration procedure of the [`Kinematics`], which probes some points of the bed
+//! and uses the results to correct the parameters of the kinematics (for example the geometry of a delta machine).
+
+use super::{
+	bed_leveling::{BedLevelingProcedure, PointsProbing},
+	kinematics::Kinematics,
+	planner::{PlanMoveError, Planner},
+};
+use crate::utils::{math::vectors::Vector2, measurement::distance::Distance};
+
+#[derive(Default)]
+pub struct AutoCalibrationProcedure
+{
+	points_probing: PointsProbing,
+	probed_points: Vec<(Vector2, Distance)>,
+}
+
+impl AutoCalibrationProcedure
+{
+	/// Plans the moves required to probe the first of the [`auto-calibration points`] of the `kinematics` (the other
+	/// ones are planned by [`Self::tick`] once the previous one has been probed).
+	///
+	/// If the `kinematics` doesn't support auto-calibration (it returns no points), nothing happens.
+	///
+	/// Returns `Err(PlanMoveError::BlocksBufferIsFull)` if not all the moves could be planned, and you **MUST** call
+	/// this method again to plan the remaining ones.
+	///
+	/// [`auto-calibration points`]: Kinematics::auto_calibration_points
+	pub fn start<const N: usize, K: Kinematics>(
+		&mut self, planner: &mut Planner<N>, kinematics: &K, steps_per_mm: [f32; N],
+	) -> Result<(), PlanMoveError>
+	{
+		// It's a new start
+		if !self.points_probing.is_probing()
+		{
+			self.points_probing.start(kinematics.auto_calibration_points());
+			self.probed_points.clear();
+		}
+
+		self.points_probing.plan_moves(planner, kinematics, steps_per_mm)
+	}
+
+	/// Returns `true` if the procedure has been [`started`] and not all the points have been probed yet.
+	///
+	/// [`started`]: Self::start
+	pub fn is_calibrating(&self) -> bool
+	{
+		self.points_probing.is_probing()
+	}
+
+	/// Reads the result of the last probed point and plans the moves required to probe the next one, and when all
+	/// the points have been probed [`auto-calibrates`] the `kinematics`.
+	///
+	/// Returns `Err(PlanMoveError::UnreachablePosition)` if the tool can't reach the next point (and the procedure is
+	/// stopped).
+	///
+	/// [`auto-calibrates`]: Kinematics::auto_calibrate
+	pub fn tick<const N: usize, K: Kinematics>(
+		&mut self, planner: &mut Planner<N>, kinematics: &mut K, steps_per_mm: impl FnOnce() -> [f32; N],
+	) -> Result<(), PlanMoveError>
+	{
+		if !self.points_probing.is_probing()
+		{
+			return Ok(());
+		}
+
+		if let Some((point_index, probing_distance)) = self.points_probing.tick(planner)
+		{
+			let point = self.points_probing.get_points()[point_index];
+			// The probe moved down starting from `DISTANCE_FROM_BED`, so if it travelled more than that the nozzle was
+			// higher than expected
+			let error = -probing_distance - BedLevelingProcedure::DISTANCE_FROM_BED;
+			self.probed_points.push((point, error));
+
+			if !self.points_probing.is_probing()
+			{
+				kinematics.auto_calibrate(&self.probed_points);
+				return Ok(());
+			}
+		}
+
+		match self.points_probing.plan_moves(planner, kinematics, (steps_per_mm)())
+		{
+			// If the buffer is full the moves are planned in the next ticks
+			Ok(()) | Err(PlanMoveError::BlocksBufferIsFull) => Ok(()),
+			Err(error) => Err(error),
+		}
+	}
+}
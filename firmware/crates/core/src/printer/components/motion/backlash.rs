@@ -0,0 +1,329 @@
+//! Module for compensating the backlash of the axes (the distance a motor must move after changing direction before
+//! the axis actually starts moving, for example due to the play of a lead screw or of a belt).
+
+use super::axes::Axis;
+use crate::{
+	printer::components::drivers::stepper_motor::RotationalDirection,
+	utils::{
+		math::vectors::{Vector3, VectorN},
+		measurement::distance::Distance,
+	},
+};
+
+/// Settings of a [`BacklashCompensation`] (they can be changed at runtime using the `M425` G-code command).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BacklashSettings
+{
+	/// Backlash of the X, Y and Z axes.
+	pub distances: Vector3,
+	/// Fraction (from `0` to `1`) of the [`distances`] that is actually compensated.
+	///
+	/// [`distances`]: Self::distances
+	pub correction: f32,
+	/// Distance (travelled by the tool) over which the correction is spread after an axis changes direction.
+	/// If it's [`Distance::ZERO`] the whole correction is applied in the move that changes direction.
+	pub smoothing_distance: Distance,
+}
+
+impl Default for BacklashSettings
+{
+	fn default() -> Self
+	{
+		Self {
+			distances: Vector3::ZERO,
+			correction: 1.,
+			smoothing_distance: Distance::ZERO,
+		}
+	}
+}
+
+/// Adds to the target position of each move the distance required to compensate the backlash of the axes that change
+/// direction.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::{utils::{measurement::distance::*, math::vectors::*}, printer::components::motion::backlash::*};
+/// #
+/// let mut backlash_compensation = BacklashCompensation::new(BacklashSettings {
+///     distances: Vector3::from_xyz(Distance::from_micrometers(100), Distance::ZERO, Distance::ZERO),
+///     ..Default::default()
+/// });
+///
+/// let x = |millimeters| VectorN::new([Distance::from_millimeters(millimeters), Distance::ZERO, Distance::ZERO, Distance::ZERO]);
+///
+/// // The first move sets the direction of the axis
+/// let mut target_position = x(10);
+/// backlash_compensation.apply(&x(0), &mut target_position);
+/// assert_eq!(target_position, x(10));
+///
+/// // Moving in the same direction doesn't require any compensation
+/// let mut target_position = x(20);
+/// backlash_compensation.apply(&x(10), &mut target_position);
+/// assert_eq!(target_position, x(20));
+///
+/// // When the axis changes direction, the backlash is compensated in this move and in all the next ones
+/// let mut target_position = x(15);
+/// backlash_compensation.apply(&x(20), &mut target_position);
+/// assert_eq!(target_position[0], Distance::from_micrometers(14_900));
+///
+/// let mut target_position = x(5);
+/// backlash_compensation.apply(&x(15), &mut target_position);
+/// assert_eq!(target_position[0], Distance::from_micrometers(4_900));
+///
+/// // Until the axis changes direction again
+/// let mut target_position = x(10);
+/// backlash_compensation.apply(&x(5), &mut target_position);
+/// assert_eq!(target_position, x(10));
+/// ```
+#[derive(Clone, Debug)]
+pub struct BacklashCompensation
+{
+	settings: BacklashSettings,
+	/// Direction in which each axis moved first (the backlash is compensated only while it moves in the other one).
+	first_directions: [Option<RotationalDirection>; 3],
+	last_directions: [Option<RotationalDirection>; 3],
+	/// Correction currently added to the position of each axis.
+	applied_correction: [Distance; 3],
+	/// Correction that must be added to the position of each axis (it's different from the `applied_correction`
+	/// while the correction is being smoothed).
+	target_correction: [Distance; 3],
+	/// Difference between the `target_correction` and the `applied_correction` of each axis when the target last
+	/// changed, which is spread over the [`smoothing distance`].
+	///
+	/// [`smoothing distance`]: BacklashSettings::smoothing_distance
+	smoothed_correction: [Distance; 3],
+}
+
+impl BacklashCompensation
+{
+	const AXES: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+	pub fn new(settings: BacklashSettings) -> Self
+	{
+		Self {
+			settings,
+			first_directions: [None; 3],
+			last_directions: [None; 3],
+			applied_correction: [Distance::ZERO; 3],
+			target_correction: [Distance::ZERO; 3],
+			smoothed_correction: [Distance::ZERO; 3],
+		}
+	}
+
+	pub fn get_settings(&self) -> BacklashSettings
+	{
+		self.settings
+	}
+
+	/// Returns a mutable reference to the [`BacklashSettings`] you provided to [`Self::new`].
+	pub fn get_settings_mut(&mut self) -> &mut BacklashSettings
+	{
+		&mut self.settings
+	}
+
+	/// Forgets the direction of the `axes` and removes their correction (you should call this when they're homed).
+	pub fn reset(&mut self, axes: &[Axis])
+	{
+		for (i, axis) in Self::AXES.into_iter().enumerate()
+		{
+			if axes.contains(&axis)
+			{
+				self.first_directions[i] = None;
+				self.last_directions[i] = None;
+				self.applied_correction[i] = Distance::ZERO;
+				self.target_correction[i] = Distance::ZERO;
+				self.smoothed_correction[i] = Distance::ZERO;
+			}
+		}
+	}
+
+	/// Updates the direction of each axis based on the move from `start_position` to `target_position` and adds the
+	/// backlash correction to `target_position`.
+	///
+	/// Both the positions must be without the correction (check [`Self::remove_correction`]).
+	pub fn apply<const N: usize>(&mut self, start_position: &VectorN<N>, target_position: &mut VectorN<N>)
+	{
+		let displacement = target_position.clone() - start_position;
+		let move_length_mm = Vector3::from_xyz(
+			displacement[Axis::X as usize],
+			displacement[Axis::Y as usize],
+			displacement[Axis::Z as usize],
+		)
+		.length_millimeters();
+
+		for (i, axis) in Self::AXES.into_iter().enumerate()
+		{
+			let backlash = self.settings.distances[i] * self.settings.correction;
+
+			let axis_displacement = displacement[axis as usize].as_tens_of_nanometers();
+			if axis_displacement != 0
+			{
+				let direction = RotationalDirection::from_sign(axis_displacement);
+				self.first_directions[i].get_or_insert(direction);
+				self.last_directions[i] = Some(direction);
+			}
+
+			// The target is derived from the current direction (instead of being updated at each direction change),
+			// so that changing the settings doesn't leave an offset in the position
+			let target_correction = match self.last_directions[i]
+			{
+				Some(direction) if self.first_directions[i] != Some(direction) => match direction
+				{
+					RotationalDirection::CW => backlash,
+					RotationalDirection::CCW => -backlash,
+				},
+				_ => Distance::ZERO,
+			};
+			if target_correction != self.target_correction[i]
+			{
+				self.target_correction[i] = target_correction;
+				self.smoothed_correction[i] = target_correction - self.applied_correction[i];
+			}
+
+			let remaining_correction = self.target_correction[i] - self.applied_correction[i];
+			if remaining_correction != Distance::ZERO
+			{
+				let smoothing_distance_mm = self.settings.smoothing_distance.as_millimeters_f32();
+				self.applied_correction[i] = if smoothing_distance_mm <= 0.
+				{
+					self.target_correction[i]
+				}
+				else
+				{
+					let max_correction = (self.smoothed_correction[i] * (move_length_mm / smoothing_distance_mm))
+						.as_tens_of_nanometers()
+						.abs();
+					self.applied_correction[i]
+						+ Distance::from_tens_of_nanometers(
+							remaining_correction
+								.as_tens_of_nanometers()
+								.clamp(-max_correction, max_correction),
+						)
+				};
+			}
+
+			target_position[axis as usize] += self.applied_correction[i];
+		}
+	}
+
+	/// Adds the correction currently applied to the axes to `position`.
+	pub fn add_correction<const N: usize>(&self, position: &mut VectorN<N>)
+	{
+		for (i, axis) in Self::AXES.into_iter().enumerate()
+		{
+			position[axis as usize] += self.applied_correction[i];
+		}
+	}
+
+	/// Removes the correction currently applied to the axes from `position`.
+	pub fn remove_correction<const N: usize>(&self, position: &mut VectorN<N>)
+	{
+		for (i, axis) in Self::AXES.into_iter().enumerate()
+		{
+			position[axis as usize] -= self.applied_correction[i];
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	/// Returns a position with the provided X and Z coordinates (in micrometers).
+	fn xz(x: i32, z: i32) -> VectorN<4>
+	{
+		VectorN::new([
+			Distance::from_micrometers(x),
+			Distance::ZERO,
+			Distance::from_micrometers(z),
+			Distance::ZERO,
+		])
+	}
+
+	/// Plans a move from `start_position` to `target_position` and returns the corrected target position.
+	fn apply(
+		backlash_compensation: &mut BacklashCompensation, start_position: VectorN<4>, mut target_position: VectorN<4>,
+	) -> VectorN<4>
+	{
+		backlash_compensation.apply(&start_position, &mut target_position);
+		target_position
+	}
+
+	#[test]
+	fn changing_the_backlash_doesnt_leave_an_offset()
+	{
+		let mut backlash_compensation = BacklashCompensation::new(BacklashSettings {
+			distances: Vector3::from_xyz(Distance::from_micrometers(100), Distance::ZERO, Distance::ZERO),
+			..Default::default()
+		});
+		apply(&mut backlash_compensation, xz(0, 0), xz(10_000, 0));
+		assert_eq!(
+			apply(&mut backlash_compensation, xz(10_000, 0), xz(5_000, 0)),
+			xz(4_900, 0)
+		);
+
+		backlash_compensation.get_settings_mut().distances[0] = Distance::from_micrometers(50);
+		assert_eq!(
+			apply(&mut backlash_compensation, xz(5_000, 0), xz(10_000, 0)),
+			xz(10_000, 0)
+		);
+		assert_eq!(
+			apply(&mut backlash_compensation, xz(10_000, 0), xz(5_000, 0)),
+			xz(4_950, 0)
+		);
+
+		backlash_compensation.get_settings_mut().correction = 0.;
+		assert_eq!(
+			apply(&mut backlash_compensation, xz(5_000, 0), xz(4_000, 0)),
+			xz(4_000, 0)
+		);
+	}
+
+	#[test]
+	fn smoothed_correction_is_removed_when_the_backlash_is_disabled()
+	{
+		let mut backlash_compensation = BacklashCompensation::new(BacklashSettings {
+			distances: Vector3::from_xyz(Distance::from_micrometers(100), Distance::ZERO, Distance::ZERO),
+			smoothing_distance: Distance::from_millimeters(1),
+			..Default::default()
+		});
+		apply(&mut backlash_compensation, xz(0, 0), xz(10_000, 0));
+		// Only half of the correction is applied, since the move is half of the smoothing distance
+		assert_eq!(
+			apply(&mut backlash_compensation, xz(10_000, 0), xz(9_500, 0)),
+			xz(9_450, 0)
+		);
+
+		backlash_compensation.get_settings_mut().distances = Vector3::ZERO;
+		assert_eq!(
+			apply(&mut backlash_compensation, xz(9_500, 0), xz(9_000, 0)),
+			xz(8_975, 0)
+		);
+		assert_eq!(
+			apply(&mut backlash_compensation, xz(9_000, 0), xz(8_000, 0)),
+			xz(8_000, 0)
+		);
+	}
+
+	#[test]
+	fn resetting_some_axes_keeps_the_correction_of_the_others()
+	{
+		let mut backlash_compensation = BacklashCompensation::new(BacklashSettings {
+			distances: Vector3::from_xyz(
+				Distance::from_micrometers(100),
+				Distance::ZERO,
+				Distance::from_micrometers(100),
+			),
+			..Default::default()
+		});
+		apply(&mut backlash_compensation, xz(0, 0), xz(10_000, 10_000));
+		apply(&mut backlash_compensation, xz(10_000, 10_000), xz(5_000, 5_000));
+
+		backlash_compensation.reset(&[Axis::X, Axis::Y]);
+
+		let mut position = xz(0, 0);
+		backlash_compensation.add_correction(&mut position);
+		assert_eq!(position, xz(0, -100));
+	}
+}
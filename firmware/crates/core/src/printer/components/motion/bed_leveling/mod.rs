@@ -10,7 +10,7 @@ pub use z_axis_probe::*;
 use super::{
 	axes::Axis,
 	kinematics::Kinematics,
-	planner::{communicate_to_ticker, BlocksBufferIsFull, Flag, Planner},
+	planner::{communicate_to_ticker, Flag, PlanMoveError, Planner},
 };
 use crate::utils::{
 	math::vectors::{Vector2, VectorN},
@@ -21,18 +21,11 @@ pub struct BedLevelingProcedure
 {
 	unified_bed_leveling: UnifiedBedLevelingProcedure,
 	bed_size: Vector2,
-	/// The whole purpose of this variable is to solve the problem of the block buffer being full in the middle of the
-	/// bed leveling procedure. With this variable it can re-start from the point where it left off.
-	current_point_planned_index: u16,
-	current_point_index: u16,
+	points_probing: PointsProbing,
 }
 
 impl BedLevelingProcedure
 {
-	/// Movement speed at which the printer is probed.
-	const MOVE_SPEED_MM_SECOND: f32 = 40.;
-	/// Movement speed of probing at which the printer is probed.
-	const PROBE_MOVE_SPEED_MM_SECOND: f32 = 10.;
 	/// Distance of the z axis probe from the bed (z axis) for each probed point.
 	pub const DISTANCE_FROM_BED: Distance = Distance::from_centimeters(3);
 	/// Distance of the z axis probe from the bed margins (x and y axes).
@@ -45,96 +38,211 @@ impl BedLevelingProcedure
 		Self {
 			unified_bed_leveling: UnifiedBedLevelingProcedure::new(),
 			bed_size,
-			current_point_planned_index: 0,
-			current_point_index: 0,
+			points_probing: PointsProbing::default(),
 		}
 	}
 
+	/// Plans the moves required to probe the first point of the grid (the other ones are planned by [`Self::tick`]
+	/// once the previous one has been probed).
+	///
+	/// Returns `Err(PlanMoveError::BlocksBufferIsFull)` if not all the moves could be planned, and you **MUST** call
+	/// this method again to plan the remaining ones.
 	pub fn start<const N: usize, K: Kinematics>(
-		&mut self, planner: &mut Planner<N>, steps_per_mm: [f32; N],
-	) -> Result<(), BlocksBufferIsFull>
+		&mut self, planner: &mut Planner<N>, kinematics: &K, steps_per_mm: [f32; N],
+	) -> Result<(), PlanMoveError>
 	{
-		let mut start_y = 0;
-		let mut start_x = 0;
-
-		// Restart from where the procedure stopped due to the block buffer being full
-		if self.current_point_planned_index != 0
-		{
-			start_y = ((self.current_point_planned_index / 2) / Self::BED_LEVELING_GRID_SIZE.0 as u16) as u8;
-			start_x = ((self.current_point_planned_index / 2) % Self::BED_LEVELING_GRID_SIZE.0 as u16) as u8;
-		}
 		// It's a new start
-		else
+		if !self.points_probing.is_probing()
 		{
-			self.current_point_index = 0;
-
 			self.unified_bed_leveling
 				.start(self.bed_size, Self::BED_LEVELING_GRID_SIZE, Distance::ZERO);
+
+			let distance_between_points = self.bed_size - &Vector2::new([Self::DISTANCE_FROM_BED_MARGINS * 2; 2]);
+			let (distance_between_points_x, distance_between_points_y) = (
+				distance_between_points.x() / Self::BED_LEVELING_GRID_SIZE.0,
+				distance_between_points.y() / Self::BED_LEVELING_GRID_SIZE.1,
+			);
+			self.points_probing.start(
+				(0..Self::BED_LEVELING_GRID_SIZE.1)
+					.flat_map(|y| {
+						(0..Self::BED_LEVELING_GRID_SIZE.0).map(move |x| {
+							Vector2::from_xy(
+								Self::DISTANCE_FROM_BED_MARGINS + distance_between_points_x * x,
+								Self::DISTANCE_FROM_BED_MARGINS + distance_between_points_y * y,
+							)
+						})
+					})
+					.collect(),
+			);
 		}
 
-		let distance_between_points = self.bed_size - &Vector2::new([Self::DISTANCE_FROM_BED_MARGINS * 2; 2]);
-		let distance_between_points = Vector2::from_xy(
-			distance_between_points.x() / Self::BED_LEVELING_GRID_SIZE.0,
-			distance_between_points.y() / Self::BED_LEVELING_GRID_SIZE.1,
-		);
+		self.points_probing.plan_moves(planner, kinematics, steps_per_mm)
+	}
 
-		for y in start_y..Self::BED_LEVELING_GRID_SIZE.1
+	/// Reads the result of the last probed point and plans the moves required to probe the next one.
+	///
+	/// Returns `Err(())` if the result couldn't be stored or if the tool can't reach the next point (and the procedure
+	/// is stopped).
+	pub fn tick<const N: usize, K: Kinematics>(
+		&mut self, planner: &mut Planner<N>, kinematics: &K, steps_per_mm: impl FnOnce() -> [f32; N],
+	) -> Result<(), ()>
+	{
+		if !self.points_probing.is_probing()
 		{
-			for x in start_x..Self::BED_LEVELING_GRID_SIZE.0
+			return Ok(());
+		}
+
+		if let Some((point_index, probing_distance)) = self.points_probing.tick(planner)
+		{
+			// The probe moved down starting from `DISTANCE_FROM_BED`, so if it travelled more than that the bed is lower
+			// than expected
+			let point_correction = Self::DISTANCE_FROM_BED + probing_distance;
+			self.unified_bed_leveling
+				.set_point_correction(point_index as u16, point_correction)?;
+
+			if !self.points_probing.is_probing()
 			{
-				let mut target_position_array = planner.get_position().get_internal_array().clone();
-
-				target_position_array[Axis::X as usize] =
-					Self::DISTANCE_FROM_BED_MARGINS + distance_between_points.x() * x;
-				target_position_array[Axis::Y as usize] =
-					Self::DISTANCE_FROM_BED_MARGINS + distance_between_points.y() * y;
-
-				if self.current_point_planned_index % 2 == 0
-				{
-					target_position_array[Axis::Z as usize] = Self::DISTANCE_FROM_BED;
-					let target_position = VectorN::new(target_position_array);
-					planner.plan_move::<K>(target_position, steps_per_mm, Self::MOVE_SPEED_MM_SECOND)?;
-					planner.mark_last_added_move_as_ready_to_go();
-					self.current_point_planned_index += 1;
-				}
-
-				target_position_array[Axis::Z as usize] = Distance::from_centimeters(-50);
-				let target_position = VectorN::new(target_position_array);
-				planner.plan_move::<K>(target_position, steps_per_mm, Self::PROBE_MOVE_SPEED_MM_SECOND)?;
-				planner.set_flags_on_last_added_block(enum_set!(Flag::BedLeveling));
-				planner.mark_last_added_move_as_ready_to_go();
-				self.current_point_planned_index += 1;
+				self.unified_bed_leveling.finish_procedure()?;
+				return Ok(());
 			}
+		}
 
-			start_x = 0;
+		match self.points_probing.plan_moves(planner, kinematics, (steps_per_mm)())
+		{
+			// If the buffer is full the moves are planned in the next ticks
+			Ok(()) | Err(PlanMoveError::BlocksBufferIsFull) => Ok(()),
+			Err(PlanMoveError::UnreachablePosition) => Err(()),
 		}
+	}
+
+	pub fn apply<const N: usize>(&mut self, target_position: &mut VectorN<N>)
+	{
+		self.unified_bed_leveling.apply(target_position)
+	}
+}
 
-		self.current_point_planned_index = 0;
+/// Probes some points of the bed one after the other (it's used by the [`BedLevelingProcedure`] and the
+/// [`AutoCalibrationProcedure`]).
+///
+/// For each point the tool moves [`above the bed`] and then down until the probe is triggered. The next point is
+/// planned only after the previous one has been probed, because the probing move is interrupted before reaching its
+/// target position, so the next moves must start from where the tool actually stopped.
+///
+/// [`AutoCalibrationProcedure`]: super::auto_calibration::AutoCalibrationProcedure
+/// [`above the bed`]: BedLevelingProcedure::DISTANCE_FROM_BED
+#[derive(Default)]
+pub(super) struct PointsProbing
+{
+	points: Vec<Vector2>,
+	current_point_index: usize,
+	/// The whole purpose of these variables is to solve the problem of the block buffer being full in the middle of
+	/// the planning of a point. With them it can re-start from the move where it left off.
+	is_move_above_point_planned: bool,
+	is_probing_move_planned: bool,
+}
 
-		Ok(())
+impl PointsProbing
+{
+	/// Movement speed at which the tool is moved between the probed points.
+	const MOVE_SPEED_MM_SECOND: f32 = 40.;
+	/// Movement speed of probing at which the points are probed.
+	const PROBE_MOVE_SPEED_MM_SECOND: f32 = 10.;
+
+	/// Starts probing the `points` (you must call [`Self::plan_moves`] to plan the moves of the first one).
+	pub fn start(&mut self, points: Vec<Vector2>)
+	{
+		*self = Self {
+			points,
+			..Default::default()
+		};
+	}
+
+	pub fn get_points(&self) -> &[Vector2]
+	{
+		&self.points
 	}
 
-	pub fn tick(&mut self) -> Result<(), ()>
+	/// Returns `true` if not all the points have been probed yet.
+	pub fn is_probing(&self) -> bool
 	{
-		if let Some(probing_distance) = communicate_to_ticker::get_z_axis_distance()
+		self.current_point_index < self.points.len()
+	}
+
+	/// Plans the moves required to probe the current point, unless they have already been planned.
+	///
+	/// Returns `Err(PlanMoveError::BlocksBufferIsFull)` if not all the moves could be planned, and you **MUST** call
+	/// this method again to plan the remaining ones. If the tool can't reach the point the probing is stopped, and
+	/// `Err(PlanMoveError::UnreachablePosition)` is returned.
+	pub fn plan_moves<const N: usize, K: Kinematics>(
+		&mut self, planner: &mut Planner<N>, kinematics: &K, steps_per_mm: [f32; N],
+	) -> Result<(), PlanMoveError>
+	{
+		let Some(point) = self.points.get(self.current_point_index)
+		else
 		{
-			let point_correction = probing_distance - Self::DISTANCE_FROM_BED;
-			self.unified_bed_leveling
-				.set_point_correction(self.current_point_index, point_correction)?;
+			return Ok(());
+		};
+
+		let result = self.plan_moves_to_point(*point, planner, kinematics, steps_per_mm);
+		if result == Err(PlanMoveError::UnreachablePosition)
+		{
+			*self = Self::default();
+		}
+		result
+	}
 
-			self.current_point_index += 1;
+	fn plan_moves_to_point<const N: usize, K: Kinematics>(
+		&mut self, point: Vector2, planner: &mut Planner<N>, kinematics: &K, steps_per_mm: [f32; N],
+	) -> Result<(), PlanMoveError>
+	{
+		let mut target_position_array = *planner.get_position().get_internal_array();
+		target_position_array[Axis::X as usize] = point.x();
+		target_position_array[Axis::Y as usize] = point.y();
 
-			if self.current_point_index == Self::BED_LEVELING_GRID_SIZE.0 as u16 * Self::BED_LEVELING_GRID_SIZE.1 as u16
-			{
-				self.unified_bed_leveling.finish_procedure().unwrap();
-			}
+		if !self.is_move_above_point_planned
+		{
+			target_position_array[Axis::Z as usize] = BedLevelingProcedure::DISTANCE_FROM_BED;
+			let target_position = VectorN::new(target_position_array);
+			planner.plan_move(kinematics, target_position, steps_per_mm, Self::MOVE_SPEED_MM_SECOND)?;
+			planner.mark_last_added_move_as_ready_to_go();
+			self.is_move_above_point_planned = true;
+		}
+
+		if !self.is_probing_move_planned
+		{
+			target_position_array[Axis::Z as usize] = Distance::from_centimeters(-50);
+			let target_position = VectorN::new(target_position_array);
+			planner.plan_move(
+				kinematics,
+				target_position,
+				steps_per_mm,
+				Self::PROBE_MOVE_SPEED_MM_SECOND,
+			)?;
+			planner.set_flags_on_last_added_block(enum_set!(Flag::BedLeveling));
+			planner.mark_last_added_move_as_ready_to_go();
+			self.is_probing_move_planned = true;
 		}
 
 		Ok(())
 	}
 
-	pub fn apply<const N: usize>(&mut self, target_position: &mut VectorN<N>)
+	/// When the probe has been triggered, moves on to the next point and returns the index of the probed point and
+	/// the distance travelled by the probe along the Z axis (which is negative, since it moves down).
+	///
+	/// The position of the `planner` is set to the one where the probe has been triggered.
+	pub fn tick<const N: usize>(&mut self, planner: &mut Planner<N>) -> Option<(usize, Distance)>
 	{
-		self.unified_bed_leveling.apply(target_position)
+		let probing_distance = communicate_to_ticker::get_z_axis_distance()?;
+
+		let mut position = planner.get_position().clone();
+		position[Axis::Z as usize] = BedLevelingProcedure::DISTANCE_FROM_BED + probing_distance;
+		planner.set_position(position);
+
+		let point_index = self.current_point_index;
+		self.current_point_index += 1;
+		self.is_move_above_point_planned = false;
+		self.is_probing_move_planned = false;
+
+		Some((point_index, probing_distance))
 	}
 }
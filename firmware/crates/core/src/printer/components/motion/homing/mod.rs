@@ -9,10 +9,10 @@ use super::{
 	axes::Axis,
 	homing::endstop::Endstop,
 	kinematics::Kinematics,
-	planner::{communicate_to_ticker, BlocksBufferIsFull, Flag, Planner},
+	planner::{communicate_to_ticker, Flag, PlanMoveError, Planner},
 };
 use crate::utils::{
-	math::vectors::{Vector2, VectorN},
+	math::vectors::{Vector2, Vector3, VectorN},
 	measurement::distance::Distance,
 };
 
@@ -24,8 +24,12 @@ pub enum HomingProcedure
 	None,
 	/// Indicates that the homing procedure should start.
 	ShouldStart,
-	/// Indicates that a homing move is currently being executed.
-	Doing(HomingMove),
+	/// Indicates that a homing move is currently being executed (and whether the Z axis will be homed after the X and
+	/// Y axes).
+	Doing
+	{
+		homing_move: HomingMove, home_z_axis: bool
+	},
 }
 
 impl HomingProcedure
@@ -33,23 +37,33 @@ impl HomingProcedure
 	/// Movement speed at which the printer is homed, in millimeters per second.
 	const MOVE_SPEED_MM_SECOND: f32 = 40.;
 
-	/// Starts the homing procedure, beginning with the X axis.
+	/// Starts the homing procedure, beginning with the [`first homing move`] of the `kinematics`.
 	///
 	/// # Parameters
 	/// - `planner`: A mutable reference to the planner that manages motion planning.
+	/// - `kinematics`: The kinematics of the machine, which decide which motors move to home each axis.
+	/// - `home_z_axis`: If it's `false` only the X and Y axes are homed, and the Z axis doesn't move (so the tool can be
+	///   homed when it's above a print). The towers of a delta machine are always homed together, since they move up.
 	/// - `calculate_steps_per_mm`: A function that calculates the number of steps required per millimeter for each axis.
 	///
 	/// # Returns
 	/// Returns `Ok(())` if successful, or an error if the blocks buffer is full.
+	///
+	/// [`first homing move`]: Kinematics::first_homing_move
 	pub fn start_homing<const N: usize, K: Kinematics>(
-		&mut self, planner: &mut Planner<N>, calculate_steps_per_mm: impl FnOnce() -> [f32; N],
-	) -> Result<(), BlocksBufferIsFull>
+		&mut self, planner: &mut Planner<N>, kinematics: &K, home_z_axis: bool,
+		calculate_steps_per_mm: impl FnOnce() -> [f32; N],
+	) -> Result<(), PlanMoveError>
 	{
 		*self = Self::ShouldStart;
 
-		Self::plan_move::<N, K>(HomingMove::X, planner, calculate_steps_per_mm)?;
+		let first_homing_move = kinematics.first_homing_move();
+		Self::plan_move(first_homing_move.clone(), planner, kinematics, calculate_steps_per_mm)?;
 
-		*self = Self::Doing(HomingMove::X);
+		*self = Self::Doing {
+			homing_move: first_homing_move,
+			home_z_axis,
+		};
 
 		Ok(())
 	}
@@ -67,6 +81,7 @@ impl HomingProcedure
 	///
 	/// # Parameters
 	/// - `planner`: A mutable reference to the planner that manages motion planning.
+	/// - `kinematics`: The kinematics of the machine, which decide which motors move to home each axis.
 	/// - `calculate_steps_per_mm`: A function that calculates the number of steps required per millimeter for each axis.
 	/// - `z_endstop`: A mutable reference to the Z-axis endstop.
 	/// - `bed_size`: A `Vector2` representing the size of the bed.
@@ -74,37 +89,55 @@ impl HomingProcedure
 	/// # Returns
 	/// Returns `Ok(())` if successful, or an error if an issue occurs during the tick.
 	pub fn tick<const N: usize, K: Kinematics, ZEndstop: Endstop>(
-		&mut self, planner: &mut Planner<N>, calculate_steps_per_mm: impl FnOnce() -> [f32; N],
+		&mut self, planner: &mut Planner<N>, kinematics: &K, calculate_steps_per_mm: impl FnOnce() -> [f32; N],
 		z_endstop: &mut ZEndstop, bed_size: Vector2,
 	) -> Result<(), TickError<ZEndstop>>
 	{
-		if let Self::Doing(homing_axis) = self.clone()
+		if let Self::Doing {
+			homing_move,
+			home_z_axis,
+		} = self.clone()
 		{
-			match homing_axis
+			match homing_move
 			{
 				HomingMove::X =>
 				{
 					if !Self::is_homing_move_being_executed(planner)
 					{
-						Self::plan_move::<N, K>(HomingMove::Y, planner, calculate_steps_per_mm)
+						Self::set_homed_axis_position(planner, Axis::X);
+						Self::plan_move(HomingMove::Y, planner, kinematics, calculate_steps_per_mm)
 							.map_err(|_| TickError::HomingX)?;
 
-						*self = Self::Doing(HomingMove::Y);
+						*self = Self::Doing {
+							homing_move: HomingMove::Y,
+							home_z_axis,
+						};
 					}
 				},
 				HomingMove::Y =>
 				{
 					if !Self::is_homing_move_being_executed(planner)
 					{
+						Self::set_homed_axis_position(planner, Axis::Y);
+						if !home_z_axis
+						{
+							*self = Self::None;
+							return Ok(());
+						}
+
 						// Move the carriage to the center of the bed
-						Self::plan_move::<N, K>(
+						Self::plan_move(
 							HomingMove::CenteringForZAxis { bed_size },
 							planner,
+							kinematics,
 							calculate_steps_per_mm,
 						)
 						.map_err(|_| TickError::HomingY)?;
 
-						*self = Self::Doing(HomingMove::CenteringForZAxis { bed_size });
+						*self = Self::Doing {
+							homing_move: HomingMove::CenteringForZAxis { bed_size },
+							home_z_axis,
+						};
 					}
 				},
 				HomingMove::CenteringForZAxis { bed_size: _ } =>
@@ -115,16 +148,34 @@ impl HomingProcedure
 							.prepare_for_homing()
 							.map_err(TickError::PreparingZAxisToProbe)?;
 
-						Self::plan_move::<N, K>(HomingMove::Z, planner, calculate_steps_per_mm)
+						Self::plan_move(HomingMove::Z, planner, kinematics, calculate_steps_per_mm)
 							.map_err(|_| TickError::HomingZ)?;
 
-						*self = Self::Doing(HomingMove::Z);
+						*self = Self::Doing {
+							homing_move: HomingMove::Z,
+							home_z_axis,
+						};
 					}
 				},
 				HomingMove::Z =>
 				{
 					if !Self::is_homing_move_being_executed(planner)
 					{
+						Self::set_homed_axis_position(planner, Axis::Z);
+						*self = Self::None;
+					}
+				},
+				HomingMove::Towers { homed_position } =>
+				{
+					if !Self::is_homing_move_being_executed(planner)
+					{
+						let mut position = planner.get_position().clone();
+						for axis in [Axis::X, Axis::Y, Axis::Z]
+						{
+							position[axis as usize] = homed_position[axis as usize];
+						}
+						planner.set_position(position);
+
 						*self = Self::None;
 					}
 				},
@@ -135,20 +186,35 @@ impl HomingProcedure
 	}
 
 	fn plan_move<const N: usize, K: Kinematics>(
-		axis: HomingMove, planner: &mut Planner<N>, calculate_steps_per_mm: impl FnOnce() -> [f32; N],
-	) -> Result<(), BlocksBufferIsFull>
+		axis: HomingMove, planner: &mut Planner<N>, kinematics: &K, calculate_steps_per_mm: impl FnOnce() -> [f32; N],
+	) -> Result<(), PlanMoveError>
 	{
-		planner.plan_move::<K>(
-			axis.target_position(),
+		let flags = match axis
+		{
+			HomingMove::Towers { .. } => enum_set!(Flag::Homing | Flag::HomingTowers),
+			_ => enum_set!(Flag::Homing),
+		};
+
+		planner.plan_move(
+			kinematics,
+			axis.target_position(planner.get_position()),
 			(calculate_steps_per_mm)(),
 			Self::MOVE_SPEED_MM_SECOND,
 		)?;
-		planner.set_flags_on_last_added_block(enum_set!(Flag::Homing));
+		planner.set_flags_on_last_added_block(flags);
 		planner.mark_last_added_move_as_ready_to_go();
 
 		Ok(())
 	}
 
+	/// Sets the position of the `axis` to `0`, since the homing move along it has stopped at its endstop.
+	fn set_homed_axis_position<const N: usize>(planner: &mut Planner<N>, axis: Axis)
+	{
+		let mut position = planner.get_position().clone();
+		position[axis as usize] = Distance::ZERO;
+		planner.set_position(position);
+	}
+
 	fn is_homing_move_being_executed<const N: usize>(planner: &mut Planner<N>) -> bool
 	{
 		planner.has_any_move_with_set_flags_planned(enum_set!(Flag::Homing))
@@ -171,6 +237,13 @@ pub enum HomingMove
 	},
 	/// Homing move for the Z axis.
 	Z,
+	/// Homing move of a delta machine, that moves all the towers up until each of them reaches its top endstop.
+	///
+	/// `homed_position` is the position of the tool when all the towers are homed.
+	Towers
+	{
+		homed_position: Vector3
+	},
 }
 
 impl HomingMove
@@ -179,13 +252,15 @@ impl HomingMove
 	///
 	/// # Parameters
 	/// - `N`: The number of dimensions in the motion space.
+	/// - `current_position`: The position from which the homing move starts.
 	///
 	/// # Returns
 	/// A `VectorN<N>` representing the target position for the homing move.
-	fn target_position<const N: usize>(&self) -> VectorN<N>
+	fn target_position<const N: usize>(&self, current_position: &VectorN<N>) -> VectorN<N>
 	{
 		const HOMING_DISTANCE: Distance = Distance::from_centimeters(-100);
-		let mut target_position = VectorN::ZERO;
+		// The axes that aren't being homed don't move (so the Z axis doesn't move while the X and Y ones are homed)
+		let mut target_position = current_position.clone();
 		match self
 		{
 			HomingMove::X => target_position[Axis::X as usize] = HOMING_DISTANCE,
@@ -195,7 +270,12 @@ impl HomingMove
 				target_position[Axis::X as usize] = bed_size.x() / 2;
 				target_position[Axis::Y as usize] = bed_size.y() / 2;
 			},
-			HomingMove::Z => target_position[Axis::Z as usize] = HOMING_DISTANCE,
+			HomingMove::Z => target_position[Axis::Z as usize] = current_position[Axis::Z as usize] + HOMING_DISTANCE,
+			HomingMove::Towers { homed_position: _ } =>
+			{
+				// Move straight up, so that all the carriages move by the same distance
+				target_position[Axis::Z as usize] = current_position[Axis::Z as usize] - HOMING_DISTANCE;
+			},
 		}
 		target_position
 	}
@@ -1,45 +1,39 @@
 use super::Kinematics;
-use crate::utils::measurement::distance::Distance;
+use crate::utils::math::vectors::VectorN;
 
 /// ["Cartesian" kinematics](https://all3dp.com/2/cartesian-3d-printer-delta-scara-belt-corexy-polar/#i-3-miscellaneous-rectilinear-cartesian).
 ///
+/// Each motor moves a single axis: the `a` motor moves the X axis, the `b` motor the Y axis, the third motor the Z
+/// axis and the fourth the extruder.
+///
 /// # Examples
 /// ```
-/// # use firmware_core::{utils::measurement::distance::*, printer::components::motion::kinematics::*};
+/// # use firmware_core::{utils::{measurement::distance::*, math::vectors::*}, printer::components::motion::{axes::Axis, kinematics::*}};
 /// #
 /// let x = Distance::from_millimeters(10);
 /// let y = Distance::from_millimeters(30);
+/// let z = Distance::from_millimeters(5);
+/// let cartesian = VectorN::new([x, y, z, Distance::ZERO]);
 ///
-/// assert_eq!(CartesianKinematics::xy_displacement_to_a(x, y), x);
-/// assert_eq!(CartesianKinematics::xy_displacement_to_b(x, y), y);
+/// let motors = CartesianKinematics.cartesian_to_motors(&cartesian);
+/// assert_eq!(motors, cartesian);
+/// assert_eq!(CartesianKinematics.motors_to_cartesian(&motors), cartesian);
 ///
-/// let a = Distance::from_millimeters(10);
-/// let b = Distance::from_millimeters(30);
-/// assert_eq!(CartesianKinematics::ab_displacement_to_x(a, b), a);
-/// assert_eq!(CartesianKinematics::ab_displacement_to_y(a, b), b);
+/// let homing_x = VectorN::new([-x, Distance::ZERO, Distance::ZERO, Distance::ZERO]);
+/// assert_eq!(CartesianKinematics.homing_axis(&CartesianKinematics.cartesian_to_motors(&homing_x)), Some(Axis::X));
 /// ```
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct CartesianKinematics;
 
 impl Kinematics for CartesianKinematics
 {
-	fn xy_displacement_to_a(x: Distance, _: Distance) -> Distance
-	{
-		x
-	}
-
-	fn xy_displacement_to_b(_: Distance, y: Distance) -> Distance
-	{
-		y
-	}
-
-	fn ab_displacement_to_x(a: Distance, _: Distance) -> Distance
+	fn cartesian_to_motors<const N: usize>(&self, cartesian: &VectorN<N>) -> VectorN<N>
 	{
-		a
+		cartesian.clone()
 	}
 
-	fn ab_displacement_to_y(_: Distance, b: Distance) -> Distance
+	fn motors_to_cartesian<const N: usize>(&self, motors: &VectorN<N>) -> VectorN<N>
 	{
-		b
+		motors.clone()
 	}
 }
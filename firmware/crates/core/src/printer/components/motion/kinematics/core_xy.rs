@@ -1,45 +1,41 @@
-use super::Kinematics;
-use crate::utils::measurement::distance::Distance;
+use super::{transform_pair, Kinematics};
+use crate::{printer::components::motion::axes::Axis, utils::math::vectors::VectorN};
 
 /// [CoreXY kinematics](https://corexy.com).
 ///
+/// The `a` and `b` motors together move the X and Y axes, the third motor moves the Z axis and the fourth the extruder.
+///
 /// # Examples
 /// ```
-/// # use firmware_core::{utils::measurement::distance::*, printer::components::motion::kinematics::*};
+/// # use firmware_core::{utils::{measurement::distance::*, math::vectors::*}, printer::components::motion::{axes::Axis, kinematics::*}};
 /// #
 /// let x = Distance::from_millimeters(10);
 /// let y = Distance::from_millimeters(30);
+/// let z = Distance::from_millimeters(5);
+/// let cartesian = VectorN::new([x, y, z, Distance::ZERO]);
 ///
-/// assert_eq!(CoreXYKinematics::xy_displacement_to_a(x, y), x + y);
-/// assert_eq!(CoreXYKinematics::xy_displacement_to_b(x, y), x - y);
+/// let motors = CoreXYKinematics.cartesian_to_motors(&cartesian);
+/// assert_eq!(motors, VectorN::new([x + y, x - y, z, Distance::ZERO]));
+/// assert_eq!(CoreXYKinematics.motors_to_cartesian(&motors), cartesian);
 ///
-/// let a = Distance::from_millimeters(10);
-/// let b = Distance::from_millimeters(30);
-/// assert_eq!(CoreXYKinematics::ab_displacement_to_x(a, b), (a + b) / 2);
-/// assert_eq!(CoreXYKinematics::ab_displacement_to_y(a, b), (a - b) / 2);
+/// // Homing the Y axis requires the `a` and `b` motors to rotate in opposite directions
+/// let homing_y = VectorN::new([Distance::ZERO, -y, Distance::ZERO, Distance::ZERO]);
+/// let homing_y_motors = CoreXYKinematics.cartesian_to_motors(&homing_y);
+/// assert_eq!(homing_y_motors, VectorN::new([-y, y, Distance::ZERO, Distance::ZERO]));
+/// assert_eq!(CoreXYKinematics.homing_axis(&homing_y_motors), Some(Axis::Y));
 /// ```
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct CoreXYKinematics;
 
 impl Kinematics for CoreXYKinematics
 {
-	fn xy_displacement_to_a(x: Distance, y: Distance) -> Distance
-	{
-		x + y
-	}
-
-	fn xy_displacement_to_b(x: Distance, y: Distance) -> Distance
-	{
-		x - y
-	}
-
-	fn ab_displacement_to_x(a: Distance, b: Distance) -> Distance
+	fn cartesian_to_motors<const N: usize>(&self, cartesian: &VectorN<N>) -> VectorN<N>
 	{
-		(a + b) / 2
+		transform_pair(cartesian, Axis::X as usize, Axis::Y as usize, |x, y| (x + y, x - y))
 	}
 
-	fn ab_displacement_to_y(a: Distance, b: Distance) -> Distance
+	fn motors_to_cartesian<const N: usize>(&self, motors: &VectorN<N>) -> VectorN<N>
 	{
-		(a - b) / 2
+		transform_pair(motors, 0, 1, |a, b| ((a + b) / 2, (a - b) / 2))
 	}
 }
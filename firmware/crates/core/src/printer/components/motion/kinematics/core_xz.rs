@@ -0,0 +1,48 @@
+use super::{transform_pair, Kinematics};
+use crate::{printer::components::motion::axes::Axis, utils::math::vectors::VectorN};
+
+/// CoreXZ kinematics (like [`CoreXY`] but on the XZ plane).
+///
+/// The `a` and `b` motors together move the X and Z axes, the third motor moves the Y axis and the fourth the extruder.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::{utils::{measurement::distance::*, math::vectors::*}, printer::components::motion::{axes::Axis, kinematics::*}};
+/// #
+/// let x = Distance::from_millimeters(10);
+/// let y = Distance::from_millimeters(30);
+/// let z = Distance::from_millimeters(5);
+/// let cartesian = VectorN::new([x, y, z, Distance::ZERO]);
+///
+/// let motors = CoreXZKinematics.cartesian_to_motors(&cartesian);
+/// assert_eq!(motors, VectorN::new([x + z, x - z, y, Distance::ZERO]));
+/// assert_eq!(CoreXZKinematics.motors_to_cartesian(&motors), cartesian);
+///
+/// // Homing the Z axis requires the `a` and `b` motors to rotate in opposite directions
+/// let homing_z = VectorN::new([Distance::ZERO, Distance::ZERO, -z, Distance::ZERO]);
+/// let homing_z_motors = CoreXZKinematics.cartesian_to_motors(&homing_z);
+/// assert_eq!(homing_z_motors, VectorN::new([-z, z, Distance::ZERO, Distance::ZERO]));
+/// assert_eq!(CoreXZKinematics.homing_axis(&homing_z_motors), Some(Axis::Z));
+/// ```
+///
+/// [`CoreXY`]: super::CoreXYKinematics
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CoreXZKinematics;
+
+impl Kinematics for CoreXZKinematics
+{
+	fn cartesian_to_motors<const N: usize>(&self, cartesian: &VectorN<N>) -> VectorN<N>
+	{
+		let motors = transform_pair(cartesian, Axis::X as usize, Axis::Z as usize, |x, z| (x + z, x - z));
+		// The Y axis is moved by the third motor, while the `b` motor is the second one
+		transform_pair(&motors, Axis::Y as usize, Axis::Z as usize, |y, b| (b, y))
+	}
+
+	fn motors_to_cartesian<const N: usize>(&self, motors: &VectorN<N>) -> VectorN<N>
+	{
+		let cartesian = transform_pair(motors, 1, 2, |b, y| (y, b));
+		transform_pair(&cartesian, Axis::X as usize, Axis::Z as usize, |a, b| {
+			((a + b) / 2, (a - b) / 2)
+		})
+	}
+}
@@ -0,0 +1,608 @@
+use super::Kinematics;
+use crate::{
+	printer::components::motion::{axes::Axis, homing::HomingMove},
+	utils::{
+		math::{
+			vectors::{Vector2, Vector3, VectorN},
+			NumberExt,
+		},
+		measurement::distance::Distance,
+	},
+};
+
+/// Angles (in degrees) of the towers `A`, `B` and `C` around the center of the bed, measured counterclockwise from
+/// the X axis.
+const TOWERS_ANGLES: [f32; 3] = [210., 330., 90.];
+
+/// Configuration of a [`DeltaKinematics`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct DeltaConfig
+{
+	/// Horizontal distance between the center of the bed and the point where the diagonal rods are attached to the
+	/// carriage of each tower (minus the same distance on the effector).
+	pub tower_radius: Distance,
+	/// Length of the diagonal rods (from the center of a joint to the center of the other one).
+	pub diagonal_rod_length: Distance,
+	/// Corrections (in degrees) to add to the nominal angle of the towers `A`, `B` and `C` (which are `210°`, `330°`
+	/// and `90°`).
+	pub tower_angle_corrections: [f32; 3],
+	/// How much lower (if negative) or higher (if positive) the endstop of each tower is compared to where it
+	/// should be.
+	pub endstop_offsets: [Distance; 3],
+	/// Height of the nozzle from the bed when all the carriages are at their top endstops.
+	pub height: Distance,
+	/// Max length on the XY plane of each segment in which a move is split.
+	pub segment_length: Distance,
+}
+
+/// Errors that can occur when creating a [`DeltaKinematics`] with an invalid [`DeltaConfig`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeltaConfigError
+{
+	/// The [`DeltaConfig::tower_radius`] isn't positive.
+	InvalidTowerRadius,
+	/// The [`DeltaConfig::diagonal_rod_length`] isn't longer than the tower radius (so the nozzle couldn't even reach
+	/// the center of the bed).
+	InvalidDiagonalRodLength,
+	/// The [`DeltaConfig::segment_length`] isn't positive.
+	InvalidSegmentLength,
+}
+
+/// [Delta (linear rail) kinematics](https://reprap.org/wiki/Delta_geometry).
+///
+/// The `a`, `b` and `c` motors (indices `0`, `1` and `2`) move the carriages of the towers `A`, `B` and `C` up and
+/// down, while the fourth motor moves the extruder. The position of each motor is the height of its carriage.
+///
+/// The origin of the XY plane is at the center of the bed, and since the kinematics are nonlinear the moves are
+/// split in [`segments`] that are short enough to be considered linear in the motors space.
+///
+/// # Homing
+/// All the carriages move up together until each of them reaches the top endstop of its tower. The top endstops of the
+/// towers `A`, `B` and `C` are respectively the X, Y and Z axis endstops of the [`Peripherals`] (so on a delta machine
+/// the Z axis endstop must report both the top endstop of the tower `C` and the probe used for the
+/// [`auto-calibration`], for example by wiring them in parallel).
+///
+/// # Examples
+/// ```
+/// # use firmware_core::{utils::{measurement::distance::*, math::vectors::*}, printer::components::motion::kinematics::*};
+/// #
+/// let delta = DeltaKinematics::new(DeltaConfig {
+///     tower_radius: Distance::from_millimeters(100),
+///     diagonal_rod_length: Distance::from_millimeters(250),
+///     tower_angle_corrections: [0.; 3],
+///     endstop_offsets: [Distance::ZERO; 3],
+///     height: Distance::from_millimeters(300),
+///     segment_length: Distance::from_millimeters(1),
+/// })
+/// .unwrap();
+///
+/// // When the nozzle is at the center of the bed all the carriages are at the same height
+/// let center = VectorN::new([Distance::ZERO, Distance::ZERO, Distance::from_millimeters(10), Distance::ZERO]);
+/// let motors = delta.cartesian_to_motors(&center);
+/// assert_eq!(motors[0], motors[1]);
+/// assert_eq!(motors[1], motors[2]);
+///
+/// // The forward transform is the inverse of the inverse transform
+/// let position = VectorN::new([
+///     Distance::from_millimeters(20),
+///     Distance::from_millimeters(-35),
+///     Distance::from_millimeters(5),
+///     Distance::ZERO,
+/// ]);
+/// let position_again = delta.motors_to_cartesian(&delta.cartesian_to_motors(&position));
+/// for axis in 0..3
+/// {
+///     assert!((position_again[axis] - position[axis]).as_micrometers().abs() <= 2);
+/// }
+///
+/// // A 10mm move on the XY plane is split in 10 segments
+/// let displacement = VectorN::new([Distance::from_millimeters(6), Distance::from_millimeters(8), Distance::ZERO, Distance::ZERO]);
+/// assert_eq!(delta.segments_count(&displacement), 10);
+///
+/// // The nozzle can't reach the points farther than the diagonal rods from a tower
+/// let out_of_reach = VectorN::new([Distance::from_millimeters(300), Distance::ZERO, Distance::ZERO, Distance::ZERO]);
+/// assert!(!delta.is_reachable(&out_of_reach));
+/// assert!(delta.is_reachable(&position));
+/// ```
+///
+/// [`segments`]: DeltaConfig::segment_length
+/// [`Peripherals`]: crate::printer::components::Peripherals
+/// [`auto-calibration`]: Kinematics::auto_calibrate
+#[derive(Clone, PartialEq, Debug)]
+pub struct DeltaKinematics
+{
+	config: DeltaConfig,
+	/// Position of the towers `A`, `B` and `C` on the XY plane (in millimeters).
+	towers: [[f32; 2]; 3],
+}
+
+impl DeltaKinematics
+{
+	/// Fraction of the [`DeltaConfig::tower_radius`] at which the points near the towers are probed during the
+	/// auto-calibration.
+	const CALIBRATION_RADIUS_FACTOR: f32 = 0.5;
+
+	/// Returns an error if one of the fields of the `config` isn't valid (check [`DeltaConfigError`]).
+	pub fn new(config: DeltaConfig) -> Result<Self, DeltaConfigError>
+	{
+		if config.tower_radius <= Distance::ZERO
+		{
+			return Err(DeltaConfigError::InvalidTowerRadius);
+		}
+		if config.diagonal_rod_length <= config.tower_radius
+		{
+			return Err(DeltaConfigError::InvalidDiagonalRodLength);
+		}
+		if config.segment_length <= Distance::ZERO
+		{
+			return Err(DeltaConfigError::InvalidSegmentLength);
+		}
+
+		let mut self_ = Self {
+			config,
+			towers: [[0.; 2]; 3],
+		};
+		self_.update_towers();
+
+		Ok(self_)
+	}
+
+	/// Returns the [`DeltaConfig`] currently used (which may be different from the one you provided to
+	/// [`Self::new`] if an [`auto-calibration`] has been done).
+	///
+	/// [`auto-calibration`]: Kinematics::auto_calibrate
+	pub fn get_config(&self) -> &DeltaConfig
+	{
+		&self.config
+	}
+
+	fn update_towers(&mut self)
+	{
+		let radius = self.config.tower_radius.as_millimeters_f32();
+		for ((tower, angle), angle_correction) in self
+			.towers
+			.iter_mut()
+			.zip(TOWERS_ANGLES)
+			.zip(self.config.tower_angle_corrections)
+		{
+			let angle = (angle + angle_correction).to_radians();
+			*tower = [radius * angle.cos(), radius * angle.sin()];
+		}
+	}
+
+	/// Returns the square of the height of the carriage of the tower with the provided `tower_index` above the nozzle
+	/// when the nozzle is at `x` and `y` (in millimeters), which is negative if the nozzle is too far from the tower to
+	/// be reached.
+	fn squared_carriage_height_above_nozzle(&self, tower_index: usize, x: f32, y: f32) -> f32
+	{
+		let [tower_x, tower_y] = self.towers[tower_index];
+		self.config.diagonal_rod_length.as_millimeters_f32().sqr() - (x - tower_x).sqr() - (y - tower_y).sqr()
+	}
+
+	/// Returns the height of the carriage of the tower with the provided `tower_index` above the nozzle when the
+	/// nozzle is at `x` and `y` (in millimeters).
+	///
+	/// If the nozzle can't be reached from the tower the carriage is considered at the height of the nozzle (instead of
+	/// returning `NaN`), but the moves to those positions are [`rejected`] by the planner anyway.
+	///
+	/// [`rejected`]: Kinematics::is_reachable
+	fn carriage_height_above_nozzle(&self, tower_index: usize, x: f32, y: f32) -> f32
+	{
+		self.squared_carriage_height_above_nozzle(tower_index, x, y)
+			.max(0.)
+			.sqrt()
+	}
+
+	/// Returns the position of the motors when all the carriages are at their top endstops.
+	fn motors_position_at_top(&self) -> f32
+	{
+		self.config.height.as_millimeters_f32() + self.carriage_height_above_nozzle(0, 0., 0.)
+	}
+
+	/// Returns how much higher (in millimeters) the nozzle is at the center of the bed than (on average) at the
+	/// [`auto-calibration points`] near the towers when the actual tower radius is `1mm` larger than the configured
+	/// one.
+	///
+	/// [`auto-calibration points`]: Kinematics::auto_calibration_points
+	fn center_error_per_millimeter_of_radius(&self) -> f32
+	{
+		let mut larger = self.clone();
+		larger.config.tower_radius += Distance::MILLIMETER;
+		larger.update_towers();
+
+		// After homing the motors are where this kinematics think the top endstops are, but the carriages are where
+		// the larger one does
+		let homing_error = distance_from_millimeters(larger.motors_position_at_top() - self.motors_position_at_top());
+		let errors: Vec<f32> = self
+			.auto_calibration_points()
+			.into_iter()
+			.map(|point| {
+				let mut motors = self.cartesian_to_motors(&Vector3::from_xyz(point.x(), point.y(), Distance::ZERO));
+				for tower_index in 0..3
+				{
+					motors[tower_index] += homing_error;
+				}
+				larger.motors_to_cartesian(&motors)[Axis::Z as usize].as_millimeters_f32()
+			})
+			.collect();
+
+		errors[0] - errors[1..].iter().sum::<f32>() / 3.
+	}
+}
+
+impl Kinematics for DeltaKinematics
+{
+	fn cartesian_to_motors<const N: usize>(&self, cartesian: &VectorN<N>) -> VectorN<N>
+	{
+		let x = cartesian[Axis::X as usize].as_millimeters_f32();
+		let y = cartesian[Axis::Y as usize].as_millimeters_f32();
+		let z = cartesian[Axis::Z as usize].as_millimeters_f32();
+
+		let mut motors = cartesian.clone();
+		for i in 0..3
+		{
+			motors[i] = distance_from_millimeters(z + self.carriage_height_above_nozzle(i, x, y))
+				- self.config.endstop_offsets[i];
+		}
+		motors
+	}
+
+	fn motors_to_cartesian<const N: usize>(&self, motors: &VectorN<N>) -> VectorN<N>
+	{
+		// Trilateration of the position of the effector starting from the positions of the 3 carriages
+		let carriage = |i: usize| {
+			[
+				self.towers[i][0],
+				self.towers[i][1],
+				(motors[i] + self.config.endstop_offsets[i]).as_millimeters_f32(),
+			]
+		};
+		let (p1, p2, p3) = (carriage(0), carriage(1), carriage(2));
+
+		let p12 = sub(p2, p1);
+		let d = length(p12);
+		let ex = scale(p12, 1. / d);
+
+		let p13 = sub(p3, p1);
+		let i = dot(ex, p13);
+		let ey_not_normalized = sub(p13, scale(ex, i));
+		let ey = scale(ey_not_normalized, 1. / length(ey_not_normalized));
+		let j = dot(ey, p13);
+		let ez = cross(ex, ey);
+
+		let x_new = d / 2.;
+		let y_new = ((i.sqr() + j.sqr()) / 2. - i * x_new) / j;
+		// When the carriages are too far from each other (which never happens with the positions returned by
+		// `cartesian_to_motors`) the effector is considered at their height instead of returning `NaN`
+		let z_new = (self.config.diagonal_rod_length.as_millimeters_f32().sqr() - x_new.sqr() - y_new.sqr())
+			.max(0.)
+			.sqrt();
+
+		let position = sub(add(p1, add(scale(ex, x_new), scale(ey, y_new))), scale(ez, z_new));
+
+		let mut cartesian = motors.clone();
+		for axis in 0..3
+		{
+			cartesian[axis] = distance_from_millimeters(position[axis]);
+		}
+		cartesian
+	}
+
+	fn is_reachable<const N: usize>(&self, cartesian: &VectorN<N>) -> bool
+	{
+		let x = cartesian[Axis::X as usize].as_millimeters_f32();
+		let y = cartesian[Axis::Y as usize].as_millimeters_f32();
+
+		(0..3).all(|tower_index| self.squared_carriage_height_above_nozzle(tower_index, x, y) >= 0.)
+	}
+
+	fn homing_axis<const N: usize>(&self, _: &VectorN<N>) -> Option<Axis>
+	{
+		// Delta machines home all the towers together (check `HomingMove::Towers`)
+		None
+	}
+
+	fn segments_count<const N: usize>(&self, displacement: &VectorN<N>) -> u32
+	{
+		let length_on_xy_plane =
+			Vector2::from_xy(displacement[Axis::X as usize], displacement[Axis::Y as usize]).length_millimeters();
+
+		((length_on_xy_plane / self.config.segment_length.as_millimeters_f32()).ceil() as u32).max(1)
+	}
+
+	fn first_homing_move(&self) -> HomingMove
+	{
+		let top = distance_from_millimeters(self.motors_position_at_top());
+		let homed_position = self.motors_to_cartesian(&Vector3::from_xyz(top, top, top));
+
+		HomingMove::Towers { homed_position }
+	}
+
+	fn auto_calibration_points(&self) -> Vec<Vector2>
+	{
+		let mut points = vec![Vector2::from_xy(Distance::ZERO, Distance::ZERO)];
+		points.extend(self.towers.iter().map(|[x, y]| {
+			Vector2::from_xy(
+				distance_from_millimeters(x * Self::CALIBRATION_RADIUS_FACTOR),
+				distance_from_millimeters(y * Self::CALIBRATION_RADIUS_FACTOR),
+			)
+		}));
+		points
+	}
+
+	/// Corrects the [`DeltaConfig::endstop_offsets`], the [`DeltaConfig::height`] and the
+	/// [`DeltaConfig::tower_radius`] using a linear approximation of how each of them changes the height of the nozzle
+	/// at the points returned by [`Self::auto_calibration_points`].
+	///
+	/// The approximation gets better the closer the configuration is to the real one, so it may be necessary to
+	/// repeat the auto-calibration a few times.
+	fn auto_calibrate(&mut self, probed_points: &[(Vector2, Distance)])
+	{
+		if probed_points.len() != 4
+		{
+			log::warn!(
+				"The delta auto-calibration requires 4 probed points, but {} have been provided",
+				probed_points.len()
+			);
+			return;
+		}
+
+		let errors: Vec<f32> = probed_points
+			.iter()
+			.map(|(_, error)| error.as_millimeters_f32())
+			.collect();
+		let center_error = errors[0];
+		let towers_errors = &errors[1..];
+		let towers_mean_error = towers_errors.iter().sum::<f32>() / 3.;
+
+		// If the nozzle is higher near a tower than near the other ones, the endstop of that tower is higher than
+		// the configured one
+		for (endstop_offset, tower_error) in self.config.endstop_offsets.iter_mut().zip(towers_errors)
+		{
+			*endstop_offset += distance_from_millimeters(tower_error - towers_mean_error);
+		}
+		self.config.height += distance_from_millimeters(errors.iter().sum::<f32>() / errors.len() as f32);
+
+		// If the nozzle is higher at the center than near the towers, the tower radius is larger than the
+		// configured one
+		let radius_correction = (center_error - towers_mean_error) / self.center_error_per_millimeter_of_radius();
+		self.config.tower_radius += distance_from_millimeters(radius_correction);
+
+		self.update_towers();
+
+		log::info!("Delta auto-calibration finished: {:?}", self.config);
+	}
+}
+
+fn distance_from_millimeters(millimeters: f32) -> Distance
+{
+	Distance::from_tens_of_nanometers((millimeters * 100_000.) as i32)
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3]
+{
+	[a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3]
+{
+	[a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f32; 3], factor: f32) -> [f32; 3]
+{
+	[a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32
+{
+	a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3]
+{
+	[
+		a[1] * b[2] - a[2] * b[1],
+		a[2] * b[0] - a[0] * b[2],
+		a[0] * b[1] - a[1] * b[0],
+	]
+}
+
+fn length(a: [f32; 3]) -> f32
+{
+	dot(a, a).sqrt()
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn config() -> DeltaConfig
+	{
+		DeltaConfig {
+			tower_radius: Distance::from_millimeters(100),
+			diagonal_rod_length: Distance::from_millimeters(250),
+			tower_angle_corrections: [0.; 3],
+			endstop_offsets: [Distance::ZERO; 3],
+			height: Distance::from_millimeters(300),
+			segment_length: Distance::from_millimeters(1),
+		}
+	}
+
+	fn position(x: f32, y: f32, z: f32) -> VectorN<4>
+	{
+		VectorN::new([
+			distance_from_millimeters(x),
+			distance_from_millimeters(y),
+			distance_from_millimeters(z),
+			Distance::ZERO,
+		])
+	}
+
+	fn assert_positions_are_close(a: &VectorN<4>, b: &VectorN<4>, max_difference_micrometers: i32)
+	{
+		for axis in 0..3
+		{
+			assert!(
+				(a[axis] - b[axis]).as_micrometers().abs() <= max_difference_micrometers,
+				"{a:?} and {b:?} are too far from each other"
+			);
+		}
+	}
+
+	/// Returns how much higher the nozzle of the `real` machine is than where the `configured` kinematics think it is
+	/// at each of the auto-calibration points of the `configured` kinematics.
+	fn probe(configured: &DeltaKinematics, real: &DeltaKinematics) -> Vec<(Vector2, Distance)>
+	{
+		// After homing the motors are where the configured kinematics think the top endstops are, but the carriages
+		// are where the real ones are
+		let homing_error =
+			distance_from_millimeters(real.motors_position_at_top() - configured.motors_position_at_top());
+
+		configured
+			.auto_calibration_points()
+			.into_iter()
+			.map(|point| {
+				let expected_position = VectorN::new([point.x(), point.y(), Distance::ZERO, Distance::ZERO]);
+				let mut motors = configured.cartesian_to_motors(&expected_position);
+				for tower_index in 0..3
+				{
+					motors[tower_index] += homing_error;
+				}
+				let real_position = real.motors_to_cartesian(&motors);
+
+				(point, real_position[Axis::Z as usize])
+			})
+			.collect()
+	}
+
+	#[test]
+	fn invalid_configs_are_rejected()
+	{
+		assert!(DeltaKinematics::new(config()).is_ok());
+		assert_eq!(
+			DeltaKinematics::new(DeltaConfig {
+				segment_length: Distance::ZERO,
+				..config()
+			})
+			.err(),
+			Some(DeltaConfigError::InvalidSegmentLength)
+		);
+		assert_eq!(
+			DeltaKinematics::new(DeltaConfig {
+				tower_radius: Distance::from_millimeters(-100),
+				..config()
+			})
+			.err(),
+			Some(DeltaConfigError::InvalidTowerRadius)
+		);
+		assert_eq!(
+			DeltaKinematics::new(DeltaConfig {
+				diagonal_rod_length: Distance::from_millimeters(100),
+				..config()
+			})
+			.err(),
+			Some(DeltaConfigError::InvalidDiagonalRodLength)
+		);
+	}
+
+	#[test]
+	fn trilateration_finds_the_nozzle_below_the_carriages()
+	{
+		let delta = DeltaKinematics::new(config()).unwrap();
+
+		// At the center of the bed each diagonal rod is the hypotenuse of a right triangle whose other sides are the
+		// tower radius and the height of the carriage above the nozzle: sqrt(250^2 - 100^2) = 229.129mm
+		let carriages_height = distance_from_millimeters(10. + 229.129);
+		let motors = VectorN::new([carriages_height, carriages_height, carriages_height, Distance::ZERO]);
+		assert_positions_are_close(&delta.motors_to_cartesian(&motors), &position(0., 0., 10.), 5);
+	}
+
+	#[test]
+	fn forward_transform_is_the_inverse_of_the_inverse_one()
+	{
+		let delta = DeltaKinematics::new(DeltaConfig {
+			tower_angle_corrections: [0.5, -0.3, 0.2],
+			endstop_offsets: [
+				Distance::from_micrometers(300),
+				Distance::from_micrometers(-200),
+				Distance::from_micrometers(100),
+			],
+			..config()
+		})
+		.unwrap();
+
+		for x in (-80..=80).step_by(20)
+		{
+			for y in (-80..=80).step_by(20)
+			{
+				for z in [0., 0.2, 150.]
+				{
+					let position = position(x as f32, y as f32, z);
+					let position_again = delta.motors_to_cartesian(&delta.cartesian_to_motors(&position));
+					assert_positions_are_close(&position_again, &position, 5);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn only_the_positions_within_the_reach_of_all_the_towers_are_reachable()
+	{
+		let delta = DeltaKinematics::new(config()).unwrap();
+
+		assert!(delta.is_reachable(&position(0., 0., 0.)));
+		// The tower C is at (0, 100), so 250mm away from it along the Y axis is the limit
+		assert!(delta.is_reachable(&position(0., -149., 0.)));
+		assert!(!delta.is_reachable(&position(0., -151., 0.)));
+
+		// The carriages never go lower than the nozzle, even when it's out of reach
+		let motors = delta.cartesian_to_motors(&position(0., -151., 20.));
+		assert!((0..3).all(|tower_index| motors[tower_index] >= Distance::from_millimeters(20)));
+	}
+
+	#[test]
+	fn auto_calibration_corrects_the_geometry()
+	{
+		let real = DeltaKinematics::new(DeltaConfig {
+			tower_radius: Distance::from_millimeters(101),
+			endstop_offsets: [
+				Distance::from_micrometers(300),
+				Distance::from_micrometers(-200),
+				Distance::from_micrometers(100),
+			],
+			height: Distance::from_millimeters(301),
+			..config()
+		})
+		.unwrap();
+		let mut configured = DeltaKinematics::new(config()).unwrap();
+		let max_error = |configured: &DeltaKinematics| {
+			probe(configured, &real)
+				.into_iter()
+				.map(|(_, error)| error.as_micrometers().abs())
+				.max()
+				.unwrap()
+		};
+		assert!(max_error(&configured) > 500);
+
+		for _ in 0..5
+		{
+			let probed_points = probe(&configured, &real);
+			configured.auto_calibrate(&probed_points);
+		}
+		assert!(max_error(&configured) < 10);
+	}
+
+	#[test]
+	fn auto_calibration_with_the_wrong_number_of_points_is_ignored()
+	{
+		let mut delta = DeltaKinematics::new(config()).unwrap();
+
+		let mut probed_points = probe(&delta, &delta);
+		probed_points.pop();
+		delta.auto_calibrate(&probed_points);
+
+		assert_eq!(delta.get_config(), &config());
+	}
+}
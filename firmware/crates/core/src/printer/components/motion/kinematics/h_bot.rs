@@ -0,0 +1,42 @@
+use super::{transform_pair, Kinematics};
+use crate::{printer::components::motion::axes::Axis, utils::math::vectors::VectorN};
+
+/// [H-bot kinematics](https://reprap.org/wiki/H-bot).
+///
+/// It uses a single long belt instead of the two of [`CoreXY`], but the relation between the rotation of the `a` and
+/// `b` motors and the movement of the tool on the X and Y axes is the same. The third motor moves the Z axis and the
+/// fourth the extruder.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::{utils::{measurement::distance::*, math::vectors::*}, printer::components::motion::{axes::Axis, kinematics::*}};
+/// #
+/// let x = Distance::from_millimeters(10);
+/// let y = Distance::from_millimeters(30);
+/// let z = Distance::from_millimeters(5);
+/// let cartesian = VectorN::new([x, y, z, Distance::ZERO]);
+///
+/// let motors = HBotKinematics.cartesian_to_motors(&cartesian);
+/// assert_eq!(motors, VectorN::new([x + y, x - y, z, Distance::ZERO]));
+/// assert_eq!(HBotKinematics.motors_to_cartesian(&motors), cartesian);
+///
+/// let homing_x = VectorN::new([-x, Distance::ZERO, Distance::ZERO, Distance::ZERO]);
+/// assert_eq!(HBotKinematics.homing_axis(&HBotKinematics.cartesian_to_motors(&homing_x)), Some(Axis::X));
+/// ```
+///
+/// [`CoreXY`]: super::CoreXYKinematics
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HBotKinematics;
+
+impl Kinematics for HBotKinematics
+{
+	fn cartesian_to_motors<const N: usize>(&self, cartesian: &VectorN<N>) -> VectorN<N>
+	{
+		transform_pair(cartesian, Axis::X as usize, Axis::Y as usize, |x, y| (x + y, x - y))
+	}
+
+	fn motors_to_cartesian<const N: usize>(&self, motors: &VectorN<N>) -> VectorN<N>
+	{
+		transform_pair(motors, 0, 1, |a, b| ((a + b) / 2, (a - b) / 2))
+	}
+}
@@ -0,0 +1,43 @@
+use super::{transform_pair, Kinematics};
+use crate::{printer::components::motion::axes::Axis, utils::math::vectors::VectorN};
+
+/// Markforged-style kinematics.
+///
+/// The Y gantry is moved by the `b` motor alone, while the X carriage is moved by a belt driven by the `a` motor that
+/// runs along the Y gantry (so moving the gantry also moves the carriage on the X axis, unless the `a` motor
+/// compensates for it). The third motor moves the Z axis and the fourth the extruder.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::{utils::{measurement::distance::*, math::vectors::*}, printer::components::motion::{axes::Axis, kinematics::*}};
+/// #
+/// let x = Distance::from_millimeters(10);
+/// let y = Distance::from_millimeters(30);
+/// let z = Distance::from_millimeters(5);
+/// let cartesian = VectorN::new([x, y, z, Distance::ZERO]);
+///
+/// let motors = MarkforgedKinematics.cartesian_to_motors(&cartesian);
+/// assert_eq!(motors, VectorN::new([x + y, y, z, Distance::ZERO]));
+/// assert_eq!(MarkforgedKinematics.motors_to_cartesian(&motors), cartesian);
+///
+/// // Homing the Y axis requires both the `a` and `b` motors to move, homing the X axis requires only the `a` one
+/// let homing_y = VectorN::new([Distance::ZERO, -y, Distance::ZERO, Distance::ZERO]);
+/// assert_eq!(MarkforgedKinematics.homing_axis(&MarkforgedKinematics.cartesian_to_motors(&homing_y)), Some(Axis::Y));
+/// let homing_x = VectorN::new([-x, Distance::ZERO, Distance::ZERO, Distance::ZERO]);
+/// assert_eq!(MarkforgedKinematics.cartesian_to_motors(&homing_x), homing_x);
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MarkforgedKinematics;
+
+impl Kinematics for MarkforgedKinematics
+{
+	fn cartesian_to_motors<const N: usize>(&self, cartesian: &VectorN<N>) -> VectorN<N>
+	{
+		transform_pair(cartesian, Axis::X as usize, Axis::Y as usize, |x, y| (x + y, y))
+	}
+
+	fn motors_to_cartesian<const N: usize>(&self, motors: &VectorN<N>) -> VectorN<N>
+	{
+		transform_pair(motors, 0, 1, |a, b| (a - b, b))
+	}
+}
@@ -1,24 +1,129 @@
-//! A module for different kinematics implementations, such as Cartesian and CoreXY.
+//! A module for different kinematics implementations, such as Cartesian, CoreXY, CoreXZ, H-bot, Markforged and Delta.
 
 mod cartesian;
 mod core_xy;
+mod core_xz;
+mod delta;
+mod h_bot;
+mod markforged;
 
 pub use cartesian::*;
 pub use core_xy::*;
+pub use core_xz::*;
+pub use delta::*;
+pub use h_bot::*;
+pub use markforged::*;
 
-use crate::utils::measurement::distance::Distance;
+use super::{axes::Axis, homing::HomingMove};
+use crate::utils::{
+	math::vectors::{Vector2, VectorN},
+	measurement::distance::Distance,
+};
 
-/// Type that represents the kinematics of the machine (i.e. how the motion of the `a` and `b` motors effects
+/// Type that represents the kinematics of the machine (i.e. how the motion of the stepper motors effects
 /// the movement of the tool carriage).
+///
+/// Positions are expressed as [`VectorN`]s whose components are indexed by [`Axis`] when they are in the cartesian
+/// space, and by the index of the motor (`0` is the left/`a` motor, `1` the right/`b` motor, `2` the Z axis motor and
+/// `3` the extruder motor) when they are in the motors space. Components a kinematics doesn't know about are copied
+/// as they are from one space to the other.
 pub trait Kinematics
 {
-	/// Converts a displacement on the XY coordinate system to the displacement required to be done by the `a` motor.
-	fn xy_displacement_to_a(x: Distance, y: Distance) -> Distance;
-	/// Converts a displacement on the XY coordinate system to the displacement required to be done by the `b` motor.
-	fn xy_displacement_to_b(x: Distance, y: Distance) -> Distance;
-
-	/// Converts a displacement done by the `a` and `b` motors to a displacement of the tool on the X axis.
-	fn ab_displacement_to_x(a: Distance, b: Distance) -> Distance;
-	/// Converts a displacement done by the `a` and `b` motors to a displacement of the tool on the Y axis.
-	fn ab_displacement_to_y(a: Distance, b: Distance) -> Distance;
+	/// Converts a position of the tool in the cartesian space to the position each motor must be at
+	/// (this is the inverse kinematics transform).
+	fn cartesian_to_motors<const N: usize>(&self, cartesian: &VectorN<N>) -> VectorN<N>;
+
+	/// Converts the position of each motor to the position of the tool in the cartesian space
+	/// (this is the forward kinematics transform).
+	fn motors_to_cartesian<const N: usize>(&self, motors: &VectorN<N>) -> VectorN<N>;
+
+	/// Returns `false` if the tool can't be moved to the `cartesian` position, for example because it's beyond the reach
+	/// of the arms of a delta machine. The moves to those positions are rejected by the [`Planner`].
+	///
+	/// The default implementation returns `true`, which is right for all the linear kinematics.
+	///
+	/// [`Planner`]: super::planner::Planner
+	fn is_reachable<const N: usize>(&self, cartesian: &VectorN<N>) -> bool
+	{
+		let _ = cartesian;
+		true
+	}
+
+	/// Homing rule of the kinematics: returns the axis whose endstop must be checked while the motors are
+	/// displaced by `motors_displacement` during an homing move, or `None` if that combination of motors doesn't
+	/// home any axis.
+	///
+	/// The default implementation converts the displacement to the cartesian space and returns the axis (between
+	/// [`Axis::X`], [`Axis::Y`] and [`Axis::Z`]) that moves the most, which is right for all the linear kinematics.
+	fn homing_axis<const N: usize>(&self, motors_displacement: &VectorN<N>) -> Option<Axis>
+	{
+		let cartesian_displacement = self.motors_to_cartesian(motors_displacement);
+
+		[Axis::X, Axis::Y, Axis::Z]
+			.into_iter()
+			.filter(|&axis| (axis as usize) < N)
+			.map(|axis| {
+				(
+					axis,
+					cartesian_displacement[axis as usize].as_tens_of_nanometers().abs(),
+				)
+			})
+			.filter(|&(_, displacement)| displacement > 0)
+			.max_by_key(|&(_, displacement)| displacement)
+			.map(|(axis, _)| axis)
+	}
+
+	/// Returns in how many segments a move of `displacement` (in the cartesian space) must be split so that each
+	/// segment can be executed moving the motors linearly.
+	///
+	/// The default implementation returns `1`, which is right for all the linear kinematics.
+	fn segments_count<const N: usize>(&self, displacement: &VectorN<N>) -> u32
+	{
+		let _ = displacement;
+		1
+	}
+
+	/// Returns the first move of the homing procedure.
+	///
+	/// The default implementation returns [`HomingMove::X`], which homes the X, Y and Z axes one after the other.
+	fn first_homing_move(&self) -> HomingMove
+	{
+		HomingMove::X
+	}
+
+	/// Returns the points of the bed (on the XY plane) that must be probed to [`auto-calibrate`] the kinematics.
+	///
+	/// The default implementation returns no points, which means the kinematics doesn't support auto-calibration.
+	///
+	/// [`auto-calibrate`]: Self::auto_calibrate
+	fn auto_calibration_points(&self) -> Vec<Vector2>
+	{
+		Vec::new()
+	}
+
+	/// Corrects the parameters of the kinematics using the result of probing the points returned by
+	/// [`Self::auto_calibration_points`].
+	///
+	/// Each probed point is paired with how much higher the nozzle actually was than where the machine thought it
+	/// was when it was at that point.
+	///
+	/// The default implementation does nothing.
+	fn auto_calibrate(&mut self, probed_points: &[(Vector2, Distance)])
+	{
+		let _ = probed_points;
+	}
+}
+
+/// Replaces the components at indices `first` and `second` of `vector` with the result of `transform`
+/// (called with the current values of those components).
+fn transform_pair<const N: usize>(
+	vector: &VectorN<N>, first: usize, second: usize,
+	transform: impl FnOnce(Distance, Distance) -> (Distance, Distance),
+) -> VectorN<N>
+{
+	let mut result = vector.clone();
+	let (first_value, second_value) = (transform)(vector[first], vector[second]);
+	result[first] = first_value;
+	result[second] = second_value;
+	result
 }
@@ -11,16 +11,20 @@
 
 use std::{fmt::Debug, time::Duration};
 
+use auto_calibration::AutoCalibrationProcedure;
+use backlash::{BacklashCompensation, BacklashSettings};
 use bed_leveling::BedLevelingProcedure;
 use embedded_hal::digital::OutputPin;
 pub use linear::*;
+use retraction::{FirmwareRetraction, RetractionSettings};
+use skew::SkewCorrection;
 
 use self::{
 	axes::Axis,
 	bed_leveling::{Probe, ZAxisProbe},
-	homing::{endstop::Endstop, HomingProcedure},
+	homing::{endstop::Endstop, HomingMove, HomingProcedure},
 	kinematics::Kinematics as KinematicsTrait,
-	planner::{communicate_to_ticker, BlocksBufferIsFull, MoveId, Planner, Settings},
+	planner::{MoveId, PlanMoveError, Planner, Settings},
 	ticker::StepperMotorsTicker,
 };
 use super::{
@@ -38,12 +42,16 @@ use crate::{
 	},
 };
 
+pub mod auto_calibration;
 pub mod axes;
+pub mod backlash;
 pub mod bed_leveling;
 pub mod homing;
 pub mod kinematics;
 mod linear;
 pub mod planner;
+pub mod retraction;
+pub mod skew;
 pub mod ticker;
 
 /// Number of stepper motors controlled by the machine.
@@ -62,6 +70,10 @@ pub struct MotionController<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEnd
 	rotations_to_linear_motions: [RotationToLinearMotion; N_MOTORS],
 
 	bed_leveling_procedure: BedLevelingProcedure,
+	auto_calibration_procedure: AutoCalibrationProcedure,
+	backlash_compensation: BacklashCompensation,
+	skew_correction: SkewCorrection,
+	firmware_retraction: FirmwareRetraction,
 
 	kinematics: Kinematics,
 
@@ -73,9 +85,13 @@ pub struct MotionController<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEnd
 	z_endstop: Probe<ZEndstop>,
 
 	bed_size: Vector2,
+	max_z: Distance,
 	next_move_feed_rate: f32,
 
 	is_paused: bool,
+
+	/// Length of filament extruded by the moves started since the last call to [`Self::take_extruded_filament`].
+	extruded_filament: Distance,
 }
 
 impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> MotionController<Timer, Kinematics, ZEndstop>
@@ -115,8 +131,6 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 		configuration: CreationConfig, uart_driver: &mut Uart,
 	) -> Result<Self, CreationError<Timer, ZEndstop, Uart>>
 	{
-		communicate_to_ticker::set_kinematics_functions::<Kinematics>();
-
 		let mut z_endstop = Probe::new(peripherals.z_endstop, configuration.offset_from_nozzle_of_z_probe);
 
 		let mut ticker = StepperMotorsTicker::new(
@@ -141,8 +155,13 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 			),
 			ticker,
 			bed_leveling_procedure: BedLevelingProcedure::new(configuration.bed_size),
+			auto_calibration_procedure: AutoCalibrationProcedure::default(),
+			backlash_compensation: BacklashCompensation::new(configuration.backlash),
+			skew_correction: configuration.skew,
+			firmware_retraction: FirmwareRetraction::new(configuration.retraction),
 			kinematics: peripherals.kinematics,
 			bed_size: configuration.bed_size,
+			max_z: configuration.max_z,
 			current_move: None,
 			last_planned_move_end_position: None,
 			homing_procedure: HomingProcedure::None,
@@ -181,6 +200,7 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 			next_move_feed_rate: DEFAULT_FEED_RATE,
 			z_endstop,
 			is_paused: false,
+			extruded_filament: Distance::ZERO,
 		})
 	}
 
@@ -192,8 +212,11 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 	///
 	/// The optional `feed_rate` will determine the speed of not only this move, but also all the subsequent ones.
 	///
-	/// Returns `Err(BlocksBufferIsFull)` if the move couldn't be planned, and you **MUST** call this method again
-	/// to try to plan it!
+	/// Returns `Err(PlanMoveError::BlocksBufferIsFull)` if the move couldn't be planned, and you **MUST** call this
+	/// method again to try to plan it!
+	///
+	/// The moves to positions the tool can't reach are rejected with `Err(PlanMoveError::UnreachablePosition)` (check
+	/// [`Planner::plan_move`]).
 	///
 	/// # Warning
 	/// This motion controller must be [`ticked`] to effectively execute the moves.
@@ -202,7 +225,7 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 	pub fn plan_move(
 		&mut self, x: Option<Distance>, y: Option<Distance>, z: Option<Distance>, e: Option<Distance>,
 		feed_rate: Option<f32>,
-	) -> Result<MoveId, BlocksBufferIsFull>
+	) -> Result<MoveId, PlanMoveError>
 	{
 		if let Some(feed_rate) = feed_rate
 		{
@@ -220,13 +243,15 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 		(apply_movement)(y, Axis::Y);
 		(apply_movement)(z, Axis::Z);
 		(apply_movement)(e, Axis::E);
-		self.last_planned_move_end_position = Some(last_planned_move_end_position);
 
-		let mut target_position = self.planner.get_position().clone();
+		let mut start_position = self.planner.get_position().clone();
+		self.backlash_compensation.remove_correction(&mut start_position);
+		let logical_start_position = self.skew_correction.unskew(&start_position);
+		let mut logical_target_position = logical_start_position.clone();
 		let mut set_target_position_axis = |distance, index| {
 			if let Some(distance) = distance
 			{
-				target_position[index] = distance;
+				logical_target_position[index] = distance;
 			}
 		};
 		(set_target_position_axis)(x, Axis::X as usize);
@@ -234,13 +259,141 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 		(set_target_position_axis)(z, Axis::Z as usize);
 		(set_target_position_axis)(e, Axis::E as usize);
 
+		// The skew is applied to the displacement (instead of the whole target position) so that the axes that don't
+		// move aren't affected by the rounding errors of the conversion between the logical and the skewed space
+		let mut target_position = start_position.clone()
+			+ &self
+				.skew_correction
+				.skew(&(logical_target_position - &logical_start_position));
+
+		let mut backlash_compensation = self.backlash_compensation.clone();
+		backlash_compensation.apply(&start_position, &mut target_position);
+
 		self.bed_leveling_procedure.apply(&mut target_position);
 
-		self.planner.plan_move::<Kinematics>(
+		// The planner rejects the moves the tool can't reach, so the relative moves must not start from their end
+		if self.kinematics.is_reachable(&target_position)
+		{
+			self.last_planned_move_end_position = Some(last_planned_move_end_position);
+		}
+
+		let planner_start_position = self.planner.get_position().clone();
+		let result = self.planner.plan_move(
+			&self.kinematics,
 			target_position,
 			calculate_microsteps_per_mm(&self.rotations_to_linear_motions, &self.tmc2209_drivers),
 			self.next_move_feed_rate,
-		)
+		);
+
+		// The backlash compensation is updated only if at least part of the move has been planned, otherwise when
+		// this method is called again the direction changes would be compensated twice
+		if result.is_ok() || *self.planner.get_position() != planner_start_position
+		{
+			self.backlash_compensation = backlash_compensation;
+		}
+		result
+	}
+
+	/// Plans the moves required to [`retract`] the filament using the [`RetractionSettings`] (all of them are
+	/// automatically marked as ready to go).
+	///
+	/// The logical position of the tool doesn't change, so the retracted length and the Z-hop don't affect the
+	/// coordinates of the next moves.
+	///
+	/// Returns the [`MoveId`] of the last planned move, or `Err(PlanMoveError::BlocksBufferIsFull)` if not all the
+	/// moves could be planned, and you **MUST** call this method again to plan the remaining ones.
+	///
+	/// [`retract`]: FirmwareRetraction::retract
+	pub fn retract(&mut self) -> Result<MoveId, PlanMoveError>
+	{
+		let mut last_move_id = MoveId::default();
+		let mut firmware_retraction = self.firmware_retraction;
+		let result = firmware_retraction.retract(|axis, displacement, speed_mm_s| {
+			last_move_id = self.plan_retraction_move(axis, displacement, speed_mm_s)?;
+			Ok(())
+		});
+		self.firmware_retraction = firmware_retraction;
+
+		result.map(|_| last_move_id)
+	}
+
+	/// Like [`Self::retract`], but it plans the moves required to [`recover`] from the last retraction.
+	///
+	/// [`recover`]: FirmwareRetraction::recover
+	pub fn recover_retraction(&mut self) -> Result<MoveId, PlanMoveError>
+	{
+		let mut last_move_id = MoveId::default();
+		let mut firmware_retraction = self.firmware_retraction;
+		let result = firmware_retraction.recover(|axis, displacement, speed_mm_s| {
+			last_move_id = self.plan_retraction_move(axis, displacement, speed_mm_s)?;
+			Ok(())
+		});
+		self.firmware_retraction = firmware_retraction;
+
+		result.map(|_| last_move_id)
+	}
+
+	/// Returns a mutable reference to the [`RetractionSettings`] you provided to [`Self::new`] (in the
+	/// [`CreationConfig`]).
+	pub fn get_retraction_settings_mut(&mut self) -> &mut RetractionSettings
+	{
+		self.firmware_retraction.get_settings_mut()
+	}
+
+	pub fn get_retraction_settings(&self) -> RetractionSettings
+	{
+		self.firmware_retraction.get_settings()
+	}
+
+	/// Plans a move of the extruder by `length` (a negative `length` pulls the filament back) that doesn't change the
+	/// logical position of the E axis, so the coordinates of the next moves aren't affected (this is used to load and
+	/// unload the filament).
+	///
+	/// The move is automatically marked as ready to go.
+	///
+	/// Returns `Err(PlanMoveError::BlocksBufferIsFull)` if the move couldn't be planned, and you **MUST** call this
+	/// method again to try to plan it!
+	pub fn plan_extruder_move(&mut self, length: Distance, speed_mm_s: f32) -> Result<MoveId, PlanMoveError>
+	{
+		self.plan_retraction_move(Axis::E, length, speed_mm_s)
+	}
+
+	/// Plans a move of the provided `axis` by `displacement` and then restores the logical position of that axis (and
+	/// the feed rate of the next moves), so that the move is invisible to the G-code.
+	fn plan_retraction_move(
+		&mut self, axis: Axis, displacement: Distance, speed_mm_s: f32,
+	) -> Result<MoveId, PlanMoveError>
+	{
+		let last_planned_move_end_position = self.last_planned_move_end_position.clone();
+		let next_move_feed_rate = self.next_move_feed_rate;
+		let position = self.get_position()[axis as usize];
+
+		let mut target_position = [None; N_MOTORS];
+		target_position[axis as usize] = Some(position + displacement);
+		let result = self.plan_move(
+			target_position[Axis::X as usize],
+			target_position[Axis::Y as usize],
+			target_position[Axis::Z as usize],
+			target_position[Axis::E as usize],
+			Some(speed_mm_s),
+		);
+
+		self.last_planned_move_end_position = last_planned_move_end_position;
+		self.next_move_feed_rate = next_move_feed_rate;
+
+		let move_id = result?;
+		self.mark_last_move_as_ready_to_go();
+
+		target_position = [None; N_MOTORS];
+		target_position[axis as usize] = Some(position);
+		self.set_position(
+			target_position[Axis::X as usize],
+			target_position[Axis::Y as usize],
+			target_position[Axis::Z as usize],
+			target_position[Axis::E as usize],
+		);
+
+		Ok(move_id)
 	}
 
 	/// Make the last [`planned move`] ready to be executed.
@@ -251,11 +404,38 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 		self.planner.mark_last_added_move_as_ready_to_go()
 	}
 
+	/// Removes the [`planned moves`] that the stepper motors haven't started executing yet, so that the tool stops as
+	/// soon as possible and the next planned move starts from where it will stop (check
+	/// [`Planner::discard_moves_not_sent_to_ticker`]).
+	///
+	/// [`planned moves`]: Self::plan_move
+	pub fn discard_planned_moves(&mut self)
+	{
+		self.planner.discard_moves_not_sent_to_ticker();
+		self.last_planned_move_end_position = Some(self.get_position());
+	}
+
 	pub fn has_move_been_executed(&self, move_to_check: MoveId) -> bool
 	{
 		self.planner.has_move_been_executed(move_to_check)
 	}
 
+	/// Returns the length of filament extruded by the moves the stepper motors have started since the last time this
+	/// method has been called (it's negative if the filament has been retracted).
+	pub fn take_extruded_filament(&mut self) -> Distance
+	{
+		std::mem::take(&mut self.extruded_filament)
+	}
+
+	/// Returns `true` if the stepper motors are executing a move, or if there are [`planned moves`] they haven't
+	/// executed yet.
+	///
+	/// [`planned moves`]: Self::plan_move
+	pub fn is_moving(&self) -> bool
+	{
+		self.planner.has_any_move_planned() || planner::communicate_to_ticker::is_block_available()
+	}
+
 	/// Returns the position of the last [`planned move`] if there has been one, otherwise returns `None`.
 	///
 	/// [`planned move`]: Self::plan_move
@@ -264,23 +444,74 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 		self.last_planned_move_end_position.clone()
 	}
 
+	/// Makes the motion controller think that the tool is at the provided position (the axes whose value is `None` keep
+	/// their position), without moving it.
 	pub fn set_position(&mut self, x: Option<Distance>, y: Option<Distance>, z: Option<Distance>, e: Option<Distance>)
 	{
-		let mut position = self.planner.get_position().clone();
+		let mut position = self.get_position();
+		// The relative moves are calculated from the end position of the last planned move
+		let mut last_planned_move_end_position = self
+			.last_planned_move_end_position
+			.clone()
+			.unwrap_or_else(|| position.clone());
 		let mut apply_position_axis = |value, axis| {
 			if let Some(value) = value
 			{
 				position[axis as usize] = value;
+				last_planned_move_end_position[axis as usize] = value;
 			}
 		};
 		(apply_position_axis)(x, Axis::X);
 		(apply_position_axis)(y, Axis::Y);
 		(apply_position_axis)(z, Axis::Z);
 		(apply_position_axis)(e, Axis::E);
+		self.last_planned_move_end_position = Some(last_planned_move_end_position);
 
+		let mut position = self.skew_correction.skew(&position);
+		self.backlash_compensation.add_correction(&mut position);
 		self.planner.set_position(position);
 	}
 
+	/// Returns the speed (in millimeters per second) of the next [`planned moves`] that don't specify their feed rate.
+	///
+	/// [`planned moves`]: Self::plan_move
+	pub fn get_feed_rate(&self) -> f32
+	{
+		self.next_move_feed_rate
+	}
+
+	/// Returns the state that determines how the next [`planned moves`] are calculated, so that you can restore it
+	/// later with [`Self::set_planning_state`].
+	///
+	/// [`planned moves`]: Self::plan_move
+	pub fn get_planning_state(&self) -> PlanningState
+	{
+		PlanningState {
+			next_move_feed_rate: self.next_move_feed_rate,
+			last_planned_move_end_position: self.last_planned_move_end_position.clone(),
+			firmware_retraction: self.firmware_retraction,
+		}
+	}
+
+	/// Restores the state returned by [`Self::get_planning_state`] (the position of the tool isn't changed).
+	pub fn set_planning_state(&mut self, planning_state: PlanningState)
+	{
+		self.next_move_feed_rate = planning_state.next_move_feed_rate;
+		self.last_planned_move_end_position = planning_state.last_planned_move_end_position;
+		self.firmware_retraction = planning_state.firmware_retraction;
+	}
+
+	/// Returns the position (in the same coordinate system of the G-code, so without the skew correction and the
+	/// backlash compensation) the tool will be at when all the [`planned moves`] are executed.
+	///
+	/// [`planned moves`]: Self::plan_move
+	pub fn get_position(&self) -> VectorN<N_MOTORS>
+	{
+		let mut position = self.planner.get_position().clone();
+		self.backlash_compensation.remove_correction(&mut position);
+		self.skew_correction.unskew(&position)
+	}
+
 	/// This method internally ticks the [`HomingProcedure`] and the [`Planner`], executing the planned moves.
 	pub fn tick(&mut self) -> Result<(), TickError<Probe<ZEndstop>>>
 	{
@@ -298,40 +529,54 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 		);*/
 
 		self.homing_procedure
-			.tick::<N_MOTORS, Kinematics, _>(
+			.tick(
 				&mut self.planner,
+				&self.kinematics,
 				|| calculate_microsteps_per_mm(&self.rotations_to_linear_motions, &self.tmc2209_drivers),
 				&mut self.z_endstop,
 				self.bed_size,
 			)
 			.map_err(TickError::Homing)?;
 
-		self.bed_leveling_procedure.tick();
+		let steps_per_mm = || calculate_microsteps_per_mm(&self.rotations_to_linear_motions, &self.tmc2209_drivers);
+		if self.auto_calibration_procedure.is_calibrating()
+		{
+			self.auto_calibration_procedure
+				.tick(&mut self.planner, &mut self.kinematics, steps_per_mm)
+				.map_err(|_| TickError::AutoCalibration)?;
+		}
+		else
+		{
+			self.bed_leveling_procedure
+				.tick(&mut self.planner, &self.kinematics, steps_per_mm)
+				.map_err(|_| TickError::BedLeveling)?;
+		}
 
-		if let Some(current_move_steps_difference) = self.planner.tick()
+		let current_move_steps_difference = self.planner.tick();
+		self.extruded_filament += self.planner.take_extruded_filament();
+		if let Some(current_move_steps_difference) = current_move_steps_difference
 		{
-			let steps_to_distance = |steps_index: usize| {
-				let steps = current_move_steps_difference[steps_index];
-				self.rotations_to_linear_motions[steps_index].microsteps_to_distance(steps)
-			};
-			let a_distance = (steps_to_distance)(0);
-			let b_distance = (steps_to_distance)(1);
-
-			let steps_difference = VectorN::new([
-				Kinematics::ab_displacement_to_x(a_distance, b_distance),
-				Kinematics::ab_displacement_to_y(a_distance, b_distance),
-				(steps_to_distance)(Axis::Z as usize),
-				(steps_to_distance)(Axis::E as usize),
-			]);
-
-			let last_end_position = self
+			let motors_displacement = VectorN::new(std::array::from_fn(|i| {
+				self.rotations_to_linear_motions[i].microsteps_to_distance(current_move_steps_difference[i])
+			}));
+
+			// The position of the motors is tracked (instead of the position of the tool) because with nonlinear
+			// kinematics the same motors displacement moves the tool differently depending on where it starts
+			let (last_end_position, last_end_motors_position) = self
 				.current_move
 				.as_ref()
-				.map(|current_move| current_move.end_position.clone())
-				.unwrap_or(VectorN::ZERO);
+				.map(|current_move| {
+					(
+						current_move.end_position.clone(),
+						current_move.end_motors_position.clone(),
+					)
+				})
+				.unwrap_or((VectorN::ZERO, VectorN::ZERO));
+			let end_motors_position = motors_displacement + &last_end_motors_position;
 			self.current_move = Some(CurrentMove {
-				start_position: last_end_position.clone(),
-				end_position: steps_difference + &last_end_position,
+				start_position: last_end_position,
+				end_position: self.kinematics.motors_to_cartesian(&end_motors_position),
+				end_motors_position,
 				start_time: self.ticker.get_time().ok(),
 			});
 		}
@@ -364,17 +609,46 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 		Ok(())
 	}
 
-	/// Make the machine start the [`HomingProcedure`] after all the planned moves are completed.
+	/// Stops the stepper motors immediately (even in the middle of a move), discards the planned moves and disables
+	/// the TMC2209 drivers, so that the motors don't hold their position anymore.
+	///
+	/// The ticker is stopped before talking to the drivers, so the motors stop even if the UART communication fails.
+	pub fn halt<Uart: UartTrait>(&mut self, uart_driver: &mut Uart) -> Result<(), SetPausedError<Uart, Timer>>
+	{
+		self.ticker.disable().map_err(SetPausedError::TryingToPauseTicker)?;
+		self.is_paused = true;
+		self.discard_planned_moves();
+
+		for tmc_driver in &mut self.tmc2209_drivers
+		{
+			tmc_driver
+				.set_enabled(false, uart_driver)
+				.map_err(SetPausedError::TMCDriver)?;
+		}
+
+		Ok(())
+	}
+
+	/// Make the machine start the [`HomingProcedure`] after all the planned moves are completed (if `home_z_axis` is
+	/// `false` only the X and Y axes are homed).
 	///
-	/// Returns `Err(BlocksBufferIsFull)` if the procedure couldn't be started, and you **MUST** call this method again
-	/// to try to start it!
-	pub fn start_homing(&mut self) -> Result<(), BlocksBufferIsFull>
+	/// Returns `Err(PlanMoveError::BlocksBufferIsFull)` if the procedure couldn't be started, and you **MUST** call
+	/// this method again to try to start it!
+	pub fn start_homing(&mut self, home_z_axis: bool) -> Result<(), PlanMoveError>
 	{
 		self.homing_procedure
-			.start_homing::<N_MOTORS, Kinematics>(&mut self.planner, || {
+			.start_homing(&mut self.planner, &self.kinematics, home_z_axis, || {
 				calculate_microsteps_per_mm(&self.rotations_to_linear_motions, &self.tmc2209_drivers)
 			})?;
 		ticker::start_homing();
+		// The Z correction must be kept if the Z axis isn't homed, since it's still part of the position of the planner
+		let is_z_axis_homed = home_z_axis || matches!(self.kinematics.first_homing_move(), HomingMove::Towers { .. });
+		match is_z_axis_homed
+		{
+			true => self.backlash_compensation.reset(&[Axis::X, Axis::Y, Axis::Z]),
+			false => self.backlash_compensation.reset(&[Axis::X, Axis::Y]),
+		}
+		self.firmware_retraction.reset_z_hop();
 
 		Ok(())
 	}
@@ -384,14 +658,80 @@ impl<Timer: TimerTrait, Kinematics: KinematicsTrait, ZEndstop: ZAxisProbe> Motio
 		self.homing_procedure.is_homing()
 	}
 
-	pub fn start_bed_leveling(&mut self) -> Result<(), BlocksBufferIsFull>
+	pub fn start_bed_leveling(&mut self) -> Result<(), PlanMoveError>
 	{
-		self.bed_leveling_procedure.start::<N_MOTORS, Kinematics>(
+		self.bed_leveling_procedure.start(
 			&mut self.planner,
+			&self.kinematics,
 			calculate_microsteps_per_mm(&self.rotations_to_linear_motions, &self.tmc2209_drivers),
 		)
 	}
 
+	/// Make the machine start the [`AutoCalibrationProcedure`] after all the planned moves are completed.
+	///
+	/// The machine should be homed before starting the procedure, and homed again after it's finished (since the
+	/// parameters of the kinematics change).
+	///
+	/// Returns `Err(PlanMoveError::BlocksBufferIsFull)` if the procedure couldn't be started, and you **MUST** call
+	/// this method again to try to start it!
+	pub fn start_auto_calibration(&mut self) -> Result<(), PlanMoveError>
+	{
+		self.auto_calibration_procedure.start(
+			&mut self.planner,
+			&self.kinematics,
+			calculate_microsteps_per_mm(&self.rotations_to_linear_motions, &self.tmc2209_drivers),
+		)
+	}
+
+	pub fn is_auto_calibrating(&self) -> bool
+	{
+		self.auto_calibration_procedure.is_calibrating()
+	}
+
+	pub fn get_backlash_settings(&self) -> BacklashSettings
+	{
+		self.backlash_compensation.get_settings()
+	}
+
+	/// Returns a mutable reference to the [`BacklashSettings`] you provided to [`Self::new`] (in the
+	/// [`CreationConfig`]).
+	pub fn get_backlash_settings_mut(&mut self) -> &mut BacklashSettings
+	{
+		self.backlash_compensation.get_settings_mut()
+	}
+
+	/// Returns the highest position the nozzle can be moved to along the Z axis.
+	pub fn get_max_z(&self) -> Distance
+	{
+		self.max_z
+	}
+
+	pub fn get_skew_correction(&self) -> SkewCorrection
+	{
+		self.skew_correction
+	}
+
+	/// Changes the [`SkewCorrection`] applied to the next [`planned moves`] (the position of the tool doesn't change).
+	///
+	/// [`planned moves`]: Self::plan_move
+	pub fn set_skew_correction(&mut self, skew_correction: SkewCorrection)
+	{
+		let position = self.get_position();
+		self.skew_correction = skew_correction;
+		self.set_position(
+			Some(position[Axis::X as usize]),
+			Some(position[Axis::Y as usize]),
+			Some(position[Axis::Z as usize]),
+			None,
+		);
+	}
+
+	/// Returns a reference to the kinematics you provided to [`Self::new`].
+	pub fn get_kinematics(&self) -> &Kinematics
+	{
+		&self.kinematics
+	}
+
 	/// Returns a mutable reference to the [`TMC2209`] driver you provided to [`Self::new`]
 	/// that drives the stepper motor on the specific `axis`.
 	///
@@ -411,6 +751,7 @@ struct CurrentMove
 {
 	start_position: VectorN<N_MOTORS>,
 	end_position: VectorN<N_MOTORS>,
+	end_motors_position: VectorN<N_MOTORS>,
 	start_time: Option<Duration>,
 }
 
@@ -453,11 +794,17 @@ pub struct CreationConfig
 	pub extruder_motor: MotorConfig,
 
 	pub bed_size: Vector2,
+	/// Highest position the nozzle can be moved to along the Z axis.
+	pub max_z: Distance,
 
 	pub offset_from_nozzle_of_z_probe: Vector3,
 
 	pub planner_blocks_count: usize,
 	pub planner_settings: Settings<N_MOTORS>,
+
+	pub backlash: BacklashSettings,
+	pub skew: SkewCorrection,
+	pub retraction: RetractionSettings,
 }
 
 /// Configuration for an individual motor.
@@ -467,6 +814,16 @@ pub struct MotorConfig
 	pub rotation_to_linear_motion: RotationToLinearMotion,
 }
 
+/// The state of a [`MotionController`] that determines how the next moves are planned (check
+/// [`MotionController::get_planning_state`]).
+#[derive(Clone, Debug)]
+pub struct PlanningState
+{
+	next_move_feed_rate: f32,
+	last_planned_move_end_position: Option<VectorN<N_MOTORS>>,
+	firmware_retraction: FirmwareRetraction,
+}
+
 /// An error that can occur when you instatiate a [`MotionController`] struct.
 #[derive(Debug)]
 pub enum CreationError<Timer: TimerTrait, ZEndstop: ZAxisProbe, Uart: UartTrait>
@@ -484,6 +841,7 @@ pub enum TickError<ZEndstop: Endstop>
 {
 	Homing(homing::TickError<ZEndstop>),
 	BedLeveling,
+	AutoCalibration,
 }
 
 impl<ZEndstop: Endstop> Debug for TickError<ZEndstop>
@@ -494,6 +852,7 @@ impl<ZEndstop: Endstop> Debug for TickError<ZEndstop>
 		{
 			Self::Homing(arg0) => f.debug_tuple("Homing").field(arg0).finish(),
 			Self::BedLeveling => write!(f, "BedLeveling"),
+			Self::AutoCalibration => write!(f, "AutoCalibration"),
 		}
 	}
 }
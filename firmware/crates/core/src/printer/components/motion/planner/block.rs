@@ -1,6 +1,9 @@
 use enumset::EnumSet;
 
-use crate::utils::measurement::distance::Distance;
+use crate::{
+	printer::components::motion::axes::Axis,
+	utils::{math::vectors::VectorN, measurement::distance::Distance},
+};
 
 pub type StepsPerSecond = u32;
 
@@ -24,6 +27,17 @@ pub struct Block<const N: usize>
 	pub acceleration_rate: u32,
 	pub millimeters: f32, // The remaining distance for this block to be executed in (mm)
 	pub travelled_z_distance: Distance,
+	/// Length of filament extruded by this move (it's negative if the filament is retracted).
+	pub travelled_e_distance: Distance,
+	/// The axis whose endstop is checked by the ticker if this block has the [`Flag::Homing`] set
+	/// (check [`Kinematics::homing_axis`]).
+	///
+	/// [`Kinematics::homing_axis`]: crate::printer::components::motion::kinematics::Kinematics::homing_axis
+	pub homing_axis: Option<Axis>,
+	/// Position of the planner when the move starts (check [`Planner::get_position`]).
+	///
+	/// [`Planner::get_position`]: super::Planner::get_position
+	pub start_position: VectorN<N>,
 
 	pub accelerate_until: u32,
 	pub decelerate_after: u32,
@@ -53,6 +67,9 @@ impl<const N: usize> Default for Block<N>
 			acceleration_rate: Default::default(),
 			millimeters: Default::default(),
 			travelled_z_distance: Default::default(),
+			travelled_e_distance: Default::default(),
+			homing_axis: None,
+			start_position: VectorN::ZERO,
 
 			accelerate_until: Default::default(),
 			decelerate_after: Default::default(),
@@ -70,6 +87,10 @@ pub enum Flag
 	/// An homing procedure move.
 	Homing,
 
+	/// An homing procedure move that makes all the towers of a delta machine reach their top endstops.
+	/// It's always set together with [`Flag::Homing`].
+	HomingTowers,
+
 	/// A bed leveling move.
 	BedLeveling,
 
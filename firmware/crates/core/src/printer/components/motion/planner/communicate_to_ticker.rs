@@ -5,7 +5,6 @@ use spin::{Mutex, MutexGuard};
 use super::Flag;
 use crate::{
 	printer::components::motion::{
-		kinematics::Kinematics,
 		planner::{self, Block},
 		N_MOTORS,
 	},
@@ -15,7 +14,6 @@ use crate::{
 static CURRENT_AND_NEXT_BLOCKS: Mutex<Communication> = Mutex::new(Communication {
 	current_motion_profile_block: None,
 	next_motion_profile_block: None,
-	kinematics_functions: None,
 	z_axis_distance: None,
 	bresenham: None,
 });
@@ -28,7 +26,6 @@ pub struct Communication
 {
 	pub current_motion_profile_block: Option<planner::Block<N_MOTORS>>,
 	next_motion_profile_block: Option<planner::Block<N_MOTORS>>,
-	pub kinematics_functions: Option<KinematicsFunctions>,
 	z_axis_distance: Option<Distance>,
 
 	pub bresenham: Option<Bresenham<N_MOTORS>>,
@@ -118,25 +115,21 @@ pub fn is_homing_block_available() -> bool
 		|| check_block(communication.next_motion_profile_block.as_ref())
 }
 
-/// Sets the [`KinematicsFunctions`] used by the [`StepperMotorsTicker`] to understand how
-/// the rotation of some motors relates to the movement of the tool.
+/// Must be called by the unit tests that move the stepper motors, which must keep the returned guard until they have
+/// finished: the blocks are passed to the ticker through a static, so those tests can't run in parallel.
 ///
-/// [`StepperMotorsTicker`]: super::StepperMotorsTicker
-pub fn set_kinematics_functions<K: Kinematics>()
+/// The blocks left by a test that panicked are removed.
+#[cfg(test)]
+pub(crate) fn lock_for_tests() -> std::sync::MutexGuard<'static, ()>
 {
-	let mut communication = CURRENT_AND_NEXT_BLOCKS.lock();
-	communication.kinematics_functions = Some(KinematicsFunctions {
-		ab_to_x: K::ab_displacement_to_x,
-		ab_to_y: K::ab_displacement_to_y,
-	});
-}
+	static TESTS_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
 
-type KinematicFunction = fn(Distance, Distance) -> Distance;
-#[derive(Clone, Copy)]
-/// Function pointers used to convert the motion of the `a` and `b` stepper motors to movements
-/// of the tool in the `X` and `Y` axes (check [`set_kinematics_functions`]).
-pub struct KinematicsFunctions
-{
-	pub ab_to_x: KinematicFunction,
-	pub ab_to_y: KinematicFunction,
+	let lock = TESTS_LOCK.lock().unwrap_or_else(|error| error.into_inner());
+	if let Some(mut communication) = get_blocks()
+	{
+		communication.finish_using_current_block();
+		communication.finish_using_current_block();
+	}
+
+	lock
 }
@@ -64,6 +64,9 @@ pub struct Planner<const N: usize>
 
 	most_optimized_block_index: usize,
 
+	/// Length of filament extruded by the blocks sent to the ticker (check [`Planner::take_extruded_filament`]).
+	extruded_filament: Distance,
+
 	stepper_ticker_frequency: Frequency,
 }
 
@@ -146,15 +149,21 @@ impl Planner<N_MOTORS>
 						two_blocks_required = false;
 					}
 
-					self.current_move_id = MoveId::next(self.current_move_id);
-
 					let used_blocks_count = 1 + two_blocks_required as usize;
-					self.most_optimized_block_index -= used_blocks_count;
+					for _ in 0..used_blocks_count
+					{
+						self.current_move_id = MoveId::next(self.current_move_id);
+					}
+					self.most_optimized_block_index = self.most_optimized_block_index.saturating_sub(used_blocks_count);
 					self.ready_to_go_blocks_count -= used_blocks_count;
 
 					// Safety: above we check the ring buffer isn't empty and this function is called at most once, so there must be at least 1 block in the buffer
 					let first = unsafe { self.blocks.dequeue().unwrap_unchecked() };
 					let second = two_blocks_required.then(|| self.blocks.dequeue()).flatten();
+					for block in std::iter::once(&first).chain(second.as_ref())
+					{
+						self.extruded_filament += block.travelled_e_distance;
+					}
 
 					next_position = self.blocks.front().map(|block| block.steps.clone());
 
@@ -190,6 +199,7 @@ impl<const N: usize> Planner<N>
 			previous_normalized_displacement: None,
 			previous_nominal_speed: 0.,
 			most_optimized_block_index: 0,
+			extruded_filament: Distance::ZERO,
 			ready_to_go_blocks_count: 0,
 			delay_in_number_of_ticks_before_delivering: 0,
 			stepper_ticker_frequency: stepper_motors_ticker.get_tick_frequency(),
@@ -234,13 +244,55 @@ impl<const N: usize> Planner<N>
 	///
 	/// The planned move won't be sent to the [`StepperMotorsTicker`] until you call [`Self::mark_last_added_move_as_ready_to_go`].
 	///
+	/// If the `kinematics` requires the move to be [`split in segments`], all the segments except the last one are
+	/// automatically marked as ready to go (and the returned [`MoveId`] is the one of the last segment). If the buffer
+	/// gets full in the middle of the segments, calling this method again with the same `target_position` plans the
+	/// remaining ones.
+	///
+	/// Returns `Err(PlanMoveError::BlocksBufferIsFull)` if the move couldn't be planned, and you **MUST** call this
+	/// method again to try to plan it, or `Err(PlanMoveError::UnreachablePosition)` if the tool can't [`reach`] the
+	/// `target_position` (in which case nothing is planned).
+	///
 	/// Check [`Self`] for more info.
+	///
+	/// [`split in segments`]: Kinematics::segments_count
+	/// [`reach`]: Kinematics::is_reachable
 	pub fn plan_move<K: Kinematics>(
 		// The move speed is equivalent to the feedrate
 		&mut self,
+		kinematics: &K,
 		target_position: VectorN<N>,
 		steps_per_mm: [f32; N],
-		mut move_speed_mm_s: f32,
+		move_speed_mm_s: f32,
+	) -> Result<MoveId, PlanMoveError>
+	{
+		// The segments of a move are all reachable if its ends are, since the area the tool can reach is convex (with all
+		// the supported kinematics)
+		if !kinematics.is_reachable(&target_position)
+		{
+			return Err(PlanMoveError::UnreachablePosition);
+		}
+
+		let start_position = self.current_position.clone();
+		let displacement = target_position.clone() - &start_position;
+		let segments_count = kinematics.segments_count(&displacement);
+		for segment in 1..segments_count
+		{
+			let segment_target_position =
+				start_position.clone() + &(displacement.clone() * (segment as f32 / segments_count as f32));
+			if !self
+				.plan_single_move(kinematics, segment_target_position, steps_per_mm, move_speed_mm_s)?
+				.is_empty()
+			{
+				self.mark_last_added_move_as_ready_to_go();
+			}
+		}
+
+		Ok(self.plan_single_move(kinematics, target_position, steps_per_mm, move_speed_mm_s)?)
+	}
+
+	fn plan_single_move<K: Kinematics>(
+		&mut self, kinematics: &K, target_position: VectorN<N>, steps_per_mm: [f32; N], mut move_speed_mm_s: f32,
 	) -> Result<MoveId, BlocksBufferIsFull>
 	{
 		if self.blocks.is_full()
@@ -248,22 +300,28 @@ impl<const N: usize> Planner<N>
 			return Err(BlocksBufferIsFull);
 		}
 
-		let mut displacement = target_position.clone() - &self.current_position;
+		let displacement = target_position.clone() - &self.current_position;
 		if displacement == VectorN::ZERO
 		{
 			return Ok(MoveId::EMPTY);
 		}
 
-		let mut block = Block::<N>::default();
+		let mut block = Block::<N> {
+			start_position: self.current_position.clone(),
+			..Default::default()
+		};
 
-		// Calculate how many steps each motor should do to move at the target_position
-		let (x, y) = (displacement[Axis::X as usize], displacement[Axis::Y as usize]);
-		displacement[0] = K::xy_displacement_to_a(x, y);
-		displacement[1] = K::xy_displacement_to_b(x, y);
+		// Calculate how many steps each motor should do to move at the target_position (the steps are calculated
+		// from the absolute positions of the motors so that the rounding errors don't add up between moves)
+		let target_motors_position = kinematics.cartesian_to_motors(&target_position);
+		let current_motors_position = kinematics.cartesian_to_motors(&self.current_position);
+		let motors_displacement = target_motors_position.clone() - &current_motors_position;
 		for i in 0..N
 		{
-			block.steps[i] = (displacement[i].as_millimeters_f32() * steps_per_mm[i]) as i32;
+			let to_steps = |position: Distance| (position.as_millimeters_f32() * steps_per_mm[i]).round() as i32;
+			block.steps[i] = (to_steps)(target_motors_position[i]) - (to_steps)(current_motors_position[i]);
 		}
+		block.homing_axis = kinematics.homing_axis(&motors_displacement);
 
 		block.step_event_count = block.steps.iter().max_by(|&a, &b| a.abs().cmp(&b.abs())).unwrap().abs() as u32;
 
@@ -288,6 +346,7 @@ impl<const N: usize> Planner<N>
 
 		block.millimeters = move_length;
 		block.travelled_z_distance = displacement[Axis::Z as usize];
+		block.travelled_e_distance = displacement[Axis::E as usize];
 
 		block.nominal_speed_in_mm_sec = move_speed_mm_s;
 		block.nominal_speed = (block.step_event_count as f32 * inverse_move_duration_s).ceil() as StepsPerSecond;
@@ -297,7 +356,7 @@ impl<const N: usize> Planner<N>
 		let mut speed_factor = 1_f32;
 		for i in 0..N
 		{
-			speed_on_axes_mm_s[i] = (displacement[i].as_millimeters_f32() * inverse_move_duration_s).abs();
+			speed_on_axes_mm_s[i] = (motors_displacement[i].as_millimeters_f32() * inverse_move_duration_s).abs();
 			speed_factor = speed_factor.min(self.settings.max_feedrate_mm_s[i] / speed_on_axes_mm_s[i]);
 		}
 
@@ -313,9 +372,13 @@ impl<const N: usize> Planner<N>
 
 		// Get the acceleration of the move in steps per second²
 		let steps_per_length = block.step_event_count as f32 * inverse_move_length;
-		let is_retracting = displacement[Axis::E as usize].as_tens_of_nanometers() < 0;
+		// Moves of the extruder alone (retractions and primes) use the retract acceleration
+		let is_retraction_move = displacement[Axis::E as usize] != Distance::ZERO
+			&& [Axis::X, Axis::Y, Axis::Z]
+				.into_iter()
+				.all(|axis| displacement[axis as usize] == Distance::ZERO);
 		let mut acceleration_steps_per_sec2 = (steps_per_length
-			* if is_retracting
+			* if is_retraction_move
 			{
 				self.settings.retract_acceleration
 			}
@@ -330,7 +393,7 @@ impl<const N: usize> Planner<N>
 		.ceil();
 
 		// Limit the acceleration during non-retraction moves
-		if !is_retracting
+		if !is_retraction_move
 		{
 			let mut max_acceleration_steps_per_sec2 = [0.; N];
 			for i in 0..N
@@ -341,7 +404,7 @@ impl<const N: usize> Planner<N>
 			let mut limit_acceleration = |axis| {
 				if block.steps[axis] != 0 && max_acceleration_steps_per_sec2[axis] < acceleration_steps_per_sec2
 				{
-					let rate = block.step_event_count as f32 / block.steps[axis] as f32;
+					let rate = block.step_event_count as f32 / (block.steps[axis] as f32).abs();
 					acceleration_steps_per_sec2 =
 						acceleration_steps_per_sec2.min(max_acceleration_steps_per_sec2[axis] * rate);
 				}
@@ -363,9 +426,8 @@ impl<const N: usize> Planner<N>
 		if let Some(previous_normalized_displacement) = self.previous_normalized_displacement.as_ref()
 		{
 			let normalized_displacement_clone = normalized_displacement.clone();
-			let junction_cos_theta_distance =
-				normalized_displacement_clone.dot(&-previous_normalized_displacement.clone());
-			let mut junction_cos_theta = junction_cos_theta_distance.as_micrometers() as f32;
+			let mut junction_cos_theta =
+				normalized_displacement_clone.dot_millimeters(&-previous_normalized_displacement.clone());
 
 			if junction_cos_theta > 0.99999
 			{
@@ -444,6 +506,34 @@ impl<const N: usize> Planner<N>
 		Ok(self.last_move_id)
 	}
 
+	/// Removes all the planned moves that haven't been sent to the [`StepperMotorsTicker`] yet (the ones that have been
+	/// sent are still executed, and they could end without decelerating), so that the next planned move starts from
+	/// the position the tool will be at when the ticker has finished. The removed moves are considered
+	/// [`executed`](Self::has_move_been_executed).
+	pub fn discard_moves_not_sent_to_ticker(&mut self)
+	{
+		if let Some(first_discarded_block) = self.blocks.front()
+		{
+			self.current_position = first_discarded_block.start_position.clone();
+		}
+
+		self.blocks.clear();
+		self.ready_to_go_blocks_count = 0;
+		self.most_optimized_block_index = 0;
+		self.previous_normalized_displacement = None;
+		self.previous_nominal_speed = 0.;
+		self.current_move_id = self.last_move_id;
+	}
+
+	/// Returns the length of filament extruded by the moves sent to the [`StepperMotorsTicker`] since the last time
+	/// this method has been called (the moves that are [`discarded`] aren't counted).
+	///
+	/// [`discarded`]: Self::discard_moves_not_sent_to_ticker
+	pub fn take_extruded_filament(&mut self) -> Distance
+	{
+		std::mem::take(&mut self.extruded_filament)
+	}
+
 	pub fn has_any_move_planned(&self) -> bool
 	{
 		!self.blocks.is_empty()
@@ -702,8 +792,27 @@ impl<const N: usize> Planner<N>
 
 			let nomr = 1. / previous.nominal_speed_in_mm_sec;
 			Self::calculate_trapezoid_for_block(previous, previous_entry_speed * nomr, current_entry_speed * nomr);
+			previous.flags.remove(Flag::Recalculate);
 		}
 	}
 }
 
 pub struct BlocksBufferIsFull;
+
+/// Errors that can occur while planning a move with [`Planner::plan_move`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanMoveError
+{
+	/// The buffer of the blocks is full, so the move must be planned again later.
+	BlocksBufferIsFull,
+	/// The target position of the move is outside the reach of the tool (check [`Kinematics::is_reachable`]).
+	UnreachablePosition,
+}
+
+impl From<BlocksBufferIsFull> for PlanMoveError
+{
+	fn from(_: BlocksBufferIsFull) -> Self
+	{
+		Self::BlocksBufferIsFull
+	}
+}
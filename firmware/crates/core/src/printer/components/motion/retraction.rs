@@ -0,0 +1,182 @@
+//! Module for the firmware retraction (the `G10` and `G11` G-code commands), which retracts the filament (and
+//! optionally lifts the nozzle) using settings stored in the machine instead of the ones chosen by the slicer.
+
+use super::{axes::Axis, planner::PlanMoveError};
+use crate::utils::measurement::distance::Distance;
+
+/// Settings of a [`FirmwareRetraction`] (they can be changed at runtime using the `M207` and `M208` G-code commands).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetractionSettings
+{
+	/// Length of filament pulled back by a retraction.
+	pub length: Distance,
+	/// Speed of the extruder while retracting.
+	pub speed_mm_s: f32,
+	/// Distance by which the nozzle is lifted after the filament is retracted (it can be [`Distance::ZERO`]).
+	pub z_hop: Distance,
+	/// Length of filament pushed in when recovering from a retraction, in addition to the retracted one.
+	pub extra_prime_length: Distance,
+	/// Speed of the extruder while recovering from a retraction.
+	pub prime_speed_mm_s: f32,
+}
+
+impl Default for RetractionSettings
+{
+	fn default() -> Self
+	{
+		Self {
+			length: Distance::from_millimeters(3),
+			speed_mm_s: 45.,
+			z_hop: Distance::ZERO,
+			extra_prime_length: Distance::ZERO,
+			prime_speed_mm_s: 25.,
+		}
+	}
+}
+
+/// Keeps track of whether the filament is retracted (and whether the nozzle is lifted), so that retracting twice or
+/// recovering without having retracted doesn't move anything.
+///
+/// The moves are planned through the function you provide to [`Self::retract`] and [`Self::recover`], which receives
+/// the axis to move, the displacement and the speed of the move.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::{utils::measurement::distance::*, printer::components::motion::{axes::*, retraction::*}};
+/// #
+/// let mut firmware_retraction = FirmwareRetraction::new(RetractionSettings {
+///     length: Distance::from_millimeters(2),
+///     z_hop: Distance::from_micrometers(400),
+///     extra_prime_length: Distance::from_micrometers(100),
+///     ..Default::default()
+/// });
+///
+/// let mut planned_moves = Vec::new();
+/// let mut plan_move = |axis, displacement, _speed| {
+///     planned_moves.push((axis, displacement));
+///     Ok(())
+/// };
+///
+/// assert!(firmware_retraction.retract(&mut plan_move).is_ok());
+/// // Retracting again doesn't do anything
+/// assert!(firmware_retraction.retract(&mut plan_move).is_ok());
+/// assert!(firmware_retraction.recover(&mut plan_move).is_ok());
+///
+/// assert_eq!(planned_moves, vec![
+///     (Axis::E, Distance::from_millimeters(-2)),
+///     (Axis::Z, Distance::from_micrometers(400)),
+///     (Axis::Z, Distance::from_micrometers(-400)),
+///     (Axis::E, Distance::from_micrometers(2_100)),
+/// ]);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct FirmwareRetraction
+{
+	settings: RetractionSettings,
+	/// Length of filament retracted (it's `None` if the filament isn't retracted).
+	retracted_length: Option<Distance>,
+	/// Distance by which the nozzle has been lifted (it's `None` if the nozzle isn't lifted).
+	z_hop: Option<Distance>,
+}
+
+impl FirmwareRetraction
+{
+	pub fn new(settings: RetractionSettings) -> Self
+	{
+		Self {
+			settings,
+			retracted_length: None,
+			z_hop: None,
+		}
+	}
+
+	pub fn get_settings(&self) -> RetractionSettings
+	{
+		self.settings
+	}
+
+	/// Returns a mutable reference to the [`RetractionSettings`] you provided to [`Self::new`].
+	pub fn get_settings_mut(&mut self) -> &mut RetractionSettings
+	{
+		&mut self.settings
+	}
+
+	/// Returns `true` if the filament has been [`retracted`] and not [`recovered`] yet.
+	///
+	/// [`retracted`]: Self::retract
+	/// [`recovered`]: Self::recover
+	pub fn is_retracted(&self) -> bool
+	{
+		self.retracted_length.is_some()
+	}
+
+	/// Forgets that the nozzle is lifted (you should call this when the machine is homed, because the position of the
+	/// Z axis is reset).
+	pub fn reset_z_hop(&mut self)
+	{
+		self.z_hop = None;
+	}
+
+	/// Plans the moves required to retract the filament and then lift the nozzle, if they haven't been done yet.
+	///
+	/// Returns `Err(PlanMoveError::BlocksBufferIsFull)` if `plan_move` failed, and you **MUST** call this method again
+	/// to plan the remaining moves.
+	pub fn retract(
+		&mut self, mut plan_move: impl FnMut(Axis, Distance, f32) -> Result<(), PlanMoveError>,
+	) -> Result<(), PlanMoveError>
+	{
+		if self.retracted_length.is_none()
+		{
+			if self.settings.length != Distance::ZERO
+			{
+				plan_move(Axis::E, -self.settings.length, self.settings.speed_mm_s)?;
+			}
+			self.retracted_length = Some(self.settings.length);
+		}
+
+		if self.z_hop.is_none()
+		{
+			if self.settings.z_hop != Distance::ZERO
+			{
+				plan_move(Axis::Z, self.settings.z_hop, self.settings.speed_mm_s)?;
+			}
+			self.z_hop = Some(self.settings.z_hop);
+		}
+
+		Ok(())
+	}
+
+	/// Plans the moves required to lower the nozzle and then prime the filament (pushing in the retracted length plus
+	/// the [`extra prime length`]), if the filament has been [`retracted`] before.
+	///
+	/// Returns `Err(PlanMoveError::BlocksBufferIsFull)` if `plan_move` failed, and you **MUST** call this method again
+	/// to plan the remaining moves.
+	///
+	/// [`extra prime length`]: RetractionSettings::extra_prime_length
+	/// [`retracted`]: Self::retract
+	pub fn recover(
+		&mut self, mut plan_move: impl FnMut(Axis, Distance, f32) -> Result<(), PlanMoveError>,
+	) -> Result<(), PlanMoveError>
+	{
+		if let Some(z_hop) = self.z_hop
+		{
+			if z_hop != Distance::ZERO
+			{
+				plan_move(Axis::Z, -z_hop, self.settings.speed_mm_s)?;
+			}
+			self.z_hop = None;
+		}
+
+		if let Some(retracted_length) = self.retracted_length
+		{
+			let prime_length = retracted_length + self.settings.extra_prime_length;
+			if prime_length != Distance::ZERO
+			{
+				plan_move(Axis::E, prime_length, self.settings.prime_speed_mm_s)?;
+			}
+			self.retracted_length = None;
+		}
+
+		Ok(())
+	}
+}
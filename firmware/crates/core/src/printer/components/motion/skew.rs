@@ -0,0 +1,136 @@
+//! Module for correcting the skew of the axes (when the frame of the machine is not perfectly square).
+
+use super::axes::Axis;
+use crate::utils::{math::vectors::VectorN, measurement::distance::Distance};
+
+/// Skew factors of each pair of axes, which are the tangent of the angle by which an axis is skewed compared to the
+/// other one.
+///
+/// You can measure them by printing a calibration square and using the lengths of its diagonals (check
+/// [`SkewCorrection::factor_from_diagonals`]).
+///
+/// They can be changed at runtime using the `M852` G-code command.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SkewCorrection
+{
+	/// Skew of the X axis compared to the Y axis.
+	pub xy: f32,
+	/// Skew of the X axis compared to the Z axis.
+	pub xz: f32,
+	/// Skew of the Y axis compared to the Z axis.
+	pub yz: f32,
+}
+
+impl SkewCorrection
+{
+	/// Calculates a skew factor from the lengths of the diagonals (`ac` and `bd`) and of a side (`ad`) of a printed
+	/// calibration square (whose corners are, in counterclockwise order, `a`, `b`, `c` and `d`).
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::motion::skew::*;
+	/// #
+	/// // A perfect square has no skew
+	/// let side = 100.;
+	/// let diagonal = side * 2_f32.sqrt();
+	/// assert!(SkewCorrection::factor_from_diagonals(diagonal, diagonal, side).abs() < 0.0001);
+	///
+	/// assert!(SkewCorrection::factor_from_diagonals(141.8, 141.0, side) > 0.);
+	/// ```
+	pub fn factor_from_diagonals(ac: f32, bd: f32, ad: f32) -> f32
+	{
+		let ab = ((2. * ac * ac + 2. * bd * bd - 4. * ad * ad).sqrt()) / 2.;
+
+		(std::f32::consts::FRAC_PI_2 - ((ac * ac - ab * ab - ad * ad) / (2. * ab * ad)).acos()).tan()
+	}
+
+	/// Converts a position (or displacement) in the logical space (the one of the G-code) to the position the skewed
+	/// axes must be moved to.
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::{utils::{measurement::distance::*, math::vectors::*}, printer::components::motion::skew::*};
+	/// #
+	/// let skew_correction = SkewCorrection { xy: 0.01, xz: 0., yz: 0. };
+	///
+	/// let position = VectorN::new([Distance::ZERO, Distance::from_millimeters(100), Distance::ZERO, Distance::ZERO]);
+	/// let skewed = skew_correction.skew(&position);
+	/// assert_eq!(skewed[0], Distance::from_millimeters(-1));
+	/// assert_eq!(skewed[1], Distance::from_millimeters(100));
+	///
+	/// assert_eq!(skew_correction.unskew(&skewed), position);
+	/// ```
+	pub fn skew<const N: usize>(&self, logical: &VectorN<N>) -> VectorN<N>
+	{
+		let (x, y, z) = Self::xyz(logical);
+
+		let mut skewed = logical.clone();
+		skewed[Axis::X as usize] = distance_from_millimeters(x - y * self.xy - z * (self.xz - self.xy * self.yz));
+		skewed[Axis::Y as usize] = distance_from_millimeters(y - z * self.yz);
+		skewed
+	}
+
+	/// Converts a position (or displacement) of the skewed axes to the position in the logical space (this is the
+	/// inverse of [`Self::skew`]).
+	pub fn unskew<const N: usize>(&self, skewed: &VectorN<N>) -> VectorN<N>
+	{
+		let (x, y, z) = Self::xyz(skewed);
+
+		let mut logical = skewed.clone();
+		logical[Axis::X as usize] = distance_from_millimeters(x + y * self.xy + z * self.xz);
+		logical[Axis::Y as usize] = distance_from_millimeters(y + z * self.yz);
+		logical
+	}
+
+	fn xyz<const N: usize>(position: &VectorN<N>) -> (f32, f32, f32)
+	{
+		(
+			position[Axis::X as usize].as_millimeters_f32(),
+			position[Axis::Y as usize].as_millimeters_f32(),
+			position[Axis::Z as usize].as_millimeters_f32(),
+		)
+	}
+}
+
+fn distance_from_millimeters(millimeters: f32) -> Distance
+{
+	Distance::from_tens_of_nanometers((millimeters * 100_000.).round() as i32)
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn position(x: i32, y: i32, z: i32, e: i32) -> VectorN<4>
+	{
+		VectorN::new([
+			Distance::from_millimeters(x),
+			Distance::from_millimeters(y),
+			Distance::from_millimeters(z),
+			Distance::from_millimeters(e),
+		])
+	}
+
+	#[test]
+	fn unskew_is_the_inverse_of_skew()
+	{
+		let skew_correction = SkewCorrection {
+			xy: 0.01,
+			xz: 0.02,
+			yz: 0.01,
+		};
+
+		for logical in [
+			position(50, 100, 200, 3),
+			position(-20, 35, 0, 0),
+			position(0, 0, 150, -1),
+		]
+		{
+			let skewed = skew_correction.skew(&logical);
+			assert_ne!(skewed, logical);
+			assert_eq!(skew_correction.unskew(&skewed), logical);
+			assert_eq!(skew_correction.skew(&skew_correction.unskew(&skewed)), skewed);
+		}
+	}
+}
@@ -195,8 +195,6 @@ impl<Timer: TimerTrait> StepperMotorsTicker<Timer>
 		{
 			let mut new_block = true;
 
-			let kinematics_functions = communication.kinematics_functions.clone();
-
 			let mut z_axis_distance = None;
 
 			if let Some(block_param) = parameters.block_parameters.as_ref()
@@ -209,31 +207,27 @@ impl<Timer: TimerTrait> StepperMotorsTicker<Timer>
 				let mut is_end_reached = false;
 				if block.flags.contains(Flag::Homing)
 				{
-					if let Some(kinematics_conversion) = kinematics_functions
+					is_end_reached = match block.homing_axis
 					{
-						// This function is needed only because the kinematics functions take `Distance` as parameters instead of `i32`
-						let steps_to_distance = |index| Distance::from_tens_of_nanometers(block.steps[index]);
-
-						let a = (steps_to_distance)(0);
-						let b = (steps_to_distance)(1);
-						let homing_axis = [
-							(kinematics_conversion.ab_to_x)(a, b),
-							(kinematics_conversion.ab_to_y)(a, b),
-							(steps_to_distance)(2),
-						]
-						.into_iter()
-						.enumerate()
-						.max_by(|(_, a), (_, b)| a.as_tens_of_nanometers().cmp(&b.as_tens_of_nanometers()))
-						.map(|(index, _)| index)
-						.unwrap() as u8;
-
-						is_end_reached = match homing_axis
-						{
-							0 => parameters.x_endstop.is_end_reached().unwrap_or(false),
-							1 => parameters.y_endstop.is_end_reached().unwrap_or(false),
-							2 => is_z_axis_triggered(),
-							_ => panic!("Invalid homing axis index"),
-						};
+						Some(Axis::X) => parameters.x_endstop.is_end_reached().unwrap_or(false),
+						Some(Axis::Y) => parameters.y_endstop.is_end_reached().unwrap_or(false),
+						Some(Axis::Z) => is_z_axis_triggered(),
+						Some(Axis::E) | None => false,
+					};
+				}
+
+				if block.flags.contains(Flag::HomingTowers)
+				{
+					// Each tower stops as soon as its carriage reaches the top endstop, and the move ends when all of
+					// them have reached it
+					if let Some(block_parameters) = parameters.block_parameters.as_mut()
+					{
+						let homed_towers = &mut block_parameters.homed_towers;
+						homed_towers[0] |= parameters.x_endstop.is_end_reached().unwrap_or(false);
+						homed_towers[1] |= parameters.y_endstop.is_end_reached().unwrap_or(false);
+						homed_towers[2] |= is_z_axis_triggered();
+
+						is_end_reached = homed_towers.iter().all(|&is_homed| is_homed);
 					}
 				}
 
@@ -241,11 +235,16 @@ impl<Timer: TimerTrait> StepperMotorsTicker<Timer>
 				{
 					is_end_reached = true;
 
-					let block_parameters = parameters.block_parameters.as_ref().unwrap();
-					// This assumes that a move with the Flag::BedLevelingProbe moves only 1 motor which is the Z axis motor
-					let travelled_distance_along_z_axis = (block_parameters.bresenham.steps_taken()
-						* block.travelled_z_distance.as_tens_of_nanometers() as u32)
-						/ block.step_event_count;
+					// The probe may already be triggered when the move starts, before any step has been taken
+					let steps_taken = parameters
+						.block_parameters
+						.as_ref()
+						.map_or(0, |block_parameters| block_parameters.bresenham.steps_taken());
+					// This assumes that a move with the Flag::BedLevelingProbe is a vertical move, so that the motor that
+					// takes the most steps moves proportionally to the Z axis
+					let travelled_distance_along_z_axis = (steps_taken as i64
+						* block.travelled_z_distance.as_tens_of_nanometers() as i64)
+						/ block.step_event_count as i64;
 					z_axis_distance = Some(Distance::from_tens_of_nanometers(
 						travelled_distance_along_z_axis as i32,
 					));
@@ -271,12 +270,15 @@ impl<Timer: TimerTrait> StepperMotorsTicker<Timer>
 							acceleration_time: DurationInTicks(0),
 							deceleration_time: DurationInTicks(0),
 							bresenham: Bresenham::new([0; N_MOTORS], block.steps),
+							homed_towers: [false; 3],
 						}
 					});
 
 					if let Some(motors_that_take_steps) = block_parameters.bresenham.next()
 					{
 						let [a, b, c, e] = motors_that_take_steps;
+						let [a_homed, b_homed, c_homed] = block_parameters.homed_towers;
+						let (a, b, c) = (a && !a_homed, b && !b_homed, c && !c_homed);
 
 						new_block = false;
 						generate_step_pulses!(a => parameters.left_motor,
@@ -433,6 +435,8 @@ struct BlockTickParameters
 	acceleration_time: DurationInTicks,
 	deceleration_time: DurationInTicks,
 	bresenham: Bresenham<N_MOTORS>,
+	/// Which towers have reached their top endstops during a [`Flag::HomingTowers`] move.
+	homed_towers: [bool; 3],
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
@@ -471,3 +475,349 @@ impl std::ops::Mul<u32> for DurationInTicks
 		Self(self.0 * rhs)
 	}
 }
+
+#[cfg(test)]
+mod tests
+{
+	use std::sync::MutexGuard;
+
+	use super::*;
+	use crate::{
+		printer::components::{
+			drivers::button::Button,
+			mock::{MockInputPin, MockTimer, MockZAxisProbe, StepTrace, StepTraceRecorder},
+			motion::{
+				kinematics::{CartesianKinematics, DeltaConfig, DeltaKinematics},
+				planner::{PlanMoveError, Planner, Settings},
+			},
+		},
+		utils::math::vectors::{Vector3, VectorN},
+	};
+
+	const STEPS_PER_MM: [f32; N_MOTORS] = [80., 80., 400., 100.];
+	const GOLDEN_FILES_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/golden/step_traces");
+
+	struct Harness
+	{
+		_lock: MutexGuard<'static, ()>,
+		timer: MockTimer,
+		recorder: StepTraceRecorder,
+		planner: Planner<N_MOTORS>,
+		_ticker: StepperMotorsTicker<MockTimer>,
+		x_endstop_pin: MockInputPin,
+	}
+
+	impl Harness
+	{
+		const PLANNER_TICK_PERIOD: Duration = Duration::from_micros(100);
+
+		fn new() -> Self
+		{
+			let lock = planner::communicate_to_ticker::lock_for_tests();
+
+			let timer = MockTimer::default();
+			let recorder = StepTraceRecorder::new(&timer);
+			let x_endstop_pin = MockInputPin::default();
+			let mut ticker = StepperMotorsTicker::new(
+				recorder.get_stepper_motor(0),
+				recorder.get_stepper_motor(1),
+				recorder.get_stepper_motor(2),
+				recorder.get_stepper_motor(3),
+				timer.clone(),
+				Button::new(x_endstop_pin.clone()),
+				Button::new(MockInputPin::default()),
+				&mut Probe::new(MockZAxisProbe::default(), Vector3::ZERO),
+			)
+			.unwrap_or_else(|_| panic!("Couldn't create the ticker"));
+			ticker.enable().unwrap();
+
+			let planner = Planner::new(
+				16,
+				&ticker,
+				Settings {
+					min_feedrate_mm_s: 0.2,
+					min_travel_feedrate_mm_s: 0.5,
+					max_feedrate_mm_s: [200., 200., 10., 45.],
+					retract_acceleration: 1_500.,
+					print_acceleration: 1_500.,
+					travel_acceleration: 2_000.,
+					max_acceleration_mm_per_s2: [9000., 9000., 100., 10000.],
+				},
+			);
+
+			Self {
+				_lock: lock,
+				timer,
+				recorder,
+				planner,
+				_ticker: ticker,
+				x_endstop_pin,
+			}
+		}
+
+		fn plan_move(&mut self, x: f32, y: f32, z: f32, e: f32, speed_mm_s: f32)
+		{
+			let target_position = VectorN::new(
+				[x, y, z, e]
+					.map(|millimeters| Distance::from_tens_of_nanometers((millimeters * 100_000.).round() as i32)),
+			);
+			assert!(self
+				.planner
+				.plan_move(&CartesianKinematics, target_position, STEPS_PER_MM, speed_mm_s)
+				.is_ok());
+			self.planner.mark_last_added_move_as_ready_to_go();
+		}
+
+		/// Ticks the planner and moves the time forward until all the planned moves have been executed, calling
+		/// `on_planner_tick` each time the planner is ticked.
+		fn run(&mut self, mut on_planner_tick: impl FnMut(&mut Self))
+		{
+			const TIMEOUT: Duration = Duration::from_secs(10);
+
+			let mut time = Duration::ZERO;
+			while self.planner.has_any_move_planned() || planner::communicate_to_ticker::is_block_available()
+			{
+				assert!(time < TIMEOUT, "The planned moves didn't finish in {TIMEOUT:?}");
+
+				self.planner.tick();
+				(on_planner_tick)(self);
+				self.timer.advance_time(Self::PLANNER_TICK_PERIOD);
+				time += Self::PLANNER_TICK_PERIOD;
+			}
+		}
+
+		fn get_trace(&self) -> StepTrace
+		{
+			self.recorder.get_trace()
+		}
+	}
+
+	fn golden_file_path(name: &str) -> String
+	{
+		format!("{GOLDEN_FILES_DIRECTORY}/{name}.txt")
+	}
+
+	/// Returns the number of steps of the acceleration, cruise and deceleration phases of the velocity profile of
+	/// the motor with the provided `index`.
+	fn trapezoid_phases(trace: &StepTrace, motor_index: usize) -> (usize, usize, usize)
+	{
+		let speeds: Vec<_> = trace
+			.get_velocity_profile(motor_index)
+			.iter()
+			.map(|sample| sample.value.abs())
+			.collect();
+		let max_speed = speeds.iter().copied().fold(0., f64::max);
+		// The speed is discretized by the timer, so the cruise speed can slightly change between steps
+		let is_cruising = |speed: f64| speed > max_speed * 0.995;
+
+		let acceleration_steps = speeds.iter().take_while(|&&speed| !is_cruising(speed)).count();
+		let cruise_steps = speeds[acceleration_steps..]
+			.iter()
+			.take_while(|&&speed| is_cruising(speed))
+			.count();
+
+		(
+			acceleration_steps,
+			cruise_steps,
+			speeds.len() - acceleration_steps - cruise_steps,
+		)
+	}
+
+	#[test]
+	fn trapezoidal_move()
+	{
+		let mut harness = Harness::new();
+		harness.plan_move(20., 0., 0., 0., 100.);
+		harness.run(|_| ());
+
+		let trace = harness.get_trace();
+		assert_eq!(trace.get_position(0), 1600);
+		assert!(trace.get_steps(1).is_empty());
+
+		// The nominal speed is 100mm/s * 80steps/mm, reached with a 2000mm/s² acceleration (160000steps/s²) in
+		// (8000steps/s)² / (2 * 160000steps/s²) = 200 steps
+		let max_speed = trace
+			.get_velocity_profile(0)
+			.iter()
+			.map(|sample| sample.value)
+			.fold(0., f64::max);
+		assert!((max_speed - 8_000.).abs() < 80., "{max_speed}");
+		let (acceleration_steps, cruise_steps, deceleration_steps) = trapezoid_phases(&trace, 0);
+		assert!(acceleration_steps.abs_diff(200) < 20, "{acceleration_steps}");
+		assert!(deceleration_steps.abs_diff(200) < 20, "{deceleration_steps}");
+		assert!(cruise_steps > 1_000, "{cruise_steps}");
+
+		trace.assert_eq_golden_file(golden_file_path("trapezoidal_move"));
+	}
+
+	#[test]
+	fn triangular_move()
+	{
+		let mut harness = Harness::new();
+		// Too short to reach the nominal speed
+		harness.plan_move(2., 0., 0., 0., 200.);
+		harness.run(|_| ());
+
+		let trace = harness.get_trace();
+		assert_eq!(trace.get_position(0), 160);
+		let max_speed = trace
+			.get_velocity_profile(0)
+			.iter()
+			.map(|sample| sample.value)
+			.fold(0., f64::max);
+		assert!(max_speed < 200. * 80. * 0.9, "{max_speed}");
+
+		trace.assert_eq_golden_file(golden_file_path("triangular_move"));
+	}
+
+	#[test]
+	fn junction_speeds()
+	{
+		let mut harness = Harness::new();
+		// Two collinear moves (no need to slow down between them), then a 90° corner
+		harness.plan_move(10., 0., 0., 0., 100.);
+		harness.plan_move(20., 0., 0., 0., 100.);
+		harness.plan_move(20., 10., 0., 0., 100.);
+		harness.run(|_| ());
+
+		let trace = harness.get_trace();
+		assert_eq!(trace.get_position(0), 1600);
+		assert_eq!(trace.get_position(1), 800);
+
+		let speed_at_step = |motor_index: usize, step: usize| trace.get_velocity_profile(motor_index)[step].value;
+		assert!(
+			speed_at_step(0, 800) > 7_000.,
+			"The speed at the collinear junction is {}",
+			speed_at_step(0, 800)
+		);
+		assert!(
+			speed_at_step(0, 1598) < 2_000.,
+			"The speed at the corner is {}",
+			speed_at_step(0, 1598)
+		);
+
+		// The second motor starts only after the first one has finished
+		let last_x_step = trace.get_steps(0).last().unwrap().time_in_ticks;
+		assert!(trace.get_steps(1)[0].time_in_ticks > last_x_step);
+
+		trace.assert_eq_golden_file(golden_file_path("junction_speeds"));
+	}
+
+	#[test]
+	fn diagonal_move_with_extrusion()
+	{
+		let mut harness = Harness::new();
+		harness.plan_move(-10., 5., 0., 1., 50.);
+		harness.run(|_| ());
+
+		let trace = harness.get_trace();
+		assert_eq!(trace.get_position(0), -800);
+		assert_eq!(trace.get_position(1), 400);
+		assert_eq!(trace.get_position(3), 100);
+		// All the motors start and end together
+		for motor_index in [1, 3]
+		{
+			let steps = trace.get_steps(motor_index);
+			assert!(steps[0].time_in_ticks >= trace.get_steps(0)[0].time_in_ticks);
+			assert!(steps.last().unwrap().time_in_ticks <= trace.get_steps(0).last().unwrap().time_in_ticks);
+		}
+
+		trace.assert_eq_golden_file(golden_file_path("diagonal_move_with_extrusion"));
+	}
+
+	#[test]
+	fn discarded_moves_are_not_executed()
+	{
+		let mut harness = Harness::new();
+		harness.plan_move(10., 0., 0., 1., 100.);
+		harness.plan_move(20., 0., 0., 2., 100.);
+		harness.plan_move(20., 10., 0., 3., 100.);
+		harness.plan_move(20., 20., 0., 4., 100.);
+		// The first two moves are sent to the ticker together
+		while !planner::communicate_to_ticker::is_block_available()
+		{
+			harness.planner.tick();
+			harness.timer.advance_time(Harness::PLANNER_TICK_PERIOD);
+		}
+
+		harness.planner.discard_moves_not_sent_to_ticker();
+		assert!(!harness.planner.has_any_move_planned());
+		assert_eq!(
+			*harness.planner.get_position(),
+			VectorN::new([
+				Distance::from_millimeters(20),
+				Distance::ZERO,
+				Distance::ZERO,
+				Distance::from_millimeters(2)
+			])
+		);
+		assert_eq!(harness.planner.take_extruded_filament(), Distance::from_millimeters(2));
+		harness.run(|_| ());
+
+		let trace = harness.get_trace();
+		assert_eq!(trace.get_position(0), 1600);
+		assert!(trace.get_steps(1).is_empty());
+		assert_eq!(trace.get_position(3), 200);
+	}
+
+	#[test]
+	fn homing_move_stops_when_endstop_is_reached()
+	{
+		const ENDSTOP_POSITION_IN_STEPS: i32 = -400;
+
+		let mut harness = Harness::new();
+		harness.plan_move(-50., 0., 0., 0., 50.);
+		harness.planner.set_flags_on_last_added_block(Flag::Homing.into());
+
+		let mut endstop_trigger_time = None;
+		harness.run(|harness| {
+			if endstop_trigger_time.is_none() && harness.get_trace().get_position(0) <= ENDSTOP_POSITION_IN_STEPS
+			{
+				harness.x_endstop_pin.set_level(true);
+				endstop_trigger_time = Some(harness.timer.get_time_in_ticks());
+			}
+		});
+
+		let trace = harness.get_trace();
+		let endstop_trigger_time = endstop_trigger_time.unwrap();
+		// The move is cut as soon as the ticker sees the endstop, way before the end of the 50mm
+		let steps_after_trigger = trace
+			.get_steps(0)
+			.iter()
+			.filter(|step| step.time_in_ticks >= endstop_trigger_time)
+			.count();
+		assert!(steps_after_trigger <= 1, "{steps_after_trigger}");
+		assert!(trace.get_position(0) > -50 * 80);
+
+		trace.assert_eq_golden_file(golden_file_path("homing_move_stops_when_endstop_is_reached"));
+	}
+
+	#[test]
+	fn moves_to_positions_the_tool_cant_reach_are_rejected()
+	{
+		let mut harness = Harness::new();
+		let kinematics = DeltaKinematics::new(DeltaConfig {
+			tower_radius: Distance::from_millimeters(100),
+			diagonal_rod_length: Distance::from_millimeters(250),
+			tower_angle_corrections: [0.; 3],
+			endstop_offsets: [Distance::ZERO; 3],
+			height: Distance::from_millimeters(300),
+			segment_length: Distance::from_millimeters(1),
+		})
+		.unwrap();
+		let target_position = VectorN::new([
+			Distance::from_millimeters(500),
+			Distance::ZERO,
+			Distance::ZERO,
+			Distance::ZERO,
+		]);
+
+		let result = harness
+			.planner
+			.plan_move(&kinematics, target_position, STEPS_PER_MM, 100.);
+
+		assert!(matches!(result, Err(PlanMoveError::UnreachablePosition)));
+		assert!(!harness.planner.has_any_move_planned());
+		assert_eq!(*harness.planner.get_position(), VectorN::ZERO);
+	}
+}
@@ -1,9 +1,31 @@
 //! This module gives the ability to the printer to pause a print and resume it later.
 //!
-//! For now this module only stores the pause state in a static [`AtomicBool`], then other parts of this firmware make a decision on what
-//! to do based on what [`is_paused`] returns.
+//! The pause state requested by the user is stored in a static [`AtomicBool`] (check [`toggle_pause`] and [`resume`]),
+//! then the [`Pauser`] ticked by the [`Printer3DComponents`] brings the machine to that state: when a print is paused
+//! the nozzle is parked using the [`PauseSequence`] (keeping the stepper motors enabled), and when it's resumed all of
+//! it is reversed. The [`Pauser`] also performs the [`filament changes`], since they need the print to be paused.
+//!
+//! [`Printer3DComponents`]: super::Printer3DComponents
+//! [`filament changes`]: filament_change
 
 use core::sync::atomic::{AtomicBool, Ordering};
+use std::collections::VecDeque;
+
+use super::{
+	config::{FilamentChangeConfig, PauseSequence},
+	filament_change::{self, ExtruderMovesPlanner},
+	hal::{
+		adc::{Adc, AdcPin},
+		pwm::PwmPin,
+	},
+	motion::{axes::Axis, planner::PlanMoveError, MotionController, PlanningState, N_MOTORS},
+	temperature::TemperaturePidController,
+	Peripherals,
+};
+use crate::utils::{
+	math::vectors::VectorN,
+	measurement::{distance::Distance, temperature::Temperature},
+};
 
 static IS_PAUSED: AtomicBool = AtomicBool::new(false);
 
@@ -21,6 +43,23 @@ pub fn resume()
 	IS_PAUSED.store(false, Ordering::Relaxed)
 }
 
+/// Makes [`is_paused`] return `true`.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::printer::components::pauser;
+/// #
+/// pauser::pause();
+/// assert_eq!(pauser::is_paused(), true);
+///
+/// pauser::pause();
+/// assert_eq!(pauser::is_paused(), true);
+/// ```
+pub fn pause()
+{
+	IS_PAUSED.store(true, Ordering::Relaxed)
+}
+
 /// Makes [`is_paused`] return the opposite of what it would return now.
 ///
 /// # Examples
@@ -70,3 +109,774 @@ pub fn is_paused() -> bool
 {
 	IS_PAUSED.load(Ordering::Relaxed)
 }
+
+/// The state of the machine managed by a [`Pauser`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseState
+{
+	/// The print is being executed normally.
+	Running,
+	/// A pause has been requested and the machine is completing the move it has already started (the moves that
+	/// haven't started yet are planned again when the print is resumed).
+	Stopping,
+	/// The nozzle is being moved away from the printed object.
+	Parking,
+	/// The old filament is being pulled out of the extruder (check [`filament_change`]), after the hotend has reached
+	/// its target temperature.
+	UnloadingFilament,
+	/// The machine is waiting for the user to insert the new filament and [`confirm`] it.
+	///
+	/// [`confirm`]: filament_change::confirm
+	WaitingForFilament,
+	/// The new filament is being pushed into the extruder and the nozzle is being purged, then the print is resumed.
+	LoadingFilament,
+	/// The machine is waiting for the print to be resumed (the stepper motors keep holding their position).
+	Paused,
+	/// The print has been resumed and the hotend is heating back to the temperature it had before the pause.
+	Reheating,
+	/// The nozzle is being moved back to where the print has been paused.
+	Unparking,
+}
+
+/// Brings the machine to the pause state requested with [`toggle_pause`] and [`resume`] (check the [`module's`]
+/// documentation).
+///
+/// [`module's`]: self
+pub struct Pauser
+{
+	sequence: PauseSequence,
+	filament_change: FilamentChangeConfig,
+	state: PauseState,
+	/// It's `true` if the filament must be changed during this pause.
+	is_changing_filament: bool,
+	filament_moves_planner: ExtruderMovesPlanner,
+
+	/// The state of the machine before it has been parked, which is restored when the print is resumed (it's `None` if
+	/// the machine hasn't been parked because nothing was being printed).
+	saved_state: Option<SavedState>,
+	/// The parking (or unparking) moves that haven't been planned yet.
+	moves_to_plan: VecDeque<ParkingMove>,
+}
+
+impl Pauser
+{
+	/// Returns a [`Pauser`] in the [`PauseState::Running`] state, that will park the nozzle using the provided
+	/// `sequence` and change the filament using the provided `filament_change` configuration.
+	pub fn new(sequence: PauseSequence, filament_change: FilamentChangeConfig) -> Self
+	{
+		Self {
+			sequence,
+			filament_change,
+			state: PauseState::Running,
+			is_changing_filament: false,
+			filament_moves_planner: ExtruderMovesPlanner::default(),
+			saved_state: None,
+			moves_to_plan: VecDeque::new(),
+		}
+	}
+
+	pub fn get_state(&self) -> PauseState
+	{
+		self.state
+	}
+
+	pub fn get_filament_change_config(&self) -> &FilamentChangeConfig
+	{
+		&self.filament_change
+	}
+
+	/// Returns `true` if the state is not [`PauseState::Running`], so the G-code commands must not be executed and the
+	/// stepper motors must keep holding their position.
+	pub fn is_active(&self) -> bool
+	{
+		self.state != PauseState::Running
+	}
+
+	/// Makes the state go back to [`PauseState::Running`] without restoring the state of the machine before the
+	/// pause (the moves planned by the [`Pauser`] must be discarded separately).
+	pub fn abort(&mut self)
+	{
+		self.state = PauseState::Running;
+		self.is_changing_filament = false;
+		filament_change::set_waiting_for_confirmation(false);
+		self.saved_state = None;
+		self.moves_to_plan.clear();
+	}
+
+	/// Advances the state of the machine towards the one returned by [`is_paused`].
+	///
+	/// When the state changes from [`PauseState::Running`] the G-code commands must be [`unprepared`], so that the
+	/// machine stops as soon as possible.
+	///
+	/// `is_printing` must be `true` if a file is being printed, since otherwise the machine isn't parked (its axes might
+	/// not even be homed).
+	///
+	/// [`unprepared`]: super::g_code::execute::GCodeExecuter::unprepare_commands
+	pub fn tick<P: Peripherals>(
+		&mut self, motion_controller: &mut MotionController<P::StepperTickerTimer, P::Kinematics, P::ZAxisEndstop>,
+		hotend_pid_controller: &mut TemperaturePidController<P::CartridgeHeaterPin, P::Adc, P::HotendAdcPin>,
+		is_printing: bool,
+	)
+	{
+		match self.state
+		{
+			PauseState::Running =>
+			{
+				// The homing and the calibrations can't be interrupted in the middle
+				if is_paused() && !motion_controller.is_homing() && !motion_controller.is_auto_calibrating()
+				{
+					self.is_changing_filament = filament_change::take_request();
+					self.state = PauseState::Stopping;
+				}
+			},
+			PauseState::Stopping =>
+			{
+				if !motion_controller.is_moving()
+				{
+					self.state = match is_printing
+					{
+						true =>
+						{
+							self.park::<P>(motion_controller, hotend_pid_controller);
+							PauseState::Parking
+						},
+						false => self.state_after_parking(),
+					};
+				}
+			},
+			PauseState::Parking =>
+			{
+				if self.plan_moves::<P>(motion_controller)
+				{
+					self.state = self.state_after_parking();
+				}
+			},
+			PauseState::UnloadingFilament =>
+			{
+				// The filament can't be moved while it's solid
+				if !has_hotend_reached_target_temperature(hotend_pid_controller)
+				{
+					return;
+				}
+
+				let moves = filament_change::unload_moves(&self.filament_change);
+				if self.filament_moves_planner.plan::<P>(motion_controller, &moves).is_ok()
+					&& !motion_controller.is_moving()
+				{
+					filament_change::set_waiting_for_confirmation(true);
+					self.state = PauseState::WaitingForFilament;
+				}
+			},
+			PauseState::WaitingForFilament =>
+			{
+				if filament_change::take_confirmation()
+				{
+					filament_change::set_waiting_for_confirmation(false);
+					self.filament_moves_planner.reset();
+					self.state = PauseState::LoadingFilament;
+				}
+			},
+			PauseState::LoadingFilament =>
+			{
+				if !has_hotend_reached_target_temperature(hotend_pid_controller)
+				{
+					return;
+				}
+
+				let moves = filament_change::load_moves(&self.filament_change);
+				if self.filament_moves_planner.plan::<P>(motion_controller, &moves).is_ok()
+					&& !motion_controller.is_moving()
+				{
+					self.is_changing_filament = false;
+					resume();
+					self.state = PauseState::Paused;
+				}
+			},
+			PauseState::Paused =>
+			{
+				if !is_paused()
+				{
+					self.state = match self.saved_state.as_ref()
+					{
+						Some(saved_state) =>
+						{
+							hotend_pid_controller.set_target_temperature(saved_state.hotend_target_temperature);
+							PauseState::Reheating
+						},
+						None => PauseState::Running,
+					};
+				}
+			},
+			PauseState::Reheating =>
+			{
+				if has_hotend_reached_target_temperature(hotend_pid_controller)
+				{
+					self.unpark();
+					self.state = PauseState::Unparking;
+				}
+			},
+			PauseState::Unparking =>
+			{
+				if self.plan_moves::<P>(motion_controller)
+				{
+					if let Some(saved_state) = self.saved_state.take()
+					{
+						motion_controller.set_planning_state(saved_state.planning_state);
+					}
+					self.state = PauseState::Running;
+				}
+			},
+		}
+	}
+
+	/// Returns the state the machine goes to once it has stopped (and eventually it has been parked).
+	fn state_after_parking(&mut self) -> PauseState
+	{
+		match self.is_changing_filament
+		{
+			true =>
+			{
+				self.filament_moves_planner.reset();
+				PauseState::UnloadingFilament
+			},
+			false => PauseState::Paused,
+		}
+	}
+
+	/// Saves the state of the machine, lowers the temperature of the hotend (unless the filament must be changed) and
+	/// adds the moves that park the nozzle to [`Self::moves_to_plan`].
+	fn park<P: Peripherals>(
+		&mut self, motion_controller: &MotionController<P::StepperTickerTimer, P::Kinematics, P::ZAxisEndstop>,
+		hotend_pid_controller: &mut TemperaturePidController<P::CartridgeHeaterPin, P::Adc, P::HotendAdcPin>,
+	)
+	{
+		let saved_state = SavedState {
+			position: motion_controller.get_position(),
+			planning_state: motion_controller.get_planning_state(),
+			hotend_target_temperature: hotend_pid_controller.get_target_temperature(),
+		};
+
+		if let (Some(pause_temperature), Some(target_temperature)) =
+			(self.sequence.hotend_temperature, saved_state.hotend_target_temperature)
+		{
+			if pause_temperature < target_temperature && !self.is_changing_filament
+			{
+				hotend_pid_controller.set_target_temperature(Some(pause_temperature));
+			}
+		}
+
+		let parking = &self.sequence.parking;
+		let position = &saved_state.position;
+		self.moves_to_plan.push_back(ParkingMove::along_axis(
+			Axis::E,
+			position[Axis::E as usize] - parking.retraction_length,
+			parking.retraction_speed_mm_s,
+		));
+		// The nozzle is raised as much as possible without crashing into the top of the Z axis (and it's never lowered)
+		let z = position[Axis::Z as usize];
+		self.moves_to_plan.push_back(ParkingMove::along_axis(
+			Axis::Z,
+			(z + parking.z_lift).min(motion_controller.get_max_z()).max(z),
+			parking.z_lift_speed_mm_s,
+		));
+		if let Some(park_position) = parking.park_position.as_ref()
+		{
+			self.moves_to_plan.push_back(ParkingMove {
+				target_position: [Some(park_position.x()), Some(park_position.y()), None, None],
+				speed_mm_s: parking.park_speed_mm_s,
+			});
+		}
+
+		self.saved_state = Some(saved_state);
+	}
+
+	/// Adds the moves that bring the nozzle back to the saved position to [`Self::moves_to_plan`] (in the reverse
+	/// order of the ones added by [`Self::park`]).
+	fn unpark(&mut self)
+	{
+		if let Some(saved_state) = self.saved_state.as_ref()
+		{
+			let parking = &self.sequence.parking;
+			let position = &saved_state.position;
+			if parking.park_position.is_some()
+			{
+				self.moves_to_plan.push_back(ParkingMove {
+					target_position: [
+						Some(position[Axis::X as usize]),
+						Some(position[Axis::Y as usize]),
+						None,
+						None,
+					],
+					speed_mm_s: parking.park_speed_mm_s,
+				});
+			}
+			self.moves_to_plan.push_back(ParkingMove::along_axis(
+				Axis::Z,
+				position[Axis::Z as usize],
+				parking.z_lift_speed_mm_s,
+			));
+			self.moves_to_plan.push_back(ParkingMove::along_axis(
+				Axis::E,
+				position[Axis::E as usize],
+				parking.retraction_speed_mm_s,
+			));
+		}
+	}
+
+	/// Plans as many moves of [`Self::moves_to_plan`] as possible, and returns `true` once all of them have been
+	/// planned and executed.
+	fn plan_moves<P: Peripherals>(
+		&mut self, motion_controller: &mut MotionController<P::StepperTickerTimer, P::Kinematics, P::ZAxisEndstop>,
+	) -> bool
+	{
+		while let Some(parking_move) = self.moves_to_plan.front()
+		{
+			let [x, y, z, e] = parking_move.target_position;
+			match motion_controller.plan_move(x, y, z, e, Some(parking_move.speed_mm_s))
+			{
+				Ok(move_id) =>
+				{
+					if !move_id.is_empty()
+					{
+						motion_controller.mark_last_move_as_ready_to_go();
+					}
+					self.moves_to_plan.pop_front();
+				},
+				Err(PlanMoveError::BlocksBufferIsFull) => return false,
+				// Otherwise the nozzle would never be parked (and the print never resumed)
+				Err(PlanMoveError::UnreachablePosition) =>
+				{
+					log::error!("Skipped a parking move to a position the tool can't reach");
+					self.moves_to_plan.pop_front();
+				},
+			}
+		}
+
+		!motion_controller.is_moving()
+	}
+}
+
+/// The state of the machine before it has been parked by a [`Pauser`].
+struct SavedState
+{
+	position: VectorN<N_MOTORS>,
+	planning_state: PlanningState,
+	hotend_target_temperature: Option<Temperature>,
+}
+
+/// A move made by a [`Pauser`] to park or unpark the nozzle.
+struct ParkingMove
+{
+	/// The absolute position each axis (in the [`Axis`] order) moves to, or `None` if it doesn't move.
+	target_position: [Option<Distance>; N_MOTORS],
+	speed_mm_s: f32,
+}
+
+impl ParkingMove
+{
+	fn along_axis(axis: Axis, position: Distance, speed_mm_s: f32) -> Self
+	{
+		let mut target_position = [None; N_MOTORS];
+		target_position[axis as usize] = Some(position);
+
+		Self {
+			target_position,
+			speed_mm_s,
+		}
+	}
+}
+
+/// Returns `true` if the temperature of the hotend is close enough to its target one (or if it has no target).
+fn has_hotend_reached_target_temperature<CHP: PwmPin, TADC: Adc, TP: AdcPin<TADC>>(
+	hotend_pid_controller: &TemperaturePidController<CHP, TADC, TP>,
+) -> bool
+{
+	const ACCEPTABLE_TEMPERATURE_RANGE: Temperature = Temperature::from_kelvin(3.);
+
+	match (
+		hotend_pid_controller.get_last_sample_of_current_temperature(),
+		hotend_pid_controller.get_target_temperature(),
+	)
+	{
+		(Some(current_temperature), Some(target_temperature)) =>
+		{
+			current_temperature + ACCEPTABLE_TEMPERATURE_RANGE >= target_temperature
+		},
+		_ => true,
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::{sync::MutexGuard, time::Duration};
+
+	use super::*;
+	use crate::{
+		printer::components::{
+			config::{
+				temperature::{PidConfig, SafetyConfig, ThermistorConfig},
+				ComponentsConfig, EndPrintSequence, ParkingSequence,
+			},
+			drivers::{
+				filament_sensor::{FilamentSensorConfig, FilamentSensorKind},
+				stepper_motor::tmc2209,
+			},
+			emergency_stop,
+			file_system::regions::metadata::FileId,
+			g_code::{
+				commands::{G0, G1, G91},
+				execute::FilePosition,
+				parameters::Param,
+				GCodeCommand,
+			},
+			mock::{MockPeripherals, MockSystemTime, MockThermalPlant, MockTimer, ThermalPlantConfig},
+			motion::{
+				self, backlash::BacklashSettings, planner::communicate_to_ticker, retraction::RetractionSettings,
+				skew::SkewCorrection, RotationToLinearMotion,
+			},
+			temperature::{safety::temperature_change::TemperatureChangeConfig, TemperaturePidGains},
+			Printer3DComponents,
+		},
+		utils::math::{
+			vectors::{Vector2, Vector3},
+			Percentage,
+		},
+	};
+
+	const Z_LIFT: Distance = Distance::from_millimeters(5);
+	const MAX_Z: Distance = Distance::from_millimeters(250);
+
+	/// The printer components ticked with a virtual time, using functional mocks as peripherals.
+	struct Harness
+	{
+		_ticker_lock: MutexGuard<'static, ()>,
+		_emergency_stop_lock: spin::MutexGuard<'static, ()>,
+		components: Printer3DComponents<MockPeripherals>,
+		stepper_ticker_timer: MockTimer,
+		system_time: MockSystemTime,
+		hotend: MockThermalPlant,
+		heated_bed: MockThermalPlant,
+		added_file_commands_count: u32,
+	}
+
+	impl Harness
+	{
+		const TICK_PERIOD: Duration = Duration::from_millis(1);
+
+		fn new() -> Self
+		{
+			let ticker_lock = communicate_to_ticker::lock_for_tests();
+			let emergency_stop_lock = emergency_stop::TESTS_LOCK.lock();
+			resume();
+
+			let mut peripherals = MockPeripherals::default();
+			let stepper_ticker_timer = peripherals.stepper_ticker_timer.clone().unwrap();
+			let system_time = peripherals.system_time.clone().unwrap();
+			let hotend = MockThermalPlant::new(
+				ThermalPlantConfig::hotend(),
+				peripherals.hotend_cartridge_heater_pin.as_ref().unwrap(),
+				peripherals.hotend_thermistor_pin.as_ref().unwrap(),
+				peripherals.adc.as_ref().unwrap(),
+			);
+			let heated_bed = MockThermalPlant::new(
+				ThermalPlantConfig::heated_bed(),
+				peripherals.bed_cartridge_heater_pin.as_ref().unwrap(),
+				peripherals.bed_thermistor_pin.as_ref().unwrap(),
+				peripherals.adc.as_ref().unwrap(),
+			);
+			let components = Printer3DComponents::new(&mut peripherals, config())
+				.unwrap_or_else(|_| panic!("Couldn't create the printer components"));
+
+			Self {
+				_ticker_lock: ticker_lock,
+				_emergency_stop_lock: emergency_stop_lock,
+				components,
+				stepper_ticker_timer,
+				system_time,
+				hotend,
+				heated_bed,
+				added_file_commands_count: 0,
+			}
+		}
+
+		/// Adds a `G1` command that moves the nozzle to `x` (in millimeters), as if it had been read from a file.
+		fn add_move_along_x(&mut self, x: i32)
+		{
+			let command = G1 {
+				x: Param::from(Distance::from_millimeters(x)),
+				feed_rate: Param::from(6_000.),
+				..Default::default()
+			};
+			self.add_file_command(Box::new(command));
+		}
+
+		/// Adds `command` to the buffer as if it had been read from a file, right after the previously added ones.
+		fn add_file_command(&mut self, command: Box<dyn GCodeCommand<MockPeripherals>>)
+		{
+			let g_code_executer = self.components.g_code_executer.as_mut().unwrap();
+			let file_position = FilePosition {
+				file_id: FileId::from_bytes([0; 4]),
+				offset: self.added_file_commands_count,
+			};
+			g_code_executer.add_file_command_to_buffer(command, file_position);
+			self.added_file_commands_count += 1;
+		}
+
+		fn tick(&mut self)
+		{
+			self.system_time.advance(Self::TICK_PERIOD);
+			self.stepper_ticker_timer.advance_time(Self::TICK_PERIOD);
+			self.hotend.tick(Self::TICK_PERIOD.as_secs_f64());
+			self.heated_bed.tick(Self::TICK_PERIOD.as_secs_f64());
+
+			if self.components.tick().is_err()
+			{
+				panic!("Couldn't tick the printer components");
+			}
+		}
+
+		/// Ticks the components until `is_done` returns `true`.
+		fn tick_until(&mut self, mut is_done: impl FnMut(&Self) -> bool)
+		{
+			const TIMEOUT: Duration = Duration::from_secs(60);
+
+			let mut time = Duration::ZERO;
+			while !(is_done)(self)
+			{
+				assert!(time < TIMEOUT, "The printer components didn't finish in {TIMEOUT:?}");
+
+				self.tick();
+				time += Self::TICK_PERIOD;
+			}
+		}
+
+		fn get_pause_state(&self) -> PauseState
+		{
+			self.components.pauser.get_state()
+		}
+
+		fn has_print_ended(&self) -> bool
+		{
+			!self
+				.components
+				.g_code_executer
+				.as_ref()
+				.unwrap()
+				.has_command_to_execute()
+				&& !self.components.motion_controller.is_moving()
+		}
+
+		/// Returns the position of the nozzle (X, Y, Z and E) in millimeters.
+		fn get_position(&self) -> [f32; N_MOTORS]
+		{
+			let position = self.components.motion_controller.get_position();
+
+			std::array::from_fn(|axis| position[axis].as_millimeters_f32())
+		}
+	}
+
+	impl Drop for Harness
+	{
+		fn drop(&mut self)
+		{
+			resume();
+		}
+	}
+
+	#[test]
+	fn paused_print_is_parked_and_then_unparked_when_resumed()
+	{
+		let mut harness = Harness::new();
+		harness.add_move_along_x(10);
+		harness.add_move_along_x(20);
+		harness.tick_until(|harness| harness.components.motion_controller.is_moving());
+		harness.tick();
+
+		pause();
+		harness.tick_until(|harness| harness.get_pause_state() == PauseState::Parking);
+		harness.tick_until(|harness| harness.get_pause_state() == PauseState::Paused);
+		assert_eq!(harness.get_position(), [0., 220., Z_LIFT.as_millimeters_f32(), -2.]);
+
+		resume();
+		harness.tick_until(|harness| harness.get_pause_state() == PauseState::Unparking);
+		harness.tick_until(|harness| harness.get_pause_state() == PauseState::Running);
+		assert_eq!(harness.get_position()[Axis::Y as usize..], [0., 0., 0.]);
+
+		harness.tick_until(Harness::has_print_ended);
+		assert_eq!(harness.get_position()[Axis::X as usize], 20.);
+	}
+
+	#[test]
+	fn parked_nozzle_isnt_raised_beyond_the_max_z()
+	{
+		let mut harness = Harness::new();
+		harness.components.motion_controller.set_position(
+			None,
+			None,
+			Some(MAX_Z - Distance::from_millimeters(2)),
+			None,
+		);
+		harness.add_move_along_x(10);
+		harness.tick();
+
+		pause();
+		harness.tick_until(|harness| harness.get_pause_state() == PauseState::Paused);
+		assert_eq!(harness.get_position()[Axis::Z as usize], MAX_Z.as_millimeters_f32());
+	}
+
+	/// A command whose move has been discarded by the pause before it was sent to the stepper motors must plan it again
+	/// (and mark it as ready to go) when the print is resumed, even if it was the command being executed.
+	#[test]
+	fn requeued_commands_are_executed_again_when_the_print_is_resumed()
+	{
+		let mut harness = Harness::new();
+		harness.add_move_along_x(10);
+		harness.add_move_along_x(20);
+		// The first move is marked as ready to go, but the planner waits a few ticks before sending it
+		harness.tick();
+
+		pause();
+		harness.tick_until(|harness| harness.get_pause_state() == PauseState::Paused);
+		assert_eq!(harness.get_position()[Axis::X as usize], 0.);
+
+		resume();
+		harness.tick_until(Harness::has_print_ended);
+		assert_eq!(harness.get_position()[Axis::X as usize], 20.);
+	}
+
+	/// A relative move prepared before the pause must move by the same distance when it's prepared again after the
+	/// print is resumed.
+	#[test]
+	fn requeued_relative_moves_are_offset_only_once()
+	{
+		let mut harness = Harness::new();
+		harness.add_file_command(Box::new(G91));
+		for _ in 0..2
+		{
+			harness.add_file_command(Box::new(G0 {
+				x: Param::from(Distance::from_millimeters(10)),
+				feed_rate: Param::from(6_000.),
+				..Default::default()
+			}));
+		}
+		harness.tick();
+
+		pause();
+		harness.tick_until(|harness| harness.get_pause_state() == PauseState::Paused);
+		assert_eq!(harness.get_position()[Axis::X as usize], 0.);
+
+		resume();
+		harness.tick_until(Harness::has_print_ended);
+		assert_eq!(harness.get_position()[Axis::X as usize], 20.);
+	}
+
+	fn config() -> ComponentsConfig
+	{
+		let temperature_pid = |max_temperature| PidConfig {
+			pid_gains: TemperaturePidGains {
+				p: 100.,
+				i: 10.,
+				d: 750.,
+			},
+			thermistor: ThermistorConfig {
+				beta: 3_950,
+				resistance_at_t0: 100_000,
+				other_resistance: 4_700,
+			},
+			safety: SafetyConfig {
+				allowed_temperature_range: Temperature::from_celsius(0.)..=Temperature::from_celsius(max_temperature),
+				keep_target_temperature_config: TemperatureChangeConfig {
+					period_in_seconds: 40.,
+					hysteresis: 4.,
+				},
+				rise_to_target_temperature_config: TemperatureChangeConfig {
+					period_in_seconds: 20.,
+					hysteresis: 2.,
+				},
+				rise_to_target_temperature_samples_count: 20,
+			},
+		};
+		let motor = |tmc2209_address, rotation_to_linear_motion| motion::MotorConfig {
+			tmc2209_address,
+			rotation_to_linear_motion,
+		};
+		let belt =
+			|| RotationToLinearMotion::new_connected_to_belt_driven(16, Distance::from_millimeters(2), 200 * 256);
+		let parking = ParkingSequence {
+			retraction_length: Distance::from_millimeters(2),
+			retraction_speed_mm_s: 40.,
+			z_lift: Z_LIFT,
+			z_lift_speed_mm_s: 10.,
+			park_position: Some(Vector2::from_xy(Distance::ZERO, Distance::from_millimeters(220))),
+			park_speed_mm_s: 100.,
+		};
+
+		ComponentsConfig {
+			layer_fan_min_duty_cycle_to_move: Percentage::ZERO,
+			hotend_fan_min_duty_cycle_to_move: Percentage::ZERO,
+			hotend_pid: temperature_pid(260.),
+			heated_bed_pid: temperature_pid(110.),
+			motion_controller: motion::CreationConfig {
+				left_motor: motor(tmc2209::UARTAddress::from_ms_pins_state(false, false), belt()),
+				right_motor: motor(tmc2209::UARTAddress::from_ms_pins_state(false, true), belt()),
+				z_axis_motor: motor(
+					tmc2209::UARTAddress::from_ms_pins_state(true, false),
+					RotationToLinearMotion::new_connected_to_lead_screw(4, Distance::from_millimeters(2), 200 * 256),
+				),
+				extruder_motor: motor(
+					tmc2209::UARTAddress::from_ms_pins_state(true, true),
+					RotationToLinearMotion::new(Distance::from_millimeters(35), 200 * 256),
+				),
+				bed_size: Vector2::from_xy(Distance::from_millimeters(235), Distance::from_millimeters(235)),
+				max_z: MAX_Z,
+				offset_from_nozzle_of_z_probe: Vector3::ZERO,
+				planner_blocks_count: 16,
+				planner_settings: motion::planner::Settings {
+					min_feedrate_mm_s: 0.2,
+					min_travel_feedrate_mm_s: 0.5,
+					max_feedrate_mm_s: [200., 200., 10., 45.],
+					retract_acceleration: 1_500.,
+					print_acceleration: 1_500.,
+					travel_acceleration: 2_000.,
+					max_acceleration_mm_per_s2: [9000., 9000., 100., 10000.],
+				},
+				backlash: BacklashSettings {
+					distances: Vector3::ZERO,
+					correction: 1.,
+					smoothing_distance: Distance::ZERO,
+				},
+				skew: SkewCorrection { xy: 0., xz: 0., yz: 0. },
+				retraction: RetractionSettings {
+					length: Distance::from_millimeters(3),
+					speed_mm_s: 45.,
+					z_hop: Distance::ZERO,
+					extra_prime_length: Distance::ZERO,
+					prime_speed_mm_s: 25.,
+				},
+			},
+			print_checkpoint_interval: Duration::from_secs(30),
+			end_print_sequence: EndPrintSequence {
+				parking: parking.clone(),
+				disable_steppers_after: Duration::from_secs(60),
+			},
+			pause_sequence: PauseSequence {
+				parking,
+				hotend_temperature: None,
+			},
+			filament_change: FilamentChangeConfig {
+				unload_length: Distance::from_millimeters(100),
+				unload_speed_mm_s: 50.,
+				load_length: Distance::from_millimeters(80),
+				load_speed_mm_s: 50.,
+				purge_length: Distance::from_millimeters(30),
+				purge_speed_mm_s: 3.,
+			},
+			filament_sensor: FilamentSensorConfig {
+				kind: FilamentSensorKind::Switch,
+				debounce_time: Duration::from_millis(50),
+				runout_distance: Distance::from_millimeters(20),
+			},
+		}
+	}
+}
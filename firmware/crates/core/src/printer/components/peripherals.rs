@@ -1,8 +1,9 @@
 use std::{fmt::Debug, net::IpAddr};
 
-#[cfg(feature = "usb")]
-use embedded_hal::digital::InputPin;
-use embedded_hal::{digital::OutputPin, spi::SpiDevice};
+use embedded_hal::{
+	digital::{InputPin, OutputPin},
+	spi::SpiDevice,
+};
 use embedded_svc::{ota::Ota, wifi::asynch::Wifi};
 #[cfg(feature = "usb")]
 use usb_device::class_prelude::UsbBus;
@@ -11,6 +12,7 @@ use super::{
 	drivers::spi_flash_memory::FlashMemoryChip,
 	hal::{
 		adc::{Adc, AdcPin},
+		interrupt::InterruptPin,
 		pwm::PwmPin,
 		timer::Timer,
 		uart::Uart,
@@ -71,6 +73,16 @@ pub trait Peripherals
 	/// The type for the Z-axis endstop.
 	type ZAxisEndstop: ZAxisProbe + 'static;
 
+	/// The input pin of the filament sensor (check [`FilamentSensor`]).
+	///
+	/// [`FilamentSensor`]: super::drivers::filament_sensor::FilamentSensor
+	type FilamentSensorPin: InputPin + Send + 'static;
+
+	/// The input pin of the emergency stop button (check [`emergency_stop`]).
+	///
+	/// [`emergency_stop`]: super::emergency_stop
+	type EmergencyStopButtonPin: InputPin + InterruptPin + Send + 'static;
+
 	/// A type representing a flash memory chip.
 	type FlashChip: FlashMemoryChip + Send + 'static;
 
@@ -178,6 +190,14 @@ pub trait Peripherals
 	/// Returns `None` if the peripheral is not available.
 	fn take_z_axis_endstop(&mut self) -> Option<Self::ZAxisEndstop>;
 
+	/// Attempts to take the filament sensor pin.
+	/// Returns `None` if the machine has no filament sensor (it's optional).
+	fn take_filament_sensor_pin(&mut self) -> Option<Self::FilamentSensorPin>;
+
+	/// Attempts to take the emergency stop button pin.
+	/// Returns `None` if the machine has no emergency stop button (it's optional).
+	fn take_emergency_stop_button_pin(&mut self) -> Option<Self::EmergencyStopButtonPin>;
+
 	/// Attempts to take the flash chip peripheral.
 	/// Returns `None` if the peripheral is not available.
 	fn take_flash_chip(&mut self) -> Option<Self::FlashChip>;
@@ -0,0 +1,148 @@
+//! Settings that can be changed at runtime (using G-code commands) and that are stored in the flash memory, so that
+//! they are kept when the machine is turned off.
+//!
+//! The settings are used by the `Components` thread, while the flash memory is owned by the `Communication` thread:
+//! - the `M500` G-code command [`requests to save`] the current settings, and the `Communication` thread
+//!   [`stores them`] in the file system.
+//! - at startup the `Communication` thread [`loads`] the settings from the file system and sends them to the
+//!   `Components` thread.
+//!
+//! [`requests to save`]: request_save
+//! [`stores them`]: store_in_file_system
+//! [`loads`]: load_from_file_system
+
+use embedded_hal::spi::SpiDevice;
+use spin::Mutex;
+
+use super::{
+	drivers::spi_flash_memory::FlashMemoryChip,
+	file_system::{FileSystem, ReplaceFileError},
+	motion::{backlash::BacklashSettings, retraction::RetractionSettings, skew::SkewCorrection},
+};
+use crate::utils::{math::vectors::Vector3, measurement::distance::Distance, slice_to_array};
+
+/// Name of the file in which the settings are stored in the file system.
+pub const FILE_NAME: &str = ".settings";
+
+static SAVE_REQUEST: Mutex<Option<PersistedSettings>> = Mutex::new(None);
+
+/// Asks the `Communication` thread to store the provided `settings` in the file system (it will do it as soon as
+/// possible).
+pub fn request_save(settings: PersistedSettings)
+{
+	*SAVE_REQUEST.lock() = Some(settings);
+}
+
+/// Returns the settings provided to the last call of [`request_save`] if they haven't been taken yet, otherwise
+/// returns `None`.
+pub fn take_save_request() -> Option<PersistedSettings>
+{
+	SAVE_REQUEST.lock().take()
+}
+
+/// All the settings that are stored in the flash memory.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PersistedSettings
+{
+	pub skew: SkewCorrection,
+	pub backlash: BacklashSettings,
+	pub retraction: RetractionSettings,
+}
+
+impl PersistedSettings
+{
+	/// Version of the format of the bytes returned by [`Self::to_bytes`]. It must be changed each time the format
+	/// changes, so that settings stored by an older firmware are ignored.
+	const VERSION: u8 = 2;
+	const SIZE_IN_BYTES: usize = 1 + 3 * 4 + 3 * 4 + 4 + 4 + 5 * 4;
+
+	pub fn to_bytes(&self) -> [u8; Self::SIZE_IN_BYTES]
+	{
+		let mut bytes = [Self::VERSION]
+			.into_iter()
+			.chain(self.skew.xy.to_le_bytes())
+			.chain(self.skew.xz.to_le_bytes())
+			.chain(self.skew.yz.to_le_bytes())
+			.chain(
+				self.backlash
+					.distances
+					.get_internal_array()
+					.iter()
+					.flat_map(|distance| distance.as_tens_of_nanometers().to_le_bytes()),
+			)
+			.chain(self.backlash.correction.to_le_bytes())
+			.chain(self.backlash.smoothing_distance.as_tens_of_nanometers().to_le_bytes())
+			.chain(self.retraction.length.as_tens_of_nanometers().to_le_bytes())
+			.chain(self.retraction.speed_mm_s.to_le_bytes())
+			.chain(self.retraction.z_hop.as_tens_of_nanometers().to_le_bytes())
+			.chain(self.retraction.extra_prime_length.as_tens_of_nanometers().to_le_bytes())
+			.chain(self.retraction.prime_speed_mm_s.to_le_bytes());
+
+		std::array::from_fn(|_| bytes.next().unwrap())
+	}
+
+	/// Returns `None` if the `bytes` have not been returned by [`Self::to_bytes`] of this version of the firmware.
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::{printer::components::{persisted_settings::*, motion::skew::*}};
+	/// #
+	/// let mut settings = PersistedSettings::default();
+	/// settings.skew = SkewCorrection { xy: 0.01, xz: -0.002, yz: 0. };
+	/// settings.backlash.correction = 0.5;
+	/// settings.retraction.speed_mm_s = 30.;
+	///
+	/// assert_eq!(PersistedSettings::from_bytes(&settings.to_bytes()), Some(settings));
+	/// assert_eq!(PersistedSettings::from_bytes(&[0; 5]), None);
+	/// ```
+	pub fn from_bytes(bytes: &[u8]) -> Option<Self>
+	{
+		if bytes.len() != Self::SIZE_IN_BYTES || bytes[0] != Self::VERSION
+		{
+			return None;
+		}
+
+		let f32_at = |index: usize| f32::from_le_bytes(slice_to_array(&bytes[index..]));
+		let distance_at =
+			|index: usize| Distance::from_tens_of_nanometers(i32::from_le_bytes(slice_to_array(&bytes[index..])));
+
+		Some(Self {
+			skew: SkewCorrection {
+				xy: (f32_at)(1),
+				xz: (f32_at)(5),
+				yz: (f32_at)(9),
+			},
+			backlash: BacklashSettings {
+				distances: Vector3::from_xyz((distance_at)(13), (distance_at)(17), (distance_at)(21)),
+				correction: (f32_at)(25),
+				smoothing_distance: (distance_at)(29),
+			},
+			retraction: RetractionSettings {
+				length: (distance_at)(33),
+				speed_mm_s: (f32_at)(37),
+				z_hop: (distance_at)(41),
+				extra_prime_length: (distance_at)(45),
+				prime_speed_mm_s: (f32_at)(49),
+			},
+		})
+	}
+}
+
+/// Reads the settings from the [`FILE_NAME`] file of the `file_system`.
+///
+/// Returns `None` if the file doesn't exist or if its content is not valid.
+pub fn load_from_file_system<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+	file_system: &mut FileSystem<Chip, Spi>,
+) -> Option<PersistedSettings>
+{
+	PersistedSettings::from_bytes(&file_system.read_replaced_file(FILE_NAME)?)
+}
+
+/// Stores the `settings` in the [`FILE_NAME`] file of the `file_system` (replacing the previous one if it exists, check
+/// [`FileSystem::replace_file`]).
+pub fn store_in_file_system<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+	file_system: &mut FileSystem<Chip, Spi>, settings: &PersistedSettings,
+) -> Result<(), ReplaceFileError<Spi>>
+{
+	file_system.replace_file(FILE_NAME, &settings.to_bytes())
+}
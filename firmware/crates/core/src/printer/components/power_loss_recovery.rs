@@ -0,0 +1,359 @@
+//! Recovery of the prints interrupted by a power loss.
+//!
+//! While a file is printed, the `Components` thread periodically [`requests to store`] a [`PrintCheckpoint`] (the
+//! position in the file of the last executed command and the state of the machine after it), and the `Communication`
+//! thread stores it in the checkpoint region of the flash memory. When the print ends the checkpoint is
+//! [`cleared`].
+//!
+//! If the machine boots and there's a checkpoint in the flash memory, the print can be resumed: the commands returned by
+//! [`PrintCheckpoint::resume_g_code`] are executed, and then the file is read again from [`PrintCheckpoint::file_offset`].
+//!
+//! [`requests to store`]: request_store
+//! [`cleared`]: request_clear
+
+use std::time::Duration;
+
+use spin::Mutex;
+
+use super::{
+	file_system::regions::metadata::FileId,
+	g_code::execute::{FileCommandState, FilePosition, PositionMode},
+};
+use crate::utils::{
+	math::{vectors::VectorN, Percentage},
+	measurement::{distance::Distance, temperature::Temperature},
+	slice_to_array,
+};
+
+static REQUEST: Mutex<Option<CheckpointRequest>> = Mutex::new(None);
+
+/// Asks the `Communication` thread to store the provided `checkpoint` in the flash memory (it will do it as soon as
+/// possible).
+pub fn request_store(checkpoint: PrintCheckpoint)
+{
+	*REQUEST.lock() = Some(CheckpointRequest::Store(checkpoint));
+}
+
+/// Asks the `Communication` thread to clear the checkpoint stored in the flash memory (it will do it as soon as
+/// possible).
+pub fn request_clear()
+{
+	*REQUEST.lock() = Some(CheckpointRequest::Clear);
+}
+
+/// Returns the last request made with [`request_store`] or [`request_clear`] if it hasn't been taken yet, otherwise
+/// returns `None`.
+pub fn take_request() -> Option<CheckpointRequest>
+{
+	REQUEST.lock().take()
+}
+
+/// A request made to the `Communication` thread (check [`take_request`]).
+#[derive(Clone, Debug, PartialEq)]
+pub enum CheckpointRequest
+{
+	Store(PrintCheckpoint),
+	Clear,
+}
+
+/// The progress of a print and the state the machine must be in to continue it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrintCheckpoint
+{
+	pub file_id: FileId,
+	/// Index of the first byte of the file that hasn't been executed yet.
+	pub file_offset: u32,
+	/// The position of the tool (in the coordinate system of the G-code) after the last executed command.
+	pub position: VectorN<4>,
+	pub feed_rate_mm_s: f32,
+	pub hotend_target_temperature: Option<Temperature>,
+	pub heated_bed_target_temperature: Option<Temperature>,
+	pub layer_fan_speed: Percentage,
+	pub position_mode: PositionMode,
+	pub extruder_position_mode: PositionMode,
+}
+
+impl PrintCheckpoint
+{
+	/// Version of the format of the bytes returned by [`Self::to_bytes`]. It must be changed each time the format
+	/// changes, so that checkpoints stored by an older firmware are ignored.
+	const VERSION: u8 = 1;
+	const SIZE_IN_BYTES: usize = 1 + 4 + 4 + 4 * 4 + 4 + 4 + 4 + 4 + 1 + 1;
+
+	/// How much the nozzle is lifted above the print while the X and Y axes are homed.
+	const Z_LIFT: Distance = Distance::from_millimeters(2);
+	const Z_FEED_RATE_MM_MIN: u32 = 300;
+	const XY_FEED_RATE_MM_MIN: u32 = 3000;
+
+	/// Returns the checkpoint of the print after the command whose state is `file_command_state` has been executed.
+	pub fn new(
+		file_command_state: &FileCommandState, hotend_target_temperature: Option<Temperature>,
+		heated_bed_target_temperature: Option<Temperature>, layer_fan_speed: Percentage,
+	) -> Self
+	{
+		Self {
+			file_id: file_command_state.file_position.file_id,
+			file_offset: file_command_state.file_position.offset,
+			position: file_command_state.position.clone(),
+			feed_rate_mm_s: file_command_state.feed_rate_mm_s,
+			hotend_target_temperature,
+			heated_bed_target_temperature,
+			layer_fan_speed,
+			position_mode: file_command_state.position_mode,
+			extruder_position_mode: file_command_state.extruder_position_mode,
+		}
+	}
+
+	pub fn to_bytes(&self) -> [u8; Self::SIZE_IN_BYTES]
+	{
+		let temperature_to_bytes =
+			|temperature: Option<Temperature>| temperature.map_or(f32::NAN, |temperature| temperature.as_kelvin());
+		let position_mode_to_byte = |position_mode| (position_mode == PositionMode::Relative) as u8;
+
+		let mut bytes = [Self::VERSION]
+			.into_iter()
+			.chain(self.file_id.to_bytes())
+			.chain(self.file_offset.to_le_bytes())
+			.chain(
+				self.position
+					.get_internal_array()
+					.iter()
+					.flat_map(|distance| distance.as_tens_of_nanometers().to_le_bytes()),
+			)
+			.chain(self.feed_rate_mm_s.to_le_bytes())
+			.chain((temperature_to_bytes)(self.hotend_target_temperature).to_le_bytes())
+			.chain((temperature_to_bytes)(self.heated_bed_target_temperature).to_le_bytes())
+			.chain(self.layer_fan_speed.into_0_to_1().to_le_bytes())
+			.chain([
+				(position_mode_to_byte)(self.position_mode),
+				(position_mode_to_byte)(self.extruder_position_mode),
+			]);
+
+		std::array::from_fn(|_| bytes.next().unwrap())
+	}
+
+	/// Returns `None` if the `bytes` have not been returned by [`Self::to_bytes`] of this version of the firmware.
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::{
+	/// # 	printer::components::{file_system::regions::metadata::FileId, g_code::execute::PositionMode, power_loss_recovery::*},
+	/// # 	utils::{math::{vectors::VectorN, Percentage}, measurement::{distance::Distance, temperature::Temperature}},
+	/// # };
+	/// #
+	/// let checkpoint = PrintCheckpoint {
+	/// 	file_id: FileId::from_bytes([3, 0, 0, 0]),
+	/// 	file_offset: 12_345,
+	/// 	position: VectorN::new([Distance::from_millimeters(10), Distance::from_micrometers(-500), Distance::from_millimeters(2), Distance::from_millimeters(1_000)]),
+	/// 	feed_rate_mm_s: 40.,
+	/// 	hotend_target_temperature: Some(Temperature::from_celsius(210.)),
+	/// 	heated_bed_target_temperature: None,
+	/// 	layer_fan_speed: Percentage::FULL,
+	/// 	position_mode: PositionMode::Absolute,
+	/// 	extruder_position_mode: PositionMode::Relative,
+	/// };
+	///
+	/// assert_eq!(PrintCheckpoint::from_bytes(&checkpoint.to_bytes()), Some(checkpoint));
+	/// assert_eq!(PrintCheckpoint::from_bytes(&[0; 5]), None);
+	/// ```
+	pub fn from_bytes(bytes: &[u8]) -> Option<Self>
+	{
+		if bytes.len() != Self::SIZE_IN_BYTES || bytes[0] != Self::VERSION
+		{
+			return None;
+		}
+
+		let f32_at = |index: usize| f32::from_le_bytes(slice_to_array(&bytes[index..]));
+		let distance_at =
+			|index: usize| Distance::from_tens_of_nanometers(i32::from_le_bytes(slice_to_array(&bytes[index..])));
+		let temperature_at = |index: usize| {
+			let kelvin = (f32_at)(index);
+			(!kelvin.is_nan()).then(|| Temperature::from_kelvin(kelvin))
+		};
+		let position_mode_at = |index: usize| match bytes[index]
+		{
+			0 => PositionMode::Absolute,
+			_ => PositionMode::Relative,
+		};
+
+		Some(Self {
+			file_id: FileId::from_bytes(slice_to_array(&bytes[1..])),
+			file_offset: u32::from_le_bytes(slice_to_array(&bytes[5..])),
+			position: VectorN::new(std::array::from_fn(|axis| (distance_at)(9 + axis * 4))),
+			feed_rate_mm_s: (f32_at)(25),
+			hotend_target_temperature: (temperature_at)(29),
+			heated_bed_target_temperature: (temperature_at)(33),
+			layer_fan_speed: Percentage::from_0_to_1((f32_at)(37)).ok()?,
+			position_mode: (position_mode_at)(41),
+			extruder_position_mode: (position_mode_at)(42),
+		})
+	}
+
+	/// Returns the G-code commands (one per line) that bring the machine back to the state of this checkpoint, so that
+	/// after they have been executed the print can continue from [`Self::file_offset`].
+	///
+	/// The Z axis isn't homed (the nozzle would hit the print): the machine is assumed to not have moved along it since
+	/// the power loss. The nozzle is lifted, the X and Y axes are homed while the bed and the hotend heat up, and then
+	/// the nozzle goes back to where it was.
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::{
+	/// # 	printer::components::{file_system::regions::metadata::FileId, g_code::execute::PositionMode, power_loss_recovery::*},
+	/// # 	utils::{math::{vectors::VectorN, Percentage}, measurement::{distance::Distance, temperature::Temperature}},
+	/// # };
+	/// #
+	/// let checkpoint = PrintCheckpoint {
+	/// 	file_id: FileId::from_bytes([3, 0, 0, 0]),
+	/// 	file_offset: 12_345,
+	/// 	position: VectorN::new([Distance::from_millimeters(10), Distance::from_micrometers(-500), Distance::from_micrometers(2_250), Distance::from_millimeters(1_000)]),
+	/// 	feed_rate_mm_s: 40.,
+	/// 	hotend_target_temperature: Some(Temperature::from_celsius(210.)),
+	/// 	heated_bed_target_temperature: None,
+	/// 	layer_fan_speed: Percentage::FULL,
+	/// 	position_mode: PositionMode::Absolute,
+	/// 	extruder_position_mode: PositionMode::Relative,
+	/// };
+	///
+	/// let resume_g_code = checkpoint.resume_g_code();
+	/// let mut lines = resume_g_code.lines();
+	/// assert_eq!(lines.next(), Some("G90"));
+	/// assert_eq!(lines.next(), Some("G92 Z2.25000"));
+	/// assert_eq!(lines.next(), Some("G1 Z4.25000 F300"));
+	/// assert_eq!(lines.next(), Some("M104 S210"));
+	/// assert_eq!(lines.next(), Some("G28 X Y"));
+	/// assert_eq!(lines.next(), Some("M109 S210"));
+	/// assert_eq!(lines.next(), Some("G1 X10.00000 Y-0.50000 F3000"));
+	/// assert_eq!(lines.next(), Some("G1 Z2.25000 F300"));
+	/// assert_eq!(lines.next(), Some("G92 E1000.00000"));
+	/// assert_eq!(lines.next(), Some("M106 P0 S255"));
+	/// assert_eq!(lines.next(), Some("G1 F2400"));
+	/// assert_eq!(lines.next(), Some("G90"));
+	/// assert_eq!(lines.next(), Some("M83"));
+	/// assert_eq!(lines.next(), None);
+	/// ```
+	pub fn resume_g_code(&self) -> String
+	{
+		let millimeters = |axis: usize| format_millimeters(self.position[axis]);
+		let celsius = |temperature: Temperature| temperature.as_celsius().round() as u16;
+
+		let mut lines = vec![
+			String::from("G90"),
+			format!("G92 Z{}", (millimeters)(2)),
+			format!(
+				"G1 Z{} F{}",
+				format_millimeters(self.position[2] + Self::Z_LIFT),
+				Self::Z_FEED_RATE_MM_MIN
+			),
+		];
+		if let Some(temperature) = self.heated_bed_target_temperature
+		{
+			lines.push(format!("M140 S{}", (celsius)(temperature)));
+		}
+		if let Some(temperature) = self.hotend_target_temperature
+		{
+			lines.push(format!("M104 S{}", (celsius)(temperature)));
+		}
+		lines.push(String::from("G28 X Y"));
+		if let Some(temperature) = self.heated_bed_target_temperature
+		{
+			lines.push(format!("M190 S{}", (celsius)(temperature)));
+		}
+		if let Some(temperature) = self.hotend_target_temperature
+		{
+			lines.push(format!("M109 S{}", (celsius)(temperature)));
+		}
+		lines.push(format!(
+			"G1 X{} Y{} F{}",
+			(millimeters)(0),
+			(millimeters)(1),
+			Self::XY_FEED_RATE_MM_MIN
+		));
+		lines.push(format!("G1 Z{} F{}", (millimeters)(2), Self::Z_FEED_RATE_MM_MIN));
+		lines.push(format!("G92 E{}", (millimeters)(3)));
+		lines.push(match (self.layer_fan_speed.into_0_to_1() * 255.).round() as u8
+		{
+			0 => String::from("M107 P0"),
+			fan_speed => format!("M106 P0 S{fan_speed}"),
+		});
+		lines.push(format!("G1 F{}", (self.feed_rate_mm_s * 60.).round() as u32));
+		lines.push(String::from(match self.position_mode
+		{
+			PositionMode::Absolute => "G90",
+			PositionMode::Relative => "G91",
+		}));
+		lines.push(String::from(match self.extruder_position_mode
+		{
+			PositionMode::Absolute => "M82",
+			PositionMode::Relative => "M83",
+		}));
+
+		lines.join("\n")
+	}
+}
+
+/// Returns the `distance` in millimeters with all the decimal digits of its precision (so that it's parsed back
+/// to the same distance).
+fn format_millimeters(distance: Distance) -> String
+{
+	let tens_of_nanometers = distance.as_tens_of_nanometers();
+	let millimeter = Distance::MILLIMETER.as_tens_of_nanometers();
+	format!(
+		"{}{}.{:05}",
+		if tens_of_nanometers < 0 { "-" } else { "" },
+		tens_of_nanometers.unsigned_abs() / millimeter as u32,
+		tens_of_nanometers.unsigned_abs() % millimeter as u32
+	)
+}
+
+/// Decides when a [`PrintCheckpoint`] must be stored or cleared, so that the flash memory isn't written too often.
+pub struct Checkpointer
+{
+	interval: Duration,
+	last_store_time: Option<Duration>,
+	last_stored_file_position: Option<FilePosition>,
+	has_stored_checkpoint: bool,
+}
+
+impl Checkpointer
+{
+	/// Returns a checkpointer that stores a checkpoint at most once every `interval`.
+	pub fn new(interval: Duration) -> Self
+	{
+		Self {
+			interval,
+			last_store_time: None,
+			last_stored_file_position: None,
+			has_stored_checkpoint: false,
+		}
+	}
+
+	/// Returns `true` if a checkpoint of the command read from `file_position` must be stored now (and in that case
+	/// you must [`request to store`] it).
+	///
+	/// [`request to store`]: request_store
+	pub fn should_store(&mut self, file_position: FilePosition, current_time: Duration) -> bool
+	{
+		let should_store = self.last_stored_file_position != Some(file_position)
+			&& self
+				.last_store_time
+				.is_none_or(|last_store_time| current_time >= last_store_time + self.interval);
+		if should_store
+		{
+			self.last_store_time = Some(current_time);
+			self.last_stored_file_position = Some(file_position);
+			self.has_stored_checkpoint = true;
+		}
+
+		should_store
+	}
+
+	/// Returns `true` if a checkpoint has been stored since the last time this method returned `true` (and in that
+	/// case you must [`request to clear`] it). Call it when the print has ended.
+	///
+	/// [`request to clear`]: request_clear
+	pub fn should_clear(&mut self) -> bool
+	{
+		std::mem::take(&mut self.has_stored_checkpoint)
+	}
+}
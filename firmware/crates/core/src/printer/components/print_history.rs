@@ -0,0 +1,418 @@
+//! A log of the prints executed by the machine (and the lifetime statistics of all of them), stored in the flash
+//! memory so that it's kept when the machine is turned off.
+//!
+//! The history is owned by the `Communication` thread: a [`PrintJob`] is created when a print starts, and when the
+//! print ends it becomes a [`PrintRecord`] that is [`added`] to the history. The filament used by a print is the
+//! length of filament [`extruded`] by the `Components` thread while the print was in execution. If the `Components`
+//! thread fails (for example because of a heater), it [`reports`] the failure so that the print is recorded as failed.
+//!
+//! [`added`]: PrintHistory::add
+//! [`extruded`]: add_extruded_filament
+//! [`reports`]: report_failure
+
+use std::{
+	collections::VecDeque,
+	sync::atomic::{AtomicBool, Ordering},
+	time::Duration,
+};
+
+use embedded_hal::spi::SpiDevice;
+use spin::Mutex;
+
+use super::{
+	drivers::spi_flash_memory::FlashMemoryChip,
+	file_system::{
+		regions::metadata::{take, take_slice},
+		FileSystem, ReplaceFileError,
+	},
+};
+use crate::utils::measurement::distance::Distance;
+
+/// Name of the file in which the history is stored in the file system.
+pub const FILE_NAME: &str = ".print_history";
+
+/// Length of filament extruded since the machine has been turned on, in tens of nanometers (it's not a [`Distance`]
+/// because it would overflow after about 21 meters).
+static EXTRUDED_FILAMENT: Mutex<i64> = Mutex::new(0);
+
+/// Adds `length` to the length of filament extruded since the machine has been turned on (a negative `length` is a
+/// retraction).
+pub fn add_extruded_filament(length: Distance)
+{
+	*EXTRUDED_FILAMENT.lock() += length.as_tens_of_nanometers() as i64;
+}
+
+/// Returns the length of filament (in millimeters) extruded since the machine has been turned on.
+pub fn get_extruded_filament_in_millimeters() -> f64
+{
+	*EXTRUDED_FILAMENT.lock() as f64 / Distance::from_millimeters(1).as_tens_of_nanometers() as f64
+}
+
+static FAILURE_REPORT: Mutex<Option<String>> = Mutex::new(None);
+/// Asks the `Communication` thread to end the print in execution (if there's one) as [`failed`] because of an error
+/// of the `Components` thread (whose description is `reason`).
+///
+/// [`failed`]: PrintOutcome::Failed
+pub fn report_failure(reason: String)
+{
+	IS_FAILURE_RECORDED.store(false, Ordering::Relaxed);
+	*FAILURE_REPORT.lock() = Some(reason);
+}
+
+/// Returns the reason of the failure [`reported`](report_failure) since the last time this function returned `Some`.
+pub fn take_failure_report() -> Option<String>
+{
+	FAILURE_REPORT.lock().take()
+}
+
+static IS_FAILURE_RECORDED: AtomicBool = AtomicBool::new(false);
+/// Tells the `Components` thread that the failure [`taken`](take_failure_report) by the `Communication` thread has
+/// been recorded in the history (or that there wasn't a print in execution).
+pub fn set_failure_recorded()
+{
+	IS_FAILURE_RECORDED.store(true, Ordering::Relaxed);
+}
+
+/// Returns `true` if the last failure [`reported`](report_failure) has been [`recorded`](set_failure_recorded).
+///
+/// # Examples
+/// ```
+/// # use firmware_core::printer::components::print_history;
+/// #
+/// print_history::report_failure(String::from("HeaterUnderTemperature"));
+/// assert!(!print_history::is_failure_recorded());
+///
+/// assert_eq!(print_history::take_failure_report().as_deref(), Some("HeaterUnderTemperature"));
+/// assert_eq!(print_history::take_failure_report(), None);
+/// print_history::set_failure_recorded();
+/// assert!(print_history::is_failure_recorded());
+/// ```
+pub fn is_failure_recorded() -> bool
+{
+	IS_FAILURE_RECORDED.load(Ordering::Relaxed)
+}
+
+/// How a print has ended.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PrintOutcome
+{
+	/// All the commands of the file have been executed.
+	Completed,
+	/// The print has been stopped by the user.
+	Cancelled,
+	/// The print has been stopped because of an error (the string describes it).
+	Failed(String),
+}
+
+/// A print that has ended.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrintRecord
+{
+	pub file_name: String,
+	/// Seconds since the Unix epoch at which the print started (if it was known).
+	pub start_unix_time: Option<u64>,
+	pub duration_in_seconds: u32,
+	pub outcome: PrintOutcome,
+	pub filament_used_in_millimeters: f32,
+}
+
+impl PrintRecord
+{
+	/// Maximum length of the description of the error of a [`PrintOutcome::Failed`] (a longer one is truncated).
+	const MAX_FAILURE_LENGTH: usize = 120;
+
+	/// Returns the seconds since the Unix epoch at which the print ended (if the start time is known).
+	pub fn get_end_unix_time(&self) -> Option<u64>
+	{
+		self.start_unix_time
+			.map(|start_unix_time| start_unix_time + self.duration_in_seconds as u64)
+	}
+}
+
+/// The totals of all the prints ever [`added`](PrintHistory::add) to the history.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrintStatistics
+{
+	pub print_duration_in_seconds: u64,
+	pub filament_used_in_millimeters: f64,
+	pub completed_jobs_count: u32,
+	pub cancelled_jobs_count: u32,
+	pub failed_jobs_count: u32,
+}
+
+impl PrintStatistics
+{
+	pub fn get_jobs_count(&self) -> u32
+	{
+		self.completed_jobs_count + self.cancelled_jobs_count + self.failed_jobs_count
+	}
+}
+
+/// The last [`PrintHistory::MAX_RECORDS_COUNT`] prints that have ended, and the [`PrintStatistics`] of all the prints.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrintHistory
+{
+	records: VecDeque<PrintRecord>,
+	statistics: PrintStatistics,
+}
+
+impl PrintHistory
+{
+	/// Version of the format of the bytes returned by [`Self::to_bytes`]. It must be changed each time the format
+	/// changes, so that a history stored by an older firmware is ignored.
+	const VERSION: u8 = 1;
+	/// Maximum number of records kept in the history (the oldest ones are removed, but they are still counted in the
+	/// statistics).
+	pub const MAX_RECORDS_COUNT: usize = 20;
+
+	/// Adds the `record` to the history (removing the oldest record if there are too many) and to the statistics.
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::print_history::*;
+	/// #
+	/// let mut history = PrintHistory::default();
+	/// for _ in 0..PrintHistory::MAX_RECORDS_COUNT + 1
+	/// {
+	/// 	history.add(PrintRecord {
+	/// 		file_name: "Cube".to_string(),
+	/// 		start_unix_time: None,
+	/// 		duration_in_seconds: 3600,
+	/// 		outcome: PrintOutcome::Completed,
+	/// 		filament_used_in_millimeters: 1000.,
+	/// 	});
+	/// }
+	///
+	/// assert_eq!(history.get_records().count(), PrintHistory::MAX_RECORDS_COUNT);
+	/// assert_eq!(history.get_statistics().get_jobs_count(), PrintHistory::MAX_RECORDS_COUNT as u32 + 1);
+	/// assert_eq!(history.get_statistics().print_duration_in_seconds, 3600 * 21);
+	/// ```
+	pub fn add(&mut self, mut record: PrintRecord)
+	{
+		let statistics = &mut self.statistics;
+		statistics.print_duration_in_seconds += record.duration_in_seconds as u64;
+		statistics.filament_used_in_millimeters += record.filament_used_in_millimeters as f64;
+		match &mut record.outcome
+		{
+			PrintOutcome::Completed => statistics.completed_jobs_count += 1,
+			PrintOutcome::Cancelled => statistics.cancelled_jobs_count += 1,
+			PrintOutcome::Failed(error) =>
+			{
+				statistics.failed_jobs_count += 1;
+				truncate_to_char_boundary(error, PrintRecord::MAX_FAILURE_LENGTH);
+			},
+		}
+
+		if self.records.len() == Self::MAX_RECORDS_COUNT
+		{
+			self.records.pop_front();
+		}
+		self.records.push_back(record);
+	}
+
+	/// Returns the records of the history, from the oldest to the newest.
+	pub fn get_records(&self) -> impl DoubleEndedIterator<Item = &PrintRecord>
+	{
+		self.records.iter()
+	}
+
+	pub fn get_statistics(&self) -> &PrintStatistics
+	{
+		&self.statistics
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8>
+	{
+		let statistics = &self.statistics;
+		let mut bytes = vec![Self::VERSION];
+		bytes.extend_from_slice(&statistics.print_duration_in_seconds.to_le_bytes());
+		bytes.extend_from_slice(&statistics.filament_used_in_millimeters.to_le_bytes());
+		bytes.extend_from_slice(&statistics.completed_jobs_count.to_le_bytes());
+		bytes.extend_from_slice(&statistics.cancelled_jobs_count.to_le_bytes());
+		bytes.extend_from_slice(&statistics.failed_jobs_count.to_le_bytes());
+
+		let push_string = |bytes: &mut Vec<u8>, string: &str| {
+			bytes.extend_from_slice(&(string.len() as u16).to_le_bytes());
+			bytes.extend_from_slice(string.as_bytes());
+		};
+		bytes.push(self.records.len() as u8);
+		for record in self.records.iter()
+		{
+			push_string(&mut bytes, &record.file_name);
+			bytes.extend_from_slice(&record.start_unix_time.unwrap_or(u64::MAX).to_le_bytes());
+			bytes.extend_from_slice(&record.duration_in_seconds.to_le_bytes());
+			bytes.extend_from_slice(&record.filament_used_in_millimeters.to_le_bytes());
+			match &record.outcome
+			{
+				PrintOutcome::Completed => bytes.push(0),
+				PrintOutcome::Cancelled => bytes.push(1),
+				PrintOutcome::Failed(error) =>
+				{
+					bytes.push(2);
+					push_string(&mut bytes, error);
+				},
+			}
+		}
+
+		bytes
+	}
+
+	/// Returns `None` if the `bytes` have not been returned by [`Self::to_bytes`] of this version of the firmware.
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::print_history::*;
+	/// #
+	/// let mut history = PrintHistory::default();
+	/// history.add(PrintRecord {
+	/// 	file_name: "models/3D Benchy".to_string(),
+	/// 	start_unix_time: Some(1700000000),
+	/// 	duration_in_seconds: 5400,
+	/// 	outcome: PrintOutcome::Completed,
+	/// 	filament_used_in_millimeters: 4321.5,
+	/// });
+	/// history.add(PrintRecord {
+	/// 	file_name: "Cube".to_string(),
+	/// 	start_unix_time: None,
+	/// 	duration_in_seconds: 60,
+	/// 	outcome: PrintOutcome::Failed("CouldntOpenFileForRead".to_string()),
+	/// 	filament_used_in_millimeters: 12.,
+	/// });
+	///
+	/// assert_eq!(PrintHistory::from_bytes(&history.to_bytes()), Some(history));
+	/// assert_eq!(PrintHistory::from_bytes(&[0; 5]), None);
+	/// ```
+	pub fn from_bytes(bytes: &[u8]) -> Option<Self>
+	{
+		if bytes.first() != Some(&Self::VERSION)
+		{
+			return None;
+		}
+
+		let mut remaining_bytes = &bytes[1..];
+		let bytes = &mut remaining_bytes;
+		let statistics = PrintStatistics {
+			print_duration_in_seconds: u64::from_le_bytes(take(bytes)?),
+			filament_used_in_millimeters: f64::from_le_bytes(take(bytes)?),
+			completed_jobs_count: u32::from_le_bytes(take(bytes)?),
+			cancelled_jobs_count: u32::from_le_bytes(take(bytes)?),
+			failed_jobs_count: u32::from_le_bytes(take(bytes)?),
+		};
+
+		let records_count = u8::from_le_bytes(take(bytes)?) as usize;
+		let mut records = VecDeque::with_capacity(records_count);
+		for _ in 0..records_count
+		{
+			let file_name = take_string(bytes)?;
+			let start_unix_time = Some(u64::from_le_bytes(take(bytes)?)).filter(|&time| time != u64::MAX);
+			let duration_in_seconds = u32::from_le_bytes(take(bytes)?);
+			let filament_used_in_millimeters = f32::from_le_bytes(take(bytes)?);
+			let outcome = match u8::from_le_bytes(take(bytes)?)
+			{
+				0 => PrintOutcome::Completed,
+				1 => PrintOutcome::Cancelled,
+				2 => PrintOutcome::Failed(take_string(bytes)?),
+				_ => return None,
+			};
+
+			records.push_back(PrintRecord {
+				file_name,
+				start_unix_time,
+				duration_in_seconds,
+				outcome,
+				filament_used_in_millimeters,
+			});
+		}
+
+		(bytes.is_empty() && records.len() <= Self::MAX_RECORDS_COUNT).then_some(Self { records, statistics })
+	}
+}
+
+/// A print in execution, that becomes a [`PrintRecord`] when it [`ends`](Self::end).
+#[derive(Clone, Debug)]
+pub struct PrintJob
+{
+	file_name: String,
+	start_unix_time: Option<u64>,
+	/// The time returned by the `SystemTime` of the microcontroller when the print started.
+	start_time: Option<Duration>,
+	extruded_filament_in_millimeters_at_start: f64,
+}
+
+impl PrintJob
+{
+	/// Starts tracking the print of the file named `file_name`, which is starting now (`start_unix_time` is the
+	/// number of seconds since the Unix epoch and `start_time` is the time returned by the `SystemTime`, if they are
+	/// known).
+	pub fn start(file_name: String, start_unix_time: Option<u64>, start_time: Option<Duration>) -> Self
+	{
+		Self {
+			file_name,
+			start_unix_time,
+			start_time,
+			extruded_filament_in_millimeters_at_start: get_extruded_filament_in_millimeters(),
+		}
+	}
+
+	/// Returns the record of this print, which has ended now with the provided `outcome` (`end_time` is the time
+	/// returned by the `SystemTime`, if it's known).
+	pub fn end(self, outcome: PrintOutcome, end_time: Option<Duration>) -> PrintRecord
+	{
+		let duration = self
+			.start_time
+			.zip(end_time)
+			.map_or(Duration::ZERO, |(start_time, end_time)| {
+				end_time.saturating_sub(start_time)
+			});
+		let filament_used_in_millimeters =
+			get_extruded_filament_in_millimeters() - self.extruded_filament_in_millimeters_at_start;
+
+		PrintRecord {
+			file_name: self.file_name,
+			start_unix_time: self.start_unix_time,
+			duration_in_seconds: duration.as_secs() as u32,
+			outcome,
+			filament_used_in_millimeters: filament_used_in_millimeters.max(0.) as f32,
+		}
+	}
+}
+
+/// Returns the string (preceded by its length) at the start of `bytes`, moving `bytes` after it.
+fn take_string(bytes: &mut &[u8]) -> Option<String>
+{
+	let length = u16::from_le_bytes(take(bytes)?) as usize;
+	String::from_utf8(take_slice(bytes, length)?.to_vec()).ok()
+}
+
+/// Removes the characters at the end of `string` so that it's at most `max_length` bytes long.
+fn truncate_to_char_boundary(string: &mut String, max_length: usize)
+{
+	if string.len() > max_length
+	{
+		let mut length = max_length;
+		while !string.is_char_boundary(length)
+		{
+			length -= 1;
+		}
+		string.truncate(length);
+	}
+}
+
+/// Reads the history from the [`FILE_NAME`] file of the `file_system`.
+///
+/// Returns `None` if the file doesn't exist or if its content is not valid.
+pub fn load_from_file_system<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+	file_system: &mut FileSystem<Chip, Spi>,
+) -> Option<PrintHistory>
+{
+	PrintHistory::from_bytes(&file_system.read_replaced_file(FILE_NAME)?)
+}
+
+/// Stores the `history` in the [`FILE_NAME`] file of the `file_system` (replacing the previous one if it exists, check
+/// [`FileSystem::replace_file`]).
+pub fn store_in_file_system<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+	file_system: &mut FileSystem<Chip, Spi>, history: &PrintHistory,
+) -> Result<(), ReplaceFileError<Spi>>
+{
+	file_system.replace_file(FILE_NAME, &history.to_bytes())
+}
@@ -8,11 +8,12 @@
 use std::{
 	fmt::Debug,
 	string::FromUtf8Error,
-	sync::atomic::{AtomicU16, Ordering},
+	sync::atomic::{AtomicBool, AtomicU16, Ordering},
 	time::Duration,
 };
 
 use embedded_hal::spi::SpiDevice;
+use spin::Mutex;
 
 use super::{
 	drivers::spi_flash_memory::FlashMemoryChip,
@@ -24,6 +25,7 @@ use super::{
 		FileSystem,
 	},
 	g_code::{
+		execute::FilePosition,
 		parser::{GCodeLine, GCodeParser},
 		GCodeCommand,
 	},
@@ -55,6 +57,35 @@ pub fn get_commands_in_buffer_count() -> u16
 	COMMANDS_IN_BUFFER.load(Ordering::Relaxed)
 }
 
+static IS_READING_FILE: AtomicBool = AtomicBool::new(false);
+/// Returns `true` if the [`PrintProcess`] is reading a file to print it (the commands read from the file may still be
+/// executed after it returns `false`).
+pub fn is_reading_file() -> bool
+{
+	IS_READING_FILE.load(Ordering::Relaxed)
+}
+
+static CANCEL_REQUEST: AtomicBool = AtomicBool::new(false);
+/// Asks the `Communication` thread to cancel the print in execution (it will do it as soon as possible).
+pub fn request_cancel()
+{
+	CANCEL_REQUEST.store(true, Ordering::Relaxed);
+}
+
+/// Returns `true` if [`request_cancel`] has been called since the last time this function returned `true`.
+pub fn take_cancel_request() -> bool
+{
+	CANCEL_REQUEST.swap(false, Ordering::Relaxed)
+}
+
+static LAST_EXECUTED_FILE_POSITION: Mutex<Option<FilePosition>> = Mutex::new(None);
+/// Sets the position of the last command read from a file that has been executed, so that the [`PrintProcess`] knows
+/// when all the commands of the file it has read have been executed.
+pub fn set_last_executed_file_position(file_position: FilePosition)
+{
+	*LAST_EXECUTED_FILE_POSITION.lock() = Some(file_position);
+}
+
 /// This struct controls the process of printing a file, by parsing the content of the
 /// file to [`G-code commmands`].
 ///
@@ -69,6 +100,13 @@ pub struct PrintProcess<P: Peripherals>
 	max_commands_in_buffer_before_reading_new: u16,
 
 	g_code_to_execute: String,
+	/// Index of the byte of the file to print after the last read one.
+	file_read_offset: u32,
+	/// The file of the current print. Unlike `file_id_to_print`, it stays `Some` after the file has been completely
+	/// read, until all the commands read from it have been executed.
+	printed_file_id: Option<FileId>,
+	/// Position of the last command read from the file of the current print.
+	last_read_file_position: Option<FilePosition>,
 	// This is taken from the GCode file
 	estimated_duration_in_seconds: Option<u32>,
 	print_start_time: Option<Duration>,
@@ -95,6 +133,9 @@ impl<P: Peripherals> PrintProcess<P>
 			estimated_duration_in_seconds: None,
 			print_start_time: None,
 			g_code_to_execute: String::with_capacity(P::FlashChip::PAGE_SIZE as usize),
+			file_read_offset: 0,
+			printed_file_id: None,
+			last_read_file_position: None,
 		}
 	}
 
@@ -103,9 +144,38 @@ impl<P: Peripherals> PrintProcess<P>
 	/// # Warning
 	/// You must call [`Self::tick`] to effectively make the print process progress.
 	pub fn print_file(&mut self, file_id_to_print: FileId, current_time: Option<Duration>)
+	{
+		self.resume_file(file_id_to_print, 0, current_time);
+	}
+
+	/// Starts printing the file with the provided `file_id_to_print` file id from the byte at `file_offset` (which
+	/// should be the start of a line), like [`Self::print_file`] does from the start of the file.
+	pub fn resume_file(&mut self, file_id_to_print: FileId, file_offset: u32, current_time: Option<Duration>)
 	{
 		self.file_id_to_print = Some(file_id_to_print);
+		self.file_to_print_reader = None;
+		self.g_code_to_execute.clear();
+		self.file_read_offset = file_offset;
+		self.printed_file_id = Some(file_id_to_print);
+		self.last_read_file_position = None;
+		*LAST_EXECUTED_FILE_POSITION.lock() = None;
+		self.estimated_duration_in_seconds = None;
 		self.print_start_time = current_time;
+		IS_READING_FILE.store(true, Ordering::Relaxed);
+	}
+
+	/// Stops the current print: no more commands are read from its file (the ones that have already been read are
+	/// still executed) and it's not [`in execution`](Self::is_printing) anymore.
+	pub fn stop(&mut self)
+	{
+		self.file_id_to_print = None;
+		self.file_to_print_reader = None;
+		self.g_code_to_execute.clear();
+		self.printed_file_id = None;
+		self.last_read_file_position = None;
+		self.estimated_duration_in_seconds = None;
+		self.print_start_time = None;
+		IS_READING_FILE.store(false, Ordering::Relaxed);
 	}
 
 	/// If a file is currently [`being printed`], calling this function will try to read new G-code commands
@@ -123,11 +193,11 @@ impl<P: Peripherals> PrintProcess<P>
 		{
 			if self.file_to_print_reader.is_none()
 			{
-				self.file_to_print_reader = Some(
-					file_system
-						.read_file(file_id_to_print)
-						.map_err(|_| PrintProcessError::CouldntOpenFileForRead)?,
-				);
+				let mut file_reader = file_system
+					.read_file(file_id_to_print)
+					.map_err(|_| PrintProcessError::CouldntOpenFileForRead)?;
+				file_reader.seek(self.file_read_offset);
+				self.file_to_print_reader = Some(file_reader);
 			}
 
 			if commands_in_buffer < self.max_commands_in_buffer_before_reading_new
@@ -145,16 +215,20 @@ impl<P: Peripherals> PrintProcess<P>
 					.map_err(PrintProcessError::SPIError)?;
 
 				read_lines.truncate(start + read_bytes_count as usize);
+				self.g_code_to_execute.clear();
+				let read_lines_file_offset = self.file_read_offset - start as u32;
+				self.file_read_offset += read_bytes_count;
 
 				let read_lines =
 					String::from_utf8(read_lines).map_err(|err| PrintProcessError::FileContainsInvalidUtf8(err))?;
 
 				let is_last_line_finished =
 					read_lines.ends_with("\n") || self.file_to_print_reader.as_mut().unwrap().has_reached_end_of_file();
+				// `line` must be a slice of `read_lines`
+				let line_start = |line: &str| line.as_ptr() as usize - read_lines.as_ptr() as usize;
 				let mut read_commands = Vec::with_capacity(read_lines.len() / 25);
-				let mut read_lines_iterator = read_lines.lines().enumerate().peekable();
-				let mut read_lines_iterator_cloned = read_lines_iterator.clone();
-				while let Some((line_number, line)) = read_lines_iterator.next()
+				let mut read_lines_iterator = read_lines.lines().peekable();
+				while let Some(line) = read_lines_iterator.next()
 				{
 					if read_lines_iterator.peek().is_none()
 					{
@@ -170,35 +244,41 @@ impl<P: Peripherals> PrintProcess<P>
 						{
 							if let Some(command) = result.command
 							{
-								read_commands.push(command);
+								let line_end = read_lines[line_start(line)..]
+									.find('\n')
+									.map_or(read_lines.len(), |index| line_start(line) + index + 1);
+								read_commands.push((
+									command,
+									FilePosition {
+										file_id: file_id_to_print,
+										offset: read_lines_file_offset + line_end as u32,
+									},
+								));
 							}
 						},
 						Err(_) =>
 						{
-							self.g_code_to_execute.clear();
-
-							read_lines_iterator_cloned.for_each(|(line_number_cloned, line)| {
-								if line_number != line_number_cloned
-								{
-									self.g_code_to_execute.push_str(line);
-									self.g_code_to_execute.push('\n');
-								}
-							});
-							// Remove the last new line if there wasn't one before
-							if !is_last_line_finished
-							{
-								let _ = self.g_code_to_execute.pop();
-							}
+							// All the lines are parsed again at the next tick, except this one that is replaced with
+							// spaces (so that the other lines are still at the same offset from the start of the file)
+							let line_range = line_start(line)..line_start(line) + line.len();
+							self.g_code_to_execute = read_lines.clone();
+							self.g_code_to_execute
+								.replace_range(line_range, &" ".repeat(line.len()));
 
 							return Err(PrintProcessError::CouldntParseLine(line.to_string()));
 						},
 					}
 				}
 
+				if let Some((_, file_position)) = read_commands.last()
+				{
+					self.last_read_file_position = Some(*file_position);
+				}
 				if self.file_to_print_reader.as_ref().unwrap().has_reached_end_of_file()
 				{
 					self.file_id_to_print = None;
 					self.file_to_print_reader = None;
+					IS_READING_FILE.store(false, Ordering::Relaxed);
 				}
 
 				Ok(PrintProcessOk {
@@ -230,6 +310,30 @@ impl<P: Peripherals> PrintProcess<P>
 		self.file_id_to_print.clone()
 	}
 
+	/// Returns `true` if a print is in execution, which means you called [`Self::print_file`] and not all the commands
+	/// of the file have been executed yet (check [`Self::take_completed_print`]).
+	pub fn is_printing(&self) -> bool
+	{
+		self.printed_file_id.is_some()
+	}
+
+	/// Returns the id of the printed file if the file has been completely read and all its commands have been executed
+	/// (after this the print is not [`in execution`](Self::is_printing) anymore), otherwise returns `None`.
+	pub fn take_completed_print(&mut self) -> Option<FileId>
+	{
+		let has_executed_all_commands = self.last_read_file_position.is_none()
+			|| *LAST_EXECUTED_FILE_POSITION.lock() == self.last_read_file_position;
+		if self.file_id_to_print.is_none() && has_executed_all_commands
+		{
+			self.last_read_file_position = None;
+			self.printed_file_id.take()
+		}
+		else
+		{
+			None
+		}
+	}
+
 	/// Returns `Some(duration_in_secs)` if a file is currently being printed (which means you called [`Self::print_file`]
 	/// and the file has not been completely read yet) and in the file there's a line containing `;TIME: {value}` where
 	/// `{value}` is a number.
@@ -285,11 +389,11 @@ impl<P: Peripherals> PrintProcess<P>
 
 /// The call to [`PrintProcess::tick`] has been successful, and this struct contains the string
 /// that has been read from the flash memory ([`Self::read_lines`]) and also the result of parsing
-/// that string to GCodeCommands (in [`Self::read_commands`]).
+/// that string to GCodeCommands (in [`Self::read_commands`], each one with its position in the file).
 pub struct PrintProcessOk<P: Peripherals>
 {
 	pub read_lines: Option<String>,
-	pub read_commands: Vec<Box<dyn GCodeCommand<P>>>,
+	pub read_commands: Vec<(Box<dyn GCodeCommand<P>>, FilePosition)>,
 }
 
 /// The call to [`PrintProcess::tick`] hasn't been successful. This enum contains the problems that
@@ -0,0 +1,174 @@
+//! A queue of files to print one after the other, that is stored in the flash memory so that it's kept when the
+//! machine is turned off.
+//!
+//! The queue is owned by the `Communication` thread (like the [`PrintProcess`]): when a print has been completed, the
+//! first file of the queue is printed, right away or (if [`PrintQueue::wait_for_bed_clearing`] is `true`) after the
+//! user has confirmed that the printed object has been removed from the bed.
+//!
+//! [`PrintProcess`]: super::print_process::PrintProcess
+
+use embedded_hal::spi::SpiDevice;
+
+use super::{
+	drivers::spi_flash_memory::FlashMemoryChip,
+	file_system::{regions::metadata::FileId, FileSystem, ReplaceFileError},
+};
+use crate::utils::slice_to_array;
+
+/// Name of the file in which the queue is stored in the file system.
+pub const FILE_NAME: &str = ".print_queue";
+
+/// The ids of the files waiting to be printed, in the order in which they will be printed (the same file can be in the
+/// queue more than once).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrintQueue
+{
+	files_ids: Vec<FileId>,
+	/// If `true`, the next file of the queue is not printed as soon as a print has been completed, but only when the
+	/// user confirms that the bed has been cleared.
+	pub wait_for_bed_clearing: bool,
+	/// `true` if a print has been completed and the next one is waiting for the user to clear the bed (this is not
+	/// stored in the flash memory).
+	pub is_waiting_for_bed_clearing: bool,
+}
+
+impl PrintQueue
+{
+	/// Version of the format of the bytes returned by [`Self::to_bytes`]. It must be changed each time the format
+	/// changes, so that a queue stored by an older firmware is ignored.
+	const VERSION: u8 = 1;
+	const HEADER_SIZE_IN_BYTES: usize = 1 + 1 + 2;
+
+	/// Returns the ids of the files in the queue, in the order in which they will be printed.
+	pub fn get_files_ids(&self) -> &[FileId]
+	{
+		&self.files_ids
+	}
+
+	/// Adds the file with the provided `file_id` to the queue at `index` (or at the end of the queue if `index` is
+	/// `None` or it's greater than the length of the queue).
+	pub fn add(&mut self, file_id: FileId, index: Option<usize>)
+	{
+		let index = index.map_or(self.files_ids.len(), |index| index.min(self.files_ids.len()));
+		self.files_ids.insert(index, file_id);
+	}
+
+	/// Removes the file at `index` from the queue, returning its id (or `None` if `index` is out of bounds).
+	pub fn remove(&mut self, index: usize) -> Option<FileId>
+	{
+		(index < self.files_ids.len()).then(|| self.files_ids.remove(index))
+	}
+
+	/// Removes all the occurrences of the file with the provided `file_id` from the queue, returning `true` if at least
+	/// one has been removed.
+	pub fn remove_file(&mut self, file_id: FileId) -> bool
+	{
+		let previous_length = self.files_ids.len();
+		self.files_ids.retain(|&queued_file_id| queued_file_id != file_id);
+		self.files_ids.len() != previous_length
+	}
+
+	/// Moves the file at `from_index` to `to_index`, shifting the files in between. Returns `false` (without changing
+	/// the queue) if one of the indices is out of bounds.
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::{print_queue::*, file_system::regions::metadata::FileId};
+	/// #
+	/// let file_id = |id: u32| FileId::from_bytes(id.to_le_bytes());
+	/// let mut queue = PrintQueue::default();
+	/// for id in 0..4
+	/// {
+	/// 	queue.add(file_id(id), None);
+	/// }
+	///
+	/// assert!(queue.move_file(3, 1));
+	/// assert_eq!(queue.get_files_ids(), [file_id(0), file_id(3), file_id(1), file_id(2)]);
+	/// assert!(!queue.move_file(0, 4));
+	/// ```
+	pub fn move_file(&mut self, from_index: usize, to_index: usize) -> bool
+	{
+		if from_index >= self.files_ids.len() || to_index >= self.files_ids.len()
+		{
+			return false;
+		}
+
+		let file_id = self.files_ids.remove(from_index);
+		self.files_ids.insert(to_index, file_id);
+		true
+	}
+
+	/// Removes the first file from the queue and returns its id, or returns `None` if the queue is empty.
+	pub fn take_next(&mut self) -> Option<FileId>
+	{
+		(!self.files_ids.is_empty()).then(|| self.files_ids.remove(0))
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8>
+	{
+		let mut bytes = Vec::with_capacity(Self::HEADER_SIZE_IN_BYTES + self.files_ids.len() * 4);
+		bytes.push(Self::VERSION);
+		bytes.push(self.wait_for_bed_clearing as u8);
+		bytes.extend_from_slice(&(self.files_ids.len() as u16).to_le_bytes());
+		bytes.extend(self.files_ids.iter().flat_map(FileId::to_bytes));
+
+		bytes
+	}
+
+	/// Returns `None` if the `bytes` have not been returned by [`Self::to_bytes`] of this version of the firmware.
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::printer::components::{print_queue::*, file_system::regions::metadata::FileId};
+	/// #
+	/// let mut queue = PrintQueue::default();
+	/// queue.add(FileId::from_bytes([7, 0, 0, 0]), None);
+	/// queue.add(FileId::from_bytes([2, 1, 0, 0]), Some(0));
+	/// queue.wait_for_bed_clearing = true;
+	///
+	/// assert_eq!(PrintQueue::from_bytes(&queue.to_bytes()), Some(queue));
+	/// assert_eq!(PrintQueue::from_bytes(&[0; 5]), None);
+	/// ```
+	pub fn from_bytes(bytes: &[u8]) -> Option<Self>
+	{
+		if bytes.len() < Self::HEADER_SIZE_IN_BYTES || bytes[0] != Self::VERSION
+		{
+			return None;
+		}
+
+		let files_count = u16::from_le_bytes(slice_to_array(&bytes[2..])) as usize;
+		let files_ids = &bytes[Self::HEADER_SIZE_IN_BYTES..];
+		if files_ids.len() != files_count * 4
+		{
+			return None;
+		}
+
+		Some(Self {
+			files_ids: files_ids
+				.chunks_exact(4)
+				.map(|file_id| FileId::from_bytes(slice_to_array(file_id)))
+				.collect(),
+			wait_for_bed_clearing: bytes[1] != 0,
+			is_waiting_for_bed_clearing: false,
+		})
+	}
+}
+
+/// Reads the queue from the [`FILE_NAME`] file of the `file_system`.
+///
+/// Returns `None` if the file doesn't exist or if its content is not valid.
+pub fn load_from_file_system<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+	file_system: &mut FileSystem<Chip, Spi>,
+) -> Option<PrintQueue>
+{
+	PrintQueue::from_bytes(&file_system.read_replaced_file(FILE_NAME)?)
+}
+
+/// Stores the `queue` in the [`FILE_NAME`] file of the `file_system` (replacing the previous one if it exists, check
+/// [`FileSystem::replace_file`]).
+pub fn store_in_file_system<Chip: FlashMemoryChip, Spi: SpiDevice<u8>>(
+	file_system: &mut FileSystem<Chip, Spi>, queue: &PrintQueue,
+) -> Result<(), ReplaceFileError<Spi>>
+{
+	file_system.replace_file(FILE_NAME, &queue.to_bytes())
+}
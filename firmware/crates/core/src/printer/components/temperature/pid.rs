@@ -228,3 +228,148 @@ pub struct PidGains
 	/// [`Derivative component`](https://en.wikipedia.org/wiki/Proportional%E2%80%93integral%E2%80%93derivative_controller#Derivative).
 	pub d: f32,
 }
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::printer::components::{
+		mock::{MockAdc, MockAdcPin, MockPwmPin, MockThermalPlant, ThermalPlantConfig},
+		temperature::safety::{temperature_change::TemperatureChangeConfig, TemperatureError},
+	};
+
+	const DELTA_TIME: f64 = 0.1;
+
+	fn hotend() -> (
+		PidController<MockPwmPin, MockAdc, MockAdcPin>,
+		MockThermalPlant,
+		MockAdc,
+		MockPwmPin,
+	)
+	{
+		let heater_pin = MockPwmPin::default();
+		let thermistor_pin = MockAdcPin::default();
+		let adc = MockAdc::default();
+
+		let config = ThermalPlantConfig::hotend();
+		let plant = MockThermalPlant::new(config, &heater_pin, &thermistor_pin, &adc);
+		let pid_controller = PidController::new(
+			Thermistor::new(
+				thermistor_pin,
+				config.thermistor.beta,
+				config.thermistor.resistance_at_t0,
+				config.thermistor.other_resistance,
+			),
+			CartridgeHeater::new(heater_pin.clone()),
+			PidGains {
+				p: 100.,
+				i: 10.,
+				d: 750.,
+			},
+			TemperatureSafety::new(
+				Temperature::from_celsius(0.)..=Temperature::from_celsius(260.),
+				TemperatureChangeConfig {
+					period_in_seconds: 40.,
+					hysteresis: 4.,
+				},
+				TemperatureChangeConfig {
+					period_in_seconds: 20.,
+					hysteresis: 2.,
+				},
+				20,
+			),
+		);
+
+		(pid_controller, plant, adc, heater_pin)
+	}
+
+	/// Ticks the plant and the PID controller for `seconds` seconds, returning the first error of the controller.
+	fn simulate(
+		pid_controller: &mut PidController<MockPwmPin, MockAdc, MockAdcPin>, plant: &mut MockThermalPlant,
+		adc: &mut MockAdc, seconds: f64,
+	) -> Result<(), TickError>
+	{
+		for _ in 0..(seconds / DELTA_TIME) as u32
+		{
+			plant.tick(DELTA_TIME);
+			pid_controller.tick(DELTA_TIME, adc)?;
+		}
+
+		Ok(())
+	}
+
+	fn assert_is_safety_error(result: Result<(), TickError>, expected_error: TemperatureError)
+	{
+		match result
+		{
+			Err(TickError::ReadTemperatureIsWrong(errors)) => assert!(errors.contains(expected_error), "{errors:?}"),
+			_ => panic!("Expected {expected_error:?}, got {result:?}"),
+		}
+	}
+
+	#[test]
+	fn reaches_and_keeps_target_temperature()
+	{
+		let (mut pid_controller, mut plant, mut adc, _) = hotend();
+		pid_controller.set_target_temperature(Some(Temperature::from_celsius(200.)));
+
+		simulate(&mut pid_controller, &mut plant, &mut adc, 300.).unwrap();
+		for _ in 0..60
+		{
+			simulate(&mut pid_controller, &mut plant, &mut adc, 1.).unwrap();
+			let temperature = pid_controller.get_last_sample_of_current_temperature().unwrap();
+			assert!((temperature.as_celsius() - 200.).abs() < 2., "{temperature:?}");
+		}
+	}
+
+	#[test]
+	fn turns_off_heater_without_target_temperature()
+	{
+		let (mut pid_controller, mut plant, mut adc, _) = hotend();
+		pid_controller.set_target_temperature(Some(Temperature::from_celsius(200.)));
+		simulate(&mut pid_controller, &mut plant, &mut adc, 30.).unwrap();
+
+		pid_controller.set_target_temperature(None);
+		simulate(&mut pid_controller, &mut plant, &mut adc, 600.).unwrap();
+		assert!(plant.get_heated_body_temperature().as_celsius() < 30.);
+	}
+
+	#[test]
+	fn detects_heater_that_cant_rise_temperature()
+	{
+		let (mut pid_controller, mut plant, mut adc, _) = hotend();
+		plant.set_heater_efficiency(0.);
+		pid_controller.set_target_temperature(Some(Temperature::from_celsius(200.)));
+
+		let result = simulate(&mut pid_controller, &mut plant, &mut adc, 60.);
+		assert_is_safety_error(result, TemperatureError::CantRiseFastEnoughToTargetTemperature);
+	}
+
+	#[test]
+	fn detects_heater_that_falls_out_after_reaching_target_temperature()
+	{
+		let (mut pid_controller, mut plant, mut adc, heater_pin) = hotend();
+		pid_controller.set_target_temperature(Some(Temperature::from_celsius(200.)));
+		simulate(&mut pid_controller, &mut plant, &mut adc, 300.).unwrap();
+
+		plant.set_heater_efficiency(0.);
+		let result = simulate(&mut pid_controller, &mut plant, &mut adc, 120.);
+		assert_is_safety_error(result, TemperatureError::CantRiseFastEnoughToTargetTemperature);
+		assert_eq!(heater_pin.get_duty_cycle(), Percentage::ZERO);
+	}
+
+	#[test]
+	fn detects_thermal_runaway_after_reaching_target_temperature()
+	{
+		let (mut pid_controller, mut plant, mut adc, heater_pin) = hotend();
+		pid_controller.set_target_temperature(Some(Temperature::from_celsius(200.)));
+		simulate(&mut pid_controller, &mut plant, &mut adc, 300.).unwrap();
+
+		// The MOSFET of the heater is shorted, so the heater is always on
+		plant.force_heater_duty_cycle(Some(Percentage::FULL));
+		let result = simulate(&mut pid_controller, &mut plant, &mut adc, 120.);
+		assert_is_safety_error(result, TemperatureError::CantKeepTargetTemperature);
+		assert!(plant.get_heated_body_temperature().as_celsius() < 260.);
+		assert_eq!(heater_pin.get_duty_cycle(), Percentage::ZERO);
+	}
+}
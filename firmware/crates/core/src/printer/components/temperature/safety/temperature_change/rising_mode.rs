@@ -99,7 +99,7 @@ impl ProtectionModeTrait for RisingMode
 
 				if self.remaining_seconds_for_new_sample <= 0.
 				{
-					let seconds_to_take_sample = config.period_in_seconds / self.samples.len() as f32;
+					let seconds_to_take_sample = config.period_in_seconds / self.samples.capacity() as f32;
 					self.remaining_seconds_for_new_sample += seconds_to_take_sample;
 
 					self.samples.push(current_temperature);
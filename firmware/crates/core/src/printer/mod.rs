@@ -37,6 +37,11 @@ pub struct Printer3D<P: Peripherals + 'static>
 	components: Printer3DComponents<SendablePeripherals<P>>,
 	communication: MultiThreadCommunication<P>,
 	watchdog: Option<<P::WatchdogCreator as WatchdogCreator>::Watchdog>,
+	/// The error returned by the components, that is returned by [`Self::tick`] only after the failure of the print in
+	/// execution (if there's one) has been recorded in the [`print history`].
+	///
+	/// [`print history`]: components::print_history
+	components_error: Option<components::TickError<P::ZAxisEndstop, P::UartDriver, P::StepperTickerTimer>>,
 }
 
 impl<P: Peripherals + 'static> Printer3D<P>
@@ -51,16 +56,19 @@ impl<P: Peripherals + 'static> Printer3D<P>
 	/// * `peripherals` - The hardware peripherals for the printer.
 	/// * `components_config` - Configuration settings for the printer's components.
 	/// * `communication_config` - Configuration settings for the communication system.
-	/// * `panic_handler` - A handler to register for panic events.
+	/// * `panic_handler` - A handler to register for panic events (its function is also called by an [`emergency stop`]).
 	///
 	/// # Returns
 	///
 	/// A `Result` containing the initialized `Printer3D` instance or a `CreationError`.
+	///
+	/// [`emergency stop`]: components::emergency_stop
 	pub fn new(
 		mut peripherals: P, components_config: ComponentsConfig, communication_config: CommunicationConfig,
 		panic_handler: PanicHandler,
 	) -> Result<Self, CreationError<P>>
 	{
+		components::emergency_stop::set_disable_all_pins_function(panic_handler.0);
 		panic_handler::register_panic_handler(panic_handler);
 
 		Ok(Self {
@@ -75,6 +83,7 @@ impl<P: Peripherals + 'static> Printer3D<P>
 				.take_watchdog_creator()
 				.map(|watchdog_creator| watchdog_creator.watch_current_thread())
 				.flatten(),
+			components_error: None,
 		})
 	}
 
@@ -83,9 +92,15 @@ impl<P: Peripherals + 'static> Printer3D<P>
 	/// This method should be called periodically to ensure the printer
 	/// operates correctly and to maintain communication with its components.
 	///
+	/// If the components fail, the machine is [`halted`] and the error is returned only after the print in execution
+	/// has been recorded as failed in the [`print history`].
+	///
 	/// # Returns
 	///
 	/// A `Result` indicating success or a `TickError` if an error occurs.
+	///
+	/// [`halted`]: components::emergency_stop
+	/// [`print history`]: components::print_history
 	pub fn tick(&mut self) -> Result<(), TickError<P>>
 	{
 		if let Some(watchdog) = self.watchdog.as_mut()
@@ -93,7 +108,21 @@ impl<P: Peripherals + 'static> Printer3D<P>
 			watchdog.feed().map_err(TickError::WatchdogReset)?;
 		}
 
-		self.components.tick().map_err(TickError::Components)?;
+		if let Some(error) = self.components_error.take()
+		{
+			if components::print_history::is_failure_recorded()
+			{
+				return Err(TickError::Components(error));
+			}
+			self.components_error = Some(error);
+		}
+		else if let Err(error) = self.components.tick()
+		{
+			// The machine is kept halted until the `Communication` thread has recorded the failure of the print
+			components::emergency_stop::stop();
+			components::print_history::report_failure(format!("{error:?}"));
+			self.components_error = Some(error);
+		}
 		self.communication.tick(&mut self.components);
 
 		//crate::utils::log_in_isr::print_logs_from_isr();
@@ -0,0 +1,69 @@
+/// Calculates the CRC-32 (the one used by Ethernet, zip and PNG) of a sequence of bytes, that can be provided in more
+/// chunks.
+///
+/// # Examples
+/// ```
+/// # use firmware_core::utils::crc::*;
+/// #
+/// let mut crc = Crc32::new();
+/// crc.update(b"1234");
+/// crc.update(b"56789");
+/// assert_eq!(crc.finish(), 0xCBF43926);
+/// assert_eq!(crc32(b"123456789"), 0xCBF43926);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32(u32);
+
+impl Crc32
+{
+	/// The reversed representation of the polynomial.
+	const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+	pub const fn new() -> Self
+	{
+		Self(u32::MAX)
+	}
+
+	/// Adds the `bytes` to the sequence whose CRC is calculated.
+	pub fn update(&mut self, bytes: &[u8])
+	{
+		for &byte in bytes
+		{
+			self.0 ^= byte as u32;
+
+			const BITS_PER_BYTE: u8 = 8;
+			for _ in 0..BITS_PER_BYTE
+			{
+				self.0 = match self.0 & 1
+				{
+					1 => (self.0 >> 1) ^ Self::POLYNOMIAL,
+					_ => self.0 >> 1,
+				};
+			}
+		}
+	}
+
+	/// Returns the CRC of all the bytes you [`provided`].
+	///
+	/// [`provided`]: Self::update
+	pub const fn finish(&self) -> u32
+	{
+		!self.0
+	}
+}
+
+impl Default for Crc32
+{
+	fn default() -> Self
+	{
+		Self::new()
+	}
+}
+
+/// Returns the [`Crc32`] of the `bytes`.
+pub fn crc32(bytes: &[u8]) -> u32
+{
+	let mut crc = Crc32::new();
+	crc.update(bytes);
+	crc.finish()
+}
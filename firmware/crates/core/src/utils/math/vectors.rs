@@ -251,4 +251,25 @@ impl<const N: usize> VectorN<N>
 		}
 		result
 	}
+
+	/// Returns the [`dot product`] of this vector with `other`, treating the components of both as millimeters.
+	///
+	/// Unlike [`Self::dot`], it doesn't overflow when the components are longer than about half a millimeter.
+	///
+	/// # Examples
+	/// ```
+	/// # use firmware_core::utils::{measurement::distance::Distance, math::vectors::*};
+	/// let a = VectorN::<2>::from_xy(Distance::from_millimeters(3), Distance::from_millimeters(4));
+	/// let b = VectorN::<2>::from_xy(Distance::from_millimeters(-2), Distance::from_millimeters(1));
+	/// assert_eq!(a.dot_millimeters(&b), -2.);
+	/// assert_eq!(a.dot_millimeters(&a), a.length_millimeters_sqr());
+	/// ```
+	///
+	/// [`dot product`]: https://en.wikipedia.org/wiki/Dot_product
+	pub fn dot_millimeters(&self, other: &Self) -> f32
+	{
+		self.0.iter().zip(other.0.iter()).fold(0., |result, (a, b)| {
+			result + a.as_millimeters_f32() * b.as_millimeters_f32()
+		})
+	}
 }
@@ -4,6 +4,7 @@
 //! reusability across different components of the firmware.
 
 pub mod bresenham;
+pub mod crc;
 pub mod math;
 pub mod measurement;
 
@@ -15,7 +15,8 @@ pub const HTTP_SERVER_CONFIG: Configuration = Configuration {
 	max_uri_handlers: firmware_core::printer::communication::http::request::http_request_handlers_count(),
 	max_resp_handlers: 8,
 	lru_purge_enable: true,
-	uri_match_wildcard: false,
+	// Some URIs contain the ID of a resource (like `/v1/files/*`)
+	uri_match_wildcard: true,
 	#[cfg(esp_idf_esp_https_server_enable)]
 	server_certificate: None,
 	#[cfg(esp_idf_esp_https_server_enable)]
@@ -39,5 +39,6 @@ pub fn configuration() -> CommunicationConfig
 		file_system: RegionsConfig::default::<<Peripherals as PeripheralsTrait>::FlashChip>(),
 		max_commands_in_buffer_before_reading_new: 200,
 		delay_between_ticks: Duration::from_millis(100),
+		thread_stack_size: 15_000,
 	}
 }
@@ -4,14 +4,19 @@ mod pwm;
 mod stepper_ticker_timer;
 mod uart;
 
+use std::time::Duration;
+
 pub use adc::*;
 use firmware_core::{
 	printer::components::{
 		config::{
 			temperature::{PidConfig, SafetyConfig, ThermistorConfig},
-			ComponentsConfig,
+			ComponentsConfig, EndPrintSequence, FilamentChangeConfig, ParkingSequence, PauseSequence,
+		},
+		drivers::{
+			filament_sensor::{FilamentSensorConfig, FilamentSensorKind},
+			stepper_motor::tmc2209,
 		},
-		drivers::stepper_motor::tmc2209,
 		motion::{self, RotationToLinearMotion},
 		temperature::{safety::temperature_change::TemperatureChangeConfig, TemperaturePidGains},
 	},
@@ -114,6 +119,7 @@ pub fn configuration() -> ComponentsConfig
 				),
 			},
 			bed_size: Vector2::from_xy(Distance::from_millimeters(235), Distance::from_millimeters(235)),
+			max_z: Distance::from_millimeters(250),
 			offset_from_nozzle_of_z_probe: Vector3::from_xyz(
 				Distance::from_millimeters(115),
 				Distance::from_millimeters(348),
@@ -129,6 +135,59 @@ pub fn configuration() -> ComponentsConfig
 				travel_acceleration: 2_000.,
 				max_acceleration_mm_per_s2: [9000., 9000., 100., 10000.],
 			},
+			backlash: motion::backlash::BacklashSettings {
+				distances: Vector3::from_xyz(Distance::ZERO, Distance::ZERO, Distance::ZERO),
+				correction: 1.,
+				smoothing_distance: Distance::ZERO,
+			},
+			skew: motion::skew::SkewCorrection {
+				xy: 0.,
+				xz: 0.,
+				yz: 0.,
+			},
+			retraction: motion::retraction::RetractionSettings {
+				length: Distance::from_millimeters(3),
+				speed_mm_s: 45.,
+				z_hop: Distance::ZERO,
+				extra_prime_length: Distance::ZERO,
+				prime_speed_mm_s: 25.,
+			},
+		},
+		print_checkpoint_interval: Duration::from_secs(30),
+		end_print_sequence: EndPrintSequence {
+			parking: ParkingSequence {
+				retraction_length: Distance::from_millimeters(2),
+				retraction_speed_mm_s: 40.,
+				z_lift: Distance::from_millimeters(10),
+				z_lift_speed_mm_s: 10.,
+				park_position: Some(Vector2::from_xy(Distance::ZERO, Distance::from_millimeters(220))),
+				park_speed_mm_s: 100.,
+			},
+			disable_steppers_after: Duration::from_secs(60),
+		},
+		pause_sequence: PauseSequence {
+			parking: ParkingSequence {
+				retraction_length: Distance::from_millimeters(2),
+				retraction_speed_mm_s: 40.,
+				z_lift: Distance::from_millimeters(5),
+				z_lift_speed_mm_s: 10.,
+				park_position: Some(Vector2::from_xy(Distance::ZERO, Distance::from_millimeters(220))),
+				park_speed_mm_s: 100.,
+			},
+			hotend_temperature: Some(Temperature::from_celsius(150.)),
+		},
+		filament_change: FilamentChangeConfig {
+			unload_length: Distance::from_millimeters(100),
+			unload_speed_mm_s: 50.,
+			load_length: Distance::from_millimeters(80),
+			load_speed_mm_s: 50.,
+			purge_length: Distance::from_millimeters(30),
+			purge_speed_mm_s: 3.,
+		},
+		filament_sensor: FilamentSensorConfig {
+			kind: FilamentSensorKind::Switch,
+			debounce_time: Duration::from_millis(50),
+			runout_distance: Distance::from_millimeters(20),
 		},
 	}
 }
@@ -64,6 +64,9 @@ pub struct Peripherals
 	y_axis_endstop: Option<<Self as PeripheralsTrait>::YAxisEndstop>,
 	z_axis_endstop: Option<<Self as PeripheralsTrait>::ZAxisEndstop>,
 
+	filament_sensor_pin: Option<<Self as PeripheralsTrait>::FilamentSensorPin>,
+	emergency_stop_button_pin: Option<<Self as PeripheralsTrait>::EmergencyStopButtonPin>,
+
 	flash_chip: <Self as PeripheralsTrait>::FlashChip,
 	flash_spi: Option<<Self as PeripheralsTrait>::FlashSpi>,
 
@@ -112,6 +115,9 @@ impl PeripheralsTrait for Peripherals
 	type YAxisEndstop = Button<InputPin<'static, Gpio36>>;
 	type ZAxisEndstop = BLTouch<LedcPwmPin<'static>, InputPin<'static, Gpio15>>;
 
+	type FilamentSensorPin = InputPin<'static, Gpio14>;
+	type EmergencyStopButtonPin = InputPin<'static, Gpio47>;
+
 	type FlashChip = MT29F2G01ABAGDWB;
 	type FlashSpi = SpiSingleDeviceDriver<'static>;
 
@@ -214,6 +220,16 @@ impl PeripheralsTrait for Peripherals
 		self.z_axis_endstop.take()
 	}
 
+	fn take_filament_sensor_pin(&mut self) -> Option<Self::FilamentSensorPin>
+	{
+		self.filament_sensor_pin.take()
+	}
+
+	fn take_emergency_stop_button_pin(&mut self) -> Option<Self::EmergencyStopButtonPin>
+	{
+		self.emergency_stop_button_pin.take()
+	}
+
 	fn take_flash_chip(&mut self) -> Option<Self::FlashChip>
 	{
 		Some(self.flash_chip.clone())
@@ -363,6 +379,11 @@ impl Peripherals
 				)?),
 				InputPin(PinDriver::input(peripherals.pins.gpio15)?),
 			)?),
+			// No filament sensor is mounted (it would be connected to GPIO14), and a floating pin would make the
+			// filament look like it has run out
+			filament_sensor_pin: None,
+			// No emergency stop button is mounted (it would be connected to GPIO47)
+			emergency_stop_button_pin: None,
 			flash_chip: MT29F2G01ABAGDWB,
 			flash_spi: Some(SpiSingleDeviceDriver::new_single(
 				peripherals.spi2,
@@ -0,0 +1,26 @@
+use std::path::Path;
+
+fn main()
+{
+	set_environment_variables();
+}
+
+fn set_environment_variables()
+{
+	const DIRECTORY_PATH: &str = "../../../private/Secrets/";
+
+	fn set_environment_variable(relative_path: &str, environment_variable_key: &str)
+	{
+		if let Ok(environment_variable_value) =
+			std::fs::read_to_string(Path::new(DIRECTORY_PATH).join(Path::new(relative_path)))
+		{
+			println!(
+				"cargo:rustc-env={}={}",
+				environment_variable_key, environment_variable_value
+			);
+		}
+	}
+
+	set_environment_variable("Password/Password.txt", "PRINTER_PASSWORD");
+	set_environment_variable("Password/Peppers.txt", "PRINTER_PASSWORD_PEPPERS");
+}
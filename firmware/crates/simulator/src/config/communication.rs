@@ -0,0 +1,45 @@
+use std::{
+	net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+	time::Duration,
+};
+
+use embedded_svc::wifi::{AuthMethod, ClientConfiguration};
+use firmware_core::printer::{
+	communication::{
+		communicator::wifi::CreationConfig as WifiCreationConfig,
+		security::{self, PasswordConfiguration},
+		CommunicationConfig,
+	},
+	components::{file_system::regions::RegionsConfig, Peripherals as PeripheralsTrait},
+};
+
+use crate::peripherals::Peripherals;
+
+/// The address the HTTP server of the simulated printer listens at.
+pub const HTTP_SERVER_ADDRESS: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 8080));
+
+pub fn configuration() -> CommunicationConfig
+{
+	CommunicationConfig {
+		wifi: WifiCreationConfig {
+			wifi_client_configuration: ClientConfiguration {
+				ssid: "simulator".into(),
+				bssid: None,
+				auth_method: AuthMethod::None,
+				password: "".into(),
+				channel: None,
+			},
+		},
+		security: security::Configuration {
+			password: PasswordConfiguration::PasswordAndBruteforce {
+				password: env!("PRINTER_PASSWORD"),
+				hash_settings: None,
+				delays_and_wrong_attempts_count_for_it: vec![(3, Duration::from_secs(1))],
+			},
+		},
+		file_system: RegionsConfig::default::<<Peripherals as PeripheralsTrait>::FlashChip>(),
+		max_commands_in_buffer_before_reading_new: 200,
+		delay_between_ticks: Duration::from_millis(100),
+		thread_stack_size: 1024 * 1024,
+	}
+}
@@ -0,0 +1,219 @@
+use std::{f32::consts::PI, time::Duration};
+
+use firmware_core::{
+	printer::components::{
+		config::{
+			temperature::{PidConfig, SafetyConfig, ThermistorConfig},
+			ComponentsConfig, EndPrintSequence, FilamentChangeConfig, ParkingSequence, PauseSequence,
+		},
+		drivers::{
+			filament_sensor::{FilamentSensorConfig, FilamentSensorKind},
+			stepper_motor::tmc2209,
+		},
+		motion::{self, kinematics::*, RotationToLinearMotion},
+		temperature::{safety::temperature_change::TemperatureChangeConfig, TemperaturePidGains},
+	},
+	utils::{
+		math::{
+			vectors::{Vector2, Vector3},
+			Percentage,
+		},
+		measurement::{distance::Distance, temperature::Temperature},
+	},
+};
+
+/// Kinematics of the simulated printer (a delta one when the `delta` feature is enabled, otherwise a CoreXY one).
+#[cfg(not(feature = "delta"))]
+pub type Kinematics = CoreXYKinematics;
+/// Kinematics of the simulated printer (a delta one when the `delta` feature is enabled, otherwise a CoreXY one).
+#[cfg(feature = "delta")]
+pub type Kinematics = DeltaKinematics;
+
+#[cfg(not(feature = "delta"))]
+pub fn kinematics() -> Kinematics
+{
+	CoreXYKinematics
+}
+
+#[cfg(feature = "delta")]
+pub fn kinematics() -> Kinematics
+{
+	DeltaKinematics::new(DeltaConfig {
+		tower_radius: Distance::from_millimeters(100),
+		diagonal_rod_length: Distance::from_millimeters(250),
+		tower_angle_corrections: [0.; 3],
+		endstop_offsets: [Distance::ZERO; 3],
+		height: Distance::from_millimeters(250),
+		segment_length: Distance::from_millimeters(1),
+	})
+	.unwrap()
+}
+
+pub fn configuration() -> ComponentsConfig
+{
+	// All the towers of a delta printer are moved by belts, and the origin of its XY plane is at the center of the bed
+	let is_delta = cfg!(feature = "delta");
+	let park_position = if is_delta
+	{
+		Vector2::from_xy(Distance::ZERO, Distance::from_millimeters(-100))
+	}
+	else
+	{
+		Vector2::from_xy(Distance::ZERO, Distance::from_millimeters(220))
+	};
+
+	ComponentsConfig {
+		layer_fan_min_duty_cycle_to_move: Percentage::from_0_to_100(30.).unwrap(),
+		hotend_fan_min_duty_cycle_to_move: Percentage::from_0_to_100(30.).unwrap(),
+		hotend_pid: PidConfig {
+			pid_gains: TemperaturePidGains {
+				p: 100.,
+				i: 10.,
+				d: 750.,
+			},
+			thermistor: ThermistorConfig {
+				beta: 3_950,
+				resistance_at_t0: 100_000,
+				other_resistance: 4_700,
+			},
+			safety: SafetyConfig {
+				allowed_temperature_range: Temperature::from_celsius(0.)..=Temperature::from_celsius(260.),
+				keep_target_temperature_config: TemperatureChangeConfig {
+					period_in_seconds: 40.,
+					hysteresis: 4.,
+				},
+				rise_to_target_temperature_config: TemperatureChangeConfig {
+					period_in_seconds: 20.,
+					hysteresis: 2.,
+				},
+				rise_to_target_temperature_samples_count: 20,
+			},
+		},
+		heated_bed_pid: PidConfig {
+			pid_gains: TemperaturePidGains {
+				p: 1000.,
+				i: 10.,
+				d: 10.,
+			},
+			thermistor: ThermistorConfig {
+				beta: 3_950,
+				resistance_at_t0: 100_000,
+				other_resistance: 4_700,
+			},
+			safety: SafetyConfig {
+				allowed_temperature_range: Temperature::from_celsius(0.)..=Temperature::from_celsius(110.),
+				keep_target_temperature_config: TemperatureChangeConfig {
+					period_in_seconds: 20.,
+					hysteresis: 2.,
+				},
+				rise_to_target_temperature_config: TemperatureChangeConfig {
+					period_in_seconds: 90.,
+					hysteresis: 2.,
+				},
+				rise_to_target_temperature_samples_count: 45,
+			},
+		},
+		motion_controller: motion::CreationConfig {
+			left_motor: motion::MotorConfig {
+				tmc2209_address: tmc2209::UARTAddress::from_ms_pins_state(false, false),
+				rotation_to_linear_motion: RotationToLinearMotion::new_connected_to_belt_driven(
+					16,
+					Distance::from_millimeters(2),
+					200 * 256,
+				),
+			},
+			right_motor: motion::MotorConfig {
+				tmc2209_address: tmc2209::UARTAddress::from_ms_pins_state(false, true),
+				rotation_to_linear_motion: RotationToLinearMotion::new_connected_to_belt_driven(
+					16,
+					Distance::from_millimeters(2),
+					200 * 256,
+				),
+			},
+			z_axis_motor: motion::MotorConfig {
+				tmc2209_address: tmc2209::UARTAddress::from_ms_pins_state(true, false),
+				rotation_to_linear_motion: if is_delta
+				{
+					RotationToLinearMotion::new_connected_to_belt_driven(16, Distance::from_millimeters(2), 200 * 256)
+				}
+				else
+				{
+					RotationToLinearMotion::new_connected_to_lead_screw(4, Distance::from_millimeters(2), 200 * 256)
+				},
+			},
+			extruder_motor: motion::MotorConfig {
+				tmc2209_address: tmc2209::UARTAddress::from_ms_pins_state(true, true),
+				rotation_to_linear_motion: RotationToLinearMotion::new(
+					Distance::from_micrometers((11. * 1_000. * PI) as i32),
+					200 * 256,
+				),
+			},
+			bed_size: Vector2::from_xy(Distance::from_millimeters(235), Distance::from_millimeters(235)),
+			max_z: Distance::from_millimeters(250),
+			offset_from_nozzle_of_z_probe: Vector3::from_xyz(
+				Distance::from_millimeters(115),
+				Distance::from_millimeters(348),
+				Distance::from_millimeters(-55),
+			),
+			planner_blocks_count: 512,
+			planner_settings: motion::planner::Settings {
+				min_feedrate_mm_s: 0.2,
+				min_travel_feedrate_mm_s: 0.5,
+				max_feedrate_mm_s: [200., 200., if is_delta { 200. } else { 10. }, 45.],
+				retract_acceleration: 1_500.,
+				print_acceleration: 1_500.,
+				travel_acceleration: 2_000.,
+				max_acceleration_mm_per_s2: [9000., 9000., if is_delta { 9000. } else { 100. }, 10000.],
+			},
+			backlash: motion::backlash::BacklashSettings {
+				distances: Vector3::from_xyz(Distance::ZERO, Distance::ZERO, Distance::ZERO),
+				correction: 1.,
+				smoothing_distance: Distance::ZERO,
+			},
+			skew: motion::skew::SkewCorrection { xy: 0., xz: 0., yz: 0. },
+			retraction: motion::retraction::RetractionSettings {
+				length: Distance::from_millimeters(3),
+				speed_mm_s: 45.,
+				z_hop: Distance::ZERO,
+				extra_prime_length: Distance::ZERO,
+				prime_speed_mm_s: 25.,
+			},
+		},
+		print_checkpoint_interval: Duration::from_secs(30),
+		end_print_sequence: EndPrintSequence {
+			parking: ParkingSequence {
+				retraction_length: Distance::from_millimeters(2),
+				retraction_speed_mm_s: 40.,
+				z_lift: Distance::from_millimeters(10),
+				z_lift_speed_mm_s: 10.,
+				park_position: Some(park_position),
+				park_speed_mm_s: 100.,
+			},
+			disable_steppers_after: Duration::from_secs(60),
+		},
+		pause_sequence: PauseSequence {
+			parking: ParkingSequence {
+				retraction_length: Distance::from_millimeters(2),
+				retraction_speed_mm_s: 40.,
+				z_lift: Distance::from_millimeters(5),
+				z_lift_speed_mm_s: 10.,
+				park_position: Some(park_position),
+				park_speed_mm_s: 100.,
+			},
+			hotend_temperature: Some(Temperature::from_celsius(150.)),
+		},
+		filament_change: FilamentChangeConfig {
+			unload_length: Distance::from_millimeters(100),
+			unload_speed_mm_s: 50.,
+			load_length: Distance::from_millimeters(80),
+			load_speed_mm_s: 50.,
+			purge_length: Distance::from_millimeters(30),
+			purge_speed_mm_s: 3.,
+		},
+		filament_sensor: FilamentSensorConfig {
+			kind: FilamentSensorKind::Switch,
+			debounce_time: Duration::from_millis(50),
+			runout_distance: Distance::from_millimeters(20),
+		},
+	}
+}
@@ -0,0 +1,6 @@
+pub mod communication;
+pub mod components;
+
+/// The file that contains the flash memory of the simulated printer (relative to the directory the simulator is run
+/// from), when no other path is provided as the first argument of the simulator.
+pub const DEFAULT_FLASH_FILE_PATH: &str = "simulator_flash.bin";
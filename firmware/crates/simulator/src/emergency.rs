@@ -0,0 +1,13 @@
+use std::sync::atomic::Ordering;
+
+use crate::simulation::ARE_ALL_PINS_DISABLED;
+
+/// Turns off the simulated heaters (on the microcontroller this function pulls all the pins low).
+///
+/// # Safety
+/// It's always safe to call this function, it's `unsafe` only to have the same signature of the one of the
+/// microcontroller.
+pub unsafe fn disable_all_pins_function()
+{
+	ARE_ALL_PINS_DISABLED.store(true, Ordering::Relaxed);
+}
@@ -0,0 +1,42 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// A [`Log`] implementation that prints the records to the standard error.
+pub struct Logger;
+
+impl Logger
+{
+	/// Makes this logger the one used by the [`log`] macros, printing the records whose level is at most `max_level`.
+	pub fn initialize(max_level: LevelFilter)
+	{
+		static LOGGER: Logger = Logger;
+
+		log::set_logger(&LOGGER).expect("The logger can only be initialized once");
+		log::set_max_level(max_level);
+	}
+}
+
+impl Log for Logger
+{
+	fn enabled(&self, metadata: &Metadata) -> bool
+	{
+		metadata.level() <= log::max_level()
+	}
+
+	fn log(&self, record: &Record)
+	{
+		if self.enabled(record.metadata())
+		{
+			let level = match record.level()
+			{
+				Level::Error => "E",
+				Level::Warn => "W",
+				Level::Info => "I",
+				Level::Debug => "D",
+				Level::Trace => "T",
+			};
+			eprintln!("{level} ({}) {}", record.target(), record.args());
+		}
+	}
+
+	fn flush(&self) {}
+}
@@ -0,0 +1,88 @@
+//! Runs the firmware of the 3D printer on the machine you are using, simulating its hardware.
+//!
+//! The simulated printer starts an HTTP server at [`HTTP_SERVER_ADDRESS`] (which is the same API exposed by the real
+//! printer), and stores its flash memory in a file (the path of the file is the first argument of the simulator, or
+//! [`DEFAULT_FLASH_FILE_PATH`] if there's none), so the files you upload are still there the next time you run it.
+//!
+//! Every second the position of the head and the temperatures of the simulated printer are printed on the console.
+//!
+//! [`HTTP_SERVER_ADDRESS`]: config::communication::HTTP_SERVER_ADDRESS
+//! [`DEFAULT_FLASH_FILE_PATH`]: config::DEFAULT_FLASH_FILE_PATH
+
+pub mod config;
+pub mod emergency;
+pub mod logger;
+pub mod peripherals;
+pub mod simulation;
+
+use std::time::{Duration, Instant};
+
+use firmware_core::printer::{panic_handler::PanicHandler, Printer3D};
+use log::LevelFilter;
+use logger::Logger;
+use peripherals::Peripherals;
+use simulation::Simulation;
+
+/// Position (X, Y and Z in millimeters) of the head when the simulator starts.
+#[cfg(not(feature = "delta"))]
+const INITIAL_HEAD_POSITION: [f32; 3] = [100., 100., 20.];
+/// Position (X, Y and Z in millimeters) of the head when the simulator starts (the origin of a delta printer is at the
+/// center of the bed).
+#[cfg(feature = "delta")]
+const INITIAL_HEAD_POSITION: [f32; 3] = [0., 0., 20.];
+/// How often the state of the simulated printer is printed on the console.
+const STATUS_PRINT_INTERVAL: Duration = Duration::from_secs(1);
+/// Time waited after each tick of the printer.
+const DELAY_BETWEEN_TICKS: Duration = Duration::from_millis(1);
+
+fn main()
+{
+	Logger::initialize(LevelFilter::Info);
+
+	let flash_file_path = std::env::args()
+		.nth(1)
+		.unwrap_or_else(|| config::DEFAULT_FLASH_FILE_PATH.to_string());
+	let mut peripherals = Peripherals::new(flash_file_path, config::communication::HTTP_SERVER_ADDRESS).unwrap();
+
+	let components_config = config::components::configuration();
+	let mut simulation = Simulation::new(&mut peripherals, &components_config, INITIAL_HEAD_POSITION);
+	let mut printer_3d = Printer3D::new(
+		peripherals,
+		components_config,
+		config::communication::configuration(),
+		PanicHandler(emergency::disable_all_pins_function),
+	)
+	.unwrap();
+
+	let mut last_tick_instant = Instant::now();
+	let mut last_status_print_instant = last_tick_instant;
+	loop
+	{
+		let now = Instant::now();
+		simulation.advance(now - last_tick_instant);
+		last_tick_instant = now;
+
+		printer_3d.tick().unwrap();
+
+		if now - last_status_print_instant >= STATUS_PRINT_INTERVAL
+		{
+			last_status_print_instant = now;
+			print_status(&simulation);
+		}
+
+		std::thread::sleep(DELAY_BETWEEN_TICKS);
+	}
+}
+
+fn print_status(simulation: &Simulation)
+{
+	let [x, y, z, e] = simulation.get_tool_position();
+	let [hotend_duty_cycle, heated_bed_duty_cycle] = simulation.get_heaters_duty_cycles();
+	println!(
+		"X: {x:.2} Y: {y:.2} Z: {z:.2} E: {e:.2} | Hotend: {:.1}°C ({:.0}%) | Bed: {:.1}°C ({:.0}%)",
+		simulation.get_hotend_temperature().as_celsius(),
+		hotend_duty_cycle.into_0_to_100(),
+		simulation.get_heated_bed_temperature().as_celsius(),
+		heated_bed_duty_cycle.into_0_to_100(),
+	);
+}
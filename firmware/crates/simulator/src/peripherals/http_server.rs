@@ -0,0 +1,342 @@
+use std::{
+	io::{BufRead, BufReader, Read as _, Write as _},
+	net::{SocketAddr, TcpListener, TcpStream},
+	panic::AssertUnwindSafe,
+	sync::{Arc, Mutex},
+};
+
+use embedded_io::{ErrorType, Read, Write};
+use embedded_svc::http::{
+	server::{Connection, HandlerError, HandlerResult, Request},
+	Headers, Method, Query,
+};
+use firmware_core::printer::{
+	communication::{
+		communicator::wifi::HttpServer as HttpServerTrait,
+		http::{request::HttpRequest, resources::Resources},
+	},
+	components::Peripherals,
+};
+
+type Handler = Box<dyn Fn(&mut HttpConnection) -> HandlerResult + Send>;
+type Handlers = Mutex<Vec<(Method, &'static str, Handler)>>;
+
+/// An HTTP server listening on a TCP socket of the machine running the simulator.
+///
+/// The requests are handled one at a time (like the server of the microcontroller does), and each connection is
+/// closed after its request has been handled.
+pub struct HttpServer
+{
+	handlers: Arc<Handlers>,
+}
+
+impl HttpServer
+{
+	/// Binds the server to the provided `address` and starts handling the incoming connections in a new thread.
+	pub fn new(address: SocketAddr) -> std::io::Result<Self>
+	{
+		let listener = TcpListener::bind(address)?;
+		log::info!("The HTTP server is listening on http://{}", listener.local_addr()?);
+
+		let handlers = Arc::new(Mutex::new(Vec::new()));
+		let handlers_clone = Arc::clone(&handlers);
+		std::thread::Builder::new()
+			.name("HTTP server".to_string())
+			.spawn(move || {
+				for stream in listener.incoming()
+				{
+					let result = stream.and_then(|stream| Self::handle_connection(stream, &handlers_clone));
+					if let Err(error) = result
+					{
+						log::warn!("Error in handling an HTTP connection: {error}");
+					}
+				}
+			})?;
+
+		Ok(Self { handlers })
+	}
+
+	fn handle_connection(stream: TcpStream, handlers: &Handlers) -> std::io::Result<()>
+	{
+		let mut connection = HttpConnection::new(stream)?;
+
+		let handlers = handlers.lock().unwrap_or_else(|error| error.into_inner());
+		let path = connection.head.uri.split('?').next().unwrap_or_default();
+		let handler = handlers
+			.iter()
+			.find(|(method, uri, _)| *method == connection.head.method && does_uri_match(uri, path))
+			.map(|(_, _, handler)| handler);
+
+		match handler
+		{
+			// A panic in a handler would stop the whole server (on the microcontroller it would reset it)
+			Some(handler) => match std::panic::catch_unwind(AssertUnwindSafe(|| handler(&mut connection)))
+				.unwrap_or_else(|_| Err(HandlerError::new("The handler panicked")))
+			{
+				Ok(()) if !connection.is_response_initiated() => connection.initiate_response(200, Some("OK"), &[]),
+				Ok(()) => Ok(()),
+				Err(error) if !connection.is_response_initiated() =>
+				{
+					connection.initiate_response(500, Some("Internal Server Error"), &[])?;
+					connection.write_all(error.message().as_bytes())
+				},
+				Err(error) =>
+				{
+					log::warn!("Error after the response has been initiated: {error}");
+					Ok(())
+				},
+			},
+			None => connection.initiate_response(404, Some("Not Found"), &[]),
+		}?;
+
+		connection.flush()
+	}
+}
+
+impl HttpServerTrait for HttpServer
+{
+	type Error = std::io::Error;
+
+	fn register_request<P: Peripherals + 'static>(
+		&mut self, request: HttpRequest, resources: Resources<P>,
+	) -> Result<(), Self::Error>
+	{
+		let callback = move |connection: &mut HttpConnection| {
+			(request.get_callback())(Request::wrap(connection), resources.clone())
+		};
+		self.handlers
+			.lock()
+			.unwrap()
+			.push((request.get_method(), request.get_uri(), Box::new(callback)));
+
+		Ok(())
+	}
+}
+
+/// Returns `true` if the `path` of a request matches the `uri` of a handler, that can end with a `*` wildcard matching
+/// any sequence of characters (like the server of the microcontroller does).
+fn does_uri_match(uri: &str, path: &str) -> bool
+{
+	match uri.strip_suffix('*')
+	{
+		Some(uri_prefix) => path.starts_with(uri_prefix),
+		None => uri == path,
+	}
+}
+
+/// A connection of the [`HttpServer`] with a client, that has already received the request line and the headers.
+pub struct HttpConnection
+{
+	head: RequestHead,
+	body: RequestBody,
+	stream: TcpStream,
+	is_response_initiated: bool,
+}
+
+impl HttpConnection
+{
+	fn new(stream: TcpStream) -> std::io::Result<Self>
+	{
+		let mut reader = BufReader::new(stream.try_clone()?);
+
+		let mut request_line = String::new();
+		reader.read_line(&mut request_line)?;
+		let mut request_line = request_line.split_whitespace();
+		let method = request_line.next().and_then(parse_method).unwrap_or(Method::Get);
+		let uri = request_line.next().unwrap_or("/").to_string();
+
+		let mut headers = Vec::new();
+		loop
+		{
+			let mut line = String::new();
+			if reader.read_line(&mut line)? == 0 || line.trim().is_empty()
+			{
+				break;
+			}
+			if let Some((name, value)) = line.split_once(':')
+			{
+				headers.push((name.trim().to_string(), value.trim().to_string()));
+			}
+		}
+
+		let head = RequestHead { method, uri, headers };
+		let body = RequestBody {
+			remaining_bytes_count: head.content_len().unwrap_or(0),
+			reader,
+		};
+
+		Ok(Self {
+			head,
+			body,
+			stream,
+			is_response_initiated: false,
+		})
+	}
+}
+
+impl Connection for HttpConnection
+{
+	type Headers = RequestHead;
+	type Read = RequestBody;
+	type RawConnectionError = std::io::Error;
+	type RawConnection = Self;
+
+	fn split(&mut self) -> (&Self::Headers, &mut Self::Read)
+	{
+		(&self.head, &mut self.body)
+	}
+
+	fn initiate_response<'a>(
+		&'a mut self, status: u16, message: Option<&'a str>, headers: &'a [(&'a str, &'a str)],
+	) -> Result<(), Self::Error>
+	{
+		let mut response_head = format!("HTTP/1.1 {status} {}\r\n", message.unwrap_or_default());
+		for (name, value) in headers
+		{
+			response_head.push_str(&format!("{name}: {value}\r\n"));
+		}
+		// The end of the body is signaled by closing the connection
+		response_head.push_str("Connection: close\r\n\r\n");
+
+		self.is_response_initiated = true;
+		self.stream.write_all(response_head.as_bytes())
+	}
+
+	fn is_response_initiated(&self) -> bool
+	{
+		self.is_response_initiated
+	}
+
+	fn raw_connection(&mut self) -> Result<&mut Self::RawConnection, Self::Error>
+	{
+		Ok(self)
+	}
+}
+
+impl Query for HttpConnection
+{
+	fn uri(&self) -> &'_ str
+	{
+		self.head.uri()
+	}
+
+	fn method(&self) -> Method
+	{
+		self.head.method()
+	}
+}
+
+impl Headers for HttpConnection
+{
+	fn header(&self, name: &str) -> Option<&'_ str>
+	{
+		self.head.header(name)
+	}
+}
+
+impl Read for HttpConnection
+{
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>
+	{
+		self.body.read(buf)
+	}
+}
+
+impl Write for HttpConnection
+{
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error>
+	{
+		std::io::Write::write(&mut self.stream, buf)
+	}
+
+	fn flush(&mut self) -> Result<(), Self::Error>
+	{
+		std::io::Write::flush(&mut self.stream)
+	}
+}
+
+impl ErrorType for HttpConnection
+{
+	type Error = std::io::Error;
+}
+
+/// The request line and the headers of a request received by the [`HttpServer`].
+pub struct RequestHead
+{
+	method: Method,
+	uri: String,
+	headers: Vec<(String, String)>,
+}
+
+impl Query for RequestHead
+{
+	fn uri(&self) -> &'_ str
+	{
+		&self.uri
+	}
+
+	fn method(&self) -> Method
+	{
+		self.method
+	}
+}
+
+impl Headers for RequestHead
+{
+	fn header(&self, name: &str) -> Option<&'_ str>
+	{
+		self.headers
+			.iter()
+			.find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
+	}
+}
+
+/// The body of a request received by the [`HttpServer`] (its length is the one in the `Content-Length` header).
+pub struct RequestBody
+{
+	reader: BufReader<TcpStream>,
+	remaining_bytes_count: u64,
+}
+
+impl Read for RequestBody
+{
+	/// Fills `buf` unless the end of the body is reached before, so that small bodies (like JSON ones) are always read
+	/// in a single call.
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>
+	{
+		let bytes_to_read_count = buf.len().min(self.remaining_bytes_count as usize);
+		let mut read_bytes_count = 0;
+		while read_bytes_count < bytes_to_read_count
+		{
+			match self.reader.read(&mut buf[read_bytes_count..bytes_to_read_count])?
+			{
+				0 => break,
+				count => read_bytes_count += count,
+			}
+		}
+		self.remaining_bytes_count -= read_bytes_count as u64;
+
+		Ok(read_bytes_count)
+	}
+}
+
+impl ErrorType for RequestBody
+{
+	type Error = std::io::Error;
+}
+
+fn parse_method(method: &str) -> Option<Method>
+{
+	match method
+	{
+		"GET" => Some(Method::Get),
+		"POST" => Some(Method::Post),
+		"PUT" => Some(Method::Put),
+		"DELETE" => Some(Method::Delete),
+		"OPTIONS" => Some(Method::Options),
+		"HEAD" => Some(Method::Head),
+		"PATCH" => Some(Method::Patch),
+		_ => None,
+	}
+}
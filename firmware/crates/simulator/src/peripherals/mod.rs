@@ -0,0 +1,352 @@
+pub mod http_server;
+pub mod ota;
+pub mod system_time;
+pub mod wifi;
+
+use std::{
+	fmt::Debug,
+	net::{IpAddr, SocketAddr},
+	path::Path,
+};
+
+use firmware_core::printer::components::{
+	drivers::{button::Button, spi_flash_memory::MT29F2G01ABAGDWB},
+	mock::*,
+	Peripherals as PeripheralsTrait,
+};
+
+use self::{http_server::HttpServer, ota::Ota, system_time::SystemTime, wifi::Wifi};
+use crate::config;
+
+/// The peripherals of the simulated printer.
+///
+/// The components are the functional mocks of the core crate, and the [`Simulation`] keeps a clone of the ones it
+/// needs to drive (cloning a mock returns a handle to the same mock), while the connection related peripherals run on
+/// the machine executing the simulator.
+///
+/// [`Simulation`]: crate::simulation::Simulation
+pub struct Peripherals
+{
+	pub(crate) stepper_ticker_timer: Option<MockTimer>,
+	pub(crate) step_trace_recorder: Option<StepTraceRecorder>,
+
+	left_motor_dir_pin: Option<MockOutputPin>,
+	left_motor_step_pin: Option<MockOutputPin>,
+	right_motor_dir_pin: Option<MockOutputPin>,
+	right_motor_step_pin: Option<MockOutputPin>,
+	z_axis_motor_dir_pin: Option<MockOutputPin>,
+	z_axis_motor_step_pin: Option<MockOutputPin>,
+	extruder_motor_dir_pin: Option<MockOutputPin>,
+	extruder_motor_step_pin: Option<MockOutputPin>,
+
+	uart_driver: Option<MockUart>,
+
+	pub(crate) x_axis_endstop_pin: MockInputPin,
+	pub(crate) y_axis_endstop_pin: MockInputPin,
+	x_axis_endstop: Option<Button<MockInputPin>>,
+	y_axis_endstop: Option<Button<MockInputPin>>,
+	pub(crate) z_axis_endstop: Option<MockZAxisProbe>,
+
+	filament_sensor_pin: Option<MockInputPin>,
+	emergency_stop_button_pin: Option<MockInputPin>,
+
+	flash_spi: Option<MockFlashMemory<MT29F2G01ABAGDWB>>,
+
+	pub(crate) layer_fan_pin: Option<MockPwmPin>,
+	pub(crate) hotend_fan_pin: Option<MockPwmPin>,
+
+	pub(crate) bed_cartridge_heater_pin: Option<MockPwmPin>,
+	pub(crate) bed_thermistor_pin: Option<MockAdcPin>,
+
+	pub(crate) hotend_cartridge_heater_pin: Option<MockPwmPin>,
+	pub(crate) hotend_thermistor_pin: Option<MockAdcPin>,
+
+	pub(crate) adc: Option<MockAdc>,
+
+	system_time: SystemTime,
+
+	wifi: Option<Wifi>,
+	server_address: Option<SocketAddr>,
+
+	ota: Option<Ota>,
+}
+
+impl Peripherals
+{
+	/// Returns the peripherals of a printer whose flash memory is stored in the file at `flash_file_path` and whose
+	/// HTTP server will listen at `server_address`.
+	pub fn new(flash_file_path: impl AsRef<Path>, server_address: SocketAddr) -> std::io::Result<Self>
+	{
+		let stepper_ticker_timer = MockTimer::default();
+		let step_trace_recorder = StepTraceRecorder::new(&stepper_ticker_timer);
+		let motor_pin = |index, is_step_pin| {
+			let (dir_pin, step_pin) = step_trace_recorder.get_motor_pins(index);
+			Some(match is_step_pin
+			{
+				true => step_pin,
+				false => dir_pin,
+			})
+		};
+		let x_axis_endstop_pin = MockInputPin::default();
+		let y_axis_endstop_pin = MockInputPin::default();
+		// The filament never runs out
+		let mut filament_sensor_pin = MockInputPin::default();
+		filament_sensor_pin.set_level(true);
+
+		Ok(Self {
+			left_motor_dir_pin: motor_pin(0, false),
+			left_motor_step_pin: motor_pin(0, true),
+			right_motor_dir_pin: motor_pin(1, false),
+			right_motor_step_pin: motor_pin(1, true),
+			z_axis_motor_dir_pin: motor_pin(2, false),
+			z_axis_motor_step_pin: motor_pin(2, true),
+			extruder_motor_dir_pin: motor_pin(3, false),
+			extruder_motor_step_pin: motor_pin(3, true),
+			step_trace_recorder: Some(step_trace_recorder),
+			stepper_ticker_timer: Some(stepper_ticker_timer),
+			uart_driver: Some(MockUart::default()),
+			x_axis_endstop: Some(Button::new(x_axis_endstop_pin.clone())),
+			y_axis_endstop: Some(Button::new(y_axis_endstop_pin.clone())),
+			x_axis_endstop_pin,
+			y_axis_endstop_pin,
+			z_axis_endstop: Some(MockZAxisProbe::default()),
+			filament_sensor_pin: Some(filament_sensor_pin),
+			// The emergency stop button is never pressed
+			emergency_stop_button_pin: Some(MockInputPin::default()),
+			flash_spi: Some(MockFlashMemory::from_file(flash_file_path)?),
+			layer_fan_pin: Some(MockPwmPin::default()),
+			hotend_fan_pin: Some(MockPwmPin::default()),
+			bed_cartridge_heater_pin: Some(MockPwmPin::default()),
+			bed_thermistor_pin: Some(MockAdcPin::default()),
+			hotend_cartridge_heater_pin: Some(MockPwmPin::default()),
+			hotend_thermistor_pin: Some(MockAdcPin::default()),
+			adc: Some(MockAdc::default()),
+			system_time: SystemTime::new(),
+			wifi: Some(Wifi::default()),
+			server_address: Some(server_address),
+			ota: Some(Ota),
+		})
+	}
+}
+
+impl PeripheralsTrait for Peripherals
+{
+	type WatchdogCreator = MockWatchdogCreator;
+
+	type StepperTickerTimer = MockTimer;
+	type Kinematics = config::components::Kinematics;
+
+	type LeftDirPin = MockOutputPin;
+	type LeftStepPin = MockOutputPin;
+	type RightDirPin = MockOutputPin;
+	type RightStepPin = MockOutputPin;
+	type ZAxisDirPin = MockOutputPin;
+	type ZAxisStepPin = MockOutputPin;
+	type ExtruderDirPin = MockOutputPin;
+	type ExtruderStepPin = MockOutputPin;
+
+	type UartDriver = MockUart;
+
+	type XAxisEndstop = Button<MockInputPin>;
+	type YAxisEndstop = Button<MockInputPin>;
+	type ZAxisEndstop = MockZAxisProbe;
+
+	type FilamentSensorPin = MockInputPin;
+	type EmergencyStopButtonPin = MockInputPin;
+
+	type FlashChip = MT29F2G01ABAGDWB;
+	type FlashSpi = MockFlashMemory<MT29F2G01ABAGDWB>;
+
+	type FanPin = MockPwmPin;
+
+	type CartridgeHeaterPin = MockPwmPin;
+	type HotendAdcPin = MockAdcPin;
+
+	type HeatedBedHeaterPin = MockPwmPin;
+	type HeatedBedAdcPin = MockAdcPin;
+
+	type Adc = MockAdc;
+
+	type SystemTime = SystemTime;
+
+	type WifiDriver = Wifi;
+
+	type Server = HttpServer;
+	type ServerError = std::io::Error;
+
+	type Ota = Ota;
+
+	fn take_watchdog_creator(&mut self) -> Option<Self::WatchdogCreator>
+	{
+		Some(MockWatchdogCreator)
+	}
+
+	fn take_stepper_ticker_timer(&mut self) -> Option<Self::StepperTickerTimer>
+	{
+		self.stepper_ticker_timer.take()
+	}
+
+	fn take_kinematics(&mut self) -> Option<Self::Kinematics>
+	{
+		Some(config::components::kinematics())
+	}
+
+	fn take_left_motor_dir_pin(&mut self) -> Option<Self::LeftDirPin>
+	{
+		self.left_motor_dir_pin.take()
+	}
+
+	fn take_left_motor_step_pin(&mut self) -> Option<Self::LeftStepPin>
+	{
+		self.left_motor_step_pin.take()
+	}
+
+	fn take_right_motor_dir_pin(&mut self) -> Option<Self::RightDirPin>
+	{
+		self.right_motor_dir_pin.take()
+	}
+
+	fn take_right_motor_step_pin(&mut self) -> Option<Self::RightStepPin>
+	{
+		self.right_motor_step_pin.take()
+	}
+
+	fn take_z_axis_motor_dir_pin(&mut self) -> Option<Self::ZAxisDirPin>
+	{
+		self.z_axis_motor_dir_pin.take()
+	}
+
+	fn take_z_axis_motor_step_pin(&mut self) -> Option<Self::ZAxisStepPin>
+	{
+		self.z_axis_motor_step_pin.take()
+	}
+
+	fn take_extruder_motor_dir_pin(&mut self) -> Option<Self::ExtruderDirPin>
+	{
+		self.extruder_motor_dir_pin.take()
+	}
+
+	fn take_extruder_motor_step_pin(&mut self) -> Option<Self::ExtruderStepPin>
+	{
+		self.extruder_motor_step_pin.take()
+	}
+
+	fn take_uart_driver(&mut self) -> Option<Self::UartDriver>
+	{
+		self.uart_driver.take()
+	}
+
+	fn take_x_axis_endstop(&mut self) -> Option<Self::XAxisEndstop>
+	{
+		self.x_axis_endstop.take()
+	}
+
+	fn take_y_axis_endstop(&mut self) -> Option<Self::YAxisEndstop>
+	{
+		self.y_axis_endstop.take()
+	}
+
+	fn take_z_axis_endstop(&mut self) -> Option<Self::ZAxisEndstop>
+	{
+		self.z_axis_endstop.take()
+	}
+
+	fn take_filament_sensor_pin(&mut self) -> Option<Self::FilamentSensorPin>
+	{
+		self.filament_sensor_pin.take()
+	}
+
+	fn take_emergency_stop_button_pin(&mut self) -> Option<Self::EmergencyStopButtonPin>
+	{
+		self.emergency_stop_button_pin.take()
+	}
+
+	fn take_flash_chip(&mut self) -> Option<Self::FlashChip>
+	{
+		Some(MT29F2G01ABAGDWB)
+	}
+
+	fn take_flash_spi(&mut self) -> Option<Self::FlashSpi>
+	{
+		self.flash_spi.take()
+	}
+
+	fn take_layer_fan_pin(&mut self) -> Option<Self::FanPin>
+	{
+		self.layer_fan_pin.take()
+	}
+
+	fn take_hotend_fan_pin(&mut self) -> Option<Self::FanPin>
+	{
+		self.hotend_fan_pin.take()
+	}
+
+	fn take_bed_cartridge_heater_pin(&mut self) -> Option<Self::HeatedBedHeaterPin>
+	{
+		self.bed_cartridge_heater_pin.take()
+	}
+
+	fn take_bed_thermistor_pin(&mut self) -> Option<Self::HeatedBedAdcPin>
+	{
+		self.bed_thermistor_pin.take()
+	}
+
+	fn take_hotend_cartridge_heater_pin(&mut self) -> Option<Self::CartridgeHeaterPin>
+	{
+		self.hotend_cartridge_heater_pin.take()
+	}
+
+	fn take_hotend_thermistor_pin(&mut self) -> Option<Self::HotendAdcPin>
+	{
+		self.hotend_thermistor_pin.take()
+	}
+
+	fn take_adc(&mut self) -> Option<Self::Adc>
+	{
+		self.adc.take()
+	}
+
+	fn take_system_time(&mut self) -> Option<Self::SystemTime>
+	{
+		Some(self.system_time.clone())
+	}
+
+	fn take_wifi_driver(&mut self) -> Option<Self::WifiDriver>
+	{
+		self.wifi.take()
+	}
+
+	fn get_ip_address_from_wifi_driver_function() -> fn(&Self::WifiDriver) -> Option<IpAddr>
+	{
+		|_| Some(IpAddr::from([127, 0, 0, 1]))
+	}
+
+	fn take_http_server(&mut self) -> Option<Box<dyn FnOnce() -> Result<Self::Server, Self::ServerError> + Send>>
+	{
+		self.server_address.take().map(|server_address| {
+			Box::new(move || HttpServer::new(server_address))
+				as Box<dyn FnOnce() -> Result<Self::Server, Self::ServerError> + Send>
+		})
+	}
+
+	fn take_ota(&mut self) -> Option<Self::Ota>
+	{
+		self.ota.take()
+	}
+
+	fn reboot_fn() -> fn()
+	{
+		|| {
+			log::info!("The firmware asked to reboot, so the simulator is closing");
+			std::process::exit(0)
+		}
+	}
+}
+
+impl Debug for Peripherals
+{
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	{
+		f.debug_struct("Peripherals").finish()
+	}
+}
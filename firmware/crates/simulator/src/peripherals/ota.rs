@@ -0,0 +1,133 @@
+use std::convert::Infallible;
+
+use embedded_io::{ErrorType, Write};
+use embedded_svc::ota::{Ota as OtaTrait, OtaUpdate as OtaUpdateTrait, OtaUpdateFinished, Slot, SlotState};
+
+/// An [`Ota`] that accepts the updates without installing them (the firmware of the simulator is the binary you are
+/// running, so it can only be updated by building it again).
+///
+/// [`Ota`]: OtaTrait
+pub struct Ota;
+
+impl Ota
+{
+	fn slot() -> Slot
+	{
+		Slot {
+			label: "simulator".into(),
+			state: SlotState::Valid,
+			firmware: None,
+		}
+	}
+}
+
+impl OtaTrait for Ota
+{
+	type Update<'a> = OtaUpdate;
+
+	fn get_boot_slot(&self) -> Result<Slot, Self::Error>
+	{
+		Ok(Self::slot())
+	}
+
+	fn get_running_slot(&self) -> Result<Slot, Self::Error>
+	{
+		Ok(Self::slot())
+	}
+
+	fn get_update_slot(&self) -> Result<Slot, Self::Error>
+	{
+		Ok(Self::slot())
+	}
+
+	fn is_factory_reset_supported(&self) -> Result<bool, Self::Error>
+	{
+		Ok(false)
+	}
+
+	fn factory_reset(&mut self) -> Result<(), Self::Error>
+	{
+		Ok(())
+	}
+
+	fn initiate_update(&mut self) -> Result<Self::Update<'_>, Self::Error>
+	{
+		log::info!("Started a simulated OTA update");
+		Ok(OtaUpdate {
+			received_bytes_count: 0,
+		})
+	}
+
+	fn mark_running_slot_valid(&mut self) -> Result<(), Self::Error>
+	{
+		Ok(())
+	}
+
+	fn mark_running_slot_invalid_and_reboot(&mut self) -> Self::Error
+	{
+		unreachable!("The slot of the simulator is always valid")
+	}
+}
+
+impl ErrorType for Ota
+{
+	type Error = Infallible;
+}
+
+/// An update started by [`Ota`], that discards the bytes it receives.
+pub struct OtaUpdate
+{
+	received_bytes_count: usize,
+}
+
+impl OtaUpdateTrait for OtaUpdate
+{
+	type OtaUpdateFinished = Self;
+
+	fn finish(self) -> Result<Self::OtaUpdateFinished, Self::Error>
+	{
+		Ok(self)
+	}
+
+	fn complete(self) -> Result<(), Self::Error>
+	{
+		self.finish()?.activate()
+	}
+
+	fn abort(self) -> Result<(), Self::Error>
+	{
+		log::info!("Aborted the simulated OTA update");
+		Ok(())
+	}
+}
+
+impl OtaUpdateFinished for OtaUpdate
+{
+	fn activate(self) -> Result<(), Self::Error>
+	{
+		log::info!(
+			"Completed the simulated OTA update ({} bytes have been discarded)",
+			self.received_bytes_count
+		);
+		Ok(())
+	}
+}
+
+impl Write for OtaUpdate
+{
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error>
+	{
+		self.received_bytes_count += buf.len();
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> Result<(), Self::Error>
+	{
+		Ok(())
+	}
+}
+
+impl ErrorType for OtaUpdate
+{
+	type Error = Infallible;
+}
@@ -0,0 +1,39 @@
+use std::time::{Duration, Instant};
+
+use firmware_core::printer::components::time::SystemTime as SystemTimeTrait;
+
+/// The time of the machine running the simulator.
+#[derive(Clone)]
+pub struct SystemTime
+{
+	start: Instant,
+}
+
+impl SystemTime
+{
+	pub fn new() -> Self
+	{
+		Self { start: Instant::now() }
+	}
+}
+
+impl Default for SystemTime
+{
+	fn default() -> Self
+	{
+		Self::new()
+	}
+}
+
+impl SystemTimeTrait for SystemTime
+{
+	fn now(&self) -> Duration
+	{
+		self.start.elapsed()
+	}
+
+	fn delay(&self, duration: Duration)
+	{
+		std::thread::sleep(duration)
+	}
+}
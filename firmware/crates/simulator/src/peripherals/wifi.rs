@@ -0,0 +1,85 @@
+use std::convert::Infallible;
+
+use embedded_svc::wifi::{asynch::Wifi as WifiTrait, AccessPointInfo, Capability, Configuration};
+use enumset::EnumSet;
+
+/// A [`Wifi`] driver that doesn't connect to anything, because the simulator is reachable from the network interfaces
+/// of the machine it's running on.
+///
+/// It only remembers the configuration it received and whether it has been started and connected.
+///
+/// [`Wifi`]: WifiTrait
+#[derive(Default)]
+pub struct Wifi
+{
+	configuration: Configuration,
+	is_started: bool,
+	is_connected: bool,
+}
+
+impl WifiTrait for Wifi
+{
+	type Error = Infallible;
+
+	async fn get_capabilities(&self) -> Result<EnumSet<Capability>, Self::Error>
+	{
+		Ok(Capability::Client.into())
+	}
+
+	async fn get_configuration(&self) -> Result<Configuration, Self::Error>
+	{
+		Ok(self.configuration.clone())
+	}
+
+	async fn set_configuration(&mut self, configuration: &Configuration) -> Result<(), Self::Error>
+	{
+		self.configuration = configuration.clone();
+		Ok(())
+	}
+
+	async fn start(&mut self) -> Result<(), Self::Error>
+	{
+		self.is_started = true;
+		Ok(())
+	}
+
+	async fn stop(&mut self) -> Result<(), Self::Error>
+	{
+		self.is_started = false;
+		self.is_connected = false;
+		Ok(())
+	}
+
+	async fn connect(&mut self) -> Result<(), Self::Error>
+	{
+		self.is_connected = self.is_started;
+		log::info!("Simulated WiFi connection: {}", self.is_connected);
+		Ok(())
+	}
+
+	async fn disconnect(&mut self) -> Result<(), Self::Error>
+	{
+		self.is_connected = false;
+		Ok(())
+	}
+
+	async fn is_started(&self) -> Result<bool, Self::Error>
+	{
+		Ok(self.is_started)
+	}
+
+	async fn is_connected(&self) -> Result<bool, Self::Error>
+	{
+		Ok(self.is_connected)
+	}
+
+	async fn scan_n<const N: usize>(&mut self) -> Result<(heapless::Vec<AccessPointInfo, N>, usize), Self::Error>
+	{
+		Ok((heapless::Vec::new(), 0))
+	}
+
+	async fn scan(&mut self) -> Result<Vec<AccessPointInfo>, Self::Error>
+	{
+		Ok(Vec::new())
+	}
+}
@@ -0,0 +1,249 @@
+use std::{
+	sync::atomic::{AtomicBool, Ordering},
+	time::Duration,
+};
+
+use firmware_core::{
+	printer::components::{
+		config::ComponentsConfig,
+		drivers::stepper_motor::tmc2209::MicrostepsPerStep,
+		hal::pwm::PwmPin,
+		mock::*,
+		motion::{bed_leveling::ZAxisProbe, kinematics::Kinematics as _, N_MOTORS},
+	},
+	utils::{
+		math::{vectors::VectorN, Percentage},
+		measurement::{distance::Distance, temperature::Temperature},
+	},
+};
+
+use crate::{
+	config::components::{self, Kinematics},
+	peripherals::Peripherals,
+};
+
+/// Set by [`emergency::disable_all_pins_function`] to turn off the simulated heaters, like the real function does by
+/// pulling all the pins of the microcontroller low.
+///
+/// [`emergency::disable_all_pins_function`]: crate::emergency::disable_all_pins_function
+pub static ARE_ALL_PINS_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// The physical world around the firmware: it moves the head of the printer based on the steps taken by the motors,
+/// presses the endstops when the head reaches them and heats the hotend and the bed based on the duty cycle of their
+/// heaters.
+pub struct Simulation
+{
+	stepper_ticker_timer: MockTimer,
+	step_trace_recorder: StepTraceRecorder,
+	millimeters_per_step: [f32; N_MOTORS],
+	motors_steps: [i64; N_MOTORS],
+	/// Position of the motors when the simulation has started.
+	initial_motors_position: VectorN<N_MOTORS>,
+	kinematics: Kinematics,
+
+	x_axis_endstop_pin: MockInputPin,
+	y_axis_endstop_pin: MockInputPin,
+	z_axis_probe: MockZAxisProbe,
+
+	hotend: MockThermalPlant,
+	heated_bed: MockThermalPlant,
+	heaters_pins: [MockPwmPin; 2],
+	fans_pins: [MockPwmPin; 2],
+}
+
+impl Simulation
+{
+	/// The time the stepper ticker timer is moved forward at once (it's short enough to not skip any endstop).
+	const MAX_TIMER_STEP: Duration = Duration::from_micros(250);
+
+	/// Keeps a handle to the peripherals the simulation needs to drive (you must call this before giving the
+	/// `peripherals` to the printer).
+	///
+	/// The motors move the head as described by the `components_config` and by the [`kinematics`], starting from
+	/// `initial_position` (the X, Y and Z coordinates in millimeters).
+	///
+	/// [`kinematics`]: components::kinematics
+	pub fn new(peripherals: &mut Peripherals, components_config: &ComponentsConfig, initial_position: [f32; 3])
+		-> Self
+	{
+		let motion_config = &components_config.motion_controller;
+		let millimeters_per_step = [
+			&motion_config.left_motor,
+			&motion_config.right_motor,
+			&motion_config.z_axis_motor,
+			&motion_config.extruder_motor,
+		]
+		.map(|motor_config| {
+			// The motion controller configures all the TMC2209 drivers with the same microstepping
+			let microsteps_per_millimeter = motor_config
+				.rotation_to_linear_motion
+				.distance_to_microsteps(Distance::MILLIMETER) as f32;
+			MicrostepsPerStep::M16.as_max_resolution_microsteps_count() as f32 / microsteps_per_millimeter
+		});
+
+		let kinematics = components::kinematics();
+		let initial_motors_position = kinematics.cartesian_to_motors(&VectorN::new([
+			distance_from_millimeters(initial_position[0]),
+			distance_from_millimeters(initial_position[1]),
+			distance_from_millimeters(initial_position[2]),
+			Distance::ZERO,
+		]));
+
+		let adc = peripherals.adc.as_ref().unwrap();
+		let hotend_heater_pin = peripherals.hotend_cartridge_heater_pin.clone().unwrap();
+		let bed_heater_pin = peripherals.bed_cartridge_heater_pin.clone().unwrap();
+
+		let mut self_ = Self {
+			stepper_ticker_timer: peripherals.stepper_ticker_timer.clone().unwrap(),
+			step_trace_recorder: peripherals.step_trace_recorder.take().unwrap(),
+			millimeters_per_step,
+			motors_steps: [0; N_MOTORS],
+			initial_motors_position,
+			kinematics,
+			x_axis_endstop_pin: peripherals.x_axis_endstop_pin.clone(),
+			y_axis_endstop_pin: peripherals.y_axis_endstop_pin.clone(),
+			z_axis_probe: peripherals.z_axis_endstop.clone().unwrap(),
+			hotend: MockThermalPlant::new(
+				ThermalPlantConfig::hotend(),
+				&hotend_heater_pin,
+				peripherals.hotend_thermistor_pin.as_ref().unwrap(),
+				adc,
+			),
+			heated_bed: MockThermalPlant::new(
+				ThermalPlantConfig::heated_bed(),
+				&bed_heater_pin,
+				peripherals.bed_thermistor_pin.as_ref().unwrap(),
+				adc,
+			),
+			heaters_pins: [hotend_heater_pin, bed_heater_pin],
+			fans_pins: [
+				peripherals.layer_fan_pin.clone().unwrap(),
+				peripherals.hotend_fan_pin.clone().unwrap(),
+			],
+		};
+		self_.update_endstops();
+
+		self_
+	}
+
+	/// Simulates `delta_time` of the physical world.
+	pub fn advance(&mut self, delta_time: Duration)
+	{
+		let mut remaining_time = delta_time;
+		while !remaining_time.is_zero()
+		{
+			let step = remaining_time.min(Self::MAX_TIMER_STEP);
+			remaining_time -= step;
+
+			// The steps are taken in the alarm callback of the timer
+			self.stepper_ticker_timer.advance_time(step);
+			let trace = self.step_trace_recorder.get_trace();
+			for (motor_index, motor_steps) in self.motors_steps.iter_mut().enumerate()
+			{
+				*motor_steps += trace.get_position(motor_index) as i64;
+			}
+			self.step_trace_recorder.clear();
+
+			self.update_endstops();
+		}
+
+		if ARE_ALL_PINS_DISABLED.load(Ordering::Relaxed)
+		{
+			self.hotend.force_heater_duty_cycle(Some(Percentage::ZERO));
+			self.heated_bed.force_heater_duty_cycle(Some(Percentage::ZERO));
+		}
+		self.hotend.tick(delta_time.as_secs_f64());
+		self.heated_bed.tick(delta_time.as_secs_f64());
+
+		// The simulation only needs the current duty cycles, so the histories would just grow forever
+		for pin in self.heaters_pins.iter_mut().chain(&mut self.fans_pins)
+		{
+			pin.clear_duty_cycle_history();
+		}
+	}
+
+	/// Returns the position of the tool (X, Y, Z and the extruded filament E) in millimeters.
+	pub fn get_tool_position(&self) -> [f32; N_MOTORS]
+	{
+		let position = self.kinematics.motors_to_cartesian(&self.get_motors_position());
+
+		std::array::from_fn(|axis| position[axis].as_millimeters_f32())
+	}
+
+	fn get_motors_position(&self) -> VectorN<N_MOTORS>
+	{
+		VectorN::new(std::array::from_fn(|motor_index| {
+			let millimeters = self.motors_steps[motor_index] as f32 * self.millimeters_per_step[motor_index];
+			self.initial_motors_position[motor_index] + distance_from_millimeters(millimeters)
+		}))
+	}
+
+	/// Returns the actual temperature of the hotend.
+	pub fn get_hotend_temperature(&self) -> Temperature
+	{
+		self.hotend.get_heated_body_temperature()
+	}
+
+	/// Returns the actual temperature of the heated bed.
+	pub fn get_heated_bed_temperature(&self) -> Temperature
+	{
+		self.heated_bed.get_heated_body_temperature()
+	}
+
+	/// Returns the duty cycles of the hotend's heater and of the heated bed's one.
+	pub fn get_heaters_duty_cycles(&self) -> [Percentage; 2]
+	{
+		self.heaters_pins.each_ref().map(|pin| pin.get_duty_cycle())
+	}
+
+	#[cfg(not(feature = "delta"))]
+	fn update_endstops(&mut self)
+	{
+		let [x, y, z, _] = self.get_tool_position();
+
+		self.x_axis_endstop_pin.set_level(x <= 0.);
+		self.y_axis_endstop_pin.set_level(y <= 0.);
+		self.update_z_axis_probe(z <= 0.);
+	}
+
+	/// The X, Y and Z axis endstops are the top endstops of the towers `A`, `B` and `C` (check [`DeltaKinematics`]),
+	/// and the Z axis endstop is also triggered by the probe.
+	///
+	/// [`DeltaKinematics`]: firmware_core::printer::components::motion::kinematics::DeltaKinematics
+	#[cfg(feature = "delta")]
+	fn update_endstops(&mut self)
+	{
+		let height = self.kinematics.get_config().height;
+		let top_motors_position = self.kinematics.cartesian_to_motors(&VectorN::new([
+			Distance::ZERO,
+			Distance::ZERO,
+			height,
+			Distance::ZERO,
+		]));
+		let motors_position = self.get_motors_position();
+		let [a, b, c] = [0, 1, 2].map(|tower_index| motors_position[tower_index] >= top_motors_position[tower_index]);
+		let [_, _, z, _] = self.get_tool_position();
+
+		self.x_axis_endstop_pin.set_level(a);
+		self.y_axis_endstop_pin.set_level(b);
+		self.update_z_axis_probe(c || z <= 0.);
+	}
+
+	fn update_z_axis_probe(&mut self, is_triggered: bool)
+	{
+		let is_probe_triggered = self.z_axis_probe.is_end_reached().unwrap_or_default();
+		if is_triggered && !is_probe_triggered
+		{
+			self.z_axis_probe.trigger();
+		}
+		else if !is_triggered && is_probe_triggered
+		{
+			self.z_axis_probe.release();
+		}
+	}
+}
+
+fn distance_from_millimeters(millimeters: f32) -> Distance
+{
+	Distance::from_micrometers((millimeters * 1_000.) as i32)
+}